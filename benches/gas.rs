@@ -0,0 +1,137 @@
+//! Measures the gas burnt by the contract's hot paths on a sandbox node
+//!
+//! Run with `cargo bench --features gas-bench`. The report is printed as JSON and also written to
+//! `target/gas-report.json`.
+//!
+//! Auctions are started by the contract calling its own `start_auction`, with a plain account
+//! standing in for the NFT contract, so the NFT calls made during settlement fail. Only the gas
+//! burnt by the contract's own receipts, such as `end_auction` and the callback that settles, is
+//! compared across runs; the gas of the whole transaction is included for reference.
+
+use near_workspaces::{result::ExecutionFinalResult, types::NearToken, Account, Contract};
+use serde_json::{json, Value};
+
+/// Number of bidders to settle an auction with
+const BIDDER_COUNTS: [usize; 5] = [0, 1, 5, 10, 25];
+
+/// Auction length in seconds, bids have to land before it runs out
+const TIMESPAN: u64 = 60;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let contract_wasm = near_workspaces::compile_project("./").await?;
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = sandbox.dev_deploy(&contract_wasm).await?;
+    let nft = sandbox.dev_create_account().await?;
+    let seller = sandbox.dev_create_account().await?;
+    let mut bidders = Vec::new();
+    for _ in 0..BIDDER_COUNTS.iter().max().copied().unwrap_or_default() {
+        bidders.push(sandbox.dev_create_account().await?);
+    }
+
+    let mut make_bid = Vec::new();
+    let mut end_auction = Vec::new();
+    for bidder_count in BIDDER_COUNTS {
+        let token_id = format!("bench-{bidder_count}");
+        let now = sandbox.view_block().await?.timestamp();
+        start_auction(
+            &contract,
+            &nft,
+            &seller,
+            &token_id,
+            now + TIMESPAN * 1_000_000_000,
+        )
+        .await?;
+
+        for (i, bidder) in bidders.iter().take(bidder_count).enumerate() {
+            let amount = NearToken::from_millinear(i as u128 + 1);
+            let outcome = bidder
+                .call(contract.id(), "make_bid")
+                .args_json(json!({
+                    "nft": nft.id(),
+                    "token_id": token_id,
+                    "amount": amount,
+                }))
+                .deposit(amount)
+                .max_gas()
+                .transact()
+                .await?;
+            make_bid.push(report(&contract, &outcome, json!({ "previous_bids": i }))?);
+        }
+
+        // Let the auction expire
+        sandbox.fast_forward(TIMESPAN * 2).await?;
+
+        let outcome = seller
+            .call(contract.id(), "end_auction")
+            .args_json(json!({ "nft": nft.id(), "token_id": token_id }))
+            .max_gas()
+            .transact()
+            .await?;
+        end_auction.push(report(
+            &contract,
+            &outcome,
+            json!({ "bidders": bidder_count }),
+        )?);
+    }
+
+    let report = json!({
+        "make_bid": make_bid,
+        "end_auction": end_auction,
+    });
+    let report = serde_json::to_string_pretty(&report)?;
+    println!("{report}");
+    std::fs::create_dir_all("target")?;
+    std::fs::write("target/gas-report.json", report)?;
+    Ok(())
+}
+
+async fn start_auction(
+    contract: &Contract,
+    nft: &Account,
+    seller: &Account,
+    token_id: &str,
+    expiry: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = contract
+        .as_account()
+        .call(contract.id(), "start_auction")
+        .args_json(json!({
+            "owner_id": seller.id(),
+            "nft": nft.id(),
+            "token_id": token_id,
+            "expiry": expiry,
+            "params": {
+                "timespan": TIMESPAN,
+                "minimum_bid": NearToken::from_yoctonear(0),
+            },
+        }))
+        .transact()
+        .await?;
+    outcome.into_result()?;
+    Ok(())
+}
+
+/// Gas figures of `outcome`, tagged with the scenario it was measured in
+fn report(
+    contract: &Contract,
+    outcome: &ExecutionFinalResult,
+    scenario: Value,
+) -> Result<Value, String> {
+    let receipts: Vec<_> = outcome
+        .receipt_outcomes()
+        .iter()
+        .filter(|receipt| receipt.executor_id == *contract.id())
+        .collect();
+    if receipts.is_empty() {
+        return Err(format!("no receipt was executed: {outcome:?}"));
+    }
+    if !receipts.iter().all(|receipt| receipt.is_success()) {
+        return Err(format!("contract call failed: {outcome:?}"));
+    }
+    Ok(json!({
+        "scenario": scenario,
+        "gas_burnt": receipts.iter().map(|receipt| receipt.gas_burnt.as_gas()).sum::<u64>(),
+        "total_gas_burnt": outcome.total_gas_burnt.as_gas(),
+    }))
+}
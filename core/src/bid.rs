@@ -0,0 +1,363 @@
+//! Rules for accepting a bid
+
+use std::fmt;
+
+use crate::{
+    listing::is_expired,
+    pay_on_win::{required_deposit, PayOnWinRules},
+    Balance, Timestamp,
+};
+
+/// Most bytes a bid's memo can have
+pub const MAX_MEMO_LEN: usize = 140;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BidError {
+    NotAboveHighest,
+    DepositTooLow,
+    AlreadyBid,
+    AuctionOver,
+    ZeroTopUp,
+    ExpiryNotInFuture,
+    TooSoon,
+    AlreadyHighest,
+    MemoTooLong,
+    PennyAuction,
+    BondingCurveSale,
+    ZeroReduction,
+    StillWinning,
+    BelowMinimum,
+}
+
+impl fmt::Display for BidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotAboveHighest => {
+                "bid amount does not exceed previous bid or minimum bid amount"
+            }
+            Self::DepositTooLow => "provided deposit does not cover bid amount",
+            Self::AlreadyBid => {
+                "bidder has already made a bid, either call `refundBid` or `updateBid`"
+            }
+            Self::AuctionOver => "cannot bid, auction is over",
+            Self::ZeroTopUp => "attached deposit must top up the bid",
+            Self::ExpiryNotInFuture => "bid expiry must be in the future",
+            Self::TooSoon => "bidder has bid too recently, wait for the bid interval to pass",
+            Self::AlreadyHighest => "bidder already has the highest bid",
+            Self::MemoTooLong => "bid memos can have at most 140 bytes",
+            Self::PennyAuction => "this is a penny auction, bid with `penny_bid`",
+            Self::BondingCurveSale => "this is a bonding-curve sale, buy with `buy`",
+            Self::ZeroReduction => "a bid has to be reduced by more than 0",
+            Self::StillWinning => "bids that are winning can't be reduced",
+            Self::BelowMinimum => "the reduced bid has to exceed the minimum bid",
+        })
+    }
+}
+
+impl std::error::Error for BidError {}
+
+/// State of an auction that a new bid is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BidContext {
+    /// Highest bid so far, or the minimum bid if there are no bids
+    pub h_bid: Balance,
+    pub expiry: Timestamp,
+    /// Time an account has to wait between its bids
+    pub bid_interval: u64,
+    pub now: Timestamp,
+}
+
+/// A bid as submitted by a bidder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BidAttempt {
+    pub amount: Balance,
+    pub deposit: Balance,
+    /// Whether this bidder already has a bid in the auction
+    pub already_bid: bool,
+    /// When the bid lapses unless it is the highest one
+    pub expires_at: Option<Timestamp>,
+    /// Set if only a share of `amount` has to be covered by `deposit`
+    pub pay_on_win: Option<PayOnWinRules>,
+}
+
+/// Checks whether `attempt` may be placed on an auction in state `ctx`
+pub fn validate_bid(ctx: &BidContext, attempt: &BidAttempt) -> Result<(), BidError> {
+    if attempt.amount <= ctx.h_bid {
+        return Err(BidError::NotAboveHighest);
+    }
+    let required = match &attempt.pay_on_win {
+        Some(rules) => required_deposit(rules, attempt.amount),
+        None => attempt.amount,
+    };
+    if attempt.deposit < required {
+        return Err(BidError::DepositTooLow);
+    }
+    if attempt.already_bid {
+        return Err(BidError::AlreadyBid);
+    }
+    if is_expired(ctx.now, ctx.expiry) {
+        return Err(BidError::AuctionOver);
+    }
+    if attempt
+        .expires_at
+        .is_some_and(|expires_at| is_expired(ctx.now, expires_at))
+    {
+        return Err(BidError::ExpiryNotInFuture);
+    }
+    Ok(())
+}
+
+/// Whether a bid of `amount`, expiring at `expires_at`, has lapsed at `now` because it is not the
+/// highest bid `h_bid`
+///
+/// A lapsed bid can no longer win and may be refunded. Bids only ever go up, so a bid that is not
+/// the highest one is never going to be again.
+pub fn is_lapsed(
+    now: Timestamp,
+    expires_at: Option<Timestamp>,
+    amount: Balance,
+    h_bid: Balance,
+) -> bool {
+    amount < h_bid && expires_at.is_some_and(|expires_at| is_expired(now, expires_at))
+}
+
+/// The smallest bid that exceeds `h_bid`
+pub fn min_next_bid(h_bid: Balance) -> Balance {
+    h_bid.saturating_add(1)
+}
+
+/// Checks whether a bid of `current`, last placed at `last_bid_at`, may be topped up by `top_up`
+/// on an auction in state `ctx`, returning the increased amount
+pub fn validate_increase(
+    ctx: &BidContext,
+    current: Balance,
+    last_bid_at: Timestamp,
+    top_up: Balance,
+) -> Result<Balance, BidError> {
+    if top_up == 0 {
+        return Err(BidError::ZeroTopUp);
+    }
+    if ctx.now.saturating_sub(last_bid_at) < ctx.bid_interval {
+        return Err(BidError::TooSoon);
+    }
+    let amount = current.saturating_add(top_up);
+    if amount <= ctx.h_bid {
+        return Err(BidError::NotAboveHighest);
+    }
+    if is_expired(ctx.now, ctx.expiry) {
+        return Err(BidError::AuctionOver);
+    }
+    Ok(amount)
+}
+
+/// Checks whether a bid of `current` may be reduced by `reduction` on an auction in state `ctx`
+/// that takes bids above `minimum_bid`, returning the reduced amount
+///
+/// Only bids below `h_bid`, which can't win as things stand, may be reduced, and they have to
+/// keep exceeding the minimum bid.
+pub fn validate_reduction(
+    ctx: &BidContext,
+    minimum_bid: Balance,
+    current: Balance,
+    reduction: Balance,
+) -> Result<Balance, BidError> {
+    if reduction == 0 {
+        return Err(BidError::ZeroReduction);
+    }
+    if current >= ctx.h_bid {
+        return Err(BidError::StillWinning);
+    }
+    let amount = current.saturating_sub(reduction);
+    if amount < min_next_bid(minimum_bid) {
+        return Err(BidError::BelowMinimum);
+    }
+    if is_expired(ctx.now, ctx.expiry) {
+        return Err(BidError::AuctionOver);
+    }
+    Ok(amount)
+}
+
+/// Checks that `memo` fits on a bid, returning the bytes it takes up in storage along with the
+/// bid
+pub fn validate_memo(memo: &str) -> Result<u64, BidError> {
+    if memo.len() > MAX_MEMO_LEN {
+        return Err(BidError::MemoTooLong);
+    }
+    // Borsh prefixes strings with their length
+    Ok(memo.len() as u64 + 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTX: BidContext = BidContext {
+        h_bid: 10,
+        expiry: 1_000,
+        bid_interval: 0,
+        now: 500,
+    };
+
+    fn attempt(amount: Balance) -> BidAttempt {
+        BidAttempt {
+            amount,
+            deposit: amount,
+            already_bid: false,
+            expires_at: None,
+            pay_on_win: None,
+        }
+    }
+
+    #[test]
+    fn accepts_bid_above_highest() {
+        assert_eq!(validate_bid(&CTX, &attempt(11)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_bid_not_above_highest() {
+        assert_eq!(
+            validate_bid(&CTX, &attempt(10)),
+            Err(BidError::NotAboveHighest)
+        );
+    }
+
+    #[test]
+    fn rejects_uncovered_bid() {
+        let attempt = BidAttempt {
+            deposit: 10,
+            ..attempt(11)
+        };
+        assert_eq!(validate_bid(&CTX, &attempt), Err(BidError::DepositTooLow));
+    }
+
+    #[test]
+    fn pay_on_win_bids_only_cover_the_deposit() {
+        let attempt = BidAttempt {
+            deposit: 2,
+            pay_on_win: Some(PayOnWinRules {
+                deposit_bps: 1_000,
+                payment_window: 100,
+            }),
+            ..attempt(20)
+        };
+        assert_eq!(validate_bid(&CTX, &attempt), Ok(()));
+        let attempt = BidAttempt {
+            deposit: 1,
+            ..attempt
+        };
+        assert_eq!(validate_bid(&CTX, &attempt), Err(BidError::DepositTooLow));
+    }
+
+    #[test]
+    fn rejects_second_bid() {
+        let attempt = BidAttempt {
+            already_bid: true,
+            ..attempt(11)
+        };
+        assert_eq!(validate_bid(&CTX, &attempt), Err(BidError::AlreadyBid));
+    }
+
+    #[test]
+    fn rejects_bid_after_expiry() {
+        let ctx = BidContext { now: 1_000, ..CTX };
+        assert_eq!(validate_bid(&ctx, &attempt(11)), Err(BidError::AuctionOver));
+    }
+
+    #[test]
+    fn rejects_bid_expiring_before_being_placed() {
+        let attempt = BidAttempt {
+            expires_at: Some(CTX.now),
+            ..attempt(11)
+        };
+        assert_eq!(
+            validate_bid(&CTX, &attempt),
+            Err(BidError::ExpiryNotInFuture)
+        );
+    }
+
+    #[test]
+    fn only_outbid_bids_lapse() {
+        assert!(is_lapsed(500, Some(500), 5, 10));
+        assert!(!is_lapsed(499, Some(500), 5, 10));
+        assert!(!is_lapsed(500, Some(500), 10, 10));
+        assert!(!is_lapsed(500, None, 5, 10));
+    }
+
+    #[test]
+    fn min_next_bid_is_accepted() {
+        let amount = min_next_bid(CTX.h_bid);
+        assert_eq!(validate_bid(&CTX, &attempt(amount)), Ok(()));
+        assert_eq!(
+            validate_bid(&CTX, &attempt(amount - 1)),
+            Err(BidError::NotAboveHighest)
+        );
+    }
+
+    #[test]
+    fn increase_adds_top_up() {
+        assert_eq!(validate_increase(&CTX, 10, 400, 5), Ok(15));
+    }
+
+    #[test]
+    fn increase_must_top_up_above_highest() {
+        assert_eq!(
+            validate_increase(&CTX, 10, 400, 0),
+            Err(BidError::ZeroTopUp)
+        );
+        assert_eq!(
+            validate_increase(&CTX, 4, 400, 6),
+            Err(BidError::NotAboveHighest)
+        );
+    }
+
+    #[test]
+    fn increase_waits_for_bid_interval() {
+        let ctx = BidContext {
+            bid_interval: 100,
+            ..CTX
+        };
+        assert_eq!(validate_increase(&ctx, 10, 401, 5), Err(BidError::TooSoon));
+        assert_eq!(validate_increase(&ctx, 10, 400, 5), Ok(15));
+    }
+
+    #[test]
+    fn only_outbid_bids_are_reduced() {
+        assert_eq!(validate_reduction(&CTX, 2, 8, 3), Ok(5));
+        assert_eq!(
+            validate_reduction(&CTX, 2, 8, 0),
+            Err(BidError::ZeroReduction)
+        );
+        assert_eq!(
+            validate_reduction(&CTX, 2, 10, 3),
+            Err(BidError::StillWinning)
+        );
+        let ctx = BidContext { now: 1_000, ..CTX };
+        assert_eq!(
+            validate_reduction(&ctx, 2, 8, 3),
+            Err(BidError::AuctionOver)
+        );
+    }
+
+    #[test]
+    fn reduced_bids_exceed_the_minimum() {
+        assert_eq!(validate_reduction(&CTX, 2, 8, 5), Ok(3));
+        assert_eq!(
+            validate_reduction(&CTX, 2, 8, 6),
+            Err(BidError::BelowMinimum)
+        );
+        assert_eq!(
+            validate_reduction(&CTX, 2, 8, 9),
+            Err(BidError::BelowMinimum)
+        );
+    }
+
+    #[test]
+    fn memos_are_bounded() {
+        assert_eq!(validate_memo("gm"), Ok(6));
+        assert_eq!(validate_memo(&"a".repeat(MAX_MEMO_LEN)), Ok(144));
+        assert_eq!(
+            validate_memo(&"a".repeat(MAX_MEMO_LEN + 1)),
+            Err(BidError::MemoTooLong)
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Rules of bonding-curve sales
+//!
+//! NFTs are sold one at a time, straight away, at a price that grows linearly with the number
+//! already sold.
+
+use std::fmt;
+
+use crate::{listing::is_expired, Balance, Timestamp};
+
+/// Price of the first NFT and what every sale adds to it, set by the seller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Curve {
+    pub base_price: Balance,
+    pub slope: Balance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveError {
+    ZeroBasePrice,
+    SaleOver,
+    SoldOut,
+    PriceOverflow,
+    DepositTooLow,
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ZeroBasePrice => "bonding curve base price must be greater than 0",
+            Self::SaleOver => "cannot buy, sale is over",
+            Self::SoldOut => "every nft of this sale has been sold",
+            Self::PriceOverflow => "bonding curve price overflowed",
+            Self::DepositTooLow => "provided deposit does not cover the quoted price",
+        })
+    }
+}
+
+impl std::error::Error for CurveError {}
+
+/// Checks the curve a seller lists a sale with
+pub fn validate_curve(curve: &Curve) -> Result<(), CurveError> {
+    if curve.base_price == 0 {
+        return Err(CurveError::ZeroBasePrice);
+    }
+    Ok(())
+}
+
+/// Price of the next NFT once `sold` have been sold
+pub fn quote(curve: &Curve, sold: u32) -> Result<Balance, CurveError> {
+    curve
+        .slope
+        .checked_mul(sold.into())
+        .and_then(|rise| rise.checked_add(curve.base_price))
+        .ok_or(CurveError::PriceOverflow)
+}
+
+/// Checks whether a buyer who attached `deposit` can buy the next of `supply` NFTs at `now`,
+/// returning its price
+pub fn plan_purchase(
+    curve: &Curve,
+    sold: u32,
+    supply: u32,
+    now: Timestamp,
+    expiry: Timestamp,
+    deposit: Balance,
+) -> Result<Balance, CurveError> {
+    if is_expired(now, expiry) {
+        return Err(CurveError::SaleOver);
+    }
+    if sold >= supply {
+        return Err(CurveError::SoldOut);
+    }
+    let price = quote(curve, sold)?;
+    if deposit < price {
+        return Err(CurveError::DepositTooLow);
+    }
+    Ok(price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVE: Curve = Curve {
+        base_price: 100,
+        slope: 10,
+    };
+
+    #[test]
+    fn price_rises_with_every_sale() {
+        assert_eq!(quote(&CURVE, 0), Ok(100));
+        assert_eq!(quote(&CURVE, 3), Ok(130));
+        assert_eq!(
+            quote(
+                &Curve {
+                    base_price: 1,
+                    slope: Balance::MAX,
+                },
+                1
+            ),
+            Err(CurveError::PriceOverflow)
+        );
+    }
+
+    #[test]
+    fn purchases_need_stock_time_and_deposit() {
+        assert_eq!(plan_purchase(&CURVE, 1, 2, 5, 10, 110), Ok(110));
+        assert_eq!(
+            plan_purchase(&CURVE, 2, 2, 5, 10, 1000),
+            Err(CurveError::SoldOut)
+        );
+        assert_eq!(
+            plan_purchase(&CURVE, 0, 2, 10, 10, 1000),
+            Err(CurveError::SaleOver)
+        );
+        assert_eq!(
+            plan_purchase(&CURVE, 1, 2, 5, 10, 109),
+            Err(CurveError::DepositTooLow)
+        );
+    }
+
+    #[test]
+    fn base_price_must_be_set() {
+        assert_eq!(
+            validate_curve(&Curve {
+                base_price: 0,
+                slope: 1,
+            }),
+            Err(CurveError::ZeroBasePrice)
+        );
+        assert_eq!(validate_curve(&CURVE), Ok(()));
+    }
+}
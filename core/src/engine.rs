@@ -0,0 +1,264 @@
+//! How each kind of auction takes bids and picks its winners
+//!
+//! The contract escrows, refunds and pays out the same way whatever the kind of auction, and asks
+//! the kind's [`AuctionEngine`] only about what differs: which bids it takes, what the next bid
+//! has to exceed and who wins at what price. A new kind of auction is a new engine, the shared
+//! escrow code stays as it is.
+
+use crate::{
+    bid::{validate_bid, validate_increase, validate_reduction, BidAttempt, BidContext, BidError},
+    settlement::{plan_settlement, Settlement, SettlementBid},
+    uniform, Balance, Timestamp,
+};
+
+/// What an engine has to know about an auction besides its bids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lot {
+    /// Number of NFTs the auction sells
+    pub supply: usize,
+    pub minimum_bid: Balance,
+}
+
+/// Winners of an auction and what they pay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome<A> {
+    /// Winning bidders, each winning the NFT at their position in the auction's NFTs
+    pub winners: Vec<A>,
+    /// Price every winner pays
+    pub price: Balance,
+    /// What is refunded of the winners' bids beyond the price, and every other outstanding bid
+    pub refunds: Vec<(A, Balance)>,
+}
+
+/// Bids of an auction as seen at settlement, read only if the engine needs them
+pub type Bids<'a, A> = &'a mut dyn Iterator<Item = SettlementBid<A>>;
+
+/// The rules of a kind of auction
+pub trait AuctionEngine<A> {
+    /// Checks whether `attempt` may be placed as a new bid on an auction in state `ctx`
+    fn validate_bid(&self, ctx: &BidContext, attempt: &BidAttempt) -> Result<(), BidError> {
+        validate_bid(ctx, attempt)
+    }
+
+    /// Checks whether a bid of `current`, last placed at `last_bid_at`, may be topped up by
+    /// `top_up` on an auction in state `ctx`, returning the increased amount
+    fn validate_increase(
+        &self,
+        ctx: &BidContext,
+        current: Balance,
+        last_bid_at: Timestamp,
+        top_up: Balance,
+    ) -> Result<Balance, BidError> {
+        validate_increase(ctx, current, last_bid_at, top_up)
+    }
+
+    /// Checks whether a bid of `current` may be reduced by `reduction` on an auction in state
+    /// `ctx` taking bids above `minimum_bid`, returning the reduced amount
+    fn validate_reduction(
+        &self,
+        ctx: &BidContext,
+        minimum_bid: Balance,
+        current: Balance,
+        reduction: Balance,
+    ) -> Result<Balance, BidError> {
+        validate_reduction(ctx, minimum_bid, current, reduction)
+    }
+
+    /// The amount new bids have to exceed once a bid was placed or raised to `amount`
+    fn next_threshold(&self, amount: Balance, _bids: Bids<A>, _lot: &Lot) -> Balance {
+        amount
+    }
+
+    /// Plans who wins the auction, `None` if nobody does
+    fn plan_settlement(&self, bids: Bids<A>, lot: &Lot) -> Option<Outcome<A>>;
+}
+
+/// The highest bid wins the NFT and pays what it bid
+#[derive(Debug, Clone, Copy)]
+pub struct English;
+
+impl<A: Clone> AuctionEngine<A> for English {
+    fn plan_settlement(&self, bids: Bids<A>, _lot: &Lot) -> Option<Outcome<A>> {
+        match plan_settlement(bids) {
+            Settlement::Sold {
+                winner,
+                price,
+                refunds,
+            } => Some(Outcome {
+                winners: vec![winner],
+                price,
+                refunds,
+            }),
+            Settlement::Unsold => None,
+        }
+    }
+}
+
+/// The highest bids win one NFT each and every winner pays the lowest winning bid, see
+/// [`uniform`]
+#[derive(Debug, Clone, Copy)]
+pub struct Uniform;
+
+impl<A: Clone> AuctionEngine<A> for Uniform {
+    fn next_threshold(&self, _amount: Balance, bids: Bids<A>, lot: &Lot) -> Balance {
+        uniform::entry_threshold(bids, lot.supply, lot.minimum_bid)
+    }
+
+    fn plan_settlement(&self, bids: Bids<A>, lot: &Lot) -> Option<Outcome<A>> {
+        uniform::plan_settlement(bids, lot.supply).map(|settlement| Outcome {
+            winners: settlement.winners,
+            price: settlement.clearing_price,
+            refunds: settlement.refunds,
+        })
+    }
+}
+
+/// Takes its bids through `penny_bid` only, the last and so highest bidder wins, see
+/// [`crate::penny`]
+#[derive(Debug, Clone, Copy)]
+pub struct Penny;
+
+impl<A: Clone> AuctionEngine<A> for Penny {
+    fn validate_bid(&self, _ctx: &BidContext, _attempt: &BidAttempt) -> Result<(), BidError> {
+        Err(BidError::PennyAuction)
+    }
+
+    fn validate_increase(
+        &self,
+        _ctx: &BidContext,
+        _current: Balance,
+        _last_bid_at: Timestamp,
+        _top_up: Balance,
+    ) -> Result<Balance, BidError> {
+        Err(BidError::PennyAuction)
+    }
+
+    fn validate_reduction(
+        &self,
+        _ctx: &BidContext,
+        _minimum_bid: Balance,
+        _current: Balance,
+        _reduction: Balance,
+    ) -> Result<Balance, BidError> {
+        Err(BidError::PennyAuction)
+    }
+
+    fn plan_settlement(&self, bids: Bids<A>, lot: &Lot) -> Option<Outcome<A>> {
+        English.plan_settlement(bids, lot)
+    }
+}
+
+/// Sells its NFTs through `buy` instead of taking bids, see [`crate::curve`]
+#[derive(Debug, Clone, Copy)]
+pub struct BondingCurve;
+
+impl<A> AuctionEngine<A> for BondingCurve {
+    fn validate_bid(&self, _ctx: &BidContext, _attempt: &BidAttempt) -> Result<(), BidError> {
+        Err(BidError::BondingCurveSale)
+    }
+
+    fn validate_increase(
+        &self,
+        _ctx: &BidContext,
+        _current: Balance,
+        _last_bid_at: Timestamp,
+        _top_up: Balance,
+    ) -> Result<Balance, BidError> {
+        Err(BidError::BondingCurveSale)
+    }
+
+    fn validate_reduction(
+        &self,
+        _ctx: &BidContext,
+        _minimum_bid: Balance,
+        _current: Balance,
+        _reduction: Balance,
+    ) -> Result<Balance, BidError> {
+        Err(BidError::BondingCurveSale)
+    }
+
+    fn plan_settlement(&self, _bids: Bids<A>, _lot: &Lot) -> Option<Outcome<A>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOT: Lot = Lot {
+        supply: 2,
+        minimum_bid: 1,
+    };
+
+    fn bids() -> Vec<SettlementBid<&'static str>> {
+        [("alice", 5), ("bob", 9), ("carol", 7)]
+            .into_iter()
+            .enumerate()
+            .map(|(sequence, (bidder, amount))| SettlementBid {
+                bidder,
+                amount,
+                paid: false,
+                sequence: sequence as u64,
+            })
+            .collect()
+    }
+
+    fn plan(engine: &dyn AuctionEngine<&'static str>) -> Option<Outcome<&'static str>> {
+        engine.plan_settlement(&mut bids().into_iter(), &LOT)
+    }
+
+    #[test]
+    fn engines_pick_their_winners() {
+        assert_eq!(
+            plan(&English),
+            Some(Outcome {
+                winners: vec!["bob"],
+                price: 9,
+                refunds: vec![("alice", 5), ("carol", 7)],
+            })
+        );
+        assert_eq!(
+            plan(&Uniform),
+            Some(Outcome {
+                winners: vec!["bob", "carol"],
+                price: 7,
+                refunds: vec![("bob", 2), ("alice", 5)],
+            })
+        );
+        assert_eq!(plan(&Penny), plan(&English));
+        assert_eq!(plan(&BondingCurve), None);
+    }
+
+    #[test]
+    fn engines_set_the_next_threshold() {
+        let threshold = |engine: &dyn AuctionEngine<&'static str>| {
+            engine.next_threshold(9, &mut bids().into_iter(), &LOT)
+        };
+        assert_eq!(threshold(&English), 9);
+        assert_eq!(threshold(&Uniform), 7);
+    }
+
+    #[test]
+    fn only_some_engines_take_bids() {
+        let ctx = BidContext {
+            h_bid: 1,
+            expiry: 1_000,
+            bid_interval: 0,
+            now: 500,
+        };
+        let attempt = BidAttempt {
+            amount: 2,
+            deposit: 2,
+            already_bid: false,
+            expires_at: None,
+            pay_on_win: None,
+        };
+        let validate =
+            |engine: &dyn AuctionEngine<&'static str>| engine.validate_bid(&ctx, &attempt);
+        assert_eq!(validate(&English), Ok(()));
+        assert_eq!(validate(&Uniform), Ok(()));
+        assert_eq!(validate(&Penny), Err(BidError::PennyAuction));
+        assert_eq!(validate(&BondingCurve), Err(BidError::BondingCurveSale));
+    }
+}
@@ -0,0 +1,66 @@
+//! Auctions ordered by when they expire
+//!
+//! Auctions are grouped into buckets of [`BUCKET`] by their expiry, and the starts of the buckets
+//! holding any auction are kept sorted, so queries by end time only visit buckets that have
+//! auctions in them.
+
+use crate::Timestamp;
+
+/// One hour
+pub const BUCKET: Timestamp = 60 * 60 * 1_000_000_000;
+
+/// Start of the bucket an auction expiring at `expiry` goes into
+pub fn bucket_of(expiry: Timestamp) -> Timestamp {
+    expiry - expiry % BUCKET
+}
+
+/// Adds `bucket` to the sorted `buckets` unless it is already there
+pub fn add_bucket(buckets: &mut Vec<Timestamp>, bucket: Timestamp) {
+    if let Err(index) = buckets.binary_search(&bucket) {
+        buckets.insert(index, bucket);
+    }
+}
+
+/// Removes `bucket` from the sorted `buckets`, once it holds no auctions
+pub fn remove_bucket(buckets: &mut Vec<Timestamp>, bucket: Timestamp) {
+    if let Ok(index) = buckets.binary_search(&bucket) {
+        buckets.remove(index);
+    }
+}
+
+/// The buckets of the sorted `buckets` that can hold auctions expiring from `from` until before
+/// `to`
+pub fn buckets_between(buckets: &[Timestamp], from: Timestamp, to: Timestamp) -> &[Timestamp] {
+    let start = buckets.partition_point(|bucket| *bucket < bucket_of(from));
+    let end = buckets.partition_point(|bucket| *bucket < to).max(start);
+    &buckets[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_stay_sorted_and_unique() {
+        let mut buckets = Vec::new();
+        for expiry in [3 * BUCKET + 5, BUCKET, 3 * BUCKET, 2 * BUCKET - 1] {
+            add_bucket(&mut buckets, bucket_of(expiry));
+        }
+        assert_eq!(buckets, vec![BUCKET, 3 * BUCKET]);
+        remove_bucket(&mut buckets, BUCKET);
+        remove_bucket(&mut buckets, 2 * BUCKET);
+        assert_eq!(buckets, vec![3 * BUCKET]);
+    }
+
+    #[test]
+    fn ranges_visit_only_the_buckets_they_overlap() {
+        let buckets = [0, BUCKET, 3 * BUCKET, 5 * BUCKET];
+        assert_eq!(
+            buckets_between(&buckets, BUCKET + 1, 3 * BUCKET + 1),
+            &[BUCKET, 3 * BUCKET]
+        );
+        assert_eq!(buckets_between(&buckets, BUCKET, 3 * BUCKET), &[BUCKET]);
+        assert!(buckets_between(&buckets, 6 * BUCKET, Timestamp::MAX).is_empty());
+        assert!(buckets_between(&buckets, 3 * BUCKET, BUCKET).is_empty());
+    }
+}
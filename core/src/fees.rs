@@ -0,0 +1,220 @@
+//! How the price of a sale is split among the marketplace, royalties, a referrer, a charity and
+//! the seller
+//!
+//! Each auction settles under a [`FeePolicy`], which [`FeePolicy::split`] applies to the price
+//! every winner pays. The marketplace fee comes first, royalties are paid on what it leaves, and
+//! the referral and charity shares are taken out of the whole price. The seller gets the rest.
+
+use std::fmt;
+
+use crate::{royalty, Balance};
+
+/// Most of a sale the marketplace fee, referral and charity shares can take together, in basis
+/// points
+pub const MAX_FEE_BPS: u16 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeError {
+    TooHigh,
+}
+
+impl fmt::Display for FeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TooHigh => "fees and shares of a fee policy can be at most 5000 basis points",
+        })
+    }
+}
+
+impl std::error::Error for FeeError {}
+
+/// What a sale pays beyond the seller's share
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeePolicy<A> {
+    /// Basis points of the price the marketplace takes
+    pub marketplace_bps: u16,
+    /// Whether the royalties of the NFT's collection are paid
+    pub royalties: bool,
+    /// Account that referred the sale and its basis points of the price
+    pub referral: Option<(A, u16)>,
+    /// Charity and its basis points of the price
+    pub charity: Option<(A, u16)>,
+}
+
+impl<A> Default for FeePolicy<A> {
+    /// Royalties and nothing else, as sales paid before there were fee policies
+    fn default() -> Self {
+        Self {
+            marketplace_bps: 0,
+            royalties: true,
+            referral: None,
+            charity: None,
+        }
+    }
+}
+
+/// A sale a [`FeePolicy`] is applied to
+#[derive(Debug, Clone, Copy)]
+pub struct Sale<'a, A> {
+    pub price: Balance,
+    /// Already charged for the marketplace besides the policy's fee, such as a quick-flip fee
+    pub surcharge: Balance,
+    /// Royalty shares of the NFT's collection, see [`royalty::shares_from_payout`]
+    pub royalty_shares: &'a [(A, u16)],
+}
+
+/// Who gets what out of a sale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split<A> {
+    /// The policy's fee along with the sale's surcharge
+    pub marketplace: Balance,
+    pub royalties: Vec<(A, Balance)>,
+    pub referral: Option<(A, Balance)>,
+    pub charity: Option<(A, Balance)>,
+    pub seller: Balance,
+}
+
+impl<A: Clone> FeePolicy<A> {
+    /// Checks that the policy leaves the seller at least half of a sale before royalties
+    pub fn validate(&self) -> Result<(), FeeError> {
+        let total: u32 = [
+            Some(self.marketplace_bps),
+            self.referral.as_ref().map(|(_, bps)| *bps),
+            self.charity.as_ref().map(|(_, bps)| *bps),
+        ]
+        .into_iter()
+        .flatten()
+        .map(u32::from)
+        .sum();
+        if total > u32::from(MAX_FEE_BPS) {
+            return Err(FeeError::TooHigh);
+        }
+        Ok(())
+    }
+
+    /// Splits `sale` as the policy says, never paying out more than its price
+    pub fn split(&self, sale: &Sale<A>) -> Split<A> {
+        let mut left = sale.price;
+        let mut take = |amount: Balance| {
+            let amount = amount.min(left);
+            left -= amount;
+            amount
+        };
+        let marketplace = take(
+            sale.surcharge
+                .saturating_add(share(sale.price, self.marketplace_bps)),
+        );
+        let royalties = if self.royalties {
+            royalty::split(sale.price - marketplace, sale.royalty_shares)
+                .into_iter()
+                .map(|(account, royalty)| (account, take(royalty)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let referral = self
+            .referral
+            .as_ref()
+            .map(|(account, bps)| (account.clone(), take(share(sale.price, *bps))));
+        let charity = self
+            .charity
+            .as_ref()
+            .map(|(account, bps)| (account.clone(), take(share(sale.price, *bps))));
+        Split {
+            marketplace,
+            royalties,
+            referral,
+            charity,
+            seller: left,
+        }
+    }
+}
+
+/// `bps` basis points of `amount`, rounded down
+fn share(amount: Balance, bps: u16) -> Balance {
+    let bps = Balance::from(bps);
+    amount / 10_000 * bps + amount % 10_000 * bps / 10_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARES: &[(&str, u16)] = &[("creator", 1_000)];
+
+    fn sale(price: Balance, surcharge: Balance) -> Sale<'static, &'static str> {
+        Sale {
+            price,
+            surcharge,
+            royalty_shares: SHARES,
+        }
+    }
+
+    #[test]
+    fn default_policy_pays_royalties_only() {
+        assert_eq!(
+            FeePolicy::default().split(&sale(10_000, 0)),
+            Split {
+                marketplace: 0,
+                royalties: vec![("creator", 1_000)],
+                referral: None,
+                charity: None,
+                seller: 9_000,
+            }
+        );
+    }
+
+    #[test]
+    fn every_party_gets_its_share() {
+        let policy = FeePolicy {
+            marketplace_bps: 250,
+            royalties: true,
+            referral: Some(("referrer", 100)),
+            charity: Some(("charity", 500)),
+        };
+        assert_eq!(
+            policy.split(&sale(10_000, 250)),
+            Split {
+                marketplace: 500,
+                royalties: vec![("creator", 950)],
+                referral: Some(("referrer", 100)),
+                charity: Some(("charity", 500)),
+                seller: 7_950,
+            }
+        );
+    }
+
+    #[test]
+    fn royalties_can_be_left_out() {
+        let policy = FeePolicy {
+            royalties: false,
+            ..FeePolicy::default()
+        };
+        assert_eq!(policy.split(&sale(10_000, 0)).seller, 10_000);
+    }
+
+    #[test]
+    fn splits_never_exceed_the_price() {
+        let policy = FeePolicy {
+            charity: Some(("charity", 5_000)),
+            ..FeePolicy::default()
+        };
+        let split = policy.split(&sale(100, 80));
+        assert_eq!(split.marketplace, 80);
+        assert_eq!(split.royalties, vec![("creator", 2)]);
+        assert_eq!(split.charity, Some(("charity", 18)));
+        assert_eq!(split.seller, 0);
+    }
+
+    #[test]
+    fn policies_leave_the_seller_half() {
+        let policy = |marketplace_bps, charity_bps| FeePolicy {
+            marketplace_bps,
+            royalties: true,
+            referral: None,
+            charity: Some(("charity", charity_bps)),
+        };
+        assert_eq!(policy(2_500, 2_500).validate(), Ok(()));
+        assert_eq!(policy(2_500, 2_501).validate(), Err(FeeError::TooHigh));
+    }
+}
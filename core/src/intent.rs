@@ -0,0 +1,80 @@
+//! Rules for bid intents, signed by a bidder and placed on their behalf by a solver
+
+use std::fmt;
+
+use crate::{listing::is_expired, Balance, Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntentError {
+    Expired,
+    NonceUsed,
+    AboveLimit,
+}
+
+impl fmt::Display for IntentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Expired => "intent has expired",
+            Self::NonceUsed => "intent nonce must exceed the last one used by the signer",
+            Self::AboveLimit => "bid exceeds the most the intent allows",
+        })
+    }
+}
+
+impl std::error::Error for IntentError {}
+
+/// Checks whether an intent with `nonce`, valid until `deadline` for bids of up to `max_amount`,
+/// can be fulfilled at `now` with a bid of `amount`, the signer's last intent having had
+/// `last_nonce`
+///
+/// Nonces only ever grow, so an intent can't be fulfilled twice.
+pub fn validate_intent(
+    now: Timestamp,
+    deadline: Timestamp,
+    nonce: u64,
+    last_nonce: Option<u64>,
+    amount: Balance,
+    max_amount: Balance,
+) -> Result<(), IntentError> {
+    if is_expired(now, deadline) {
+        return Err(IntentError::Expired);
+    }
+    if last_nonce.is_some_and(|last_nonce| nonce <= last_nonce) {
+        return Err(IntentError::NonceUsed);
+    }
+    if amount > max_amount {
+        return Err(IntentError::AboveLimit);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intents_are_bounded() {
+        assert_eq!(validate_intent(10, 20, 0, None, 5, 5), Ok(()));
+        assert_eq!(
+            validate_intent(20, 20, 0, None, 5, 5),
+            Err(IntentError::Expired)
+        );
+        assert_eq!(
+            validate_intent(10, 20, 0, None, 6, 5),
+            Err(IntentError::AboveLimit)
+        );
+    }
+
+    #[test]
+    fn nonces_cannot_be_reused() {
+        assert_eq!(validate_intent(10, 20, 3, Some(2), 5, 5), Ok(()));
+        assert_eq!(
+            validate_intent(10, 20, 2, Some(2), 5, 5),
+            Err(IntentError::NonceUsed)
+        );
+        assert_eq!(
+            validate_intent(10, 20, 1, Some(2), 5, 5),
+            Err(IntentError::NonceUsed)
+        );
+    }
+}
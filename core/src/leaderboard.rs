@@ -0,0 +1,50 @@
+//! Ranking accounts by the volume of the auctions they won
+//!
+//! Only the top of the ranking is kept, so it can be updated with every win without looking at
+//! every account that ever won.
+
+use crate::Balance;
+
+/// Puts the new `total` of `key` on the `board`, which keeps the `capacity` keys with the highest
+/// totals, highest first
+///
+/// Totals only ever grow. Of equal totals, the one that got there first ranks higher.
+pub fn update<K: PartialEq>(
+    board: &mut Vec<(K, Balance)>,
+    key: K,
+    total: Balance,
+    capacity: usize,
+) {
+    board.retain(|(entry, _)| *entry != key);
+    let rank = board.partition_point(|(_, entry)| *entry >= total);
+    if rank < capacity {
+        board.insert(rank, (key, total));
+        board.truncate(capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn board_is_sorted_by_total() {
+        let mut board = Vec::new();
+        update(&mut board, "a", 10, 3);
+        update(&mut board, "b", 30, 3);
+        update(&mut board, "c", 20, 3);
+        assert_eq!(board, vec![("b", 30), ("c", 20), ("a", 10)]);
+
+        update(&mut board, "a", 40, 3);
+        assert_eq!(board, vec![("a", 40), ("b", 30), ("c", 20)]);
+    }
+
+    #[test]
+    fn board_keeps_the_top() {
+        let mut board = vec![("a", 30), ("b", 20)];
+        update(&mut board, "c", 20, 2);
+        assert_eq!(board, vec![("a", 30), ("b", 20)]);
+        update(&mut board, "c", 25, 2);
+        assert_eq!(board, vec![("a", 30), ("c", 25)]);
+    }
+}
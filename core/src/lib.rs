@@ -0,0 +1,36 @@
+//! Pure auction logic of the `nftauction` contract
+//!
+//! Nothing in here depends on `near_sdk`, so these rules can be unit-tested natively and reused
+//! by off-chain simulators and indexers. Amounts are in yoctoNEAR and times are in nanoseconds,
+//! exactly as the contract stores them, but for the `timespan` of listings, in seconds.
+
+pub mod bid;
+pub mod curve;
+pub mod engine;
+pub mod expiry;
+pub mod fees;
+pub mod intent;
+pub mod leaderboard;
+pub mod listing;
+pub mod multisig;
+pub mod offer;
+pub mod pay_on_win;
+pub mod payment;
+pub mod penny;
+pub mod retry;
+pub mod royalty;
+pub mod settlement;
+pub mod staking;
+pub mod time;
+pub mod token_id;
+pub mod trending;
+pub mod uniform;
+pub mod units;
+pub mod volume;
+pub mod wash;
+
+/// Amount in yoctoNEAR
+pub type Balance = u128;
+
+/// Block timestamp in nanoseconds
+pub type Timestamp = u64;
@@ -0,0 +1,119 @@
+//! Rules for starting an auction
+
+use std::fmt;
+
+use crate::{time, Timestamp};
+
+/// Longest an auction can wait to be settled once it expired, a week
+pub const MAX_SETTLEMENT_DELAY: u64 = 7 * 24 * 60 * 60 * time::NANOS_PER_SEC;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingError {
+    ZeroTimespan,
+    TimespanOverflow,
+    TimespanTooShort,
+    SettlementDelayTooLong,
+}
+
+impl fmt::Display for ListingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ZeroTimespan => "timestamp must be greater than 0",
+            Self::TimespanOverflow => {
+                "adding `timespan` to `timestamp` overflowed, `timespan` is too big"
+            }
+            Self::TimespanTooShort => "`timespan` is shorter than the minimum for this nft",
+            Self::SettlementDelayTooLong => "`settlement_delay` can be at most a week",
+        })
+    }
+}
+
+impl std::error::Error for ListingError {}
+
+/// Computes when an auction started at `now` and lasting `timespan` seconds expires
+pub fn expiry(now: Timestamp, timespan: u64) -> Result<Timestamp, ListingError> {
+    if timespan == 0 {
+        return Err(ListingError::ZeroTimespan);
+    }
+    time::secs_to_nanos(timespan)
+        .and_then(|timespan| now.checked_add(timespan))
+        .ok_or(ListingError::TimespanOverflow)
+}
+
+/// Checks that an auction lasting `timespan` seconds lasts at least `min_timespan` nanoseconds
+pub fn check_min_timespan(timespan: u64, min_timespan: u64) -> Result<(), ListingError> {
+    if time::secs_to_nanos(timespan).is_some_and(|timespan| timespan < min_timespan) {
+        return Err(ListingError::TimespanTooShort);
+    }
+    Ok(())
+}
+
+/// Computes the delay of an auction waiting `settlement_delay` seconds to be settled, which can
+/// be at most [`MAX_SETTLEMENT_DELAY`] as bids stay escrowed until then
+pub fn settlement_delay(settlement_delay: u64) -> Result<u64, ListingError> {
+    time::secs_to_nanos(settlement_delay)
+        .filter(|delay| *delay <= MAX_SETTLEMENT_DELAY)
+        .ok_or(ListingError::SettlementDelayTooLong)
+}
+
+/// Whether an auction expiring at `expiry` is over at `now`
+pub fn is_expired(now: Timestamp, expiry: Timestamp) -> bool {
+    now >= expiry
+}
+
+/// When an auction expiring at `expiry` can be settled, `settlement_delay` after it expired
+pub fn settles_at(expiry: Timestamp, settlement_delay: u64) -> Timestamp {
+    expiry.saturating_add(settlement_delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_is_offset_from_now() {
+        assert_eq!(expiry(100, 50), Ok(100 + 50 * time::NANOS_PER_SEC));
+    }
+
+    #[test]
+    fn expiry_rejects_zero_and_overflowing_timespans() {
+        assert_eq!(expiry(100, 0), Err(ListingError::ZeroTimespan));
+        assert_eq!(expiry(u64::MAX - 1, 1), Err(ListingError::TimespanOverflow));
+        assert_eq!(expiry(0, u64::MAX), Err(ListingError::TimespanOverflow));
+    }
+
+    #[test]
+    fn timespan_has_to_reach_the_minimum() {
+        let min_timespan = 50 * time::NANOS_PER_SEC;
+        assert_eq!(check_min_timespan(50, min_timespan), Ok(()));
+        assert_eq!(
+            check_min_timespan(49, min_timespan),
+            Err(ListingError::TimespanTooShort)
+        );
+        assert_eq!(check_min_timespan(u64::MAX, u64::MAX), Ok(()));
+    }
+
+    #[test]
+    fn settlement_waits_out_the_delay() {
+        assert_eq!(settles_at(150, 0), 150);
+        assert_eq!(settles_at(150, 50), 200);
+        assert_eq!(settles_at(u64::MAX, 1), u64::MAX);
+        let week = MAX_SETTLEMENT_DELAY / time::NANOS_PER_SEC;
+        assert_eq!(settlement_delay(60), Ok(60 * time::NANOS_PER_SEC));
+        assert_eq!(settlement_delay(week), Ok(MAX_SETTLEMENT_DELAY));
+        assert_eq!(
+            settlement_delay(week + 1),
+            Err(ListingError::SettlementDelayTooLong)
+        );
+        assert_eq!(
+            settlement_delay(u64::MAX),
+            Err(ListingError::SettlementDelayTooLong)
+        );
+    }
+
+    #[test]
+    fn expired_at_expiry() {
+        assert!(!is_expired(149, 150));
+        assert!(is_expired(150, 150));
+    }
+}
@@ -0,0 +1,120 @@
+//! M-of-N confirmation of privileged actions
+//!
+//! Once admins are set, a privileged action only runs after `threshold` of them confirmed it.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisigError {
+    ThresholdOutOfRange,
+    DuplicateAdmin,
+    NotAdmin,
+    AlreadyConfirmed,
+}
+
+impl fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ThresholdOutOfRange => "threshold must be between 1 and the number of admins",
+            Self::DuplicateAdmin => "admins must be distinct",
+            Self::NotAdmin => "only admins can propose or confirm actions",
+            Self::AlreadyConfirmed => "caller already confirmed this action",
+        })
+    }
+}
+
+impl std::error::Error for MultisigError {}
+
+/// Checks a set of `admins` that have to reach `threshold` confirmations, no admins turning
+/// confirmations off
+pub fn validate_admins<A: PartialEq>(admins: &[A], threshold: u32) -> Result<(), MultisigError> {
+    if admins.is_empty() {
+        return match threshold {
+            0 => Ok(()),
+            _ => Err(MultisigError::ThresholdOutOfRange),
+        };
+    }
+    if threshold == 0 || threshold as usize > admins.len() {
+        return Err(MultisigError::ThresholdOutOfRange);
+    }
+    if admins
+        .iter()
+        .enumerate()
+        .any(|(i, admin)| admins[..i].contains(admin))
+    {
+        return Err(MultisigError::DuplicateAdmin);
+    }
+    Ok(())
+}
+
+/// Adds the confirmation of `admin` to the `confirmations` of an action, returning whether the
+/// action reached `threshold` and can run
+pub fn confirm<A: PartialEq>(
+    confirmations: &mut Vec<A>,
+    admin: A,
+    admins: &[A],
+    threshold: u32,
+) -> Result<bool, MultisigError> {
+    if !admins.contains(&admin) {
+        return Err(MultisigError::NotAdmin);
+    }
+    if confirmations.contains(&admin) {
+        return Err(MultisigError::AlreadyConfirmed);
+    }
+    confirmations.push(admin);
+    // Admins that were removed meanwhile no longer count
+    let confirmed = confirmations
+        .iter()
+        .filter(|confirmation| admins.contains(confirmation))
+        .count();
+    Ok(confirmed >= threshold as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_has_to_be_reachable() {
+        assert_eq!(validate_admins::<&str>(&[], 0), Ok(()));
+        assert_eq!(
+            validate_admins::<&str>(&[], 1),
+            Err(MultisigError::ThresholdOutOfRange)
+        );
+        assert_eq!(validate_admins(&["a", "b"], 2), Ok(()));
+        assert_eq!(
+            validate_admins(&["a", "b"], 3),
+            Err(MultisigError::ThresholdOutOfRange)
+        );
+        assert_eq!(
+            validate_admins(&["a", "b"], 0),
+            Err(MultisigError::ThresholdOutOfRange)
+        );
+        assert_eq!(
+            validate_admins(&["a", "a"], 1),
+            Err(MultisigError::DuplicateAdmin)
+        );
+    }
+
+    #[test]
+    fn action_runs_at_threshold() {
+        let admins = ["a", "b", "c"];
+        let mut confirmations = Vec::new();
+        assert_eq!(confirm(&mut confirmations, "a", &admins, 2), Ok(false));
+        assert_eq!(
+            confirm(&mut confirmations, "a", &admins, 2),
+            Err(MultisigError::AlreadyConfirmed)
+        );
+        assert_eq!(
+            confirm(&mut confirmations, "d", &admins, 2),
+            Err(MultisigError::NotAdmin)
+        );
+        assert_eq!(confirm(&mut confirmations, "c", &admins, 2), Ok(true));
+    }
+
+    #[test]
+    fn removed_admins_do_not_count() {
+        let mut confirmations = vec!["a"];
+        assert_eq!(confirm(&mut confirmations, "b", &["b", "c"], 2), Ok(false));
+    }
+}
@@ -0,0 +1,138 @@
+//! Rules for standing offers on NFTs that are not in auction
+
+use std::fmt;
+
+use crate::{listing::is_expired, Balance, Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfferError {
+    ZeroAmount,
+    ExpiryNotInFuture,
+    CounterNotAboveOffer,
+    CounterExpired,
+    TopUpTooLow,
+}
+
+impl fmt::Display for OfferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ZeroAmount => "attached deposit must be the amount offered",
+            Self::ExpiryNotInFuture => "offer expiry must be in the future",
+            Self::CounterNotAboveOffer => "counter price must exceed the amount offered",
+            Self::CounterExpired => "counteroffer has expired",
+            Self::TopUpTooLow => "attached deposit does not cover the counter price",
+        })
+    }
+}
+
+impl std::error::Error for OfferError {}
+
+/// Checks whether an offer of `amount`, expiring at `expires_at`, may be made at `now`
+pub fn validate_offer(
+    now: Timestamp,
+    amount: Balance,
+    expires_at: Option<Timestamp>,
+) -> Result<(), OfferError> {
+    if amount == 0 {
+        return Err(OfferError::ZeroAmount);
+    }
+    if expires_at.is_some_and(|expires_at| is_expired(now, expires_at)) {
+        return Err(OfferError::ExpiryNotInFuture);
+    }
+    Ok(())
+}
+
+/// Whether an offer expiring at `expires_at` can still be accepted at `now`
+pub fn is_open(now: Timestamp, expires_at: Option<Timestamp>) -> bool {
+    !expires_at.is_some_and(|expires_at| is_expired(now, expires_at))
+}
+
+/// Checks whether an offer of `amount` may be countered at `now` with `price`, which stands until
+/// `expires_at`
+pub fn validate_counter(
+    now: Timestamp,
+    amount: Balance,
+    price: Balance,
+    expires_at: Timestamp,
+) -> Result<(), OfferError> {
+    if price <= amount {
+        return Err(OfferError::CounterNotAboveOffer);
+    }
+    if is_expired(now, expires_at) {
+        return Err(OfferError::ExpiryNotInFuture);
+    }
+    Ok(())
+}
+
+/// Checks whether a counter `price`, standing until `expires_at`, can be accepted at `now` by
+/// topping up an offer of `amount` with `deposit`, returning the part of `deposit` that is needed
+pub fn counter_top_up(
+    now: Timestamp,
+    amount: Balance,
+    price: Balance,
+    expires_at: Timestamp,
+    deposit: Balance,
+) -> Result<Balance, OfferError> {
+    if is_expired(now, expires_at) {
+        return Err(OfferError::CounterExpired);
+    }
+    let top_up = price.saturating_sub(amount);
+    if deposit < top_up {
+        return Err(OfferError::TopUpTooLow);
+    }
+    Ok(top_up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_offers() {
+        assert_eq!(validate_offer(10, 0, None), Err(OfferError::ZeroAmount));
+    }
+
+    #[test]
+    fn rejects_expiry_in_the_past() {
+        assert_eq!(
+            validate_offer(10, 5, Some(10)),
+            Err(OfferError::ExpiryNotInFuture)
+        );
+        assert_eq!(validate_offer(10, 5, Some(11)), Ok(()));
+        assert_eq!(validate_offer(10, 5, None), Ok(()));
+    }
+
+    #[test]
+    fn offers_without_expiry_stay_open() {
+        assert!(is_open(u64::MAX, None));
+        assert!(is_open(10, Some(11)));
+        assert!(!is_open(11, Some(11)));
+    }
+
+    #[test]
+    fn counters_must_ask_for_more_before_expiring() {
+        assert_eq!(
+            validate_counter(10, 5, 5, 20),
+            Err(OfferError::CounterNotAboveOffer)
+        );
+        assert_eq!(
+            validate_counter(10, 5, 6, 10),
+            Err(OfferError::ExpiryNotInFuture)
+        );
+        assert_eq!(validate_counter(10, 5, 6, 11), Ok(()));
+    }
+
+    #[test]
+    fn accepting_a_counter_takes_the_difference() {
+        assert_eq!(counter_top_up(10, 5, 8, 11, 3), Ok(3));
+        assert_eq!(counter_top_up(10, 5, 8, 11, 4), Ok(3));
+        assert_eq!(
+            counter_top_up(10, 5, 8, 11, 2),
+            Err(OfferError::TopUpTooLow)
+        );
+        assert_eq!(
+            counter_top_up(11, 5, 8, 11, 3),
+            Err(OfferError::CounterExpired)
+        );
+    }
+}
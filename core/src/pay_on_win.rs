@@ -0,0 +1,141 @@
+//! Rules of pay-on-win auctions
+//!
+//! Bidders only escrow a share of their bid. Once the auction is over, the winner has a grace
+//! period to pay the rest, otherwise their deposit is slashed and the sale falls through to the
+//! next highest bid.
+
+use std::fmt;
+
+use crate::Balance;
+
+/// Parameters of a pay-on-win auction, set by the seller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayOnWinRules {
+    /// Share of every bid, in basis points, escrowed when it is placed
+    pub deposit_bps: u16,
+    /// Time the winner has to pay the rest of their bid
+    pub payment_window: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayOnWinError {
+    DepositOutOfRange,
+    ZeroPaymentWindow,
+    PaymentTooLow,
+}
+
+impl fmt::Display for PayOnWinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::DepositOutOfRange => "pay-on-win deposit must be between 1 and 10000 bps",
+            Self::ZeroPaymentWindow => "pay-on-win payment window must be greater than 0",
+            Self::PaymentTooLow => "attached deposit does not cover the rest of the bid",
+        })
+    }
+}
+
+impl std::error::Error for PayOnWinError {}
+
+/// Checks the rules a seller lists a pay-on-win auction with
+pub fn validate_rules(rules: &PayOnWinRules) -> Result<(), PayOnWinError> {
+    if rules.deposit_bps == 0 || rules.deposit_bps > 10_000 {
+        return Err(PayOnWinError::DepositOutOfRange);
+    }
+    if rules.payment_window == 0 {
+        return Err(PayOnWinError::ZeroPaymentWindow);
+    }
+    Ok(())
+}
+
+/// The share of a bid of `amount` that has to be escrowed, rounded up
+pub fn required_deposit(rules: &PayOnWinRules, amount: Balance) -> Balance {
+    let bps = Balance::from(rules.deposit_bps);
+    let rest = amount % 10_000 * bps;
+    amount / 10_000 * bps + rest / 10_000 + Balance::from(rest % 10_000 != 0)
+}
+
+/// What the winner still owes on a bid of `amount` of which `escrowed` is already paid, checked
+/// against the `payment` they attach
+pub fn remaining_payment(
+    amount: Balance,
+    escrowed: Balance,
+    payment: Balance,
+) -> Result<Balance, PayOnWinError> {
+    let remaining = amount.saturating_sub(escrowed);
+    if payment < remaining {
+        return Err(PayOnWinError::PaymentTooLow);
+    }
+    Ok(remaining)
+}
+
+/// Splits the slashed `deposit` of a defaulting winner into the seller's and the treasury's
+/// share, the treasury taking `treasury_bps` of it rounded down
+pub fn split_slash(deposit: Balance, treasury_bps: u16) -> (Balance, Balance) {
+    let bps = Balance::from(treasury_bps.min(10_000));
+    let treasury = deposit / 10_000 * bps + deposit % 10_000 * bps / 10_000;
+    (deposit - treasury, treasury)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: PayOnWinRules = PayOnWinRules {
+        deposit_bps: 1_000,
+        payment_window: 100,
+    };
+
+    #[test]
+    fn deposit_is_rounded_up() {
+        assert_eq!(required_deposit(&RULES, 1_000), 100);
+        assert_eq!(required_deposit(&RULES, 1_001), 101);
+        assert_eq!(required_deposit(&RULES, 0), 0);
+    }
+
+    #[test]
+    fn deposit_does_not_overflow() {
+        let rules = PayOnWinRules {
+            deposit_bps: 10_000,
+            ..RULES
+        };
+        assert_eq!(required_deposit(&rules, Balance::MAX), Balance::MAX);
+    }
+
+    #[test]
+    fn rules_need_a_deposit_and_a_window() {
+        assert_eq!(validate_rules(&RULES), Ok(()));
+        let no_deposit = PayOnWinRules {
+            deposit_bps: 0,
+            ..RULES
+        };
+        assert_eq!(
+            validate_rules(&no_deposit),
+            Err(PayOnWinError::DepositOutOfRange)
+        );
+        let no_window = PayOnWinRules {
+            payment_window: 0,
+            ..RULES
+        };
+        assert_eq!(
+            validate_rules(&no_window),
+            Err(PayOnWinError::ZeroPaymentWindow)
+        );
+    }
+
+    #[test]
+    fn payment_covers_the_rest_of_the_bid() {
+        assert_eq!(remaining_payment(1_000, 100, 900), Ok(900));
+        assert_eq!(
+            remaining_payment(1_000, 100, 899),
+            Err(PayOnWinError::PaymentTooLow)
+        );
+    }
+
+    #[test]
+    fn slash_is_split_without_losing_dust() {
+        assert_eq!(split_slash(1_000, 2_500), (750, 250));
+        assert_eq!(split_slash(3, 5_000), (2, 1));
+        assert_eq!(split_slash(1_000, 0), (1_000, 0));
+        assert_eq!(split_slash(Balance::MAX, 10_000), (0, Balance::MAX));
+    }
+}
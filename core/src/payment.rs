@@ -0,0 +1,79 @@
+//! Rules for the tokens auctions take bids in, each with its own bid unit and fee
+
+use std::fmt;
+
+use crate::Balance;
+
+/// Highest fee a payment token can charge on a sale, in basis points
+pub const MAX_TOKEN_FEE_BPS: u16 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentError {
+    ZeroUnit,
+    FeeTooHigh,
+    OffUnit,
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ZeroUnit => "`min_bid_unit` must be greater than 0",
+            Self::FeeTooHigh => "payment token fees can be at most 1000 basis points",
+            Self::OffUnit => "amount must be a multiple of the token's `min_bid_unit`",
+        })
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+/// Checks the bid unit and fee a payment token is registered with
+pub fn validate_token(min_bid_unit: Balance, fee_bps: u16) -> Result<(), PaymentError> {
+    if min_bid_unit == 0 {
+        return Err(PaymentError::ZeroUnit);
+    }
+    if fee_bps > MAX_TOKEN_FEE_BPS {
+        return Err(PaymentError::FeeTooHigh);
+    }
+    Ok(())
+}
+
+/// Checks that `amount` is a whole number of `min_bid_unit`
+pub fn check_unit(amount: Balance, min_bid_unit: Balance) -> Result<(), PaymentError> {
+    if min_bid_unit == 0 || amount % min_bid_unit != 0 {
+        return Err(PaymentError::OffUnit);
+    }
+    Ok(())
+}
+
+/// The fee of `fee_bps` on a sale for `price`, rounded down
+pub fn token_fee(price: Balance, fee_bps: u16) -> Balance {
+    let bps = Balance::from(fee_bps);
+    price / 10_000 * bps + price % 10_000 * bps / 10_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_validated() {
+        assert_eq!(validate_token(1, MAX_TOKEN_FEE_BPS), Ok(()));
+        assert_eq!(validate_token(0, 0), Err(PaymentError::ZeroUnit));
+        assert_eq!(
+            validate_token(1, MAX_TOKEN_FEE_BPS + 1),
+            Err(PaymentError::FeeTooHigh)
+        );
+    }
+
+    #[test]
+    fn amounts_come_in_whole_units() {
+        assert_eq!(check_unit(3_000, 1_000), Ok(()));
+        assert_eq!(check_unit(3_500, 1_000), Err(PaymentError::OffUnit));
+    }
+
+    #[test]
+    fn fee_is_rounded_down() {
+        assert_eq!(token_fee(1_999, 250), 49);
+        assert_eq!(token_fee(u128::MAX, 0), 0);
+    }
+}
@@ -0,0 +1,177 @@
+//! Rules of penny auctions
+//!
+//! Every bid costs a non-refundable fee, raises the price by a fixed increment and resets a short
+//! countdown, so the auction only ends once nobody is willing to pay for another bid.
+
+use std::fmt;
+
+use crate::{
+    bid::{BidContext, BidError},
+    listing::is_expired,
+    Balance, Timestamp,
+};
+
+/// Parameters of a penny auction, set by the seller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PennyRules {
+    pub bid_fee: Balance,
+    pub increment: Balance,
+    /// Time left on the clock after every bid
+    pub countdown: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PennyRulesError {
+    ZeroIncrement,
+    ZeroCountdown,
+}
+
+impl fmt::Display for PennyRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ZeroIncrement => "penny auction increment must be greater than 0",
+            Self::ZeroCountdown => "penny auction countdown must be greater than 0",
+        })
+    }
+}
+
+impl std::error::Error for PennyRulesError {}
+
+/// Checks the rules a seller lists a penny auction with
+pub fn validate_rules(rules: &PennyRules) -> Result<(), PennyRulesError> {
+    if rules.increment == 0 {
+        return Err(PennyRulesError::ZeroIncrement);
+    }
+    if rules.countdown == 0 {
+        return Err(PennyRulesError::ZeroCountdown);
+    }
+    Ok(())
+}
+
+/// Outcome of an accepted penny bid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PennyBid {
+    /// The bidder's new bid, which is the auction's new price
+    pub amount: Balance,
+    /// Deposit taken from the bidder: the fee plus whatever their escrowed bid has to grow by
+    pub cost: Balance,
+    pub expiry: Timestamp,
+}
+
+/// Plans a bid on a penny auction in state `ctx` by a bidder whose outstanding bid is `current`
+/// (0 if they have none), last placed at `last_bid_at`, who attached `deposit`
+pub fn plan_bid(
+    rules: &PennyRules,
+    ctx: &BidContext,
+    current: Balance,
+    last_bid_at: Option<Timestamp>,
+    deposit: Balance,
+) -> Result<PennyBid, BidError> {
+    if is_expired(ctx.now, ctx.expiry) {
+        return Err(BidError::AuctionOver);
+    }
+    if current != 0 && current == ctx.h_bid {
+        return Err(BidError::AlreadyHighest);
+    }
+    if last_bid_at.is_some_and(|last_bid_at| ctx.now.saturating_sub(last_bid_at) < ctx.bid_interval)
+    {
+        return Err(BidError::TooSoon);
+    }
+    let amount = ctx.h_bid.saturating_add(rules.increment);
+    let cost = rules.bid_fee.saturating_add(amount - current.min(amount));
+    if deposit < cost {
+        return Err(BidError::DepositTooLow);
+    }
+    Ok(PennyBid {
+        amount,
+        cost,
+        expiry: extend_expiry(ctx.expiry, ctx.now, rules.countdown),
+    })
+}
+
+/// The expiry of an auction expiring at `expiry` once its clock is reset at `now` to `countdown`
+///
+/// The clock is only ever extended, never shortened.
+pub fn extend_expiry(expiry: Timestamp, now: Timestamp, countdown: u64) -> Timestamp {
+    expiry.max(now.saturating_add(countdown))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: PennyRules = PennyRules {
+        bid_fee: 2,
+        increment: 1,
+        countdown: 100,
+    };
+
+    const CTX: BidContext = BidContext {
+        h_bid: 10,
+        expiry: 1_000,
+        bid_interval: 0,
+        now: 950,
+    };
+
+    #[test]
+    fn rules_need_increment_and_countdown() {
+        assert_eq!(validate_rules(&RULES), Ok(()));
+        let rules = PennyRules {
+            increment: 0,
+            ..RULES
+        };
+        assert_eq!(validate_rules(&rules), Err(PennyRulesError::ZeroIncrement));
+        let rules = PennyRules {
+            countdown: 0,
+            ..RULES
+        };
+        assert_eq!(validate_rules(&rules), Err(PennyRulesError::ZeroCountdown));
+    }
+
+    #[test]
+    fn first_bid_raises_price_and_resets_countdown() {
+        assert_eq!(
+            plan_bid(&RULES, &CTX, 0, None, 13),
+            Ok(PennyBid {
+                amount: 11,
+                cost: 13,
+                expiry: 1_050,
+            })
+        );
+    }
+
+    #[test]
+    fn repeat_bid_only_costs_fee_and_difference() {
+        let ctx = BidContext { h_bid: 12, ..CTX };
+        assert_eq!(
+            plan_bid(&RULES, &ctx, 11, Some(900), 4),
+            Ok(PennyBid {
+                amount: 13,
+                cost: 4,
+                expiry: 1_050,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_highest_bidder_bidding_again() {
+        assert_eq!(
+            plan_bid(&RULES, &CTX, 10, Some(900), 100),
+            Err(BidError::AlreadyHighest)
+        );
+    }
+
+    #[test]
+    fn rejects_uncovered_bid() {
+        assert_eq!(
+            plan_bid(&RULES, &CTX, 0, None, 12),
+            Err(BidError::DepositTooLow)
+        );
+    }
+
+    #[test]
+    fn countdown_never_shortens_expiry() {
+        assert_eq!(extend_expiry(1_000, 500, 100), 1_000);
+        assert_eq!(extend_expiry(1_000, 950, 100), 1_050);
+    }
+}
@@ -0,0 +1,32 @@
+//! Backing off from retrying payouts that keep failing
+
+use crate::Timestamp;
+
+/// When a payout retried at `now`, after `retries` earlier retries, may be retried again
+///
+/// The first retry has to wait `base_delay`, and every retry after it twice as long as the one
+/// before.
+pub fn next_retry_at(now: Timestamp, retries: u32, base_delay: u64) -> Timestamp {
+    let delay = 2u64
+        .checked_pow(retries)
+        .map_or(u64::MAX, |factor| base_delay.saturating_mul(factor));
+    now.saturating_add(delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_with_every_retry() {
+        assert_eq!(next_retry_at(100, 0, 10), 110);
+        assert_eq!(next_retry_at(100, 1, 10), 120);
+        assert_eq!(next_retry_at(100, 3, 10), 180);
+    }
+
+    #[test]
+    fn delay_saturates() {
+        assert_eq!(next_retry_at(100, 64, 10), u64::MAX);
+        assert_eq!(next_retry_at(100, 63, 10), u64::MAX);
+    }
+}
@@ -0,0 +1,107 @@
+//! Royalties on sales, split the way the NFT contract's `nft_payout` splits them
+//!
+//! A payout is queried once for a balance of [`BASIS`], which turns it into shares in basis
+//! points that can be cached and applied to any later sale of the collection.
+
+use std::fmt;
+
+use crate::{Balance, Timestamp};
+
+/// Balance payouts are queried for, so they come back in basis points
+pub const BASIS: Balance = 10_000;
+
+/// Most of a sale royalties can take, payouts asking for more are not honored
+pub const MAX_ROYALTY_BPS: u16 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoyaltyError {
+    TooHigh,
+}
+
+impl fmt::Display for RoyaltyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TooHigh => "royalties exceed the most a sale can pay",
+        })
+    }
+}
+
+impl std::error::Error for RoyaltyError {}
+
+/// Royalty shares in a `payout` of [`BASIS`], leaving out the `excluded` accounts, such as the
+/// token's owner, who get whatever royalties leave
+pub fn shares_from_payout<A: PartialEq>(
+    payout: Vec<(A, Balance)>,
+    excluded: &[A],
+) -> Result<Vec<(A, u16)>, RoyaltyError> {
+    let shares: Vec<(A, u16)> = payout
+        .into_iter()
+        .filter(|(account, share)| *share > 0 && !excluded.contains(account))
+        .map(|(account, share)| (account, share.min(BASIS) as u16))
+        .collect();
+    let total: u32 = shares.iter().map(|(_, share)| u32::from(*share)).sum();
+    if total > u32::from(MAX_ROYALTY_BPS) {
+        return Err(RoyaltyError::TooHigh);
+    }
+    Ok(shares)
+}
+
+/// Whether shares fetched at `fetched_at` can still be used at `now`
+pub fn is_fresh(fetched_at: Timestamp, ttl: Timestamp, now: Timestamp) -> bool {
+    now.saturating_sub(fetched_at) < ttl
+}
+
+/// The royalty each account of `shares` gets out of a sale for `amount`
+pub fn split<A: Clone>(amount: Balance, shares: &[(A, u16)]) -> Vec<(A, Balance)> {
+    shares
+        .iter()
+        .map(|(account, share)| {
+            (
+                account.clone(),
+                amount / BASIS * Balance::from(*share)
+                    + amount % BASIS * Balance::from(*share) / BASIS,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_is_left_out_of_the_shares() {
+        let payout = vec![("creator", 1_000), ("owner", 9_000), ("zero", 0)];
+        assert_eq!(
+            shares_from_payout(payout, &["owner"]),
+            Ok(vec![("creator", 1_000)])
+        );
+    }
+
+    #[test]
+    fn royalties_are_capped() {
+        let payout = vec![("a", 3_000), ("b", 3_000), ("owner", 4_000)];
+        assert_eq!(
+            shares_from_payout(payout, &["owner"]),
+            Err(RoyaltyError::TooHigh)
+        );
+    }
+
+    #[test]
+    fn split_follows_the_shares() {
+        assert_eq!(
+            split(1_000_001, &[("a", 1_000), ("b", 250)]),
+            vec![("a", 100_000), ("b", 25_000)]
+        );
+        assert_eq!(
+            split(u128::MAX, &[("a", 5_000)]),
+            vec![("a", u128::MAX / 2)]
+        );
+    }
+
+    #[test]
+    fn cached_shares_expire() {
+        assert!(is_fresh(10, 5, 14));
+        assert!(!is_fresh(10, 5, 15));
+    }
+}
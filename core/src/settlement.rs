@@ -0,0 +1,172 @@
+//! Planning what gets paid to whom when an auction ends
+
+use std::cmp::Reverse;
+
+use crate::Balance;
+
+/// A bid as seen at settlement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettlementBid<A> {
+    pub bidder: A,
+    pub amount: Balance,
+    /// Whether the bid was already refunded
+    pub paid: bool,
+    /// Order in which bids reached their current amount, lower is earlier
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Settlement<A> {
+    /// There are no outstanding bids, the NFT goes back to its owner
+    Unsold,
+    /// The NFT goes to `winner`, `price` goes to its owner and every other outstanding bid is
+    /// refunded
+    Sold {
+        winner: A,
+        price: Balance,
+        refunds: Vec<(A, Balance)>,
+    },
+}
+
+/// Plans the settlement of an auction with `bids`
+///
+/// The highest outstanding bid wins, the one with the lowest `sequence` winning among equal bids,
+/// so the outcome doesn't depend on the order `bids` are given in.
+pub fn plan_settlement<A: Clone>(
+    bids: impl IntoIterator<Item = SettlementBid<A>>,
+) -> Settlement<A> {
+    let outstanding: Vec<SettlementBid<A>> = bids.into_iter().filter(|bid| !bid.paid).collect();
+    let Some(winner_idx) = outstanding
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, bid)| (bid.amount, Reverse(bid.sequence)))
+        .map(|(idx, _)| idx)
+    else {
+        return Settlement::Unsold;
+    };
+    let winner = outstanding[winner_idx].clone();
+    let refunds = outstanding
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| *idx != winner_idx)
+        .map(|(_, bid)| (bid.bidder, bid.amount))
+        .collect();
+    Settlement::Sold {
+        winner: winner.bidder,
+        price: winner.amount,
+        refunds,
+    }
+}
+
+/// Plans the refunds of an auction with `bids` that its owner cancels, sharing the `slashed`
+/// stake equally among the outstanding bidders
+///
+/// What can't be shared equally goes to the earliest bid. Returns no refunds if there are no
+/// outstanding bids, in which case nothing should be slashed.
+pub fn plan_cancellation<A>(
+    bids: impl IntoIterator<Item = SettlementBid<A>>,
+    slashed: Balance,
+) -> Vec<(A, Balance)> {
+    let mut outstanding: Vec<SettlementBid<A>> = bids.into_iter().filter(|bid| !bid.paid).collect();
+    outstanding.sort_by_key(|bid| bid.sequence);
+    let Some(count) = Balance::try_from(outstanding.len())
+        .ok()
+        .filter(|count| *count > 0)
+    else {
+        return Vec::new();
+    };
+    let share = slashed / count;
+    let remainder = slashed % count;
+    outstanding
+        .into_iter()
+        .enumerate()
+        .map(|(idx, bid)| {
+            let extra = if idx == 0 { remainder } else { 0 };
+            (bid.bidder, bid.amount + share + extra)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(
+        bidder: &'static str,
+        amount: Balance,
+        paid: bool,
+        sequence: u64,
+    ) -> SettlementBid<&'static str> {
+        SettlementBid {
+            bidder,
+            amount,
+            paid,
+            sequence,
+        }
+    }
+
+    #[test]
+    fn no_bids_is_unsold() {
+        assert_eq!(plan_settlement::<&str>([]), Settlement::Unsold);
+    }
+
+    #[test]
+    fn only_paid_bids_is_unsold() {
+        assert_eq!(
+            plan_settlement([bid("alice", 5, true, 0)]),
+            Settlement::Unsold
+        );
+    }
+
+    #[test]
+    fn highest_bid_wins_and_others_are_refunded() {
+        assert_eq!(
+            plan_settlement([
+                bid("alice", 5, false, 0),
+                bid("bob", 7, false, 1),
+                bid("carol", 6, false, 2),
+                bid("dave", 9, true, 3),
+            ]),
+            Settlement::Sold {
+                winner: "bob",
+                price: 7,
+                refunds: vec![("alice", 5), ("carol", 6)],
+            }
+        );
+    }
+
+    #[test]
+    fn earliest_equal_bid_wins() {
+        assert_eq!(
+            plan_settlement([bid("bob", 7, false, 1), bid("alice", 7, false, 0)]),
+            Settlement::Sold {
+                winner: "alice",
+                price: 7,
+                refunds: vec![("bob", 7)],
+            }
+        );
+    }
+
+    #[test]
+    fn cancelling_shares_the_stake_among_bidders() {
+        assert_eq!(
+            plan_cancellation(
+                [
+                    bid("bob", 7, false, 1),
+                    bid("alice", 5, false, 0),
+                    bid("carol", 6, true, 2),
+                ],
+                11
+            ),
+            vec![("alice", 11), ("bob", 12)]
+        );
+    }
+
+    #[test]
+    fn cancelling_without_bids_refunds_nobody() {
+        assert_eq!(
+            plan_cancellation([bid("alice", 5, true, 0)], 10),
+            Vec::new()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Sharing marketplace fees among the stakers of a token
+//!
+//! Fees collected during an epoch are distributed once it ends, pro rata to what every account
+//! has staked at that point. Distributing only raises the reward per staked token, so it costs
+//! the same no matter how many accounts staked, and every account settles its own rewards
+//! whenever its stake changes or it claims.
+
+use std::fmt;
+
+use crate::{volume::DAY, Balance, Timestamp};
+
+/// One day, which is also how long a stake is locked for
+pub const EPOCH: Timestamp = DAY;
+
+/// Scale of the reward per staked token
+pub const PRECISION: Balance = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakingError {
+    NothingStaked,
+    NotEnoughStaked,
+    Locked,
+}
+
+impl fmt::Display for StakingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NothingStaked => "amount staked must be positive",
+            Self::NotEnoughStaked => "cannot unstake more than is staked",
+            Self::Locked => "stake is locked for an epoch after it was last added to",
+        })
+    }
+}
+
+impl std::error::Error for StakingError {}
+
+/// Everything staked and the fees shared among it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pool {
+    pub staked: Balance,
+    /// Rewards distributed per staked token so far, scaled by [`PRECISION`]
+    pub reward_per_token: Balance,
+    /// Fees collected since the last distribution
+    pub undistributed: Balance,
+}
+
+impl Pool {
+    /// Distributes the undistributed fees to what is staked, returning how much was distributed
+    ///
+    /// What can't be distributed, rounding dust or everything if nothing is staked, carries over
+    /// to the next distribution.
+    pub fn distribute(&mut self) -> Balance {
+        if self.staked == 0 {
+            return 0;
+        }
+        let increase = self.undistributed.saturating_mul(PRECISION) / self.staked;
+        let distributed = increase.saturating_mul(self.staked) / PRECISION;
+        self.reward_per_token = self.reward_per_token.saturating_add(increase);
+        self.undistributed -= distributed;
+        distributed
+    }
+}
+
+/// What an account staked and the rewards it earned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub staked: Balance,
+    /// `reward_per_token` of the pool as of the last time the position settled
+    pub reward_per_token: Balance,
+    /// Rewards earned and not claimed yet
+    pub unclaimed: Balance,
+    /// The stake can't be taken out before then
+    pub unlocks_at: Timestamp,
+}
+
+impl Position {
+    /// Adds the rewards distributed since the position last settled
+    pub fn settle(&mut self, pool: &Pool) {
+        let increase = pool.reward_per_token - self.reward_per_token;
+        let earned = self.staked.saturating_mul(increase) / PRECISION;
+        self.unclaimed = self.unclaimed.saturating_add(earned);
+        self.reward_per_token = pool.reward_per_token;
+    }
+
+    /// Stakes `amount` more at `now`, locking the whole stake for an [`EPOCH`]
+    pub fn stake(
+        &mut self,
+        pool: &mut Pool,
+        amount: Balance,
+        now: Timestamp,
+    ) -> Result<(), StakingError> {
+        if amount == 0 {
+            return Err(StakingError::NothingStaked);
+        }
+        self.settle(pool);
+        self.staked += amount;
+        self.unlocks_at = now.saturating_add(EPOCH);
+        pool.staked += amount;
+        Ok(())
+    }
+
+    /// Takes `amount` out of the stake at `now`
+    pub fn unstake(
+        &mut self,
+        pool: &mut Pool,
+        amount: Balance,
+        now: Timestamp,
+    ) -> Result<(), StakingError> {
+        if amount == 0 {
+            return Err(StakingError::NothingStaked);
+        }
+        if amount > self.staked {
+            return Err(StakingError::NotEnoughStaked);
+        }
+        if now < self.unlocks_at {
+            return Err(StakingError::Locked);
+        }
+        self.settle(pool);
+        self.staked -= amount;
+        pool.staked -= amount;
+        Ok(())
+    }
+
+    /// Takes out every reward earned so far
+    pub fn claim(&mut self, pool: &Pool) -> Balance {
+        self.settle(pool);
+        std::mem::take(&mut self.unclaimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fees_are_shared_pro_rata() {
+        let mut pool = Pool::default();
+        let (mut a, mut b) = (Position::default(), Position::default());
+        a.stake(&mut pool, 1_000, 0).unwrap();
+        b.stake(&mut pool, 3_000, 0).unwrap();
+        pool.undistributed = 400;
+        assert_eq!(pool.distribute(), 400);
+        assert_eq!(a.claim(&pool), 100);
+        assert_eq!(b.claim(&pool), 300);
+        assert_eq!(a.claim(&pool), 0);
+    }
+
+    #[test]
+    fn late_stakes_miss_earlier_distributions() {
+        let mut pool = Pool::default();
+        let (mut a, mut b) = (Position::default(), Position::default());
+        a.stake(&mut pool, 1_000, 0).unwrap();
+        pool.undistributed = 100;
+        pool.distribute();
+        b.stake(&mut pool, 1_000, EPOCH).unwrap();
+        pool.undistributed = 100;
+        pool.distribute();
+        assert_eq!(a.claim(&pool), 150);
+        assert_eq!(b.claim(&pool), 50);
+    }
+
+    #[test]
+    fn undistributable_fees_carry_over() {
+        let mut pool = Pool {
+            undistributed: 10,
+            ..Pool::default()
+        };
+        assert_eq!(pool.distribute(), 0);
+        assert_eq!(pool.undistributed, 10);
+
+        let mut a = Position::default();
+        a.stake(&mut pool, 3, 0).unwrap();
+        let distributed = pool.distribute();
+        assert_eq!(distributed + pool.undistributed, 10);
+        assert_eq!(a.claim(&pool), distributed);
+    }
+
+    #[test]
+    fn stakes_are_locked_for_an_epoch() {
+        let mut pool = Pool::default();
+        let mut a = Position::default();
+        a.stake(&mut pool, 100, 0).unwrap();
+        assert_eq!(
+            a.unstake(&mut pool, 50, EPOCH - 1),
+            Err(StakingError::Locked)
+        );
+        assert_eq!(
+            a.unstake(&mut pool, 101, EPOCH),
+            Err(StakingError::NotEnoughStaked)
+        );
+        assert_eq!(a.unstake(&mut pool, 50, EPOCH), Ok(()));
+        assert_eq!((a.staked, pool.staked), (50, 50));
+    }
+}
@@ -0,0 +1,78 @@
+//! Times in seconds, as listings take them, and shown as dates the way people read them
+//!
+//! The contract keeps every time in nanoseconds, as block timestamps are, which are easy to get
+//! wrong by a factor of a billion when typed by hand. Durations are taken in seconds instead.
+
+use crate::Timestamp;
+
+pub const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// `secs` seconds in nanoseconds, `None` if that overflows
+pub fn secs_to_nanos(secs: u64) -> Option<u64> {
+    secs.checked_mul(NANOS_PER_SEC)
+}
+
+/// `secs` seconds in nanoseconds, `u64::MAX` if that overflows
+pub fn saturating_secs_to_nanos(secs: u64) -> u64 {
+    secs.saturating_mul(NANOS_PER_SEC)
+}
+
+/// `timestamp` as an ISO 8601 date and time in UTC, to the second
+pub fn format_iso8601(timestamp: Timestamp) -> String {
+    let secs = timestamp / NANOS_PER_SEC;
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Year, month and day of the date `days` after 1970-01-01, in the proleptic Gregorian calendar
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Counted from 0000-03-01, so that leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds_are_converted_to_nanoseconds() {
+        assert_eq!(secs_to_nanos(60), Some(60 * NANOS_PER_SEC));
+        assert_eq!(secs_to_nanos(u64::MAX / NANOS_PER_SEC + 1), None);
+        assert_eq!(saturating_secs_to_nanos(60), 60 * NANOS_PER_SEC);
+        assert_eq!(saturating_secs_to_nanos(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn timestamps_are_shown_as_utc_dates() {
+        assert_eq!(format_iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_iso8601(1_700_000_000 * NANOS_PER_SEC + 999_999_999),
+            "2023-11-14T22:13:20Z"
+        );
+        assert_eq!(
+            format_iso8601(951_825_599 * NANOS_PER_SEC),
+            "2000-02-29T11:59:59Z"
+        );
+        assert_eq!(format_iso8601(u64::MAX), "2554-07-21T23:34:33Z");
+    }
+}
@@ -0,0 +1,76 @@
+//! Which token ids NFTs can be listed and bid on under
+//!
+//! NEP-171 leaves token ids as free-form strings, but they end up in storage keys, event logs and
+//! views indexers read, so the contract only takes ones of a bounded length without whitespace or
+//! control characters.
+
+use std::fmt;
+
+/// Most bytes a token id can have
+pub const MAX_TOKEN_ID_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenIdError {
+    Empty,
+    TooLong,
+    /// The token id has whitespace or a control character in it
+    InvalidChar(char),
+}
+
+impl fmt::Display for TokenIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("token ids can't be empty"),
+            Self::TooLong => write!(f, "token ids can have at most {MAX_TOKEN_ID_LEN} bytes"),
+            Self::InvalidChar(c) => write!(f, "token ids can't contain {c:?}"),
+        }
+    }
+}
+
+impl std::error::Error for TokenIdError {}
+
+pub fn validate_token_id(token_id: &str) -> Result<(), TokenIdError> {
+    if token_id.is_empty() {
+        return Err(TokenIdError::Empty);
+    }
+    if token_id.len() > MAX_TOKEN_ID_LEN {
+        return Err(TokenIdError::TooLong);
+    }
+    if let Some(c) = token_id
+        .chars()
+        .find(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(TokenIdError::InvalidChar(c));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_ids_of_common_collections_are_valid() {
+        for token_id in ["1", "1234:56", "ft-lot-0", "d1f3a0c2-5e4b-4f1a", "ñandú"] {
+            assert_eq!(validate_token_id(token_id), Ok(()), "{token_id}");
+        }
+    }
+
+    #[test]
+    fn token_ids_are_bounded() {
+        assert_eq!(validate_token_id(""), Err(TokenIdError::Empty));
+        assert_eq!(validate_token_id(&"a".repeat(MAX_TOKEN_ID_LEN)), Ok(()));
+        assert_eq!(
+            validate_token_id(&"a".repeat(MAX_TOKEN_ID_LEN + 1)),
+            Err(TokenIdError::TooLong)
+        );
+        assert_eq!(
+            validate_token_id("one two"),
+            Err(TokenIdError::InvalidChar(' '))
+        );
+        assert_eq!(
+            validate_token_id("1\n"),
+            Err(TokenIdError::InvalidChar('\n'))
+        );
+    }
+}
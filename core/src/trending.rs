@@ -0,0 +1,112 @@
+//! Ranking auctions by how much bidding they recently saw
+//!
+//! Every bid adds to an auction's activity score, which halves every [`HALF_LIFE`]. Only a small
+//! board of the most active auctions is kept, updated as bids come in, so ranking never has to
+//! look at every auction.
+
+use crate::{Balance, Timestamp};
+
+/// One hour
+pub const HALF_LIFE: Timestamp = 60 * 60 * 1_000_000_000;
+
+/// Score a single bid adds
+pub const BID_WEIGHT: Balance = 1_000_000;
+
+/// Bidding activity of an auction, as of `at`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Activity {
+    pub score: Balance,
+    pub at: Timestamp,
+}
+
+impl Activity {
+    /// This activity decayed up to `now`
+    pub fn decayed(self, now: Timestamp) -> Self {
+        let elapsed = now.saturating_sub(self.at);
+        let halvings = elapsed / HALF_LIFE;
+        let score = self.score.checked_shr(halvings as u32).unwrap_or(0);
+        // Linear in between halvings
+        let partial = Balance::from(elapsed % HALF_LIFE);
+        let score = score - (score / 2).saturating_mul(partial) / Balance::from(HALF_LIFE);
+        Self { score, at: now }
+    }
+
+    /// This activity after a bid placed at `now`
+    pub fn record_bid(self, now: Timestamp) -> Self {
+        let decayed = self.decayed(now);
+        Self {
+            score: decayed.score.saturating_add(BID_WEIGHT),
+            ..decayed
+        }
+    }
+}
+
+/// Puts the current `activity` of `key` on the `board`, which keeps the `capacity` most active
+/// keys, most active first
+pub fn update_board<K: PartialEq>(
+    board: &mut Vec<(K, Activity)>,
+    key: K,
+    activity: Activity,
+    now: Timestamp,
+    capacity: usize,
+) {
+    board.retain(|(entry, _)| *entry != key);
+    board.push((key, activity));
+    rank(board, now);
+    board.truncate(capacity);
+}
+
+/// Decays every entry of the `board` up to `now` and sorts it, most active first
+pub fn rank<K>(board: &mut [(K, Activity)], now: Timestamp) {
+    for (_, activity) in board.iter_mut() {
+        *activity = activity.decayed(now);
+    }
+    board.sort_by(|(_, a), (_, b)| b.score.cmp(&a.score));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activity_halves_every_half_life() {
+        let activity = Activity {
+            score: 1_000,
+            at: 0,
+        };
+        assert_eq!(activity.decayed(HALF_LIFE).score, 500);
+        assert_eq!(activity.decayed(2 * HALF_LIFE).score, 250);
+        assert_eq!(activity.decayed(HALF_LIFE / 2).score, 750);
+        assert_eq!(activity.decayed(200 * HALF_LIFE).score, 0);
+    }
+
+    #[test]
+    fn bids_add_to_the_decayed_score() {
+        let activity = Activity::default().record_bid(0).record_bid(HALF_LIFE);
+        assert_eq!(
+            activity,
+            Activity {
+                score: BID_WEIGHT / 2 + BID_WEIGHT,
+                at: HALF_LIFE,
+            }
+        );
+    }
+
+    #[test]
+    fn board_keeps_the_most_active() {
+        let mut board = Vec::new();
+        let one = Activity::default().record_bid(0);
+        let two = one.record_bid(0);
+        update_board(&mut board, "a", one, 0, 2);
+        update_board(&mut board, "b", two, 0, 2);
+        update_board(&mut board, "c", one, 0, 2);
+        assert_eq!(board, vec![("b", two), ("a", one)]);
+
+        // An older bid counts for less than a fresh one
+        update_board(&mut board, "c", one.record_bid(HALF_LIFE), HALF_LIFE, 2);
+        assert_eq!(board[0].0, "c");
+        update_board(&mut board, "a", two.record_bid(HALF_LIFE), HALF_LIFE, 2);
+        let keys: Vec<_> = board.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec!["a", "c"]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Rules of uniform clearing-price auctions
+//!
+//! A lot of several units is sold at once. The highest bids win one unit each and every winner
+//! pays the same clearing price, which is the lowest winning bid.
+
+use std::cmp::Reverse;
+
+use crate::{settlement::SettlementBid, Balance};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformSettlement<A> {
+    /// Price every winner pays
+    pub clearing_price: Balance,
+    /// Winning bidders, highest bid first
+    pub winners: Vec<A>,
+    /// Overpayments of winners and full bids of everyone else
+    pub refunds: Vec<(A, Balance)>,
+}
+
+/// Outstanding `bids` ordered from the best to the worst, ties going to the lowest `sequence`
+fn ranked<A>(bids: impl IntoIterator<Item = SettlementBid<A>>) -> Vec<SettlementBid<A>> {
+    let mut outstanding: Vec<SettlementBid<A>> = bids.into_iter().filter(|bid| !bid.paid).collect();
+    outstanding.sort_by_key(|bid| (Reverse(bid.amount), bid.sequence));
+    outstanding
+}
+
+/// The amount a new bid has to exceed to win one of `supply` units, given the outstanding `bids`
+/// and the auction's `minimum_bid`
+pub fn entry_threshold<A>(
+    bids: impl IntoIterator<Item = SettlementBid<A>>,
+    supply: usize,
+    minimum_bid: Balance,
+) -> Balance {
+    let ranked = ranked(bids);
+    match supply.checked_sub(1).and_then(|last| ranked.get(last)) {
+        Some(lowest_winning) => lowest_winning.amount.max(minimum_bid),
+        None => minimum_bid,
+    }
+}
+
+/// Plans the settlement of an auction selling `supply` units to `bids`
+///
+/// Returns `None` if there are no outstanding bids.
+pub fn plan_settlement<A: Clone>(
+    bids: impl IntoIterator<Item = SettlementBid<A>>,
+    supply: usize,
+) -> Option<UniformSettlement<A>> {
+    let mut ranked = ranked(bids);
+    let losers = ranked.split_off(supply.min(ranked.len()));
+    let clearing_price = ranked.last()?.amount;
+    let mut winners = Vec::with_capacity(ranked.len());
+    let mut refunds = Vec::new();
+    for bid in ranked {
+        let overpayment = bid.amount - clearing_price;
+        if overpayment > 0 {
+            refunds.push((bid.bidder.clone(), overpayment));
+        }
+        winners.push(bid.bidder);
+    }
+    refunds.extend(losers.into_iter().map(|bid| (bid.bidder, bid.amount)));
+    Some(UniformSettlement {
+        clearing_price,
+        winners,
+        refunds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bids() -> Vec<SettlementBid<&'static str>> {
+        [
+            ("alice", 5),
+            ("bob", 9),
+            ("carol", 7),
+            ("dave", 7),
+            ("erin", 3),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(sequence, (bidder, amount))| SettlementBid {
+            bidder,
+            amount,
+            paid: false,
+            sequence: sequence as u64,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn winners_pay_lowest_winning_bid() {
+        assert_eq!(
+            plan_settlement(bids(), 3),
+            Some(UniformSettlement {
+                clearing_price: 7,
+                winners: vec!["bob", "carol", "dave"],
+                refunds: vec![("bob", 2), ("alice", 5), ("erin", 3)],
+            })
+        );
+    }
+
+    #[test]
+    fn every_bid_wins_when_supply_exceeds_bids() {
+        let settlement = plan_settlement(bids(), 10).unwrap();
+        assert_eq!(settlement.clearing_price, 3);
+        assert_eq!(settlement.winners.len(), 5);
+    }
+
+    #[test]
+    fn no_bids_is_unsold() {
+        assert_eq!(plan_settlement::<&str>([], 3), None);
+    }
+
+    #[test]
+    fn threshold_is_lowest_winning_bid_once_supply_is_taken() {
+        assert_eq!(entry_threshold(bids(), 3, 1), 7);
+        assert_eq!(entry_threshold(bids(), 6, 1), 1);
+        assert_eq!(entry_threshold(bids(), 3, 8), 8);
+    }
+}
@@ -0,0 +1,66 @@
+//! Token amounts in their smallest unit, shown in whole tokens the way wallets show them
+//!
+//! Tokens bridged from Ethereum keep their ERC-20 decimals, 18 for wETH and 6 for USDC, so the
+//! same raw amount means very different things from one token to the next.
+
+use std::fmt;
+
+use crate::Balance;
+
+/// Most decimals a token can have for its whole range of amounts to fit a [`Balance`]
+pub const MAX_DECIMALS: u8 = 38;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitsError {
+    TooManyDecimals,
+}
+
+impl fmt::Display for UnitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TooManyDecimals => "tokens can have at most 38 decimals",
+        })
+    }
+}
+
+impl std::error::Error for UnitsError {}
+
+pub fn validate_decimals(decimals: u8) -> Result<(), UnitsError> {
+    if decimals > MAX_DECIMALS {
+        return Err(UnitsError::TooManyDecimals);
+    }
+    Ok(())
+}
+
+/// `amount` in whole tokens of `decimals` decimals, without trailing zeros
+pub fn format_units(amount: Balance, decimals: u8) -> String {
+    let decimals = usize::from(decimals);
+    let digits = format!("{amount:0>width$}", width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{whole}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_are_shown_in_whole_tokens() {
+        assert_eq!(format_units(1_500_000_000_000_000_000, 18), "1.5");
+        assert_eq!(format_units(1, 18), "0.000000000000000001");
+        assert_eq!(format_units(25_000_000, 6), "25");
+        assert_eq!(format_units(0, 6), "0");
+        assert_eq!(format_units(7, 0), "7");
+    }
+
+    #[test]
+    fn decimals_are_bounded() {
+        assert_eq!(validate_decimals(18), Ok(()));
+        assert_eq!(validate_decimals(39), Err(UnitsError::TooManyDecimals));
+    }
+}
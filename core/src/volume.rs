@@ -0,0 +1,60 @@
+//! Sale volume over time, in buckets of a fixed period
+//!
+//! Only the most recent buckets are kept, so the history stays the same size no matter how long
+//! the marketplace runs.
+
+use crate::{Balance, Timestamp};
+
+/// One day
+pub const DAY: Timestamp = 24 * 60 * 60 * 1_000_000_000;
+
+/// One week
+pub const WEEK: Timestamp = 7 * DAY;
+
+/// Adds `amount` sold at `now` to the `buckets` of `period`, oldest first, keeping only the
+/// `keep` most recent ones
+///
+/// Buckets are keyed by the start of their period, periods without sales have no bucket.
+pub fn record(
+    buckets: &mut Vec<(Timestamp, Balance)>,
+    period: Timestamp,
+    keep: usize,
+    now: Timestamp,
+    amount: Balance,
+) {
+    let start = now - now % period;
+    match buckets.last_mut() {
+        Some((last, volume)) if *last == start => *volume = volume.saturating_add(amount),
+        _ => buckets.push((start, amount)),
+    }
+    // Drop buckets that fell out of the window
+    let oldest = start.saturating_sub(period.saturating_mul((keep as u64).saturating_sub(1)));
+    buckets.retain(|(bucket, _)| *bucket >= oldest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sales_add_up_within_a_period() {
+        let mut buckets = Vec::new();
+        record(&mut buckets, DAY, 7, 10, 5);
+        record(&mut buckets, DAY, 7, DAY - 1, 3);
+        record(&mut buckets, DAY, 7, DAY, 1);
+        assert_eq!(buckets, vec![(0, 8), (DAY, 1)]);
+    }
+
+    #[test]
+    fn only_recent_buckets_are_kept() {
+        let mut buckets = Vec::new();
+        record(&mut buckets, DAY, 3, 0, 1);
+        record(&mut buckets, DAY, 3, DAY, 2);
+        record(&mut buckets, DAY, 3, 2 * DAY, 3);
+        assert_eq!(buckets, vec![(0, 1), (DAY, 2), (2 * DAY, 3)]);
+
+        // A quiet period still pushes older buckets out
+        record(&mut buckets, DAY, 3, 4 * DAY, 4);
+        assert_eq!(buckets, vec![(2 * DAY, 3), (4 * DAY, 4)]);
+    }
+}
@@ -0,0 +1,109 @@
+//! Detecting the same parties trading an NFT back and forth to fake volume
+
+use crate::{Balance, Timestamp};
+
+/// Thresholds above which trades between the same two accounts are charged a quick-flip fee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WashTradeRules {
+    /// How far back trades are counted
+    pub window: u64,
+    /// Trades of an NFT between the same two accounts within `window` that are charged no fee
+    pub free_trades: u32,
+    /// Fee in basis points of the price, charged on every trade beyond `free_trades`
+    pub fee_bps: u16,
+}
+
+/// A past trade of an NFT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trade<A> {
+    pub seller: A,
+    pub buyer: A,
+    pub at: Timestamp,
+}
+
+/// Drops the `trades` that are too old to count at `now`
+pub fn prune<A>(trades: &mut Vec<Trade<A>>, rules: &WashTradeRules, now: Timestamp) {
+    trades.retain(|trade| now.saturating_sub(trade.at) < rules.window);
+}
+
+/// The quick-flip fee on a trade of `price` between `seller` and `buyer` at `now`, given the
+/// NFT's past `trades`
+pub fn quick_flip_fee<A: PartialEq>(
+    trades: &[Trade<A>],
+    rules: &WashTradeRules,
+    seller: &A,
+    buyer: &A,
+    now: Timestamp,
+    price: Balance,
+) -> Balance {
+    let between_them = trades
+        .iter()
+        .filter(|trade| now.saturating_sub(trade.at) < rules.window)
+        .filter(|trade| {
+            (trade.seller == *seller && trade.buyer == *buyer)
+                || (trade.seller == *buyer && trade.buyer == *seller)
+        })
+        .count();
+    if between_them < rules.free_trades as usize {
+        return 0;
+    }
+    price / 10_000 * Balance::from(rules.fee_bps)
+        + price % 10_000 * Balance::from(rules.fee_bps) / 10_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: WashTradeRules = WashTradeRules {
+        window: 100,
+        free_trades: 1,
+        fee_bps: 500,
+    };
+
+    fn trade(seller: &'static str, buyer: &'static str, at: Timestamp) -> Trade<&'static str> {
+        Trade { seller, buyer, at }
+    }
+
+    #[test]
+    fn first_trades_are_free() {
+        assert_eq!(quick_flip_fee(&[], &RULES, &"alice", &"bob", 10, 1000), 0);
+    }
+
+    #[test]
+    fn flipping_back_and_forth_is_charged() {
+        let trades = [trade("alice", "bob", 10)];
+        assert_eq!(
+            quick_flip_fee(&trades, &RULES, &"bob", &"alice", 50, 1000),
+            50
+        );
+        assert_eq!(
+            quick_flip_fee(&trades, &RULES, &"bob", &"carol", 50, 1000),
+            0
+        );
+    }
+
+    #[test]
+    fn old_trades_stop_counting() {
+        let mut trades = vec![trade("alice", "bob", 10), trade("bob", "alice", 90)];
+        assert_eq!(
+            quick_flip_fee(&trades, &RULES, &"alice", &"bob", 110, 1000),
+            50
+        );
+        prune(&mut trades, &RULES, 110);
+        assert_eq!(trades, vec![trade("bob", "alice", 90)]);
+    }
+
+    #[test]
+    fn fees_do_not_overflow() {
+        let rules = WashTradeRules {
+            free_trades: 0,
+            fee_bps: 10_000,
+            ..RULES
+        };
+        assert_eq!(
+            quick_flip_fee(&[], &rules, &"alice", &"bob", 0, Balance::MAX),
+            Balance::MAX
+        );
+    }
+}
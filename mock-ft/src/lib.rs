@@ -0,0 +1,291 @@
+//! A NEP-141 fungible token contract for tests and local demos of the `nftauction` contract
+//!
+//! Anyone can mint on it, and anyone can change how it behaves, so the auction contract's payouts
+//! in fungible tokens can be run against transfers that fail, that succeed without moving the
+//! tokens, or that take a few blocks to go through.
+
+use near_contract_standards::{
+    fungible_token::{
+        metadata::{FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC},
+        FungibleToken, FungibleTokenCore, FungibleTokenResolver,
+    },
+    storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement},
+};
+use near_sdk::{
+    assert_one_yocto, env, json_types::U128, near, require, AccountId, BorshStorageKey, NearToken,
+    PanicOnDefault, PromiseOrValue,
+};
+
+/// What `ft_transfer` and `ft_transfer_call` do
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum TransferBehavior {
+    /// Transfer the tokens as the standard says
+    #[default]
+    Transfer,
+    /// Panic, as if the sender did not have the tokens
+    Fail,
+    /// Return successfully without moving the tokens
+    Ignore,
+}
+
+/// How the contract behaves, the standard way by default
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default, Debug)]
+pub struct Behavior {
+    #[serde(default)]
+    pub transfers: TransferBehavior,
+    /// Blocks `ft_transfer` waits before transferring
+    ///
+    /// Whether a delayed transfer goes ahead is decided once the delay is over.
+    #[serde(default)]
+    pub delay_blocks: u32,
+}
+
+#[near]
+#[derive(BorshStorageKey)]
+enum StorageKey {
+    Accounts,
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct Contract {
+    token: FungibleToken,
+    behavior: Behavior,
+}
+
+#[near]
+impl Contract {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            token: FungibleToken::new(StorageKey::Accounts),
+            behavior: Behavior::default(),
+        }
+    }
+
+    /// Mints `amount` to `account_id`, registering it if it is not
+    pub fn ft_mint(&mut self, account_id: AccountId, amount: U128) {
+        if !self.token.accounts.contains_key(&account_id) {
+            self.token.internal_register_account(&account_id);
+        }
+        self.token.internal_deposit(&account_id, amount.0);
+        self.token.total_supply += amount.0;
+    }
+
+    pub fn set_behavior(&mut self, behavior: Behavior) {
+        self.behavior = behavior;
+    }
+
+    pub fn behavior(&self) -> Behavior {
+        self.behavior.clone()
+    }
+
+    /// Transfers `amount` from `sender_id` as `ft_transfer` does, after `hops_left` more blocks
+    #[private]
+    pub fn delayed_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        hops_left: u32,
+    ) -> PromiseOrValue<()> {
+        if hops_left > 0 {
+            return Self::ext(env::current_account_id())
+                .delayed_transfer(sender_id, receiver_id, amount, memo, hops_left - 1)
+                .into();
+        }
+        if self.transfers() {
+            self.token
+                .internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+        }
+        PromiseOrValue::Value(())
+    }
+}
+
+impl Contract {
+    /// Whether a transfer should go ahead, panicking if it should fail
+    fn transfers(&self) -> bool {
+        require!(
+            self.behavior.transfers != TransferBehavior::Fail,
+            "transfers are set to fail"
+        );
+        self.behavior.transfers == TransferBehavior::Transfer
+    }
+}
+
+#[near]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        if self.behavior.delay_blocks > 0 {
+            assert_one_yocto();
+            Self::ext(env::current_account_id())
+                .delayed_transfer(
+                    env::predecessor_account_id(),
+                    receiver_id,
+                    amount,
+                    memo,
+                    self.behavior.delay_blocks - 1,
+                )
+                .as_return();
+            return;
+        }
+        if self.transfers() {
+            self.token.ft_transfer(receiver_id, amount, memo);
+        }
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        if !self.transfers() {
+            return PromiseOrValue::Value(U128(0));
+        }
+        self.token.ft_transfer_call(receiver_id, amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near]
+impl FungibleTokenResolver for Contract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        self.token
+            .ft_resolve_transfer(sender_id, receiver_id, amount)
+    }
+}
+
+#[near]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        self.token.storage_deposit(account_id, registration_only)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        self.token.storage_withdraw(amount)
+    }
+
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        self.token.storage_unregister(force)
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.token.storage_balance_of(account_id)
+    }
+}
+
+#[near]
+impl FungibleTokenMetadataProvider for Contract {
+    fn ft_metadata(&self) -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "Mock FT".to_string(),
+            symbol: "MOCK".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::{test_utils::VMContextBuilder, testing_env};
+
+    use super::*;
+
+    fn owner() -> AccountId {
+        "owner.near".parse().unwrap()
+    }
+
+    fn alice() -> AccountId {
+        "alice.near".parse().unwrap()
+    }
+
+    fn minted() -> Contract {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(owner())
+            .build());
+        let mut contract = Contract::new();
+        contract.ft_mint(owner(), U128(100));
+        contract.ft_mint(alice(), U128(0));
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(owner())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract
+    }
+
+    #[test]
+    fn transfers_by_default() {
+        let mut contract = minted();
+        contract.ft_transfer(alice(), U128(40), None);
+        assert_eq!(contract.ft_balance_of(alice()), U128(40));
+        assert_eq!(contract.ft_balance_of(owner()), U128(60));
+    }
+
+    #[test]
+    fn ignored_transfers_keep_the_balances() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            transfers: TransferBehavior::Ignore,
+            ..Behavior::default()
+        });
+        contract.ft_transfer(alice(), U128(40), None);
+        assert_eq!(contract.ft_balance_of(alice()), U128(0));
+    }
+
+    #[test]
+    fn delayed_transfers_keep_the_balances_for_now() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            delay_blocks: 2,
+            ..Behavior::default()
+        });
+        contract.ft_transfer(alice(), U128(40), None);
+        assert_eq!(contract.ft_balance_of(alice()), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "transfers are set to fail")]
+    fn failing_transfers_panic() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            transfers: TransferBehavior::Fail,
+            ..Behavior::default()
+        });
+        contract.ft_transfer(alice(), U128(40), None);
+    }
+}
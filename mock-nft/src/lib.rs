@@ -0,0 +1,504 @@
+//! A NEP-171 NFT contract for tests and local demos of the `nftauction` contract
+//!
+//! Anyone can mint on it, and anyone can change how it behaves, so the auction contract's
+//! cross-contract calls can be run against the ways real NFT contracts break, such as transfers
+//! that fail or that succeed without moving the token, approvals that fail, or either of them
+//! taking a few blocks to go through. Anyone can set the royalties its NEP-199 payouts pay as
+//! well.
+
+use near_contract_standards::non_fungible_token::{
+    approval::{ext_nft_approval_receiver, NonFungibleTokenApproval},
+    core::{NonFungibleTokenCore, NonFungibleTokenResolver},
+    metadata::{
+        NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
+    },
+    NonFungibleToken, Token, TokenId,
+};
+use near_sdk::{
+    assert_one_yocto, env, json_types::U128, near, require, AccountId, BorshStorageKey,
+    PanicOnDefault, Promise, PromiseOrValue,
+};
+use std::collections::HashMap;
+
+/// What `nft_transfer` and `nft_transfer_call` do
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum TransferBehavior {
+    /// Transfer the token as the standard says
+    #[default]
+    Transfer,
+    /// Panic, as if the caller was not allowed to transfer it
+    Fail,
+    /// Return successfully without moving the token
+    Ignore,
+}
+
+/// What `nft_approve` does
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum ApprovalBehavior {
+    /// Approve the account as the standard says
+    #[default]
+    Approve,
+    /// Panic, as if the caller was not allowed to approve it
+    Fail,
+}
+
+/// How the contract behaves, the standard way by default
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default, Debug)]
+pub struct Behavior {
+    #[serde(default)]
+    pub transfers: TransferBehavior,
+    #[serde(default)]
+    pub approvals: ApprovalBehavior,
+    /// Blocks `nft_transfer` waits before transferring, and `nft_approve` before calling
+    /// `nft_on_approve` on the approved account
+    ///
+    /// Whether a delayed transfer goes ahead is decided once the delay is over.
+    #[serde(default)]
+    pub delay_blocks: u32,
+}
+
+/// A NEP-199 payout, what each account gets out of a sale
+#[near(serializers = [json])]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+#[near]
+#[derive(BorshStorageKey)]
+enum StorageKey {
+    Owners,
+    Metadata,
+    Enumeration,
+    Approvals,
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct Contract {
+    tokens: NonFungibleToken,
+    metadata: NFTContractMetadata,
+    behavior: Behavior,
+    /// Royalty of each account in basis points of a sale, the owner getting the rest
+    royalties: HashMap<AccountId, u16>,
+}
+
+#[near]
+impl Contract {
+    /// Initializes the contract with `owner_id` owning the collection
+    #[init]
+    pub fn new_default_meta(owner_id: AccountId) -> Self {
+        Self {
+            tokens: NonFungibleToken::new(
+                StorageKey::Owners,
+                owner_id,
+                Some(StorageKey::Metadata),
+                Some(StorageKey::Enumeration),
+                Some(StorageKey::Approvals),
+            ),
+            metadata: NFTContractMetadata {
+                spec: NFT_METADATA_SPEC.to_string(),
+                name: "Mock NFT".to_string(),
+                symbol: "MOCK".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+            behavior: Behavior::default(),
+            royalties: HashMap::new(),
+        }
+    }
+
+    /// Mints `token_id` to `token_owner_id`, the attached deposit paying for its storage and the
+    /// rest being refunded
+    #[payable]
+    pub fn nft_mint(
+        &mut self,
+        token_id: TokenId,
+        token_owner_id: AccountId,
+        token_metadata: TokenMetadata,
+    ) -> Token {
+        self.tokens
+            .internal_mint(token_id, token_owner_id, Some(token_metadata))
+    }
+
+    pub fn set_behavior(&mut self, behavior: Behavior) {
+        self.behavior = behavior;
+    }
+
+    pub fn behavior(&self) -> Behavior {
+        self.behavior.clone()
+    }
+
+    /// Sets the royalty each account gets out of every sale, in basis points
+    pub fn set_royalties(&mut self, royalties: HashMap<AccountId, u16>) {
+        require!(
+            royalties.values().map(|bps| u32::from(*bps)).sum::<u32>() <= 10_000,
+            "royalties can add up to 10000 basis points at most"
+        );
+        self.royalties = royalties;
+    }
+
+    /// How a sale of `token_id` for `balance` is split among its royalties and its owner
+    pub fn nft_payout(
+        &self,
+        token_id: TokenId,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let Some(owner_id) = self.tokens.owner_by_id.get(&token_id) else {
+            env::panic_str("token not found")
+        };
+        let mut payout: HashMap<AccountId, U128> = self
+            .royalties
+            .iter()
+            .map(|(account_id, bps)| {
+                let bps = u128::from(*bps);
+                let royalty = balance.0 / 10_000 * bps + balance.0 % 10_000 * bps / 10_000;
+                (account_id.clone(), U128(royalty))
+            })
+            .collect();
+        let royalties: u128 = payout.values().map(|royalty| royalty.0).sum();
+        let rest = payout.entry(owner_id).or_insert(U128(0));
+        rest.0 += balance.0 - royalties;
+        require!(
+            max_len_payout.is_none_or(|max_len| payout.len() <= max_len as usize),
+            "payout is longer than `max_len_payout`"
+        );
+        Payout { payout }
+    }
+
+    /// Transfers `token_id` as `nft_transfer` does, returning the payout of the sale for `balance`
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let payout = self.nft_payout(token_id.clone(), balance, max_len_payout);
+        self.nft_transfer(receiver_id, token_id, approval_id, memo);
+        payout
+    }
+
+    /// Transfers `token_id` for `sender_id` as `nft_transfer` does, after `hops_left` more blocks
+    #[private]
+    pub fn delayed_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        hops_left: u32,
+    ) -> PromiseOrValue<()> {
+        if hops_left > 0 {
+            return Self::ext(env::current_account_id())
+                .delayed_transfer(
+                    sender_id,
+                    receiver_id,
+                    token_id,
+                    approval_id,
+                    memo,
+                    hops_left - 1,
+                )
+                .into();
+        }
+        if self.transfers() {
+            self.tokens
+                .internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
+        }
+        PromiseOrValue::Value(())
+    }
+
+    /// Calls `nft_on_approve` on `account_id` for the approval `approval_id` of `token_id`, after
+    /// `hops_left` more blocks
+    #[private]
+    pub fn delayed_on_approve(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        account_id: AccountId,
+        approval_id: u64,
+        msg: String,
+        hops_left: u32,
+    ) -> Promise {
+        if hops_left > 0 {
+            return Self::ext(env::current_account_id()).delayed_on_approve(
+                token_id,
+                owner_id,
+                account_id,
+                approval_id,
+                msg,
+                hops_left - 1,
+            );
+        }
+        ext_nft_approval_receiver::ext(account_id).nft_on_approve(
+            token_id,
+            owner_id,
+            approval_id,
+            msg,
+        )
+    }
+}
+
+impl Contract {
+    /// Whether a transfer should go ahead, panicking if it should fail
+    fn transfers(&self) -> bool {
+        require!(
+            self.behavior.transfers != TransferBehavior::Fail,
+            "transfers are set to fail"
+        );
+        self.behavior.transfers == TransferBehavior::Transfer
+    }
+}
+
+#[near]
+impl NonFungibleTokenCore for Contract {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        if self.behavior.delay_blocks > 0 {
+            assert_one_yocto();
+            Self::ext(env::current_account_id())
+                .delayed_transfer(
+                    env::predecessor_account_id(),
+                    receiver_id,
+                    token_id,
+                    approval_id,
+                    memo,
+                    self.behavior.delay_blocks - 1,
+                )
+                .as_return();
+            return;
+        }
+        if self.transfers() {
+            self.tokens
+                .nft_transfer(receiver_id, token_id, approval_id, memo);
+        }
+    }
+
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        if !self.transfers() {
+            return PromiseOrValue::Value(false);
+        }
+        self.tokens
+            .nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.tokens.nft_token(token_id)
+    }
+}
+
+#[near]
+impl NonFungibleTokenResolver for Contract {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        self.tokens.nft_resolve_transfer(
+            previous_owner_id,
+            receiver_id,
+            token_id,
+            approved_account_ids,
+        )
+    }
+}
+
+#[near]
+impl NonFungibleTokenApproval for Contract {
+    #[payable]
+    fn nft_approve(
+        &mut self,
+        token_id: TokenId,
+        account_id: AccountId,
+        msg: Option<String>,
+    ) -> Option<Promise> {
+        require!(
+            self.behavior.approvals != ApprovalBehavior::Fail,
+            "approvals are set to fail"
+        );
+        let (Some(msg), 1..) = (msg.clone(), self.behavior.delay_blocks) else {
+            return self.tokens.nft_approve(token_id, account_id, msg);
+        };
+        self.tokens
+            .nft_approve(token_id.clone(), account_id.clone(), None);
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(&token_id)
+            .expect("token exists");
+        let approval_id = self
+            .tokens
+            .approvals_by_id
+            .as_ref()
+            .and_then(|approvals| approvals.get(&token_id))
+            .and_then(|approvals| approvals.get(&account_id).copied())
+            .expect("approval was recorded");
+        Some(Self::ext(env::current_account_id()).delayed_on_approve(
+            token_id,
+            owner_id,
+            account_id,
+            approval_id,
+            msg,
+            self.behavior.delay_blocks - 1,
+        ))
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
+        self.tokens.nft_revoke(token_id, account_id);
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, token_id: TokenId) {
+        self.tokens.nft_revoke_all(token_id);
+    }
+
+    fn nft_is_approved(
+        &self,
+        token_id: TokenId,
+        approved_account_id: AccountId,
+        approval_id: Option<u64>,
+    ) -> bool {
+        self.tokens
+            .nft_is_approved(token_id, approved_account_id, approval_id)
+    }
+}
+
+#[near]
+impl NonFungibleTokenMetadataProvider for Contract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::{test_utils::VMContextBuilder, testing_env, NearToken};
+
+    use super::*;
+
+    fn owner() -> AccountId {
+        "owner.near".parse().unwrap()
+    }
+
+    fn minted() -> Contract {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(owner())
+            .attached_deposit(NearToken::from_millinear(10))
+            .build());
+        let mut contract = Contract::new_default_meta(owner());
+        contract.nft_mint("first".into(), owner(), TokenMetadata::default());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(owner())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract
+    }
+
+    #[test]
+    fn transfers_by_default() {
+        let mut contract = minted();
+        contract.nft_transfer("alice.near".parse().unwrap(), "first".into(), None, None);
+        assert_eq!(
+            contract.nft_token("first".into()).unwrap().owner_id,
+            "alice.near"
+        );
+    }
+
+    #[test]
+    fn ignored_transfers_keep_the_owner() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            transfers: TransferBehavior::Ignore,
+            ..Behavior::default()
+        });
+        contract.nft_transfer("alice.near".parse().unwrap(), "first".into(), None, None);
+        assert_eq!(
+            contract.nft_token("first".into()).unwrap().owner_id,
+            owner()
+        );
+    }
+
+    #[test]
+    fn delayed_transfers_keep_the_owner_for_now() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            delay_blocks: 2,
+            ..Behavior::default()
+        });
+        contract.nft_transfer("alice.near".parse().unwrap(), "first".into(), None, None);
+        assert_eq!(
+            contract.nft_token("first".into()).unwrap().owner_id,
+            owner()
+        );
+    }
+
+    #[test]
+    fn payout_pays_the_owner_what_royalties_leave() {
+        let mut contract = minted();
+        contract.set_royalties(HashMap::from([
+            ("creator.near".parse().unwrap(), 250),
+            ("label.near".parse().unwrap(), 100),
+        ]));
+        let payout = contract
+            .nft_payout("first".into(), U128(1_999), Some(3))
+            .payout;
+        assert_eq!(
+            payout[&"creator.near".parse::<AccountId>().unwrap()],
+            U128(49)
+        );
+        assert_eq!(
+            payout[&"label.near".parse::<AccountId>().unwrap()],
+            U128(19)
+        );
+        assert_eq!(payout[&owner()], U128(1_931));
+    }
+
+    #[test]
+    #[should_panic(expected = "transfers are set to fail")]
+    fn failing_transfers_panic() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            transfers: TransferBehavior::Fail,
+            ..Behavior::default()
+        });
+        contract.nft_transfer("alice.near".parse().unwrap(), "first".into(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "approvals are set to fail")]
+    fn failing_approvals_panic() {
+        let mut contract = minted();
+        contract.set_behavior(Behavior {
+            approvals: ApprovalBehavior::Fail,
+            ..Behavior::default()
+        });
+        contract.nft_approve("first".into(), "alice.near".parse().unwrap(), None);
+    }
+}
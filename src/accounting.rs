@@ -0,0 +1,36 @@
+//! Running totals of the NEAR moving through the contract
+//!
+//! Totals only grow by amounts the contract actually took in, so one overflowing means the
+//! accounting is broken. That fails the call instead of wrapping or saturating silently.
+
+use near_sdk::{env, near, NearToken};
+
+use crate::*;
+
+/// Adds `amount` to `total`, failing the call if that overflows
+pub(crate) fn add(total: &mut NearToken, amount: NearToken) {
+    *total = total
+        .checked_add(amount)
+        .unwrap_or_else(|| env::panic_str("accounting total overflowed"));
+}
+
+/// Takes `amount` out of `total`, which never drops below zero
+pub(crate) fn sub(total: &mut NearToken, amount: NearToken) {
+    *total = total.saturating_sub(amount);
+}
+
+#[near]
+impl Contract {
+    pub fn accounting_totals(&self) -> AccountingTotalsView {
+        AccountingTotalsView {
+            volume: self.volume,
+            bid_fees: self.fees_accrued,
+            protocol_fees: self.protocol_fees_total,
+            nft_deposits_paid: self.nft_deposits_paid,
+            ft_storage_paid: self.ft_storage_paid,
+            bids_escrowed: self.bids_escrowed,
+            balances: self.balances_total,
+            burned: self.burned_total,
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Settings of the contract, changed by its owner
+//!
+//! The owner is the contract account itself until `set_owner` hands the role to another
+//! account, such as a Sputnik DAO that changes settings through function-call proposals. Every
+//! change is logged in a `parameter_set` event, for voters to audit.
+
+use near_sdk::{env, json_types::U64, near, require, serde_json::json, AccountId, Promise};
+
+use crate::*;
+
+#[near]
+impl Contract {
+    /// Hands the owner role to `owner_id`, such as a DAO
+    pub fn set_owner(&mut self, owner_id: AccountId) {
+        self.internal_assert_owner();
+        AuctionEvent::ParameterSet {
+            parameter: "owner".into(),
+            value: json!(owner_id),
+        }
+        .emit();
+        self.owner_id = Some(owner_id);
+    }
+
+    pub fn owner(&self) -> AccountId {
+        self.owner_id
+            .clone()
+            .unwrap_or_else(env::current_account_id)
+    }
+
+    /// Sets how long past expiry an auction that was never ended may be collected by `gc`, in
+    /// seconds
+    pub fn set_gc_staleness(&mut self, gc_staleness: U64) {
+        self.internal_assert_owner();
+        self.gc_staleness = time::saturating_secs_to_nanos(gc_staleness.into());
+        Self::internal_emit_parameter("gc_staleness", json!(gc_staleness));
+    }
+
+    pub fn set_wash_trade_rules(&mut self, wash_trade_rules: Option<WashTradeConfig>) {
+        self.internal_assert_owner();
+        Self::internal_emit_parameter("wash_trade_rules", json!(wash_trade_rules));
+        self.wash_trade_rules = wash_trade_rules;
+    }
+
+    /// Sends the protocol fees collected so far to `receiver_id`, such as a DAO treasury
+    ///
+    /// Has to be proposed as an action instead once there are admins.
+    pub fn withdraw_protocol_fees(&mut self, receiver_id: AccountId) -> Promise {
+        self.internal_assert_owner();
+        self.internal_assert_no_admins();
+        self.internal_withdraw_protocol_fees(receiver_id)
+    }
+
+    pub fn set_max_active_bids(&mut self, max_active_bids: Option<u32>) {
+        self.internal_assert_owner();
+        self.max_active_bids = max_active_bids;
+        Self::internal_emit_parameter("max_active_bids", json!(max_active_bids));
+    }
+
+    /// Caps bids in auctions taking NEAR at `max_bid`, unless they set a cap of their own
+    pub fn set_max_bid(&mut self, max_bid: Option<NearToken>) {
+        self.internal_assert_owner();
+        self.max_bid = max_bid;
+        Self::internal_emit_parameter("max_bid", json!(max_bid));
+    }
+
+    pub fn set_max_active_listings(&mut self, max_active_listings: Option<u32>) {
+        self.internal_assert_owner();
+        self.max_active_listings = max_active_listings;
+        Self::internal_emit_parameter("max_active_listings", json!(max_active_listings));
+    }
+
+    pub fn max_active_listings(&self) -> Option<u32> {
+        self.max_active_listings
+    }
+
+    /// Burns `fee` on every bid placed or raised in auctions of `kind`, or none if absent
+    pub fn set_bid_burn_fee(&mut self, kind: AuctionKindName, fee: Option<NearToken>) {
+        self.internal_assert_owner();
+        self.bid_burn_fees.retain(|(burned, _)| *burned != kind);
+        if let Some(fee) = fee.filter(|fee| !fee.is_zero()) {
+            self.bid_burn_fees.push((kind, fee));
+        }
+        Self::internal_emit_parameter("bid_burn_fees", json!(self.bid_burn_fees));
+    }
+
+    pub fn bid_burn_fees(&self) -> Vec<(AuctionKindName, NearToken)> {
+        self.bid_burn_fees.clone()
+    }
+
+    /// Has `burn` send the burned bid fees to `burn_account`, an account nobody holds keys of
+    pub fn set_burn_account(&mut self, burn_account: Option<AccountId>) {
+        self.internal_assert_owner();
+        Self::internal_emit_parameter("burn_account", json!(burn_account));
+        self.burn_account = burn_account;
+    }
+
+    /// Sends the bid fees charged so far to the burn account, callable by anyone
+    pub fn burn(&mut self) -> Promise {
+        let Some(burn_account) = self.burn_account.clone() else {
+            env::panic_str("no burn account is set, bid fees stay locked in the contract")
+        };
+        let amount = std::mem::replace(&mut self.burn_pending, NearToken::from_yoctonear(0));
+        require!(!amount.is_zero(), "there is nothing to burn");
+        AuctionEvent::FeesBurned {
+            burn_account: burn_account.clone(),
+            amount,
+        }
+        .emit();
+        Self::internal_pay(burn_account, amount)
+    }
+
+    pub fn set_listing_stake(&mut self, listing_stake: NearToken) {
+        self.internal_assert_owner();
+        self.listing_stake = listing_stake;
+        Self::internal_emit_parameter("listing_stake", json!(listing_stake));
+    }
+
+    pub fn set_slash_treasury_bps(&mut self, slash_treasury_bps: u16) {
+        self.internal_assert_owner();
+        require!(
+            slash_treasury_bps <= 10_000,
+            "slash treasury share must be at most 10000 bps"
+        );
+        self.slash_treasury_bps = slash_treasury_bps;
+        Self::internal_emit_parameter("slash_treasury_bps", json!(slash_treasury_bps));
+    }
+
+    /// Sets how long listings have to last at least, in seconds
+    pub fn set_min_timespan(&mut self, min_timespan: U64) {
+        self.internal_assert_owner();
+        self.min_timespan = time::saturating_secs_to_nanos(min_timespan.into());
+        Self::internal_emit_parameter("min_timespan", json!(min_timespan));
+    }
+
+    pub fn min_timespan(&self) -> U64 {
+        (self.min_timespan / time::NANOS_PER_SEC).into()
+    }
+
+    /// Grants the NFTs of `nft` terms of their own, or takes them back if `policy` is absent
+    pub fn set_collection_policy(&mut self, nft: AccountId, policy: Option<CollectionPolicy>) {
+        self.internal_assert_owner();
+        Self::internal_emit_parameter(&format!("collection_policy:{nft}"), json!(policy));
+        match policy {
+            Some(policy) => self.collection_policies.insert(nft, policy),
+            None => self.collection_policies.remove(&nft),
+        };
+    }
+
+    pub fn collection_policy(&self, nft: AccountId) -> Option<CollectionPolicy> {
+        self.collection_policies.get(&nft).cloned()
+    }
+
+    /// Turns listing auctions of `kind` on or off, auctions already listed are not affected
+    pub fn set_kind_enabled(&mut self, kind: AuctionKindName, enabled: bool) {
+        self.internal_assert_owner();
+        self.disabled_kinds.retain(|disabled| *disabled != kind);
+        if !enabled {
+            self.disabled_kinds.push(kind);
+        }
+        Self::internal_emit_parameter("disabled_kinds", json!(self.disabled_kinds));
+    }
+
+    pub fn disabled_kinds(&self) -> Vec<AuctionKindName> {
+        self.disabled_kinds.clone()
+    }
+
+    /// Has to be proposed as an action instead once there are admins
+    pub fn set_arbiter(&mut self, arbiter: Option<AccountId>) {
+        self.internal_assert_owner();
+        self.internal_assert_no_admins();
+        self.internal_set_arbiter(arbiter);
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_assert_owner(&self) {
+        require!(
+            env::predecessor_account_id() == self.owner(),
+            "only the owner can call this method"
+        );
+    }
+
+    pub(crate) fn internal_withdraw_protocol_fees(&mut self, receiver_id: AccountId) -> Promise {
+        let amount = std::mem::replace(&mut self.protocol_fees, NearToken::from_yoctonear(0));
+        AuctionEvent::ProtocolFeesWithdrawn {
+            receiver_id: receiver_id.clone(),
+            amount,
+        }
+        .emit();
+        Self::internal_pay(receiver_id, amount)
+    }
+
+    pub(crate) fn internal_set_arbiter(&mut self, arbiter: Option<AccountId>) {
+        Self::internal_emit_parameter("arbiter", json!(arbiter));
+        self.arbiter = arbiter;
+    }
+
+    pub(crate) fn internal_emit_parameter(parameter: &str, value: serde_json::Value) {
+        AuctionEvent::ParameterSet {
+            parameter: parameter.into(),
+            value,
+        }
+        .emit();
+    }
+}
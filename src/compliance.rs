@@ -0,0 +1,233 @@
+//! Checking bidders against a compliance registry, such as a KYC or credential contract, before
+//! accepting their large bids
+
+use near_sdk::{env, ext_contract, json_types::U64, near, serde_json::json, AccountId, Promise};
+
+use crate::*;
+
+/// The interface a compliance registry has to implement
+#[ext_contract(ext_compliance_registry)]
+pub trait ComplianceRegistry {
+    fn is_compliant(&self, account_id: AccountId) -> bool;
+}
+
+/// A bid held back until the registry answers
+#[near(serializers = [json])]
+pub enum CheckedBid {
+    Make {
+        amount: NearToken,
+        expires_at: Option<U64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+    Increase {
+        top_up: NearToken,
+    },
+    Penny,
+}
+
+#[near]
+impl Contract {
+    /// Has bids above `threshold` wait for `registry` to confirm the bidder is compliant, no bid
+    /// is checked unless both are set
+    ///
+    /// Bids paid in a token are all checked once both are set.
+    ///
+    /// Has to be proposed as an action instead once there are admins.
+    pub fn set_compliance(&mut self, registry: Option<AccountId>, threshold: Option<NearToken>) {
+        self.internal_assert_owner();
+        self.internal_assert_no_admins();
+        self.internal_set_compliance(registry, threshold);
+    }
+
+    pub fn compliance(&self) -> ComplianceView {
+        ComplianceView {
+            registry: self.compliance_registry.clone(),
+            threshold: self.compliance_threshold,
+        }
+    }
+
+    /// Places the checked bid if the registry found the bidder compliant, refunds the deposit
+    /// otherwise or if the bid can no longer be placed
+    #[private]
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_compliance_checked(
+        &mut self,
+        bidder: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        bid: CheckedBid,
+        deposit: NearToken,
+        use_balance: bool,
+        #[callback_result] compliant: Result<bool, PromiseError>,
+    ) -> bool {
+        let placed = match compliant {
+            Ok(true) => match bid {
+                CheckedBid::Make {
+                    amount,
+                    expires_at,
+                    memo,
+                } => self
+                    .internal_make_bid_with_memo(
+                        bidder.clone(),
+                        &NFTId::new(&nft, &token_id),
+                        amount,
+                        expires_at.map(u64::from),
+                        deposit,
+                        memo,
+                    )
+                    .map(|taken| deposit.saturating_sub(taken)),
+                CheckedBid::Increase { top_up } => self
+                    .internal_increase_bid(
+                        bidder.clone(),
+                        nft.clone(),
+                        token_id.clone(),
+                        top_up,
+                        None,
+                    )
+                    .map(|()| deposit.saturating_sub(top_up)),
+                CheckedBid::Penny => {
+                    self.internal_penny_bid(bidder.clone(), &NFTId::new(&nft, &token_id), deposit)
+                }
+            },
+            _ => Err(NOT_COMPLIANT.into()),
+        };
+        let placed = placed.map(|excess| ((), excess));
+        self.internal_settle_checked(bidder, nft, token_id, deposit, use_balance, placed)
+            .is_some()
+    }
+
+    /// Makes the checked purchase if the registry found the buyer compliant, returning the
+    /// `token_id` bought, refunds the deposit otherwise or if the purchase can no longer be made
+    #[private]
+    pub fn on_purchase_compliance_checked(
+        &mut self,
+        buyer: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        deposit: NearToken,
+        use_balance: bool,
+        #[callback_result] compliant: Result<bool, PromiseError>,
+    ) -> Option<TokenId> {
+        let bought = match compliant {
+            Ok(true) => self.internal_buy(buyer.clone(), nft.clone(), token_id.clone(), deposit),
+            _ => Err(NOT_COMPLIANT.into()),
+        };
+        self.internal_settle_checked(buyer, nft, token_id, deposit, use_balance, bought)
+    }
+}
+
+pub(crate) const NOT_COMPLIANT: &str = "bidder did not pass the compliance check";
+
+impl Contract {
+    pub(crate) fn internal_set_compliance(
+        &mut self,
+        registry: Option<AccountId>,
+        threshold: Option<NearToken>,
+    ) {
+        AuctionEvent::ParameterSet {
+            parameter: "compliance".into(),
+            value: json!({ "registry": registry, "threshold": threshold }),
+        }
+        .emit();
+        self.compliance_registry = registry;
+        self.compliance_threshold = threshold;
+    }
+
+    /// Returns what is left of the `deposit` of `bidder` once their checked bid was `placed`,
+    /// all of it if it was not, as in `make_bid`, returning what the bid returned if it was placed
+    fn internal_settle_checked<T>(
+        &mut self,
+        bidder: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        deposit: NearToken,
+        use_balance: bool,
+        placed: Result<(T, NearToken), String>,
+    ) -> Option<T> {
+        let (placed, refund, reason) = match placed {
+            Ok((placed, excess)) => (Some(placed), excess, None),
+            Err(reason) => (None, deposit, Some(reason)),
+        };
+        self.internal_return_funds(bidder.clone(), refund, use_balance);
+        if let Some(reason) = reason {
+            AuctionEvent::BidRejected {
+                nft,
+                token_id,
+                bidder,
+                reason,
+            }
+            .emit();
+        }
+        placed
+    }
+
+    /// The registry to check a bid of `amount` with, if it has to be checked
+    pub(crate) fn internal_compliance_registry(&self, amount: NearToken) -> Option<AccountId> {
+        let threshold = self.compliance_threshold?;
+        self.compliance_registry
+            .clone()
+            .filter(|_| amount > threshold)
+    }
+
+    /// The registry to check a bid paid in a token with, if compliance is on
+    ///
+    /// Token amounts can't be held against the threshold, which is in NEAR, so all of them are
+    /// checked.
+    pub(crate) fn internal_token_compliance_registry(&self) -> Option<AccountId> {
+        self.compliance_threshold?;
+        self.compliance_registry.clone()
+    }
+
+    /// The registry to check the next bid or purchase on the auction of `nft_id` with, if it has
+    /// to be checked because of the price it reaches
+    pub(crate) fn internal_price_compliance_registry(&self, nft_id: &NFTId) -> Option<AccountId> {
+        let price = self.auctions.get(nft_id)?.next_price()?;
+        self.internal_compliance_registry(price)
+    }
+
+    /// Asks `registry` whether `bidder` is compliant, then places their `bid` funded by `deposit`
+    pub(crate) fn internal_check_compliance(
+        registry: AccountId,
+        bidder: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        bid: CheckedBid,
+        deposit: NearToken,
+        use_balance: bool,
+    ) -> Promise {
+        ext_compliance_registry::ext(registry)
+            .is_compliant(bidder.clone())
+            .then(Self::ext(env::current_account_id()).on_compliance_checked(
+                bidder,
+                nft,
+                token_id,
+                bid,
+                deposit,
+                use_balance,
+            ))
+    }
+
+    /// Asks `registry` whether `buyer` is compliant, then makes their purchase funded by
+    /// `deposit`
+    pub(crate) fn internal_check_purchase_compliance(
+        registry: AccountId,
+        buyer: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        deposit: NearToken,
+        use_balance: bool,
+    ) -> Promise {
+        ext_compliance_registry::ext(registry)
+            .is_compliant(buyer.clone())
+            .then(
+                Self::ext(env::current_account_id()).on_purchase_compliance_checked(
+                    buyer,
+                    nft,
+                    token_id,
+                    deposit,
+                    use_balance,
+                ),
+            )
+    }
+}
@@ -0,0 +1,215 @@
+//! Proceeds held after settlement, which an arbiter can freeze and resolve while disputed, and
+//! which are released only once the NFTs reached their buyers
+
+use near_sdk::{env, json_types::U64, near, require};
+
+use crate::*;
+
+#[near]
+impl Contract {
+    /// Credits the seller with held proceeds once they are due, callable by anyone
+    pub fn release_proceeds(&mut self, escrow_id: U64) {
+        // Validations
+        let Some(held) = self.held_proceeds.get(&escrow_id.into()) else {
+            env::panic_str("there are no such held proceeds")
+        };
+        require!(!held.frozen, "proceeds are frozen by a dispute");
+        require!(
+            listing::is_expired(env::block_timestamp(), held.release_at),
+            "proceeds are not due yet"
+        );
+        require!(
+            held.undelivered.is_empty(),
+            "nfts have not reached their buyers yet"
+        );
+
+        // Operations
+        self.internal_release_proceeds(escrow_id.into());
+    }
+
+    /// Stops held proceeds from being released until the dispute is resolved
+    ///
+    /// Only the arbiter can freeze, and only within the auction's dispute window.
+    pub fn freeze_payout(&mut self, escrow_id: U64) {
+        // Validations
+        self.internal_assert_arbiter();
+        let Some(held) = self.held_proceeds.get_mut(&escrow_id.into()) else {
+            env::panic_str("there are no such held proceeds")
+        };
+        require!(!held.frozen, "proceeds are already frozen");
+        require!(
+            !listing::is_expired(env::block_timestamp(), held.dispute_until),
+            "dispute window is over"
+        );
+
+        // Operations
+        held.frozen = true;
+        AuctionEvent::PayoutFrozen { escrow_id }.emit();
+    }
+
+    /// Settles a dispute over frozen proceeds, refunding the buyers if `refund_buyers` is set,
+    /// paying the seller otherwise
+    ///
+    /// Only the arbiter can resolve. Buyers are refunded everything they paid, fees included, but
+    /// keep the NFTs they won.
+    pub fn resolve_dispute(&mut self, escrow_id: U64, refund_buyers: bool) {
+        // Validations
+        self.internal_assert_arbiter();
+        require!(
+            self.held_proceeds
+                .get(&escrow_id.into())
+                .is_some_and(|held| held.frozen),
+            "there are no such frozen proceeds"
+        );
+
+        // Operations
+        if refund_buyers {
+            let held = self
+                .held_proceeds
+                .remove(&escrow_id.into())
+                .expect("held proceeds exist");
+            self.internal_reputation(&held.seller).disputes_lost += 1;
+            accounting::sub(&mut self.proceeds_held, held.amount());
+            for payment in held.payments {
+                self.internal_credit(payment.buyer, payment.amount);
+            }
+        } else {
+            let buyers: Vec<AccountId> = self.held_proceeds[&escrow_id.into()]
+                .payments
+                .iter()
+                .map(|payment| payment.buyer.clone())
+                .collect();
+            for buyer in &buyers {
+                self.internal_reputation(buyer).disputes_lost += 1;
+            }
+            self.internal_release_proceeds(escrow_id.into());
+        }
+        AuctionEvent::DisputeResolved {
+            escrow_id,
+            refund_buyers,
+        }
+        .emit();
+    }
+
+    pub fn arbiter(&self) -> Option<AccountId> {
+        self.arbiter.clone()
+    }
+
+    pub fn get_held_proceeds(&self, escrow_id: U64) -> Option<HeldProceedsView> {
+        self.held_proceeds
+            .get(&escrow_id.into())
+            .map(|held| HeldProceedsView {
+                nft: held.nft.clone(),
+                token_id: held.token_id.clone(),
+                seller: held.seller.clone(),
+                payments: held.payments.clone(),
+                undelivered: held.undelivered.clone(),
+                release_at: held.release_at.into(),
+                dispute_until: held.dispute_until.into(),
+                frozen: held.frozen,
+            })
+    }
+}
+
+impl Contract {
+    fn internal_assert_arbiter(&self) {
+        require!(
+            self.arbiter
+                .as_ref()
+                .is_some_and(|arbiter| *arbiter == env::predecessor_account_id()),
+            "only the arbiter can do this"
+        );
+    }
+
+    /// Holds sale proceeds that would otherwise be credited to the seller right away, returning
+    /// their escrow id
+    pub(crate) fn internal_hold_proceeds(&mut self, held: HeldProceeds) -> u64 {
+        let escrow_id = self.next_escrow_id;
+        self.next_escrow_id += 1;
+        AuctionEvent::ProceedsHeld {
+            escrow_id: escrow_id.into(),
+            nft: held.nft.clone(),
+            token_id: held.token_id.clone(),
+            seller: held.seller.clone(),
+            amount: held.amount(),
+            release_at: held.release_at.into(),
+        }
+        .emit();
+        accounting::add(&mut self.proceeds_held, held.amount());
+        self.held_proceeds.insert(escrow_id, held);
+        escrow_id
+    }
+
+    /// Records that `buyer` got their NFT, releasing the proceeds if nothing else holds them
+    pub(crate) fn internal_delivered(&mut self, escrow_id: u64, buyer: &AccountId) {
+        // Resolved by the arbiter meanwhile
+        let Some(held) = self.held_proceeds.get_mut(&escrow_id) else {
+            return;
+        };
+        held.undelivered.retain(|undelivered| undelivered != buyer);
+        self.internal_release_if_due(escrow_id);
+    }
+
+    /// Refunds `buyer` everything they paid, as the NFT they paid for can no longer reach them
+    pub(crate) fn internal_refund_undelivered(&mut self, escrow_id: u64, buyer: &AccountId) {
+        // Resolved by the arbiter meanwhile
+        let Some(held) = self.held_proceeds.get_mut(&escrow_id) else {
+            return;
+        };
+        held.undelivered.retain(|undelivered| undelivered != buyer);
+        let refund = held
+            .payments
+            .iter()
+            .filter(|payment| payment.buyer == *buyer)
+            .fold(NearToken::from_yoctonear(0), |total, payment| {
+                total.saturating_add(payment.amount)
+            });
+        held.payments.retain(|payment| payment.buyer != *buyer);
+        accounting::sub(&mut self.proceeds_held, refund);
+        if held.payments.is_empty() {
+            self.held_proceeds.remove(&escrow_id);
+        } else {
+            self.internal_release_if_due(escrow_id);
+        }
+        self.internal_credit(buyer.clone(), refund);
+    }
+
+    /// Releases held proceeds once they are due and nothing else holds them
+    fn internal_release_if_due(&mut self, escrow_id: u64) {
+        let Some(held) = self.held_proceeds.get(&escrow_id) else {
+            return;
+        };
+        if !held.frozen
+            && held.undelivered.is_empty()
+            && listing::is_expired(env::block_timestamp(), held.release_at)
+        {
+            self.internal_release_proceeds(escrow_id);
+        }
+    }
+
+    /// Credits the seller with held proceeds, less what their fee policy takes
+    fn internal_release_proceeds(&mut self, escrow_id: u64) {
+        let held = self
+            .held_proceeds
+            .remove(&escrow_id)
+            .expect("held proceeds exist");
+        accounting::sub(&mut self.proceeds_held, held.amount());
+        let mut amount = NearToken::from_yoctonear(0);
+        for payment in &held.payments {
+            let left = self.internal_charge_fees(
+                &held.fee_policy,
+                &held.nft,
+                payment.amount,
+                payment.protocol_fee,
+            );
+            amount = amount.saturating_add(left);
+        }
+        self.internal_credit(held.seller.clone(), amount);
+        AuctionEvent::ProceedsReleased {
+            escrow_id: escrow_id.into(),
+            seller: held.seller,
+            amount,
+        }
+        .emit();
+    }
+}
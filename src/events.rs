@@ -0,0 +1,219 @@
+//! [NEP-297](https://github.com/near/NEPs/blob/master/neps/nep-0297.md) events emitted by the
+//! contract
+
+use near_sdk::{
+    json_types::{Base58CryptoHash, U128, U64},
+    near, AccountId, NearToken,
+};
+
+use crate::{OwedPayout, TokenId};
+
+#[near(event_json(standard = "nftauction"))]
+pub enum AuctionEvent {
+    /// `owner` put `token_id` of `nft` up for auction until `expiry`
+    #[event_version("1.0.0")]
+    AuctionStarted {
+        nft: AccountId,
+        token_id: TokenId,
+        owner: AccountId,
+        minimum_bid: NearToken,
+        expiry: U64,
+    },
+    /// `bidder` placed a bid of `amount`, replacing any earlier bid of theirs
+    #[event_version("1.0.0")]
+    BidPlaced {
+        nft: AccountId,
+        token_id: TokenId,
+        bidder: AccountId,
+        amount: NearToken,
+    },
+    /// The auction ended without a winner before it expired, its bids were refunded
+    #[event_version("1.0.0")]
+    AuctionCancelled {
+        nft: AccountId,
+        token_id: TokenId,
+        owner: AccountId,
+    },
+    /// The auction was settled, `winners` each paying `price`, or none if it had no bids
+    #[event_version("1.0.0")]
+    AuctionSettled {
+        nft: AccountId,
+        token_id: TokenId,
+        owner: AccountId,
+        winners: Vec<AccountId>,
+        price: NearToken,
+    },
+    /// A bid held back for a compliance check was not placed, its deposit was refunded
+    #[event_version("1.0.0")]
+    BidRejected {
+        nft: AccountId,
+        token_id: TokenId,
+        bidder: AccountId,
+        reason: String,
+    },
+    /// The owner changed the setting `parameter` to `value`
+    #[event_version("1.0.0")]
+    ParameterSet {
+        parameter: String,
+        value: serde_json::Value,
+    },
+    /// An admin proposed the privileged action of `action_id`
+    #[event_version("1.0.0")]
+    ActionProposed { action_id: U64, proposer: AccountId },
+    /// An admin confirmed the privileged action of `action_id`
+    #[event_version("1.0.0")]
+    ActionConfirmed { action_id: U64, admin: AccountId },
+    /// The privileged action of `action_id` got its last confirmation and ran
+    #[event_version("1.0.0")]
+    ActionExecuted { action_id: U64 },
+    /// The proposer of the privileged action of `action_id` dropped it
+    #[event_version("1.0.0")]
+    ActionRevoked { action_id: U64 },
+    /// Code of hash `code_hash` was deployed to the contract account, which migrates to it next
+    #[event_version("1.0.0")]
+    Upgraded { code_hash: Base58CryptoHash },
+    /// Bid fees were sent to the burn account
+    #[event_version("1.0.0")]
+    FeesBurned {
+        burn_account: AccountId,
+        amount: NearToken,
+    },
+    /// The owner sent the protocol fees collected so far to `receiver_id`
+    #[event_version("1.0.0")]
+    ProtocolFeesWithdrawn {
+        receiver_id: AccountId,
+        amount: NearToken,
+    },
+    /// An existing bid was topped up to `amount`
+    #[event_version("1.0.0")]
+    BidIncreased {
+        nft: AccountId,
+        token_id: TokenId,
+        bidder: AccountId,
+        top_up: NearToken,
+        amount: NearToken,
+    },
+    /// An outbid bid was reduced by `reduction` to `amount`, which was refunded
+    #[event_version("1.0.0")]
+    BidReduced {
+        nft: AccountId,
+        token_id: TokenId,
+        bidder: AccountId,
+        reduction: NearToken,
+        amount: NearToken,
+    },
+    /// `amount` of the fungible token `ft` was put up for auction under `lot_id`, the `token_id`
+    /// to bid on
+    #[event_version("1.0.0")]
+    FtLotListed {
+        ft: AccountId,
+        lot_id: TokenId,
+        owner: AccountId,
+        amount: U128,
+    },
+    /// Sale proceeds are held under `escrow_id` until `release_at`
+    #[event_version("1.0.0")]
+    ProceedsHeld {
+        escrow_id: U64,
+        nft: AccountId,
+        token_id: TokenId,
+        seller: AccountId,
+        amount: NearToken,
+        release_at: U64,
+    },
+    /// The arbiter froze held proceeds over a dispute
+    #[event_version("1.0.0")]
+    PayoutFrozen { escrow_id: U64 },
+    /// The arbiter resolved a dispute in favour of the buyers if `refund_buyers` is set, of the
+    /// seller otherwise
+    #[event_version("1.0.0")]
+    DisputeResolved { escrow_id: U64, refund_buyers: bool },
+    /// A trade of an NFT between accounts that recently traded it was charged a quick-flip fee
+    #[event_version("1.0.0")]
+    QuickFlipFeeCharged {
+        nft: AccountId,
+        token_id: TokenId,
+        seller: AccountId,
+        buyer: AccountId,
+        fee: NearToken,
+    },
+    /// The contract no longer owns the NFT an auction sells, or is no longer approved to transfer
+    /// it if the auction is escrowless, so the auction was cancelled and its bids refunded
+    #[event_version("1.0.0")]
+    EscrowLost {
+        nft: AccountId,
+        token_id: TokenId,
+        owner: AccountId,
+    },
+    /// A payout to `account_id` failed and was added to what the contract owes them
+    #[event_version("1.0.0")]
+    PayoutFailed {
+        account_id: AccountId,
+        payout: OwedPayout,
+    },
+    /// A pay-on-win auction is over and `winner` has until `due` to pay the rest of their bid
+    #[event_version("1.0.0")]
+    PaymentDue {
+        nft: AccountId,
+        token_id: TokenId,
+        winner: AccountId,
+        amount_due: NearToken,
+        due: U64,
+    },
+    /// The winner of a pay-on-win auction did not pay in time and their deposit was slashed
+    #[event_version("1.0.0")]
+    WinnerDefaulted {
+        nft: AccountId,
+        token_id: TokenId,
+        winner: AccountId,
+        slashed: NearToken,
+        seller_share: NearToken,
+        treasury_share: NearToken,
+    },
+    /// `account_id` staked `amount` of the stake token
+    #[event_version("1.0.0")]
+    Staked { account_id: AccountId, amount: U128 },
+    /// `account_id` took `amount` of the stake token out of their stake
+    #[event_version("1.0.0")]
+    Unstaked { account_id: AccountId, amount: U128 },
+    /// An epoch ended and `amount` of fees was shared among the `staked` tokens
+    #[event_version("1.0.0")]
+    RewardsDistributed { amount: NearToken, staked: U128 },
+    /// A staker claimed their rewards
+    #[event_version("1.0.0")]
+    RewardsClaimed {
+        account_id: AccountId,
+        amount: NearToken,
+    },
+    /// A moderator delisted an auction, returning what it sells to `seller` and refunding its bids
+    #[event_version("1.0.0")]
+    AuctionTakenDown {
+        nft: AccountId,
+        token_id: TokenId,
+        seller: AccountId,
+        moderator: AccountId,
+        reason: String,
+    },
+    /// A moderator barred `account_id` from listing, or lifted the bar
+    #[event_version("1.0.0")]
+    SellerBlacklisted {
+        account_id: AccountId,
+        blacklisted: bool,
+    },
+    /// `solver` placed a bid of `amount` for `signer_id` following their signed bid intent
+    #[event_version("1.0.0")]
+    IntentFulfilled {
+        intent_hash: Base58CryptoHash,
+        signer_id: AccountId,
+        solver: AccountId,
+        amount: NearToken,
+        solver_fee: NearToken,
+    },
+    /// Held proceeds were credited to the seller
+    #[event_version("1.0.0")]
+    ProceedsReleased {
+        escrow_id: U64,
+        seller: AccountId,
+        amount: NearToken,
+    },
+}
@@ -0,0 +1,95 @@
+//! Ongoing auctions by when they expire, see [`expiry`]
+//!
+//! Front-ends list the auctions ending soon with `auctions_ending_soon`, and keepers find the
+//! auctions they can settle with `auctions_ending_between` up to the current block. `gc` walks it
+//! from the oldest expiry to find stale auctions.
+
+use near_nft_auction_core::expiry;
+use near_sdk::{env, json_types::U64, near, store::IterableSet};
+
+use crate::*;
+
+#[near]
+impl Contract {
+    /// Ongoing auctions that have yet to expire, the soonest to expire first
+    pub fn auctions_ending_soon(&self, limit: Option<u32>) -> Vec<AuctionView> {
+        self.auctions_ending_between(env::block_timestamp().into(), U64(u64::MAX), None, limit)
+    }
+
+    /// Ongoing auctions expiring from `from` until before `to`, the soonest to expire first
+    ///
+    /// Auctions that expired but were not ended yet are still ongoing, so a range up to the
+    /// current block lists the auctions `end_auction` can settle, once any settlement delay is
+    /// over.
+    pub fn auctions_ending_between(
+        &self,
+        from: U64,
+        to: U64,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<AuctionView> {
+        self.internal_auctions_expiring_between(from.into(), to.into())
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|nft_id| {
+                self.internal_auction_view(
+                    self.auctions.get(&nft_id).expect("indexed auction exists"),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Ongoing auctions expiring from `from` until before `to`, the soonest to expire first
+    pub(crate) fn internal_auctions_expiring_between(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> impl Iterator<Item = NFTId> + '_ {
+        let buckets = self.expiry_buckets.get().as_deref().unwrap_or_default();
+        expiry::buckets_between(buckets, from, to)
+            .iter()
+            .flat_map(move |bucket| {
+                let mut ending: Vec<(u64, NFTId)> = self
+                    .auctions_by_expiry
+                    .get(bucket)
+                    .into_iter()
+                    .flatten()
+                    .filter(|(expiry, _)| (from..to).contains(expiry))
+                    .cloned()
+                    .collect();
+                ending.sort();
+                ending
+            })
+            .map(|(_, nft_id)| nft_id)
+    }
+
+    /// Adds the auction of `nft_id` to the index as expiring at `expiry`
+    pub(crate) fn internal_index_expiry(&mut self, nft_id: &NFTId, expiry: u64) {
+        let bucket = expiry::bucket_of(expiry);
+        let auctions = self
+            .auctions_by_expiry
+            .entry(bucket)
+            .or_insert_with(|| IterableSet::new(StorageKey::AuctionsExpiringIn { bucket }));
+        auctions.insert((expiry, nft_id.clone()));
+        let mut buckets = self.expiry_buckets.get().clone().unwrap_or_default();
+        expiry::add_bucket(&mut buckets, bucket);
+        self.expiry_buckets.set(Some(buckets));
+    }
+
+    /// Removes the auction of `nft_id`, expiring at `expiry`, from the index
+    pub(crate) fn internal_unindex_expiry(&mut self, nft_id: &NFTId, expiry: u64) {
+        let bucket = expiry::bucket_of(expiry);
+        let Some(auctions) = self.auctions_by_expiry.get_mut(&bucket) else {
+            return;
+        };
+        auctions.remove(&(expiry, nft_id.clone()));
+        if auctions.is_empty() {
+            self.auctions_by_expiry.remove(&bucket);
+            let mut buckets = self.expiry_buckets.get().clone().unwrap_or_default();
+            expiry::remove_bucket(&mut buckets, bucket);
+            self.expiry_buckets.set(Some(buckets));
+        }
+    }
+}
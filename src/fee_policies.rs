@@ -0,0 +1,103 @@
+//! Fee policies sales are settled under, see [`fees::FeePolicy`]
+//!
+//! The owner registers policies by name, each with its marketplace fee, whether royalties are
+//! paid and any referral or charity share. A listing picks one with `fee_policy` and keeps a copy,
+//! so changing or removing the policy later leaves ongoing auctions as they were. Listings that
+//! pick none pay royalties only, as do accepted offers.
+
+use near_nft_auction_core::fees::{self, Split};
+use near_sdk::{env, near, serde_json::json, AccountId, NearToken};
+
+use crate::*;
+
+#[near]
+impl Contract {
+    /// Lets listings pick `policy` by `name`, or stops letting new ones if `policy` is `None`
+    ///
+    /// Auctions already listed under the policy keep settling under it as it was.
+    pub fn set_fee_policy(&mut self, name: String, policy: Option<FeePolicyConfig>) {
+        self.internal_assert_owner();
+        Self::internal_emit_parameter(&format!("fee_policy:{name}"), json!(policy));
+        let Some(policy) = policy else {
+            self.fee_policies.remove(&name);
+            return;
+        };
+        policy
+            .policy()
+            .validate()
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        self.fee_policies.insert(name, policy);
+    }
+
+    /// Every policy listings can pick, by name
+    pub fn fee_policies(&self) -> Vec<(String, FeePolicyConfig)> {
+        self.fee_policies
+            .iter()
+            .map(|(name, policy)| (name.clone(), policy.clone()))
+            .collect()
+    }
+}
+
+impl Contract {
+    /// The policy `params` picks, `Err` if there is none by its name
+    pub(crate) fn internal_fee_policy(
+        &self,
+        params: &AuctionParams,
+    ) -> Result<FeePolicyConfig, String> {
+        match &params.fee_policy {
+            None => Ok(FeePolicyConfig::default()),
+            Some(name) => self
+                .fee_policies
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("there is no fee policy named `{name}`")),
+        }
+    }
+
+    /// How `policy` splits a sale for `price` of an NFT of `nft`, `surcharge` included in the
+    /// marketplace's part
+    pub(crate) fn internal_fee_split(
+        &self,
+        policy: &FeePolicyConfig,
+        nft: &AccountId,
+        price: NearToken,
+        surcharge: NearToken,
+    ) -> Split<AccountId> {
+        let royalty_shares = match self.royalty_splits.get(nft) {
+            Some(split) if self.royalties_enabled => split.shares.clone(),
+            _ => Vec::new(),
+        };
+        policy.policy().split(&fees::Sale {
+            price: price.as_yoctonear(),
+            surcharge: surcharge.as_yoctonear(),
+            royalty_shares: &royalty_shares,
+        })
+    }
+
+    /// Pays out what `policy` takes out of a sale for `price` of an NFT of `nft`, `surcharge`
+    /// going to the protocol fees along with the marketplace fee, returning what is left for the
+    /// seller
+    pub(crate) fn internal_charge_fees(
+        &mut self,
+        policy: &FeePolicyConfig,
+        nft: &AccountId,
+        price: NearToken,
+        surcharge: NearToken,
+    ) -> NearToken {
+        let split = self.internal_fee_split(policy, nft, price, surcharge);
+        if split.marketplace > 0 {
+            self.internal_collect_protocol_fee(NearToken::from_yoctonear(split.marketplace));
+        }
+        for (account_id, amount) in split
+            .royalties
+            .iter()
+            .chain(&split.referral)
+            .chain(&split.charity)
+        {
+            if *amount > 0 {
+                self.internal_credit(account_id.clone(), NearToken::from_yoctonear(*amount));
+            }
+        }
+        NearToken::from_yoctonear(split.seller)
+    }
+}
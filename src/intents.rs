@@ -0,0 +1,213 @@
+//! Bids placed by solvers on behalf of bidders, following a bid intent the bidder signed
+//!
+//! A bidder registers an ed25519 intent key and deposits NEAR into their balance once. They can
+//! then sign a [`BidIntent`], "bid on this NFT up to this much", off-chain as a NEP-413 message,
+//! as wallets sign them, and any solver can fulfill it with `fulfill_bid_intent`. The bid is
+//! escrowed from the bidder's balance, and the solver is paid the fee the intent offers out of it
+//! too. The intent framework the intent names, if any, is notified once it is settled.
+
+use near_nft_auction_core::intent::validate_intent;
+use near_sdk::{
+    borsh, env, ext_contract,
+    json_types::{Base58CryptoHash, Base64VecU8, U64},
+    near, require, serde_json, AccountId, CurveType, NearToken, PublicKey,
+};
+
+use crate::*;
+
+/// Prefixed to NEP-413 payloads before hashing, so they can't be mistaken for transactions
+const NEP413_TAG: u32 = (1 << 31) + 413;
+
+/// What a bidder signs to have a solver bid for them, as the message of a [`SignedBidIntent`]
+#[near(serializers = [json])]
+#[derive(Clone)]
+pub struct BidIntent {
+    pub signer_id: AccountId,
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    /// Most the solver can bid
+    pub max_amount: NearToken,
+    /// Paid to the solver out of the signer's balance once the bid is placed
+    pub solver_fee: NearToken,
+    /// Has to exceed the nonce of every intent of the signer fulfilled before
+    pub nonce: U64,
+    pub deadline: U64,
+    /// Intent framework notified with `on_bid_intent_settled` once the bid is placed
+    pub settle_to: Option<AccountId>,
+}
+
+/// A [`BidIntent`] signed as a NEP-413 message
+#[near(serializers = [json])]
+pub struct SignedBidIntent {
+    /// The [`BidIntent`] as JSON
+    pub message: String,
+    /// 32 bytes the wallet signed along, replays are prevented by the nonce of the intent itself
+    pub nonce: Base64VecU8,
+    /// Has to be this contract, so the intent can't be replayed on another deployment
+    pub recipient: String,
+    pub callback_url: Option<String>,
+    /// ed25519 signature of the NEP-413 payload by the signer's intent key
+    pub signature: Base64VecU8,
+}
+
+/// The payload NEP-413 signatures are over, borsh-serialized after [`NEP413_TAG`]
+#[near(serializers = [borsh])]
+struct Nep413Payload {
+    message: String,
+    nonce: [u8; 32],
+    recipient: String,
+    callback_url: Option<String>,
+}
+
+/// Implemented by intent frameworks that want to learn which of their intents were settled
+#[ext_contract(ext_intent_framework)]
+pub trait IntentFramework {
+    fn on_bid_intent_settled(&mut self, receipt: IntentReceipt);
+}
+
+#[near]
+impl Contract {
+    /// Sets the key the caller signs bid intents with, or removes it so no intent of theirs can
+    /// be fulfilled
+    pub fn set_intent_key(&mut self, public_key: Option<PublicKey>) {
+        let account_id = env::predecessor_account_id();
+        match public_key {
+            Some(public_key) => {
+                require!(
+                    public_key.curve_type() == CurveType::ED25519,
+                    "intent keys have to be ed25519"
+                );
+                self.intent_keys.insert(account_id, public_key);
+            }
+            None => {
+                self.intent_keys.remove(&account_id);
+            }
+        }
+    }
+
+    pub fn intent_key(&self, account_id: AccountId) -> Option<PublicKey> {
+        self.intent_keys.get(&account_id).cloned()
+    }
+
+    /// Nonce of the last intent of `account_id` that was fulfilled
+    pub fn intent_nonce(&self, account_id: AccountId) -> Option<U64> {
+        self.intent_nonces.get(&account_id).copied().map(U64)
+    }
+
+    /// Places a bid of `amount` following `signed`, an intent signed with the signer's intent
+    /// key
+    ///
+    /// The bid and the solver fee are taken out of the signer's balance.
+    pub fn fulfill_bid_intent(
+        &mut self,
+        signed: SignedBidIntent,
+        amount: NearToken,
+    ) -> IntentReceipt {
+        // Validations
+        require!(
+            signed.recipient == env::current_account_id().as_str(),
+            "intent is for another contract"
+        );
+        let intent: BidIntent = serde_json::from_str(&signed.message)
+            .unwrap_or_else(|_| env::panic_str("message is not a bid intent"));
+        let Some(public_key) = self.intent_keys.get(&intent.signer_id) else {
+            env::panic_str("signer has no intent key")
+        };
+        let public_key: &[u8; 32] = public_key.as_bytes()[1..]
+            .try_into()
+            .expect("ed25519 keys are 32 bytes");
+        let signature: &[u8; 64] = signed
+            .signature
+            .0
+            .as_slice()
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("signature must be 64 bytes"));
+        let intent_hash = Self::internal_nep413_hash(&signed);
+        require!(
+            env::ed25519_verify(signature, &intent_hash, public_key),
+            "invalid intent signature"
+        );
+        validate_intent(
+            env::block_timestamp(),
+            intent.deadline.into(),
+            intent.nonce.into(),
+            self.intent_nonces.get(&intent.signer_id).copied(),
+            amount.as_yoctonear(),
+            intent.max_amount.as_yoctonear(),
+        )
+        .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        require!(
+            self.internal_compliance_registry(amount).is_none(),
+            "bids above the compliance threshold have to be placed with `make_bid`"
+        );
+        let balance = self.balance_of(intent.signer_id.clone());
+        let Some(funds) = balance.checked_sub(intent.solver_fee) else {
+            env::panic_str("signer's balance does not cover the solver fee")
+        };
+
+        // Operations
+        let signer_id = intent.signer_id;
+        let solver = env::predecessor_account_id();
+        self.intent_nonces
+            .insert(signer_id.clone(), intent.nonce.into());
+        let nft_id = NFTId::new(&intent.nft, &intent.token_id);
+        let drawn = self
+            .internal_make_bid(
+                signer_id.clone(),
+                &nft_id,
+                amount,
+                None,
+                funds,
+                None,
+                BidOrigin::Relayed,
+            )
+            .unwrap_or_else(|err| env::panic_str(&err));
+        self.internal_debit(&signer_id, drawn.saturating_add(intent.solver_fee));
+        if !intent.solver_fee.is_zero() {
+            self.internal_credit(solver.clone(), intent.solver_fee);
+        }
+        let intent_hash = Base58CryptoHash::from(intent_hash);
+        AuctionEvent::IntentFulfilled {
+            intent_hash,
+            signer_id: signer_id.clone(),
+            solver,
+            amount,
+            solver_fee: intent.solver_fee,
+        }
+        .emit();
+        let receipt = IntentReceipt {
+            intent_hash,
+            bidder: signer_id,
+            nft: intent.nft,
+            token_id: intent.token_id,
+            amount,
+            solver_fee: intent.solver_fee,
+        };
+        if let Some(framework) = intent.settle_to {
+            // Scheduled without a callback, the bid stands whatever the framework makes of it
+            ext_intent_framework::ext(framework).on_bid_intent_settled(receipt.clone());
+        }
+        receipt
+    }
+}
+
+impl Contract {
+    /// The hash NEP-413 signatures of `signed` are over
+    fn internal_nep413_hash(signed: &SignedBidIntent) -> [u8; 32] {
+        let nonce: [u8; 32] = signed
+            .nonce
+            .0
+            .as_slice()
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("nonce must be 32 bytes"));
+        let payload = Nep413Payload {
+            message: signed.message.clone(),
+            nonce,
+            recipient: signed.recipient.clone(),
+            callback_url: signed.callback_url.clone(),
+        };
+        let mut bytes = borsh::to_vec(&NEP413_TAG).expect("tag serializes");
+        bytes.extend(borsh::to_vec(&payload).expect("payload serializes"));
+        env::sha256_array(&bytes)
+    }
+}
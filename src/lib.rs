@@ -1,233 +1,7886 @@
 //! This contract starts an auction when it receives an
 //! [NFT](https://github.com/near/NEPs/blob/master/neps/nep-0171.md)
+//!
+//! Off-chain code can depend on this crate with the `library` feature to reuse the contract's
+//! [`types`], [`events`] and errors.
 
-use std::hash::{DefaultHasher, Hash, Hasher};
+use events::AuctionEvent;
 
-use near_contract_standards::non_fungible_token::{
-    approval::{ext_nft_approval, NonFungibleTokenApprovalReceiver},
-    core::ext_nft_core,
+use near_contract_standards::{
+    fungible_token::{core::ext_ft_core, receiver::FungibleTokenReceiver},
+    non_fungible_token::{
+        approval::{ext_nft_approval, NonFungibleTokenApprovalReceiver},
+        core::ext_nft_core,
+        metadata::TokenMetadata,
+        Token,
+    },
+};
+use near_nft_auction_core::{
+    bid::{is_lapsed, min_next_bid, validate_memo, BidAttempt, BidContext},
+    curve, engine, leaderboard, listing,
+    pay_on_win::{self, split_slash},
+    penny,
+    settlement::{plan_cancellation, plan_settlement, Settlement, SettlementBid},
+    time,
+    trending::{self, Activity},
+    volume,
+    wash::{self, Trade},
+};
+use near_sdk::{
+    assert_one_yocto, env,
+    json_types::{U128, U64},
+    near, require,
+    store::{IterableMap, IterableSet, LazyOption, LookupMap, LookupSet, Vector},
+    AccountId, BorshStorageKey, CryptoHash, NearToken, Promise, PromiseError, PromiseOrValue,
+    PublicKey, StorageUsage,
 };
-use near_sdk::{env, near, require, store::IterableMap, AccountId, NearToken, Promise};
 
-#[near(serializers = [borsh])]
-pub struct Bid {
-    amount: NearToken,
-    paid: bool,
-}
+pub mod accounting;
+pub mod admin;
+pub mod compliance;
+pub mod disputes;
+pub mod events;
+pub mod expiries;
+pub mod fee_policies;
+pub mod intents;
+pub mod minting;
+pub mod moderation;
+pub mod multi_token;
+pub mod multisig;
+pub mod offers;
+pub mod payment_tokens;
+pub mod payouts;
+pub mod royalties;
+pub mod social;
+pub mod staking;
+pub mod types;
+pub mod upgrade;
+
+pub use compliance::CheckedBid;
+pub use intents::{BidIntent, SignedBidIntent};
+pub use minting::PendingMint;
+pub use moderation::Takedown;
+pub use multisig::{AdminAction, PendingAction};
+pub use near_nft_auction_core::{bid::BidError, listing::ListingError};
+pub use payment_tokens::{PaymentTokenConfig, TokenBidMsg, TokenFee};
+pub use royalties::RoyaltySplit;
+pub use staking::Staker;
+pub use types::*;
 
 #[near(serializers = [borsh])]
 pub struct Auction {
     owner: AccountId,
-    bids: IterableMap<AccountId, Bid>,
+    nft: AccountId,
+    token_id: TokenId,
+    /// NFTs sold along with `token_id` in a uniform auction or bonding-curve sale, or instead of
+    /// it in a collection auction
+    ///
+    /// Kept apart from the auction so bids on auctions with large lots don't load it, see
+    /// [`Auction::lot`].
+    lot: LazyOption<Vec<TokenId>>,
+    /// NFTs a bonding-curve sale has sold so far, in order from `token_id` on
+    sold: u32,
+    bids: IterableMap<AccountId, VersionedBid>,
+    /// Every bid placed, raised or reduced on the auction, in order
+    history: Vector<BidRecord>,
     h_bid: NearToken,
+    minimum_bid: NearToken,
     expiry: u64,
+    /// Whether the NFT is handed to the winner at settlement instead of being claimed by them
+    push_nft: bool,
+    /// Nanoseconds an account has to wait between its bids
+    bid_interval: u64,
+    /// `sequence` of the next bid that is placed or increased
+    next_bid_sequence: u64,
+    kind: AuctionKind,
+    /// Bid fees collected so far, paid to the owner at settlement
+    fees_accrued: NearToken,
+    /// NEAR escrowed by the auction's outstanding bids
+    bids_escrowed: NearToken,
+    /// Bytes of storage taken up by this auction's listing and bids
+    storage_usage: StorageUsage,
+    on_unsold: UnsoldAction,
+    asset: AuctionedAsset,
+    /// Posted by the owner, returned at settlement and shared among the bidders if the owner
+    /// cancels the auction once it has bids
+    stake: NearToken,
+    /// Nanoseconds after settlement during which the proceeds are held and can be disputed
+    dispute_window: u64,
+    /// Nanoseconds after settlement the proceeds are held for, disputed or not
+    proceeds_delay: u64,
+    /// Nanoseconds after expiry before the auction can be settled
+    settlement_delay: u64,
+    /// Approval the NFT was listed with, `None` if it was not listed through `nft_approve`
+    approval_id: Option<u64>,
+    /// Whether the NFT stays with the owner, who only approved the contract to transfer it to the
+    /// winner at settlement with `approval_id`
+    escrowless: bool,
+    /// When the auction was started
+    listed_at: u64,
+    /// Set if bidders only escrow a deposit and the winner pays the rest once the auction is over
+    pay_on_win: Option<PayOnWin>,
+    /// Winner of a pay-on-win auction that has yet to pay the rest of their bid
+    pending_payment: Option<PendingPayment>,
+    /// Bidding activity as of the last bid, see [`Activity`]
+    activity: u128,
+    activity_at: u64,
+    /// Set if bids are paid in this token instead of NEAR
+    payment: Option<PaymentToken>,
+    /// Metadata a lazily minted NFT is minted with, kept apart and only loaded to mint it
+    mint_metadata: LazyOption<TokenMetadata>,
+    /// What sales pay beyond the seller's share, copied from the policy the listing picked
+    fee_policy: FeePolicyConfig,
+    /// Largest bid the auction takes, replacing the global `max_bid`
+    max_bid: Option<NearToken>,
 }
 
-#[near(serializers = [borsh, json])]
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct NFTId(u64);
+impl Auction {
+    fn view(&self) -> AuctionView {
+        AuctionView {
+            nft: self.nft.clone(),
+            token_id: self.token_id.clone(),
+            nft_id: NFTId::new(&self.nft, &self.token_id),
+            status: self.status(env::block_timestamp()),
+            owner: self.owner.clone(),
+            h_bid: self.h_bid,
+            minimum_bid: self.minimum_bid,
+            lot: self.lot().to_vec(),
+            sold: self.sold,
+            expiry: self.expiry.into(),
+            expiry_iso: time::format_iso8601(self.expiry),
+            bid_count: self.bids.len(),
+            push_nft: self.push_nft,
+            bid_interval: self.bid_interval.into(),
+            kind: self.kind.clone(),
+            fees_accrued: self.fees_accrued,
+            bids_escrowed: self.bids_escrowed,
+            on_unsold: self.on_unsold.clone(),
+            asset: self.asset.clone(),
+            stake: self.stake,
+            dispute_window: self.dispute_window.into(),
+            proceeds_delay: self.proceeds_delay.into(),
+            settlement_delay: self.settlement_delay.into(),
+            settles_at: listing::settles_at(self.expiry, self.settlement_delay).into(),
+            approval_id: self.approval_id.map(U64),
+            escrowless: self.escrowless,
+            listed_at: self.listed_at.into(),
+            listing_age: env::block_timestamp().saturating_sub(self.listed_at).into(),
+            pay_on_win: self.pay_on_win.clone(),
+            pending_payment: self
+                .pending_payment
+                .as_ref()
+                .map(|pending| PendingPaymentView {
+                    winner: pending.winner.clone(),
+                    due: pending.due.into(),
+                }),
+            payment: self.payment.clone(),
+            mint_metadata: self.mint_metadata().cloned(),
+            fee_policy: self.fee_policy.clone(),
+            max_bid: self.max_bid,
+            display: None,
+        }
+    }
+
+    /// Whether this is the auction of `token_id` of `nft`, rather than one whose [`NFTId`] merely
+    /// collides with theirs
+    fn is_of(&self, nft: &AccountId, token_id: &TokenId) -> bool {
+        self.nft == *nft && self.token_id == *token_id
+    }
+
+    fn status(&self, now: u64) -> AuctionStatus {
+        if self.pending_payment.is_some() {
+            AuctionStatus::AwaitingPayment
+        } else if listing::is_expired(now, self.expiry) {
+            AuctionStatus::Expired
+        } else {
+            AuctionStatus::Open
+        }
+    }
+
+    /// Whether the auction meets every condition of `filter` at `now`
+    fn matches(&self, filter: &AuctionFilter, now: u64) -> bool {
+        filter.min_bid.is_none_or(|min_bid| self.h_bid >= min_bid)
+            && filter.max_bid.is_none_or(|max_bid| self.h_bid <= max_bid)
+            && filter
+                .status
+                .is_none_or(|status| self.status(now) == status)
+            && match &filter.paid_in {
+                None => true,
+                Some(PaidIn::Near) => self.payment.is_none(),
+                Some(PaidIn::Token(token)) => self.payment.as_ref() == Some(token),
+            }
+    }
+
+    /// NFTs sold along with `token_id`, loaded from storage the first time they are needed
+    ///
+    /// Only auctions of kinds with lots store one, reading a lot that was never stored panics.
+    fn lot(&self) -> &[TokenId] {
+        if !self.kind.has_lot() {
+            return &[];
+        }
+        self.lot.get().as_deref().unwrap_or_default()
+    }
+
+    /// Metadata of the NFT a lazy mint mints, only stored for lazy mints
+    fn mint_metadata(&self) -> Option<&TokenMetadata> {
+        match self.asset {
+            AuctionedAsset::LazyMint { .. } => self.mint_metadata.get().as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The record of this auction ending now with `outcome`
+    fn provenance(&self, outcome: AuctionOutcome) -> ProvenanceRecord {
+        ProvenanceRecord {
+            seller: self.owner.clone(),
+            listed_at: self.listed_at.into(),
+            ended_at: env::block_timestamp().into(),
+            payment: self.payment.clone(),
+            outcome,
+        }
+    }
+
+    /// Number of NFTs this auction sells
+    fn supply(&self) -> usize {
+        match self.kind {
+            AuctionKind::Uniform => 1 + self.lot().len(),
+            _ => 1,
+        }
+    }
+
+    /// Whether `token_id` is one of the NFTs this auction holds
+    fn holds(&self, token_id: &TokenId) -> bool {
+        self.nfts().any(|held| held == token_id)
+    }
+
+    /// NFTs this auction still holds, in the order they are sold
+    fn nfts(&self) -> impl Iterator<Item = &TokenId> + '_ {
+        std::iter::once(&self.token_id)
+            .chain(self.lot())
+            .skip(self.sold as usize)
+    }
+
+    fn settlement_bids(&self) -> impl Iterator<Item = SettlementBid<AccountId>> + '_ {
+        self.bids.iter().map(|(bidder, bid)| SettlementBid {
+            bidder: bidder.clone(),
+            amount: bid.amount.as_yoctonear(),
+            // Bid-entries may already be refunded in case of calls to:
+            // 1. `update_bid`: Bidders old entry just gets marked as paid
+            // 2. `refund_bid`
+            paid: bid.paid,
+            sequence: bid.sequence,
+        })
+    }
+
+    /// What the auction's engine has to know about it besides its bids
+    fn engine_lot(&self) -> engine::Lot {
+        engine::Lot {
+            supply: self.supply(),
+            minimum_bid: self.minimum_bid.as_yoctonear(),
+        }
+    }
+
+    /// The amount new bids have to exceed once a bid was placed or raised to `amount`
+    fn next_threshold(&self, amount: NearToken) -> NearToken {
+        NearToken::from_yoctonear(self.kind.engine().next_threshold(
+            amount.as_yoctonear(),
+            &mut self.settlement_bids(),
+            &self.engine_lot(),
+        ))
+    }
+
+    /// The amount new bids would have to exceed if `bidder` bid `amount`, replacing their bid
+    fn simulated_threshold(&self, bidder: &AccountId, amount: NearToken) -> NearToken {
+        let simulated = SettlementBid {
+            bidder: bidder.clone(),
+            amount: amount.as_yoctonear(),
+            paid: false,
+            sequence: self.next_bid_sequence,
+        };
+        NearToken::from_yoctonear(
+            self.kind.engine().next_threshold(
+                amount.as_yoctonear(),
+                &mut self
+                    .settlement_bids()
+                    .filter(|bid| bid.bidder != *bidder)
+                    .chain(std::iter::once(simulated)),
+                &self.engine_lot(),
+            ),
+        )
+    }
+
+    /// The price the next penny bid or bonding-curve purchase reaches, other kinds have none
+    fn next_price(&self) -> Option<NearToken> {
+        if let Some(rules) = self.kind.penny_rules() {
+            return Some(
+                self.h_bid
+                    .saturating_add(NearToken::from_yoctonear(rules.increment)),
+            );
+        }
+        let price = curve::quote(&self.kind.curve()?, self.sold).ok()?;
+        Some(NearToken::from_yoctonear(price))
+    }
+
+    /// Caps `refunds` of whole bids at what each bidder actually escrowed
+    fn escrowed_refunds(&self, refunds: Vec<(AccountId, u128)>) -> Vec<(AccountId, u128)> {
+        refunds
+            .into_iter()
+            .map(|(bidder, amount)| {
+                let unpaid = self.bids.get(&bidder).map_or(0, |bid| {
+                    bid.amount.saturating_sub(bid.escrowed()).as_yoctonear()
+                });
+                (bidder, amount.saturating_sub(unpaid))
+            })
+            .collect()
+    }
+
+    /// Updates `h_bid` after a bid was placed or raised to `amount`
+    fn record_bid(&mut self, bidder: &AccountId, amount: NearToken, now: u64) {
+        self.history.push(BidRecord {
+            bidder: bidder.clone(),
+            amount,
+            placed_at: now.into(),
+        });
+        self.h_bid = self.next_threshold(amount);
+        let activity = self.activity().record_bid(now);
+        self.activity = activity.score;
+        self.activity_at = activity.at;
+    }
 
-impl NFTId {
-    pub fn new(nft: &AccountId, token_id: &TokenId) -> Self {
-        let mut hasher = DefaultHasher::new();
-        nft.hash(&mut hasher);
-        token_id.hash(&mut hasher);
-        NFTId(hasher.finish())
+    fn activity(&self) -> Activity {
+        Activity {
+            score: self.activity,
+            at: self.activity_at,
+        }
     }
 }
 
+/// How the bids of an auction are paid out at settlement
+struct Payout {
+    /// Accounts that won an NFT, each awarded the NFT at its position in `nfts`
+    winners: Vec<AccountId>,
+    /// All of the auction's NFTs, those beyond the winners go back to the owner
+    nfts: Vec<TokenId>,
+    /// Owed to the owner
+    proceeds: u128,
+    refunds: Vec<(AccountId, u128)>,
+}
+
+/// What a seller can pass as `msg` to `nft_approve`
+#[near(serializers = [json])]
+#[serde(untagged)]
+enum ApprovalMsg {
+    AddToLot(LotParams),
+    List(Box<AuctionParams>),
+}
+
+#[derive(BorshStorageKey)]
+#[near]
+enum StorageKey {
+    Auctions,
+    Bids { nft_id: NFTId },
+    NftClaims,
+    Balances,
+    Offers,
+    OffersOf { nft_id: NFTId },
+    UnsoldStreaks,
+    HeldProceeds,
+    Trades,
+    Reputations,
+    ActiveBids,
+    Owed,
+    AuctionsByNft,
+    AuctionsOfNft { nft: AccountId },
+    WinningVolumes,
+    AccountStats,
+    PendingActions,
+    CollectionPolicies,
+    ActiveListings,
+    Stakers,
+    Takedowns,
+    BlacklistedSellers,
+    IntentKeys,
+    IntentNonces,
+    SellerAuthorizations,
+    RoyaltySplits,
+    PaymentTokens,
+    Lot { nft_id: NFTId },
+    BidHistory { nft_id: NFTId, listed_at: u64 },
+    Provenance,
+    ProvenanceOf { nft_id: NFTId },
+    MintMetadata { nft_id: NFTId },
+    PastBidHistories,
+    FeePolicies,
+    AuctionsByBidder,
+    AuctionsOfBidder { bidder: AccountId },
+    ExpiryBuckets,
+    AuctionsByExpiry,
+    AuctionsExpiringIn { bucket: u64 },
+}
+
 #[near(contract_state)]
 pub struct Contract {
     auctions: IterableMap<NFTId, Auction>,
+    /// Bytes of storage taken up by all ongoing auctions
+    storage_usage: StorageUsage,
+    /// Nanoseconds past expiry after which an auction that was never ended may be collected
+    /// by `gc`
+    gc_staleness: u64,
+    nft_claims: LookupMap<NFTId, NftClaim>,
+    /// NEAR owed to accounts, such as sale proceeds, paid out by `withdraw`
+    balances: LookupMap<AccountId, NearToken>,
+    /// Sum of all `balances`
+    balances_total: NearToken,
+    /// NEAR escrowed by the outstanding bids of all auctions
+    bids_escrowed: NearToken,
+    /// NEAR escrowed by all standing offers
+    offers_escrowed: NearToken,
+    /// Listing stakes posted by the owners of all ongoing auctions
+    stakes_escrowed: NearToken,
+    /// Bid fees collected by all ongoing auctions, paid out once they end
+    bid_fees_escrowed: NearToken,
+    /// Sum of all `held_proceeds`
+    proceeds_held: NearToken,
+    /// NEAR of the failed payouts in `owed`
+    owed_total: NearToken,
+    /// Deposits the contract attached to NFT and FT calls out of its own balance
+    nft_deposits_paid: NearToken,
+    /// Storage deposits taken out of the protocol fees to register payees and the contract itself
+    /// with fungible tokens, net of what the tokens refunded
+    ft_storage_paid: NearToken,
+    /// Bid fees collected by all auctions, past and ongoing
+    fees_accrued: NearToken,
+    offers: LookupMap<NFTId, offers::OfferBook>,
+    /// Id of the next offer made on any NFT
+    next_offer_id: u64,
+    /// Number of listings of an NFT in a row that ended without a winner
+    unsold_streaks: LookupMap<NFTId, u32>,
+    /// Number of fungible token lots listed so far, used to give each its lot id
+    ft_lots_listed: u64,
+    /// Stake taken from a seller's balance for every listing
+    listing_stake: NearToken,
+    /// Account that can freeze held proceeds and resolve disputes over them
+    arbiter: Option<AccountId>,
+    held_proceeds: LookupMap<u64, HeldProceeds>,
+    /// Id of the next proceeds held
+    next_escrow_id: u64,
+    /// Quick-flip fees are only charged if set
+    wash_trade_rules: Option<WashTradeConfig>,
+    /// Recent trades of each NFT
+    trades: LookupMap<NFTId, Vec<TradeRecord>>,
+    /// Quick-flip fees and the treasury's share of slashed deposits, withdrawn by the contract
+    /// account
+    protocol_fees: NearToken,
+    /// Protocol fees collected so far, withdrawn or not
+    protocol_fees_total: NearToken,
+    /// NEAR paid for everything sold so far, before fees
+    volume: NearToken,
+    reputations: LookupMap<AccountId, Reputation>,
+    /// Number of outstanding bids of each account across all auctions
+    active_bids: LookupMap<AccountId, u32>,
+    /// Sum of all `active_bids`
+    bids_outstanding: u64,
+    /// Number of accounts with a `balances` entry
+    balance_holders: u32,
+    /// Number of pay-on-win auctions waiting for their winner to pay
+    payments_pending: u32,
+    /// Account that changes the contract's settings, the contract account itself unless set
+    owner_id: Option<AccountId>,
+    /// Accounts that confirm privileged actions, which anyone with the owner role can call
+    /// directly if there are none
+    admins: Vec<AccountId>,
+    /// Number of `admins` that have to confirm an action
+    admin_threshold: u32,
+    pending_actions: LookupMap<u64, PendingAction>,
+    /// Id of the next action proposed
+    next_action_id: u64,
+    /// Hash of the code the admins approved to be deployed with `upgrade`
+    approved_code_hash: Option<CryptoHash>,
+    /// Auction kinds that can't be listed, until the owner turns them back on
+    disabled_kinds: Vec<AuctionKindName>,
+    /// Nanoseconds listings have to last at least
+    min_timespan: u64,
+    /// Terms overriding the global settings for the NFTs of a contract
+    collection_policies: LookupMap<AccountId, CollectionPolicy>,
+    /// Contract bids above `compliance_threshold` are checked with
+    compliance_registry: Option<AccountId>,
+    compliance_threshold: Option<NearToken>,
+    /// Accounts can bid in any number of auctions at once unless set
+    max_active_bids: Option<u32>,
+    /// Bids in NEAR can be of any amount unless set, or their auction sets a cap of its own
+    max_bid: Option<NearToken>,
+    /// Number of ongoing auctions of each seller
+    active_listings: LookupMap<AccountId, u32>,
+    /// Sellers can have any number of auctions at once unless set
+    max_active_listings: Option<u32>,
+    /// Fee burned on every bid placed or raised in auctions of each kind, none if absent
+    bid_burn_fees: Vec<(AuctionKindName, NearToken)>,
+    /// Account burned bid fees are sent to by `burn`, they stay locked in the contract if unset
+    burn_account: Option<AccountId>,
+    /// Bid fees charged but not yet sent to `burn_account`
+    burn_pending: NearToken,
+    /// Bid fees charged so far, burned or not
+    burned_total: NearToken,
+    /// Token staked for a share of the protocol fees, no fees are shared unless set
+    stake_token: Option<AccountId>,
+    /// Share of every protocol fee that goes to the stakers
+    staker_share_bps: u16,
+    stakers: LookupMap<AccountId, Staker>,
+    /// Sum of every staker's stake
+    total_staked: u128,
+    /// Rewards distributed per staked token so far, see [`staking`]
+    reward_per_token: u128,
+    /// Stakers' share of the fees collected since the last distribution
+    staking_undistributed: NearToken,
+    /// NEAR held for the stakers, distributed and not claimed yet or not
+    staking_rewards: NearToken,
+    /// Start of the current staking epoch
+    epoch_started: u64,
+    /// Accounts that can take down infringing auctions
+    moderators: Vec<AccountId>,
+    /// NFTs taken down, which can't be listed again until cleared
+    takedowns: LookupMap<NFTId, Takedown>,
+    /// Sellers moderators barred from listing
+    blacklisted_sellers: LookupSet<AccountId>,
+    /// SocialDB contract listings and sales are announced on, none are unless set
+    social_db: Option<AccountId>,
+    /// Keys bidders sign their bid intents with
+    intent_keys: LookupMap<AccountId, PublicKey>,
+    /// Nonce of the last intent of each bidder that was fulfilled
+    intent_nonces: LookupMap<AccountId, u64>,
+    /// Pairs of a minter and a seller it lets list NFTs minted by this contract
+    seller_authorizations: LookupSet<(AccountId, AccountId)>,
+    /// Deposits held by lazy-mint auctions to mint their NFT to the winner
+    mint_deposits_held: NearToken,
+    /// Whether sales pay royalties as the NFT contracts' `nft_payout` splits them
+    royalties_enabled: bool,
+    /// Nanoseconds a cached royalty split is used for before it is queried again
+    royalty_cache_ttl: u64,
+    /// Royalty split of each NFT contract, queried when its NFTs are listed
+    royalty_splits: LookupMap<AccountId, RoyaltySplit>,
+    /// Allowlist of the tokens auctions can take bids in
+    payment_tokens: IterableMap<PaymentToken, PaymentTokenConfig>,
+    /// Fee policies listings can pick, by name
+    fee_policies: IterableMap<String, FeePolicyConfig>,
+    /// Payouts that failed, by the account they are owed to
+    owed: LookupMap<AccountId, OwedPayouts>,
+    /// Share of the deposit of a defaulting pay-on-win winner, in basis points, that goes to the
+    /// protocol fees rather than the seller
+    slash_treasury_bps: u16,
+    /// Ongoing auctions of each NFT contract
+    auctions_by_nft: LookupMap<AccountId, IterableSet<NFTId>>,
+    /// Auctions each account has an outstanding bid on
+    auctions_by_bidder: LookupMap<AccountId, IterableSet<NFTId>>,
+    /// Starts of the expiry buckets holding any ongoing auction, sorted
+    expiry_buckets: LazyOption<Vec<u64>>,
+    /// Ongoing auctions by the start of their expiry bucket, along with their expiry
+    auctions_by_expiry: LookupMap<u64, IterableSet<(u64, NFTId)>>,
+    /// Past auctions of each NFT, oldest first
+    provenance: LookupMap<NFTId, Vector<ProvenanceRecord>>,
+    /// Ongoing auctions with the most bidding activity, most active first
+    trending: Vec<TrendingEntry>,
+    /// NEAR paid for all auctions each account won
+    winning_volumes: LookupMap<AccountId, NearToken>,
+    /// Accounts with the highest `winning_volumes` in yoctoNEAR, highest first
+    leaderboard: Vec<(AccountId, u128)>,
+    account_stats: LookupMap<AccountId, AccountStats>,
+    /// Bid histories of past auctions by their NFT and when they were listed
+    past_bid_histories: LookupMap<(NFTId, u64), Vector<BidRecord>>,
+    /// Volume of each recent day with sales in yoctoNEAR, by the start of the day, oldest first
+    daily_volume: Vec<(u64, u128)>,
+    /// Volume of each recent week with sales, like `daily_volume`
+    weekly_volume: Vec<(u64, u128)>,
+    /// Auctions listed by the first deployed code, ended with `end_legacy_auction`
+    legacy_auctions: IterableMap<NFTId, upgrade::LegacyAuction>,
 }
 
+/// Number of items returned by paginated views unless asked otherwise
+const DEFAULT_PAGE_LIMIT: u32 = 50;
+
+/// One day
+const DEFAULT_ROYALTY_CACHE_TTL: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Attached to register a payee with a fungible token, which refunds whatever registering does
+/// not take
+const FT_STORAGE_DEPOSIT: NearToken = NearToken::from_millinear(13);
+
+/// Number of auctions `get_trending` keeps track of
+const TRENDING_CAPACITY: usize = 20;
+
+/// Number of accounts `get_leaderboard` ranks
+const LEADERBOARD_CAPACITY: usize = 100;
+
+/// Number of days `volume_history` goes back
+const DAILY_VOLUME_KEPT: usize = 30;
+
+/// Number of weeks `volume_history` goes back
+const WEEKLY_VOLUME_KEPT: usize = 52;
+
+/// Half of a slashed deposit goes to the protocol fees unless set otherwise
+const DEFAULT_SLASH_TREASURY_BPS: u16 = 5_000;
+
+/// 30 days
+const DEFAULT_GC_STALENESS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
 impl Default for Contract {
     fn default() -> Self {
         Self {
-            auctions: IterableMap::new(b"a"),
+            auctions: IterableMap::new(StorageKey::Auctions),
+            storage_usage: 0,
+            gc_staleness: DEFAULT_GC_STALENESS,
+            nft_claims: LookupMap::new(StorageKey::NftClaims),
+            balances: LookupMap::new(StorageKey::Balances),
+            balances_total: NearToken::from_yoctonear(0),
+            bids_escrowed: NearToken::from_yoctonear(0),
+            offers_escrowed: NearToken::from_yoctonear(0),
+            stakes_escrowed: NearToken::from_yoctonear(0),
+            bid_fees_escrowed: NearToken::from_yoctonear(0),
+            proceeds_held: NearToken::from_yoctonear(0),
+            owed_total: NearToken::from_yoctonear(0),
+            nft_deposits_paid: NearToken::from_yoctonear(0),
+            ft_storage_paid: NearToken::from_yoctonear(0),
+            fees_accrued: NearToken::from_yoctonear(0),
+            offers: LookupMap::new(StorageKey::Offers),
+            next_offer_id: 0,
+            unsold_streaks: LookupMap::new(StorageKey::UnsoldStreaks),
+            ft_lots_listed: 0,
+            listing_stake: NearToken::from_yoctonear(0),
+            arbiter: None,
+            held_proceeds: LookupMap::new(StorageKey::HeldProceeds),
+            next_escrow_id: 0,
+            wash_trade_rules: None,
+            trades: LookupMap::new(StorageKey::Trades),
+            protocol_fees: NearToken::from_yoctonear(0),
+            protocol_fees_total: NearToken::from_yoctonear(0),
+            volume: NearToken::from_yoctonear(0),
+            reputations: LookupMap::new(StorageKey::Reputations),
+            active_bids: LookupMap::new(StorageKey::ActiveBids),
+            bids_outstanding: 0,
+            balance_holders: 0,
+            payments_pending: 0,
+            owner_id: None,
+            admins: Vec::new(),
+            admin_threshold: 0,
+            pending_actions: LookupMap::new(StorageKey::PendingActions),
+            next_action_id: 0,
+            approved_code_hash: None,
+            disabled_kinds: Vec::new(),
+            min_timespan: 0,
+            collection_policies: LookupMap::new(StorageKey::CollectionPolicies),
+            compliance_registry: None,
+            compliance_threshold: None,
+            max_active_bids: None,
+            max_bid: None,
+            active_listings: LookupMap::new(StorageKey::ActiveListings),
+            max_active_listings: None,
+            bid_burn_fees: Vec::new(),
+            burn_account: None,
+            burn_pending: NearToken::from_yoctonear(0),
+            burned_total: NearToken::from_yoctonear(0),
+            stake_token: None,
+            staker_share_bps: 0,
+            stakers: LookupMap::new(StorageKey::Stakers),
+            total_staked: 0,
+            reward_per_token: 0,
+            staking_undistributed: NearToken::from_yoctonear(0),
+            staking_rewards: NearToken::from_yoctonear(0),
+            epoch_started: env::block_timestamp(),
+            moderators: Vec::new(),
+            takedowns: LookupMap::new(StorageKey::Takedowns),
+            blacklisted_sellers: LookupSet::new(StorageKey::BlacklistedSellers),
+            social_db: None,
+            intent_keys: LookupMap::new(StorageKey::IntentKeys),
+            intent_nonces: LookupMap::new(StorageKey::IntentNonces),
+            seller_authorizations: LookupSet::new(StorageKey::SellerAuthorizations),
+            mint_deposits_held: NearToken::from_yoctonear(0),
+            royalties_enabled: false,
+            royalty_cache_ttl: DEFAULT_ROYALTY_CACHE_TTL,
+            royalty_splits: LookupMap::new(StorageKey::RoyaltySplits),
+            payment_tokens: IterableMap::new(StorageKey::PaymentTokens),
+            fee_policies: IterableMap::new(StorageKey::FeePolicies),
+            owed: LookupMap::new(StorageKey::Owed),
+            slash_treasury_bps: DEFAULT_SLASH_TREASURY_BPS,
+            auctions_by_nft: LookupMap::new(StorageKey::AuctionsByNft),
+            auctions_by_bidder: LookupMap::new(StorageKey::AuctionsByBidder),
+            // Stored empty, reading a value that was never stored panics
+            expiry_buckets: LazyOption::new(StorageKey::ExpiryBuckets, Some(Vec::new())),
+            auctions_by_expiry: LookupMap::new(StorageKey::AuctionsByExpiry),
+            provenance: LookupMap::new(StorageKey::Provenance),
+            trending: Vec::new(),
+            winning_volumes: LookupMap::new(StorageKey::WinningVolumes),
+            leaderboard: Vec::new(),
+            account_stats: LookupMap::new(StorageKey::AccountStats),
+            past_bid_histories: LookupMap::new(StorageKey::PastBidHistories),
+            daily_volume: Vec::new(),
+            weekly_volume: Vec::new(),
+            legacy_auctions: IterableMap::new(upgrade::LEGACY_AUCTIONS_PREFIX),
         }
     }
 }
 
-type TokenId = String;
-
-#[near(serializers = [json])]
-pub struct AuctionParams {
-    timespan: u64,
-    minimum_bid: NearToken,
-}
-
 #[near]
 impl NonFungibleTokenApprovalReceiver for Contract {
     fn nft_on_approve(
         &mut self,
-        token_id: TokenId,
+        token_id: String,
         owner_id: AccountId,
         approval_id: u64,
         msg: String,
-    ) -> near_sdk::PromiseOrValue<String> {
+    ) -> PromiseOrValue<String> {
+        let token_id =
+            TokenId::try_from(token_id).unwrap_or_else(|err| env::panic_str(&err.to_string()));
+
         // Get Auction parameters
         let nft = env::predecessor_account_id();
-        let AuctionParams {
-            timespan,
-            minimum_bid,
-        } = serde_json::from_str(&msg).expect("Invalid message");
+        let params = match serde_json::from_str(&msg).expect("Invalid message") {
+            ApprovalMsg::AddToLot(LotParams { lot }) => {
+                return self.internal_add_to_lot(owner_id, nft, lot, token_id, approval_id)
+            }
+            ApprovalMsg::List(params) => *params,
+        };
 
         // Validations
-        require!(timespan > 0, "timestamp must be greater than 0");
-        let current_time = env::block_timestamp();
-        let Some(expiry) = current_time.checked_add(timespan) else {
-            env::panic_str("adding `timespan` to `timestamp` overflowed, `timespan` is too big")
-        };
-        let nft_id = NFTId::new(&nft, &token_id);
+        let expiry = self
+            .internal_check_listing(&nft, &params)
+            .unwrap_or_else(|violations| env::panic_str(&violations.join(", ")));
+        self.internal_check_listable(&owner_id, &nft, &token_id)
+            .and_then(|()| self.internal_check_listing_capacity(&owner_id))
+            .unwrap_or_else(|err| env::panic_str(&err));
 
         // Operations
-        let promise = ext_nft_core::ext(nft)
+        let stake = self.internal_take_stake(&owner_id);
+        if params.escrowless {
+            require!(
+                !self.auctions.contains_key(&NFTId::new(&nft, &token_id)),
+                "this nft is already in auction"
+            );
+            // The NFT stays with the owner, the approval is enough to transfer it to the winner
+            let auction = self.internal_start_auction(
+                owner_id,
+                nft,
+                token_id,
+                expiry,
+                params,
+                AuctionedAsset::Nft,
+            );
+            auction.stake = stake;
+            auction.approval_id = Some(approval_id);
+            auction.escrowless = true;
+            return PromiseOrValue::Value("Auction started".into());
+        }
+        let promise = ext_nft_core::ext(nft.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .nft_transfer(
                 env::current_account_id(),
-                token_id,
+                token_id.clone().into(),
                 Some(approval_id),
                 Some("Auction started".into()),
             )
-            // TODO HERE: figure out conventions to call yourself
-            //
-            // maybe use:
-            // #[ext_contract(ext_nft_approval)]
-            .then(Self::ext(env::current_account_id()).start_auction(
-                owner_id,
-                nft_id,
-                expiry,
-                minimum_bid,
+            // Some NFT contracts don't move the token on a successful `nft_transfer`, so check
+            // that it actually arrived before it can be bid on
+            .then(ext_nft_core::ext(nft.clone()).nft_token(token_id.clone().into()))
+            .then(Self::ext(env::current_account_id()).on_escrow_nft_token(
+                nft,
+                token_id,
+                PendingListing {
+                    owner_id,
+                    expiry: expiry.into(),
+                    params,
+                    stake,
+                    approval_id: Some(approval_id.into()),
+                },
             ));
-        near_sdk::PromiseOrValue::Promise(promise)
+        PromiseOrValue::Promise(promise)
+    }
+}
+
+#[near]
+impl FungibleTokenReceiver for Contract {
+    /// Auctions the transferred tokens as a single lot, with `msg` holding its `AuctionParams`
+    ///
+    /// The lot id the auction is listed under is logged in an `ft_lot_listed` event.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let ft = env::predecessor_account_id();
+        if self.stake_token.as_ref() == Some(&ft) {
+            self.internal_stake(sender_id, amount);
+            return PromiseOrValue::Value(U128(0));
+        }
+        // Payment tokens can still be sold as lots, bids are told apart by their `msg`
+        if let Ok(bid) = serde_json::from_str::<TokenBidMsg>(&msg) {
+            let payment = PaymentToken::Ft { contract: ft };
+            if let Some(registry) = self.internal_token_compliance_registry() {
+                return PromiseOrValue::Promise(
+                    compliance::ext_compliance_registry::ext(registry)
+                        .is_compliant(sender_id.clone())
+                        .then(
+                            Self::ext(env::current_account_id())
+                                .on_ft_bid_compliance_checked(sender_id, payment, amount, bid),
+                        ),
+                );
+            }
+            self.internal_token_bid(sender_id, payment, NearToken::from_yoctonear(amount.0), bid)
+                .unwrap_or_else(|err| env::panic_str(&err));
+            return PromiseOrValue::Value(U128(0));
+        }
+
+        // Get Auction parameters
+        let params: AuctionParams = serde_json::from_str(&msg).expect("Invalid message");
+
+        // Validations
+        let expiry = self
+            .internal_check_listing(&ft, &params)
+            .unwrap_or_else(|violations| env::panic_str(&violations.join(", ")));
+        require!(
+            !params.kind.has_lot(),
+            "fungible tokens can only be sold as a single lot"
+        );
+        require!(
+            params.on_unsold.is_none(),
+            "unsold fungible tokens always go back to their owner"
+        );
+        require!(!params.escrowless, "fungible tokens are always escrowed");
+        let lot_id: TokenId = format!("ft-lot-{}", self.ft_lots_listed)
+            .parse()
+            .expect("lot ids are valid token ids");
+        self.internal_check_listable(&sender_id, &ft, &lot_id)
+            .and_then(|()| self.internal_check_listing_capacity(&sender_id))
+            .unwrap_or_else(|err| env::panic_str(&err));
+
+        // Operations
+        let stake = self.internal_take_stake(&sender_id);
+        self.ft_lots_listed += 1;
+        AuctionEvent::FtLotListed {
+            ft: ft.clone(),
+            lot_id: lot_id.clone(),
+            owner: sender_id.clone(),
+            amount,
+        }
+        .emit();
+        self.internal_start_auction(
+            sender_id,
+            ft,
+            lot_id,
+            expiry,
+            params,
+            AuctionedAsset::Ft { amount },
+        )
+        .stake = stake;
+        PromiseOrValue::Value(U128(0))
     }
 }
 
 #[near]
 impl Contract {
+    /// Starts an auction for an NFT the contract received, `stake` being what the owner posted
     #[private]
     pub fn start_auction(
         &mut self,
         owner_id: AccountId,
-        nft_id: NFTId,
+        nft: AccountId,
+        token_id: TokenId,
         expiry: u64,
-        minimum_bid: NearToken,
+        params: AuctionParams,
+        stake: Option<NearToken>,
     ) {
-        let auction = Auction {
-            owner: owner_id,
-            bids: IterableMap::new(b"a"),
-            h_bid: minimum_bid,
+        self.internal_start_auction(owner_id, nft, token_id, expiry, params, AuctionedAsset::Nft)
+            .stake = stake.unwrap_or(NearToken::from_yoctonear(0));
+    }
+
+    /// Starts the `pending` auction once the contract owns the NFT, returns the stake to the owner
+    /// otherwise
+    #[private]
+    pub fn on_escrow_nft_token(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        pending: PendingListing,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> bool {
+        let PendingListing {
+            owner_id,
             expiry,
+            params,
+            stake,
+            approval_id,
+        } = pending;
+        if !matches!(token, Ok(Some(token)) if token.owner_id == env::current_account_id()) {
+            if !stake.is_zero() {
+                accounting::sub(&mut self.stakes_escrowed, stake);
+                self.internal_credit(owner_id, stake);
+            }
+            return false;
+        }
+        let auction = self.internal_start_auction(
+            owner_id,
+            nft,
+            token_id,
+            expiry.into(),
+            params,
+            AuctionedAsset::Nft,
+        );
+        auction.stake = stake;
+        auction.approval_id = approval_id.map(u64::from);
+        true
+    }
+
+    /// Adds `token_id` to the lot of the auction of `lead` once it was transferred to the
+    /// contract, or returns it to its owner if that auction has ended meanwhile
+    ///
+    /// Nothing is added if the transfer failed.
+    #[private]
+    pub fn add_to_lot(
+        &mut self,
+        owner_id: AccountId,
+        nft: AccountId,
+        lead: TokenId,
+        token_id: TokenId,
+        #[callback_result] transferred: Result<(), PromiseError>,
+    ) -> PromiseOrValue<()> {
+        if transferred.is_err() {
+            return PromiseOrValue::Value(());
+        }
+        let initial_storage_usage = env::storage_usage();
+        let nft_id = NFTId::new(&nft, &lead);
+        let Some(auction) = self.auctions.get_mut(&nft_id) else {
+            return PromiseOrValue::Promise(self.internal_nft_transfer(
+                nft,
+                owner_id,
+                token_id,
+                "Auction ended before the NFT was added to its lot",
+            ));
         };
-        self.auctions.insert(nft_id, auction);
+        auction
+            .lot
+            .get_mut()
+            .get_or_insert_with(Vec::new)
+            .push(token_id);
+        auction.h_bid = auction.next_threshold(auction.h_bid);
+
+        // Collections write lazily, flush to measure the lot
+        auction.lot.flush();
+        self.auctions.flush();
+        let storage_usage = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.auctions
+            .get_mut(&nft_id)
+            .expect("auction exists")
+            .storage_usage += storage_usage;
+        self.storage_usage += storage_usage;
+        PromiseOrValue::Value(())
+    }
+
+    /// Settles an expired auction
+    ///
+    /// Callable by anyone without a deposit, the yoctoNEAR required by NFT calls is paid by the
+    /// contract. `nft` and `token_id` only pick the auction, settlement calls the NFT contract and
+    /// token the auction was started with.
+    pub fn end_auction(&mut self, nft: AccountId, token_id: TokenId) -> PromiseOrValue<()> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        require!(
+            self.auctions
+                .get(&nft_id)
+                .is_some_and(|auction| auction.is_of(&nft, &token_id)),
+            "this nft is not in auction"
+        );
+        self.internal_end_auction(&nft_id)
+    }
+
+    /// Settles the expired auction of `nft_id`, as in `end_auction`
+    pub fn end_auction_by_id(&mut self, nft_id: NFTId) -> PromiseOrValue<()> {
+        self.internal_end_auction(&nft_id)
+    }
+
+    /// Settles up to `limit` auctions that could be settled for more than `gc_staleness` but were
+    /// never ended
+    ///
+    /// Callable by anyone, auctions are settled exactly as in `end_auction`. The oldest expiries
+    /// are settled first.
+    pub fn gc(&mut self, limit: u32) -> u32 {
+        let current_time = env::block_timestamp();
+        // No auction settles before it expires, so none expiring after the cutoff is stale
+        let cutoff = current_time.saturating_sub(self.gc_staleness);
+        let stale: Vec<NFTId> = self
+            .internal_auctions_expiring_between(0, cutoff)
+            .filter(|nft_id| {
+                let auction = self.auctions.get(nft_id).expect("indexed auction exists");
+                let settles_at = listing::settles_at(auction.expiry, auction.settlement_delay);
+                current_time.saturating_sub(settles_at) > self.gc_staleness
+            })
+            .take(limit as usize)
+            .collect();
+
+        for nft_id in &stale {
+            self.internal_end(nft_id);
+        }
+        stale.len() as u32
     }
 
+    /// Transfers an NFT won in an auction to its winner
+    ///
+    /// Requires exactly 1 yoctoNEAR attached, which is passed on to the NFT contract.
     #[payable]
-    pub fn end_auction(&mut self, nft: AccountId, token_id: TokenId) -> Promise {
+    pub fn claim_nft(&mut self, nft: AccountId, token_id: TokenId) -> Promise {
         // Validations
+        assert_one_yocto();
         let nft_id = NFTId::new(&nft, &token_id);
-        let Some(auction) = self.auctions.get(&nft_id) else {
-            env::panic_str("this nft is not in auction")
+        let Some(claim) = self.nft_claims.remove(&nft_id) else {
+            env::panic_str("this nft has not been won in an auction")
         };
-        let current_time = env::block_timestamp();
         require!(
-            current_time >= auction.expiry,
-            "cannot end, auction is still ongoing"
+            claim.winner == env::predecessor_account_id(),
+            "only the winner of the auction can claim this nft"
         );
-        // ext_nft_approval::ext(nft.clone()).nft_is_approved(token_id, approved_account_id, approval_id)
-
-        // Operations
-        let promise = match auction.bids.iter().last() {
-            // Highest bidder exists
-            Some((h_bidder, Bid { amount, paid: _ })) => {
-                // Transfer NFT to highest bidder
-                ext_nft_approval::ext(nft)
-                    .with_attached_deposit(NearToken::from_yoctonear(1))
-                    .nft_approve(token_id, h_bidder.clone(), None)
-                    .as_return()
-                    .then(
-                        auction
-                            .bids
-                            .iter()
-                            // Don't refund the highest-bidder & those already refunded (having
-                            // `paid == true`)
-                            //
-                            // Bid-entries may already be refunded in case of calls to:
-                            // 1. `update_bid`: Bidders old entry just gets marked as paid
-                            // 2. `refund_bid`
-                            .filter(|(acc_id, Bid { paid, .. })| *acc_id != h_bidder && !paid)
-                            .fold(
-                                // Pay bid-amount to NFT owner
-                                // (always called once)
-                                Promise::new(auction.owner.clone()).transfer(*amount),
-                                // Refund all bidders that didn't win the bid
-                                // (called 0 or more times)
-                                |accum_promise, (acc_id, Bid { amount, .. })| {
-                                    accum_promise
-                                        .then(Promise::new(acc_id.clone()).transfer(*amount))
-                                },
-                            ),
-                    )
-            }
 
-            // No bidders, Return NFT to owner
-            None => ext_nft_approval::ext(nft)
-                .with_attached_deposit(env::attached_deposit()) // Pass through all attached deposit
-                .nft_approve(token_id, auction.owner.clone(), None)
-                .as_return(),
+        // Operations
+        ext_nft_core::ext(claim.nft.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .nft_transfer(
+                claim.winner.clone(),
+                claim.token_id.clone().into(),
+                None,
+                Some("Auction won".into()),
+            )
+            .then(Self::ext(env::current_account_id()).on_claim_nft(nft_id, claim))
+    }
+
+    /// Puts an NFT the caller won, and has not claimed yet, straight back up for auction
+    ///
+    /// The contract already holds the NFT, so this skips claiming, approving and transferring it
+    /// back in. `params` are checked just as in `nft_on_approve`.
+    pub fn relist_won(&mut self, nft: AccountId, token_id: TokenId, params: AuctionParams) {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let winner = env::predecessor_account_id();
+        require!(
+            self.nft_claims
+                .get(&nft_id)
+                .is_some_and(|claim| claim.winner == winner),
+            "only the winner of the auction can relist this nft"
+        );
+        require!(
+            !self.auctions.contains_key(&nft_id),
+            "this nft is already in auction"
+        );
+        require!(!params.escrowless, "the contract already holds this nft");
+        let expiry = self
+            .internal_check_listing(&nft, &params)
+            .unwrap_or_else(|violations| env::panic_str(&violations.join(", ")));
+        self.internal_check_listable(&winner, &nft, &token_id)
+            .and_then(|()| self.internal_check_listing_capacity(&winner))
+            .unwrap_or_else(|err| env::panic_str(&err));
+
+        // Operations
+        let claim = self.nft_claims.remove(&nft_id).expect("claim exists");
+        if let Some(escrow_id) = claim.escrow_id {
+            // The NFT was never handed over, but the winner has it relisted as theirs
+            self.internal_delivered(escrow_id, &winner);
+        }
+        let stake = self.internal_take_stake(&winner);
+        self.internal_start_auction(winner, nft, token_id, expiry, params, AuctionedAsset::Nft)
+            .stake = stake;
+    }
+
+    /// Restores the claim if the NFT could not be transferred to the winner
+    #[private]
+    pub fn on_claim_nft(
+        &mut self,
+        nft_id: NFTId,
+        claim: NftClaim,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_err() {
+            self.nft_claims.insert(nft_id, claim);
+            return false;
+        }
+        if let Some(escrow_id) = claim.escrow_id {
+            self.internal_delivered(escrow_id, &claim.winner);
+        }
+        true
+    }
+
+    /// Lets the proceeds of an NFT handed over at settlement be released once it arrived
+    ///
+    /// Otherwise the winner can claim the NFT if the contract still holds it, and gets their
+    /// payment back if it doesn't.
+    #[private]
+    pub fn on_nft_delivered(
+        &mut self,
+        claim: NftClaim,
+        escrowed: bool,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        let escrow_id = claim.escrow_id.expect("delivery is tracked");
+        if result.is_ok() {
+            self.internal_delivered(escrow_id, &claim.winner);
+            return true;
+        }
+        if escrowed {
+            self.nft_claims
+                .insert(NFTId::new(&claim.nft, &claim.token_id), claim);
+        } else {
+            self.internal_refund_undelivered(escrow_id, &claim.winner);
+        }
+        false
+    }
+
+    /// Transfers the caller's whole balance, such as the proceeds of their sales, to them
+    pub fn withdraw(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let Some(amount) = self.balances.remove(&account_id) else {
+            env::panic_str("there is nothing to withdraw")
         };
-        assert!(self.auctions.remove(&nft_id).is_some());
-        promise
+        self.balance_holders -= 1;
+        accounting::sub(&mut self.balances_total, amount);
+        Promise::new(account_id.clone())
+            .transfer(amount)
+            .then(Self::ext(env::current_account_id()).on_withdraw(account_id, amount))
+    }
+
+    /// Restores the balance if it could not be transferred
+    #[private]
+    pub fn on_withdraw(
+        &mut self,
+        account_id: AccountId,
+        amount: NearToken,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_err() {
+            self.internal_credit(account_id, amount);
+            return false;
+        }
+        true
+    }
+
+    /// Returns the NFT to its owner if the fractionalizer did not take it
+    #[private]
+    pub fn on_fractionalize(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        owner: AccountId,
+        #[callback_result] transferred: Result<bool, PromiseError>,
+    ) -> bool {
+        if let Ok(true) = transferred {
+            return true;
+        }
+        self.internal_nft_transfer(nft, owner, token_id, "Fractionalization failed");
+        false
     }
 
-    pub fn make_bid(&mut self, nft: AccountId, token_id: TokenId, amount: NearToken) {
+    /// Adds the attached deposit to the caller's balance, such as to post listing stakes from
+    #[payable]
+    pub fn deposit(&mut self) {
+        self.internal_credit(env::predecessor_account_id(), env::attached_deposit());
+    }
+
+    /// Ends an ongoing auction early and returns what it sells to the owner
+    ///
+    /// Only the owner can cancel. Every outstanding bid is refunded, and if there are any, the
+    /// owner's stake and the bid fees collected are shared among the bidders instead of being
+    /// returned.
+    pub fn cancel_auction(&mut self, nft: AccountId, token_id: TokenId) -> PromiseOrValue<()> {
         // Validations
         let nft_id = NFTId::new(&nft, &token_id);
-        let Some(auction) = self.auctions.get_mut(&nft_id) else {
+        let Some(auction) = self.auctions.get(&nft_id) else {
             env::panic_str("this nft is not in auction")
         };
         require!(
-            amount > auction.h_bid,
-            "bid amount does not exceed previous bid or minimum bid amount"
+            auction.owner == env::predecessor_account_id(),
+            "only the owner of the auction can cancel it"
         );
         require!(
-            env::attached_deposit() >= amount,
-            "provided deposit does not cover bid amount"
+            !listing::is_expired(env::block_timestamp(), auction.expiry),
+            "auction is over, end it instead"
         );
-        let bidder = env::signer_account_id();
+
+        // Operations
+        let owner = auction.owner.clone();
+        let nfts = auction.nfts().cloned().collect();
+        self.internal_reputation(&owner).cancellations += 1;
+        match self.internal_cancel(&nft_id, nfts, "Auction cancelled", true) {
+            Some(promise) => PromiseOrValue::Promise(promise),
+            None => PromiseOrValue::Value(()),
+        }
+    }
+
+    /// Checks that the contract still owns the NFTs of an auction, or for an escrowless auction
+    /// is still approved to transfer its NFT, and cancels the auction as in `cancel_auction` if
+    /// not, such as when the NFT contract burned or took back the NFT
+    ///
+    /// Callable by anyone at any time, `end_auction` does the same check before settling.
+    pub fn check_escrow(&mut self, nft: AccountId, token_id: TokenId) -> Promise {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
         require!(
-            !auction.bids.contains_key(&bidder),
-            "bidder has already made a bid, either call `refundBid` or `updateBid`"
+            self.auctions
+                .get(&nft_id)
+                .is_some_and(|auction| matches!(auction.asset, AuctionedAsset::Nft)),
+            "this nft is not in auction"
         );
-        let current_time = env::block_timestamp();
-        require!(current_time < auction.expiry, "cannot bid, auction is over");
 
         // Operations
-        auction.bids.insert(
-            bidder,
-            Bid {
-                amount,
-                paid: false,
-            },
-        );
+        self.internal_check_escrow(&nft_id, false)
+    }
+
+    /// Cancels the auction if the contract no longer owns its next NFT, settles it if `settle`
+    /// is set and it does, returning whether the auction went on
+    #[private]
+    pub fn on_escrow_checked(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        settle: bool,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> bool {
+        let held = match token {
+            Ok(Some(token)) => token.owner_id == env::current_account_id(),
+            Ok(None) => false,
+            // A failed call says nothing about the NFT, only a missing or moved one cancels
+            Err(_) => true,
+        };
+        self.internal_escrow_checked(&NFTId::new(&nft, &token_id), held, settle)
     }
 
-    pub fn len(&self) -> u32 {
-        self.auctions.len()
+    /// As `on_escrow_checked`, for an escrowless auction that needs its approval to still stand
+    #[private]
+    pub fn on_approval_checked(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        settle: bool,
+        #[callback_result] approved: Result<bool, PromiseError>,
+    ) -> bool {
+        // A failed call says nothing about the approval, only a revoked one cancels
+        self.internal_escrow_checked(
+            &NFTId::new(&nft, &token_id),
+            approved.unwrap_or(true),
+            settle,
+        )
     }
 
-    pub fn expired(&self, nft: AccountId, token_id: TokenId) -> bool {
+    /// Places a bid of `amount`, which lapses at `expires_at` unless it is the highest bid by then
+    ///
+    /// Anything attached beyond what the bid escrows is refunded, such as all but the deposit of
+    /// a bid on a pay-on-win auction. Bids above the compliance threshold are only placed once
+    /// the compliance registry confirms the bidder.
+    ///
+    /// If `use_balance` is set, the caller's balance funds the bid along with the attached
+    /// deposit, and what is left goes back to the balance, see `internal_bid_funds`.
+    #[payable]
+    pub fn make_bid(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        amount: NearToken,
+        expires_at: Option<U64>,
+        use_balance: Option<bool>,
+        memo: Option<String>,
+    ) -> PromiseOrValue<()> {
+        self.internal_make_bid_call(
+            NFTId::new(&nft, &token_id),
+            amount,
+            expires_at,
+            use_balance,
+            memo,
+        )
+    }
+
+    /// Bids on the auction of `nft_id`, as in `make_bid`
+    #[payable]
+    pub fn make_bid_by_id(
+        &mut self,
+        nft_id: NFTId,
+        amount: NearToken,
+        expires_at: Option<U64>,
+        use_balance: Option<bool>,
+        memo: Option<String>,
+    ) -> PromiseOrValue<()> {
+        self.internal_make_bid_call(nft_id, amount, expires_at, use_balance, memo)
+    }
+
+    /// Places a bid of exactly the attached deposit, as in `make_bid`
+    #[payable]
+    pub fn bid(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        expires_at: Option<U64>,
+    ) -> PromiseOrValue<()> {
+        self.make_bid(
+            nft,
+            token_id,
+            env::attached_deposit(),
+            expires_at,
+            None,
+            None,
+        )
+    }
+
+    /// Pays the rest of the caller's winning bid on a pay-on-win auction, which is then settled
+    ///
+    /// Has to be called before the payment window runs out, anything attached beyond the rest of
+    /// the bid is refunded. The caller's balance can pay as well, as in `make_bid`.
+    #[payable]
+    pub fn complete_purchase(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        use_balance: Option<bool>,
+    ) -> PromiseOrValue<()> {
+        // Validations
+        let buyer = env::predecessor_account_id();
+        let use_balance = use_balance.unwrap_or(false);
+        let deposit = self.internal_bid_funds(&buyer, use_balance);
         let nft_id = NFTId::new(&nft, &token_id);
-        let Some(auction) = self.auctions.get(&nft_id) else {
+        let Some(auction) = self.auctions.get_mut(&nft_id) else {
             env::panic_str("this nft is not in auction")
         };
-        let current_time = env::block_timestamp();
-        current_time >= auction.expiry
+        let Some(pending) = auction
+            .pending_payment
+            .as_ref()
+            .filter(|pending| pending.winner == buyer)
+        else {
+            env::panic_str("caller has no payment due on this auction")
+        };
+        require!(
+            !listing::is_expired(env::block_timestamp(), pending.due),
+            "payment window is over"
+        );
+        let bid = auction.bids.get_mut(&buyer).expect("winner has a bid");
+        let remaining = pay_on_win::remaining_payment(
+            bid.amount.as_yoctonear(),
+            bid.escrowed().as_yoctonear(),
+            deposit.as_yoctonear(),
+        )
+        .map(NearToken::from_yoctonear)
+        .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+
+        // Operations
+        bid.deposit = None;
+        auction.pending_payment = None;
+        self.payments_pending -= 1;
+        accounting::add(&mut auction.bids_escrowed, remaining);
+        accounting::add(&mut self.bids_escrowed, remaining);
+        self.internal_return_funds(buyer, deposit.saturating_sub(remaining), use_balance);
+        match self.internal_end(&nft_id) {
+            Some(promise) => PromiseOrValue::Promise(promise),
+            None => PromiseOrValue::Value(()),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    // use super::*;
+    /// Places the next bid on a penny auction
+    ///
+    /// The attached deposit has to cover the bid fee plus whatever the caller's escrowed bid has
+    /// to grow by to reach the new price, anything beyond that is refunded. Bids raising the price
+    /// above the compliance threshold wait for the compliance registry, and the caller's balance
+    /// can pay as well, as in `make_bid`.
+    #[payable]
+    pub fn penny_bid(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        use_balance: Option<bool>,
+    ) -> PromiseOrValue<()> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let bidder = env::predecessor_account_id();
+        let use_balance = use_balance.unwrap_or(false);
+        let deposit = self.internal_bid_funds(&bidder, use_balance);
+        if let Some(registry) = self.internal_price_compliance_registry(&nft_id) {
+            return PromiseOrValue::Promise(Self::internal_check_compliance(
+                registry,
+                bidder,
+                nft,
+                token_id,
+                CheckedBid::Penny,
+                deposit,
+                use_balance,
+            ));
+        }
+        let excess = self
+            .internal_penny_bid(bidder.clone(), &nft_id, deposit)
+            .unwrap_or_else(|err| env::panic_str(&err));
+        self.internal_return_funds(bidder, excess, use_balance);
+        PromiseOrValue::Value(())
+    }
+
+    /// Places the next bid on a penny auction by `bidder`, who attached `deposit`, returning what
+    /// is left of it
+    fn internal_penny_bid(
+        &mut self,
+        bidder: AccountId,
+        nft_id: &NFTId,
+        deposit: NearToken,
+    ) -> Result<NearToken, String> {
+        // Validations
+        let Some(auction) = self.auctions.get_mut(nft_id) else {
+            return Err("this nft is not in auction".into());
+        };
+        let Some(rules) = auction.kind.penny_rules() else {
+            return Err("this is not a penny auction".into());
+        };
+        let burn_fee = Self::internal_bid_burn_fee(&self.bid_burn_fees, &auction.kind);
+        let Some(deposit) = deposit.checked_sub(burn_fee) else {
+            return Err("attached deposit does not cover the bid fee".into());
+        };
+        let now = env::block_timestamp();
+        let previous = auction.bids.get(&bidder).filter(|bid| !bid.paid);
+        let new_bid = previous.is_none();
+        if new_bid {
+            Self::internal_check_bid_capacity(&self.active_bids, self.max_active_bids, &bidder)?;
+        }
+        let planned = penny::plan_bid(
+            &rules,
+            &BidContext {
+                h_bid: auction.h_bid.as_yoctonear(),
+                expiry: auction.expiry,
+                bid_interval: auction.bid_interval,
+                now,
+            },
+            previous.map_or(0, |bid| bid.amount.as_yoctonear()),
+            previous.map(|bid| bid.last_bid_at),
+            deposit.as_yoctonear(),
+        )
+        .map_err(|err| err.to_string())?;
+        Self::internal_check_max_bid(
+            auction,
+            self.max_bid,
+            NearToken::from_yoctonear(planned.amount),
+        )?;
+        let placed_at = previous.map_or(now, |bid| bid.placed_at);
+        let escrowed = previous.map_or(NearToken::from_yoctonear(0), |bid| bid.amount);
+        let fees_paid = auction
+            .bids
+            .get(&bidder)
+            .map_or(NearToken::from_yoctonear(0), |bid| bid.fees_paid);
+
+        // Operations
+        let initial_storage_usage = env::storage_usage();
+        let amount = NearToken::from_yoctonear(planned.amount);
+        let fee = NearToken::from_yoctonear(rules.bid_fee);
+        auction.bids.insert(
+            bidder.clone(),
+            Bid {
+                amount,
+                paid: false,
+                expires_at: None,
+                placed_at,
+                last_bid_at: now,
+                sequence: auction.next_bid_sequence,
+                pick: None,
+                deposit: None,
+                fees_paid: fees_paid.saturating_add(fee),
+                payment: None,
+                origin: BidOrigin::Direct,
+                memo: None,
+            }
+            .into(),
+        );
+        auction.next_bid_sequence += 1;
+        auction.record_bid(&bidder, amount, now);
+        AuctionEvent::BidPlaced {
+            nft: auction.nft.clone(),
+            token_id: auction.token_id.clone(),
+            bidder: bidder.clone(),
+            amount,
+        }
+        .emit();
+        Self::internal_update_trending(&mut self.trending, nft_id, auction.activity());
+        let previous_expiry = std::mem::replace(&mut auction.expiry, planned.expiry);
+        accounting::add(&mut auction.fees_accrued, fee);
+        accounting::add(&mut self.fees_accrued, fee);
+        accounting::add(&mut self.bid_fees_escrowed, fee);
+        let stats = self.account_stats.entry(bidder.clone()).or_default();
+        stats.fees_paid = stats.fees_paid.saturating_add(fee);
+        accounting::add(&mut auction.bids_escrowed, amount.saturating_sub(escrowed));
+        accounting::add(&mut self.bids_escrowed, amount.saturating_sub(escrowed));
+
+        // Collections write lazily, flush to measure the bid
+        auction.bids.flush();
+        auction.history.flush();
+        let storage_usage = env::storage_usage().saturating_sub(initial_storage_usage);
+        auction.storage_usage += storage_usage;
+        self.storage_usage += storage_usage;
+        if new_bid {
+            self.internal_add_active_bid(&bidder, nft_id);
+        }
+        if previous_expiry != planned.expiry {
+            self.internal_unindex_expiry(nft_id, previous_expiry);
+            self.internal_index_expiry(nft_id, planned.expiry);
+        }
+
+        self.internal_burn(burn_fee);
+        Ok(deposit.saturating_sub(NearToken::from_yoctonear(planned.cost)))
+    }
+
+    /// Refunds the caller's bid once it has lapsed
+    pub fn withdraw_lapsed_bid(&mut self, nft: AccountId, token_id: TokenId) -> Promise {
+        let bidder = env::predecessor_account_id();
+        let (payment, amount) = self
+            .internal_withdraw_lapsed_bid(&NFTId::new(&nft, &token_id), &bidder)
+            .unwrap_or_else(|err| env::panic_str(err));
+        match payment {
+            Some(payment) => self.internal_token_transfer(
+                &payment,
+                bidder,
+                U128(amount.as_yoctonear()),
+                "Lapsed bid withdrawn",
+            ),
+            None => {
+                accounting::sub(&mut self.bids_escrowed, amount);
+                Self::internal_pay(bidder, amount)
+            }
+        }
+    }
+
+    /// Refunds every bid of the caller that has lapsed, up to `limit` of them, returning how
+    /// many were
+    ///
+    /// Bids that are still the highest or have yet to expire stay in their auctions, as with
+    /// `withdraw_lapsed_bid`. Refunds in NEAR are paid in a single transfer. Call again while
+    /// `limit` bids were refunded to get the rest.
+    pub fn withdraw_all_bids(&mut self, limit: Option<u32>) -> u32 {
+        let bidder = env::predecessor_account_id();
+        let nft_ids: Vec<NFTId> = self
+            .auctions_by_bidder
+            .get(&bidder)
+            .map(|nft_ids| nft_ids.iter().cloned().collect())
+            .unwrap_or_default();
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        let mut withdrawn = 0;
+        let mut refund = NearToken::from_yoctonear(0);
+        for nft_id in &nft_ids {
+            if withdrawn == limit {
+                break;
+            }
+            let Ok((payment, amount)) = self.internal_withdraw_lapsed_bid(nft_id, &bidder) else {
+                continue;
+            };
+            withdrawn += 1;
+            match payment {
+                Some(payment) => {
+                    self.internal_token_transfer(
+                        &payment,
+                        bidder.clone(),
+                        U128(amount.as_yoctonear()),
+                        "Lapsed bid withdrawn",
+                    );
+                }
+                None => refund = refund.saturating_add(amount),
+            }
+        }
+        if !refund.is_zero() {
+            accounting::sub(&mut self.bids_escrowed, refund);
+            Self::internal_pay(bidder, refund);
+        }
+        withdrawn
+    }
+
+    /// Tops up the caller's bid by `top_up`, or by all the funds of the call unless set
+    ///
+    /// Raising a bid above the compliance threshold waits for the compliance registry, and the
+    /// caller's balance can pay as well, as in `make_bid`.
+    #[payable]
+    pub fn increase_bid(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        top_up: Option<NearToken>,
+        use_balance: Option<bool>,
+    ) -> PromiseOrValue<()> {
+        let bidder = env::predecessor_account_id();
+        let use_balance = use_balance.unwrap_or(false);
+        let funds = self.internal_bid_funds(&bidder, use_balance);
+        let top_up = top_up.unwrap_or(funds);
+        require!(
+            top_up <= funds,
+            "attached deposit does not cover the top-up"
+        );
+        let current = self
+            .auctions
+            .get(&NFTId::new(&nft, &token_id))
+            .and_then(|auction| auction.bids.get(&bidder))
+            .map_or(NearToken::from_yoctonear(0), |bid| bid.amount);
+        if let Some(registry) = self.internal_compliance_registry(current.saturating_add(top_up)) {
+            return PromiseOrValue::Promise(Self::internal_check_compliance(
+                registry,
+                bidder,
+                nft,
+                token_id,
+                CheckedBid::Increase { top_up },
+                funds,
+                use_balance,
+            ));
+        }
+        self.internal_increase_bid(bidder.clone(), nft, token_id, top_up, None)
+            .unwrap_or_else(|err| env::panic_str(&err));
+        self.internal_return_funds(bidder, funds.saturating_sub(top_up), use_balance);
+        PromiseOrValue::Value(())
+    }
+
+    /// Tops up the bid of `bidder` by `top_up` of `payment`, a token or NEAR if `None`
+    fn internal_increase_bid(
+        &mut self,
+        bidder: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        top_up: NearToken,
+        payment: Option<&PaymentToken>,
+    ) -> Result<(), String> {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get_mut(&nft_id) else {
+            return Err("this nft is not in auction".into());
+        };
+        Self::internal_check_payment(auction, payment)?;
+        let burn_fee = match payment {
+            Some(_) => NearToken::from_yoctonear(0),
+            None => Self::internal_bid_burn_fee(&self.bid_burn_fees, &auction.kind),
+        };
+        let Some(top_up) = top_up.checked_sub(burn_fee) else {
+            return Err("attached deposit does not cover the bid fee".into());
+        };
+        let Some(bid) = auction.bids.get(&bidder).filter(|bid| !bid.paid) else {
+            return Err("bidder has no bid to increase, call `make_bid` instead".into());
+        };
+        let now = env::block_timestamp();
+        let amount = auction
+            .kind
+            .engine::<AccountId>()
+            .validate_increase(
+                &BidContext {
+                    h_bid: auction.h_bid.as_yoctonear(),
+                    expiry: auction.expiry,
+                    bid_interval: auction.bid_interval,
+                    now,
+                },
+                bid.amount.as_yoctonear(),
+                bid.last_bid_at,
+                top_up.as_yoctonear(),
+            )
+            .map(NearToken::from_yoctonear)
+            .map_err(|err| err.to_string())?;
+        Self::internal_check_max_bid(auction, self.max_bid, amount)?;
+
+        // Operations
+        let bid = auction.bids.get_mut(&bidder).expect("bid was checked");
+        bid.amount = amount;
+        bid.deposit = bid.deposit.map(|deposit| deposit.saturating_add(top_up));
+        bid.last_bid_at = now;
+        bid.sequence = auction.next_bid_sequence;
+        auction.next_bid_sequence += 1;
+        let initial_storage_usage = env::storage_usage();
+        auction.record_bid(&bidder, amount, now);
+        // Collections write lazily, flush to measure the bid's record
+        auction.history.flush();
+        let storage_usage = env::storage_usage() - initial_storage_usage;
+        auction.storage_usage += storage_usage;
+        self.storage_usage += storage_usage;
+        Self::internal_update_trending(&mut self.trending, &nft_id, auction.activity());
+        accounting::add(&mut auction.bids_escrowed, top_up);
+        if payment.is_none() {
+            accounting::add(&mut self.bids_escrowed, top_up);
+        }
+        self.internal_burn(burn_fee);
+        AuctionEvent::BidIncreased {
+            nft,
+            token_id,
+            bidder,
+            top_up,
+            amount,
+        }
+        .emit();
+        Ok(())
+    }
+
+    /// Reduces the caller's bid by `reduction` and refunds it, the bid staying in the auction
+    ///
+    /// Only bids that are not winning as things stand can be reduced, down to just above the
+    /// minimum bid, while the auction is ongoing. Refunds are paid in the token bids are paid in.
+    /// The bid keeps its place among equal bids, and can be topped up again with `increase_bid`.
+    pub fn reduce_bid(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        reduction: NearToken,
+    ) -> Promise {
+        let bidder = env::predecessor_account_id();
+        let payment = self
+            .internal_reduce_bid(bidder.clone(), nft, token_id, reduction)
+            .unwrap_or_else(|err| env::panic_str(&err));
+        match payment {
+            Some(payment) => self.internal_token_transfer(
+                &payment,
+                bidder,
+                U128(reduction.as_yoctonear()),
+                "Bid reduced",
+            ),
+            None => {
+                accounting::sub(&mut self.bids_escrowed, reduction);
+                Self::internal_pay(bidder, reduction)
+            }
+        }
+    }
+
+    /// Reduces the bid of `bidder` by `reduction`, returning the token it is to be refunded in,
+    /// or `None` if NEAR
+    fn internal_reduce_bid(
+        &mut self,
+        bidder: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        reduction: NearToken,
+    ) -> Result<Option<PaymentToken>, String> {
+        // Validations
+        let now = env::block_timestamp();
+        let Some(auction) = self.auctions.get_mut(&NFTId::new(&nft, &token_id)) else {
+            return Err("this nft is not in auction".into());
+        };
+        if auction.pay_on_win.is_some() {
+            return Err("pay-on-win bids only escrow a deposit, they can't be reduced".into());
+        }
+        let Some(bid) = auction.bids.get_mut(&bidder).filter(|bid| !bid.paid) else {
+            return Err("bidder has no bid to reduce".into());
+        };
+        let amount = auction
+            .kind
+            .engine::<AccountId>()
+            .validate_reduction(
+                &BidContext {
+                    h_bid: auction.h_bid.as_yoctonear(),
+                    expiry: auction.expiry,
+                    bid_interval: auction.bid_interval,
+                    now,
+                },
+                auction.minimum_bid.as_yoctonear(),
+                bid.amount.as_yoctonear(),
+                reduction.as_yoctonear(),
+            )
+            .map(NearToken::from_yoctonear)
+            .map_err(|err| err.to_string())?;
+
+        // Operations
+        bid.amount = amount;
+        let initial_storage_usage = env::storage_usage();
+        auction.history.push(BidRecord {
+            bidder: bidder.clone(),
+            amount,
+            placed_at: now.into(),
+        });
+        // Collections write lazily, flush to measure the bid's record
+        auction.history.flush();
+        let storage_usage = env::storage_usage() - initial_storage_usage;
+        auction.storage_usage += storage_usage;
+        self.storage_usage += storage_usage;
+        accounting::sub(&mut auction.bids_escrowed, reduction);
+        let payment = auction.payment.clone();
+        AuctionEvent::BidReduced {
+            nft,
+            token_id,
+            bidder,
+            reduction,
+            amount,
+        }
+        .emit();
+        Ok(payment)
+    }
+
+    /// Buys the next NFT of a bonding-curve sale at its quoted price, returning its `token_id`
+    ///
+    /// The buyer gets the NFT just as an auction winner would, the price is credited to the
+    /// owner and anything attached beyond it is refunded. The sale ends once it sells out.
+    /// Purchases above the compliance threshold wait for the compliance registry, as bids in
+    /// `make_bid` do, and return no `token_id` if it rejects the buyer. The caller's balance can
+    /// pay as well, as in `make_bid`.
+    #[payable]
+    pub fn buy(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        use_balance: Option<bool>,
+    ) -> PromiseOrValue<Option<TokenId>> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let buyer = env::predecessor_account_id();
+        let use_balance = use_balance.unwrap_or(false);
+        let deposit = self.internal_bid_funds(&buyer, use_balance);
+        if let Some(registry) = self.internal_price_compliance_registry(&nft_id) {
+            return PromiseOrValue::Promise(Self::internal_check_purchase_compliance(
+                registry,
+                buyer,
+                nft,
+                token_id,
+                deposit,
+                use_balance,
+            ));
+        }
+        let (bought, excess) = self
+            .internal_buy(buyer.clone(), nft, token_id, deposit)
+            .unwrap_or_else(|err| env::panic_str(&err));
+        self.internal_return_funds(buyer, excess, use_balance);
+        PromiseOrValue::Value(Some(bought))
+    }
+
+    /// Buys the next NFT of a bonding-curve sale for `buyer`, who attached `deposit`, returning
+    /// its `token_id` and what is left of the deposit
+    fn internal_buy(
+        &mut self,
+        buyer: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        deposit: NearToken,
+    ) -> Result<(TokenId, NearToken), String> {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get_mut(&nft_id) else {
+            return Err("this nft is not in auction".into());
+        };
+        let Some(rules) = auction.kind.curve() else {
+            return Err("this is not a bonding-curve sale".into());
+        };
+        let supply = 1 + auction.lot().len() as u32;
+        let price = curve::plan_purchase(
+            &rules,
+            auction.sold,
+            supply,
+            env::block_timestamp(),
+            auction.expiry,
+            deposit.as_yoctonear(),
+        )
+        .map(NearToken::from_yoctonear)
+        .map_err(|err| err.to_string())?;
+
+        // Operations
+        let bought = auction.nfts().next().expect("sale is not sold out").clone();
+        auction.sold += 1;
+        let owner = auction.owner.clone();
+        let push_nft = auction.push_nft;
+        let fee_policy = auction.fee_policy.clone();
+        let record = auction.provenance(AuctionOutcome::Sold {
+            buyer: buyer.clone(),
+            price,
+        });
+        let stake = if auction.sold == supply {
+            // Sold out, the stake goes back to the owner too
+            self.internal_remove_auction(&nft_id).stake
+        } else {
+            NearToken::from_yoctonear(0)
+        };
+        self.internal_add_provenance(&nft, &bought, record);
+        let fee = self.internal_record_trade(&fee_policy, &nft, &bought, &owner, &buyer, price);
+        let proceeds = self.internal_charge_fees(&fee_policy, &nft, price, fee);
+        self.internal_credit(owner, proceeds.saturating_add(stake));
+        self.internal_award_nft(nft, bought.clone(), buyer, push_nft);
+        Ok((bought, deposit.saturating_sub(price)))
+    }
+
+    /// Picks the NFT the caller wants out of a collection auction's set, should their bid win
+    pub fn pick_nft(&mut self, nft: AccountId, token_id: TokenId, pick: TokenId) {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get_mut(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        require!(
+            matches!(auction.kind, AuctionKind::Collection),
+            "only collection auctions let bidders pick an nft"
+        );
+        require!(
+            !listing::is_expired(env::block_timestamp(), auction.expiry),
+            "auction is over"
+        );
+        require!(auction.holds(&pick), "this nft is not part of the auction");
+        let Some(bid) = auction
+            .bids
+            .get_mut(&env::predecessor_account_id())
+            .filter(|bid| !bid.paid)
+        else {
+            env::panic_str("bidder has no bid, call `make_bid` first")
+        };
+
+        // Operations
+        bid.pick = Some(pick);
+    }
+
+    /// Places several bids at once, funded by the attached deposit and, if `use_balance` is set,
+    /// then by the caller's balance
+    ///
+    /// A bid that can't be placed doesn't fail the others, the reason is reported in its result
+    /// instead. Whatever is left of the funds is returned as in `make_bid`.
+    #[payable]
+    pub fn make_bids(
+        &mut self,
+        bids: Vec<BidRequest>,
+        use_balance: Option<bool>,
+    ) -> Vec<BidResult> {
+        let bidder = env::predecessor_account_id();
+        let use_balance = use_balance.unwrap_or(false);
+        let mut funds = self.internal_bid_funds(&bidder, use_balance);
+
+        let results = bids
+            .into_iter()
+            .map(|request| {
+                let nft_id = NFTId::new(&request.nft, &request.token_id);
+                let placed = match self.internal_compliance_registry(request.amount) {
+                    Some(_) => Err(
+                        "bids above the compliance threshold have to be placed with `make_bid`"
+                            .into(),
+                    ),
+                    None => self.internal_make_bid(
+                        bidder.clone(),
+                        &nft_id,
+                        request.amount,
+                        request.expires_at.map(u64::from),
+                        funds,
+                        None,
+                        BidOrigin::Direct,
+                    ),
+                };
+                if let Ok(escrowed) = placed {
+                    funds = funds.saturating_sub(escrowed);
+                }
+                let error = placed.err();
+                BidResult {
+                    nft: request.nft,
+                    token_id: request.token_id,
+                    error,
+                }
+            })
+            .collect();
+
+        self.internal_return_funds(bidder, funds, use_balance);
+        results
+    }
+
+    /// Checks the `msg` a seller would pass to `nft_approve` of the `nft` contract against the
+    /// listing rules of this deployment
+    pub fn validate_auction_params(&self, nft: AccountId, msg: String) -> ListingValidation {
+        match serde_json::from_str::<AuctionParams>(&msg) {
+            Ok(params) => ListingValidation {
+                violations: self
+                    .internal_check_listing(&nft, &params)
+                    .err()
+                    .unwrap_or_default(),
+                payment_token_rejection: self.internal_check_payment_token(&params).err(),
+                params: Some(params),
+            },
+            Err(err) => ListingValidation {
+                params: None,
+                violations: vec![format!("Invalid message: {err}")],
+                payment_token_rejection: None,
+            },
+        }
+    }
+
+    /// The deposit `bidder` has to attach to bid `amount` right now, with `memo` left on the bid
+    /// if set, which `penny_bid` and `buy` don't take
+    ///
+    /// The contract covers the storage bids take up, except for that of their memo, so what is
+    /// escrowed, any fee and the memo's storage count.
+    pub fn required_deposit(
+        &self,
+        nft: AccountId,
+        token_id: TokenId,
+        bidder: AccountId,
+        amount: Option<NearToken>,
+        memo: Option<String>,
+    ) -> RequiredDepositView {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        let (escrow, fee) = if let Some(rules) = auction.kind.penny_rules() {
+            let previous = auction.bids.get(&bidder).filter(|bid| !bid.paid);
+            let planned = penny::plan_bid(
+                &rules,
+                &BidContext {
+                    h_bid: auction.h_bid.as_yoctonear(),
+                    expiry: auction.expiry,
+                    bid_interval: auction.bid_interval,
+                    now: env::block_timestamp(),
+                },
+                previous.map_or(0, |bid| bid.amount.as_yoctonear()),
+                previous.map(|bid| bid.last_bid_at),
+                u128::MAX,
+            )
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+            (planned.cost - rules.bid_fee, rules.bid_fee)
+        } else if let Some(rules) = auction.kind.curve() {
+            let price = curve::quote(&rules, auction.sold)
+                .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+            (price, 0)
+        } else {
+            let Some(amount) = amount else {
+                env::panic_str("amount is required for this auction")
+            };
+            let escrow = auction
+                .pay_on_win
+                .as_ref()
+                .map_or(amount.as_yoctonear(), |pay_on_win| {
+                    pay_on_win::required_deposit(&pay_on_win.rules(), amount.as_yoctonear())
+                });
+            (escrow, 0)
+        };
+        let fee = match auction.kind {
+            // Buying off a bonding curve is no bid
+            AuctionKind::BondingCurve { .. } => fee,
+            _ => fee.saturating_add(
+                Self::internal_bid_burn_fee(&self.bid_burn_fees, &auction.kind).as_yoctonear(),
+            ),
+        };
+        let storage = memo.map_or(NearToken::from_yoctonear(0), |memo| {
+            let bytes = validate_memo(&memo).unwrap_or_else(|err| env::panic_str(&err.to_string()));
+            env::storage_byte_cost().saturating_mul(bytes.into())
+        });
+        RequiredDepositView {
+            escrow: NearToken::from_yoctonear(escrow),
+            fee: NearToken::from_yoctonear(fee),
+            storage,
+            total: NearToken::from_yoctonear(escrow.saturating_add(fee)).saturating_add(storage),
+        }
+    }
+
+    /// The NEAR `account_id` still has to `deposit` before it can list, to cover the listing stake
+    pub fn listing_deposit(&self, account_id: AccountId) -> NearToken {
+        self.listing_stake
+            .saturating_sub(self.balance_of(account_id))
+    }
+
+    /// Checks whether `bidder` could bid `amount` right now, assuming they attach enough deposit
+    pub fn simulate_bid(
+        &self,
+        nft: AccountId,
+        token_id: TokenId,
+        bidder: AccountId,
+        amount: NearToken,
+    ) -> BidSimulation {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        let reason = Self::internal_validate_bid(
+            auction,
+            &bidder,
+            amount,
+            None,
+            amount,
+            auction.payment.as_ref(),
+        )
+        .and_then(|()| Self::internal_check_max_bid(auction, self.max_bid, amount))
+        .and_then(|()| {
+            Self::internal_check_bid_capacity(&self.active_bids, self.max_active_bids, &bidder)
+        })
+        .err();
+        let h_bid = match reason {
+            None => auction.simulated_threshold(&bidder, amount),
+            Some(_) => auction.h_bid,
+        };
+        BidSimulation {
+            accepted: reason.is_none(),
+            reason,
+            min_next_bid: NearToken::from_yoctonear(min_next_bid(h_bid.as_yoctonear())),
+        }
+    }
+
+    pub fn get_auction(&self, nft: AccountId, token_id: TokenId) -> AuctionView {
+        self.get_auction_by_id(NFTId::new(&nft, &token_id))
+    }
+
+    pub fn get_auction_by_id(&self, nft_id: NFTId) -> AuctionView {
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        self.internal_auction_view(auction)
+    }
+
+    pub fn get_bids(
+        &self,
+        nft: AccountId,
+        token_id: TokenId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<BidView> {
+        self.get_bids_by_id(NFTId::new(&nft, &token_id), from_index, limit)
+    }
+
+    pub fn get_bids_by_id(
+        &self,
+        nft_id: NFTId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<BidView> {
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        auction
+            .bids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(bidder, bid)| bid.view(bidder.clone()))
+            .collect()
+    }
+
+    /// Every bid placed, raised or reduced on the auction, oldest first, including those since
+    /// replaced, withdrawn or refunded
+    ///
+    /// Past auctions are picked by when they were listed, the ongoing auction otherwise.
+    pub fn get_bid_history(
+        &self,
+        nft: AccountId,
+        token_id: TokenId,
+        listed_at: Option<U64>,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<BidRecord> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let ongoing = self
+            .auctions
+            .get(&nft_id)
+            .filter(|auction| listed_at.is_none_or(|at| auction.listed_at == at.0));
+        let history = match (ongoing, listed_at) {
+            (Some(auction), _) => &auction.history,
+            (None, None) => env::panic_str("this nft is not in auction"),
+            (None, Some(at)) => match self.past_bid_histories.get(&(nft_id, at.0)) {
+                Some(history) => history,
+                // Auctions ended without bids keep no history
+                None => return Vec::new(),
+            },
+        };
+        history
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// The bid of `account_id` on the auction, `paid` once it was refunded or paid out
+    pub fn get_bid(
+        &self,
+        account_id: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+    ) -> Option<BidView> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        auction
+            .bids
+            .get(&account_id)
+            .map(|bid| bid.view(account_id))
+    }
+
+    /// Full records of the ongoing auctions, to snapshot the state before a migration
+    ///
+    /// Their bids are dumped by `export_bids`.
+    pub fn export_auctions(
+        &self,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<AuctionExport> {
+        self.auctions
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(nft_id, auction)| AuctionExport {
+                nft_id: nft_id.clone(),
+                auction: auction.view(),
+                next_bid_sequence: auction.next_bid_sequence.into(),
+                storage_usage: auction.storage_usage.into(),
+                activity: auction.activity.into(),
+                activity_at: auction.activity_at.into(),
+            })
+            .collect()
+    }
+
+    /// All bids of the auction of `nft_id`, refunded or not, as listed by `export_auctions`
+    pub fn export_bids(
+        &self,
+        nft_id: NFTId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<BidView> {
+        self.get_bids_by_id(nft_id, from_index, limit)
+    }
+
+    /// Auctions `token_id` of `nft` went through on this contract and how they ended, oldest
+    /// first
+    pub fn get_provenance(
+        &self,
+        nft: AccountId,
+        token_id: TokenId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<ProvenanceRecord> {
+        let Some(records) = self.provenance.get(&NFTId::new(&nft, &token_id)) else {
+            return Vec::new();
+        };
+        records
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Ongoing auctions of NFTs of the `nft` contract, only those matching `filter` if set
+    ///
+    /// `from_index` and `limit` page through all auctions of the contract, of which only those
+    /// matching are returned, so a page can have fewer than `limit` auctions, or none, before the
+    /// last. The next page starts at `next_index`.
+    pub fn auctions_by_nft_contract(
+        &self,
+        nft: AccountId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+        filter: Option<AuctionFilter>,
+    ) -> AuctionPage {
+        let Some(nft_ids) = self.auctions_by_nft.get(&nft) else {
+            return AuctionPage {
+                auctions: Vec::new(),
+                next_index: None,
+            };
+        };
+        let filter = filter.unwrap_or_default();
+        let now = env::block_timestamp();
+        let from_index = from_index.unwrap_or(0);
+        let page: Vec<&NFTId> = nft_ids
+            .iter()
+            .skip(from_index as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .collect();
+        let end = from_index.saturating_add(page.len() as u32);
+        AuctionPage {
+            auctions: page
+                .into_iter()
+                .filter_map(|nft_id| self.auctions.get(nft_id))
+                .filter(|auction| auction.matches(&filter, now))
+                .map(|auction| self.internal_auction_view(auction))
+                .collect(),
+            next_index: (end < nft_ids.len()).then_some(end),
+        }
+    }
+
+    /// Ongoing auctions with the most bidding activity, most active first
+    ///
+    /// Every bid adds to an auction's activity, which halves every hour.
+    pub fn get_trending(&self, limit: Option<u32>) -> Vec<TrendingView> {
+        let mut board: Vec<(&NFTId, Activity)> = self
+            .trending
+            .iter()
+            .map(|entry| (&entry.nft_id, entry.activity()))
+            .collect();
+        trending::rank(&mut board, env::block_timestamp());
+        board
+            .into_iter()
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(nft_id, activity)| {
+                let auction = self.auctions.get(nft_id).expect("trending auction exists");
+                TrendingView {
+                    nft: auction.nft.clone(),
+                    token_id: auction.token_id.clone(),
+                    bids_placed: auction.next_bid_sequence.into(),
+                    activity: activity.score.into(),
+                }
+            })
+            .collect()
+    }
+
+    /// Accounts that paid the most for the auctions they won, highest first
+    pub fn get_leaderboard(
+        &self,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<LeaderboardEntryView> {
+        self.leaderboard
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(account_id, volume)| LeaderboardEntryView {
+                account_id: account_id.clone(),
+                volume: NearToken::from_yoctonear(*volume),
+            })
+            .collect()
+    }
+
+    pub fn nft_marketplace_metadata(&self) -> MarketplaceMetadata {
+        let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        MarketplaceMetadata {
+            version: env!("CARGO_PKG_VERSION").into(),
+            standards: strings(&["nep141", "nep171", "nep178", "nep245", "nep297"]),
+            receivers: strings(&["nft_on_approve", "ft_on_transfer", "mt_on_transfer"]),
+            auction_kinds: AuctionKindName::ALL
+                .iter()
+                .filter(|kind| !self.disabled_kinds.contains(kind))
+                .map(|kind| kind.as_str().into())
+                .collect(),
+            payment_tokens: std::iter::once("near".to_string())
+                .chain(self.payment_tokens.keys().map(|token| match token {
+                    PaymentToken::Ft { contract } => contract.to_string(),
+                    PaymentToken::Mt { contract, token_id } => format!("{contract}:{token_id}"),
+                }))
+                .collect(),
+        }
+    }
+
+    /// Volume of every recent day or week with sales, oldest first
+    pub fn volume_history(&self, period: VolumePeriod) -> Vec<VolumeBucketView> {
+        let buckets = match period {
+            VolumePeriod::Daily => &self.daily_volume,
+            VolumePeriod::Weekly => &self.weekly_volume,
+        };
+        buckets
+            .iter()
+            .map(|(start, volume)| VolumeBucketView {
+                start: U64(*start),
+                volume: NearToken::from_yoctonear(*volume),
+            })
+            .collect()
+    }
+
+    pub fn account_stats(&self, account_id: AccountId) -> AccountStats {
+        self.account_stats
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// NEAR paid for all auctions `account_id` won
+    pub fn winning_volume_of(&self, account_id: AccountId) -> NearToken {
+        self.winning_volumes
+            .get(&account_id)
+            .copied()
+            .unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    pub fn counts(&self) -> CountsView {
+        CountsView {
+            auctions: self.auctions.len(),
+            bids_outstanding: self.bids_outstanding.into(),
+            payments_pending: self.payments_pending,
+            balance_holders: self.balance_holders,
+        }
+    }
+
+    /// Whether there are no ongoing auctions
+    pub fn is_empty(&self) -> bool {
+        self.auctions.is_empty()
+    }
+
+    pub fn expired(&self, nft: AccountId, token_id: TokenId) -> bool {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        listing::is_expired(env::block_timestamp(), auction.expiry)
+    }
+
+    /// The account entitled to claim `token_id`, if it has been won in an auction
+    pub fn nft_claim_of(&self, nft: AccountId, token_id: TokenId) -> Option<AccountId> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        self.nft_claims
+            .get(&nft_id)
+            .map(|claim| claim.winner.clone())
+    }
+
+    /// The balance `account_id` can `withdraw`
+    pub fn balance_of(&self, account_id: AccountId) -> NearToken {
+        self.balances
+            .get(&account_id)
+            .copied()
+            .unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    pub fn fees_accrued(&self) -> NearToken {
+        self.fees_accrued
+    }
+
+    pub fn nft_deposits_paid(&self) -> NearToken {
+        self.nft_deposits_paid
+    }
+
+    pub fn gc_staleness(&self) -> U64 {
+        (self.gc_staleness / time::NANOS_PER_SEC).into()
+    }
+
+    pub fn listing_stake(&self) -> NearToken {
+        self.listing_stake
+    }
+
+    pub fn slash_treasury_bps(&self) -> u16 {
+        self.slash_treasury_bps
+    }
+
+    pub fn wash_trade_rules(&self) -> Option<WashTradeConfig> {
+        self.wash_trade_rules.clone()
+    }
+
+    pub fn protocol_fees(&self) -> NearToken {
+        self.protocol_fees
+    }
+
+    pub fn max_active_bids(&self) -> Option<u32> {
+        self.max_active_bids
+    }
+
+    pub fn max_bid(&self) -> Option<NearToken> {
+        self.max_bid
+    }
+
+    /// Number of auctions the account is bidding in, and how many more it may join
+    pub fn bid_capacity_of(&self, account_id: AccountId) -> BidCapacityView {
+        let active = self.active_bids.get(&account_id).copied().unwrap_or(0);
+        BidCapacityView {
+            active,
+            remaining: self.max_active_bids.map(|max| max.saturating_sub(active)),
+        }
+    }
+
+    pub fn listing_capacity_of(&self, account_id: AccountId) -> ListingCapacityView {
+        let active = self.active_listings.get(&account_id).copied().unwrap_or(0);
+        ListingCapacityView {
+            active,
+            remaining: self
+                .max_active_listings
+                .map(|max| max.saturating_sub(active)),
+        }
+    }
+
+    pub fn reputation_of(&self, account_id: AccountId) -> Reputation {
+        self.reputations
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn storage_usage_of(&self, nft: AccountId, token_id: TokenId) -> StorageUsageView {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        auction.storage_usage.into()
+    }
+
+    /// What the contract owes, next to what it holds, to check that it stays solvent
+    pub fn reconcile(&self) -> ReconciliationView {
+        let storage_staked = env::storage_byte_cost().saturating_mul(env::storage_usage().into());
+        let liabilities = [
+            self.bids_escrowed,
+            self.offers_escrowed,
+            self.stakes_escrowed,
+            self.bid_fees_escrowed,
+            self.proceeds_held,
+            self.balances_total,
+            self.owed_total,
+            self.protocol_fees,
+            self.burn_pending,
+            self.staking_rewards,
+            self.mint_deposits_held,
+        ]
+        .into_iter()
+        .fold(NearToken::from_yoctonear(0), NearToken::saturating_add);
+        ReconciliationView {
+            bids_escrowed: self.bids_escrowed,
+            offers_escrowed: self.offers_escrowed,
+            stakes_escrowed: self.stakes_escrowed,
+            bid_fees_escrowed: self.bid_fees_escrowed,
+            proceeds_held: self.proceeds_held,
+            balances: self.balances_total,
+            owed: self.owed_total,
+            protocol_fees: self.protocol_fees,
+            burn_pending: self.burn_pending,
+            staking_rewards: self.staking_rewards,
+            mint_deposits_held: self.mint_deposits_held,
+            liabilities,
+            storage_staked,
+            liquid: env::account_balance().saturating_sub(storage_staked),
+        }
+    }
+
+    pub fn storage_usage_totals(&self) -> StorageUsageTotalsView {
+        StorageUsageTotalsView {
+            auctions: self.storage_usage.into(),
+            contract: env::storage_usage().into(),
+        }
+    }
+}
+
+impl Contract {
+    fn internal_start_auction(
+        &mut self,
+        owner_id: AccountId,
+        nft: AccountId,
+        token_id: TokenId,
+        expiry: u64,
+        params: AuctionParams,
+        asset: AuctionedAsset,
+    ) -> &mut Auction {
+        let initial_storage_usage = env::storage_usage();
+        let nft_id = NFTId::new(&nft, &token_id);
+        // Checked at listing, a policy removed since the NFT was escrowed falls back to the default
+        let fee_policy = self.internal_fee_policy(&params).unwrap_or_default();
+        let mut auction = Auction {
+            owner: owner_id,
+            nft,
+            token_id,
+            // Stored empty, so adding to it in a later call finds it
+            lot: LazyOption::new(
+                StorageKey::Lot {
+                    nft_id: nft_id.clone(),
+                },
+                params.kind.has_lot().then(Vec::new),
+            ),
+            sold: 0,
+            bids: IterableMap::new(StorageKey::Bids {
+                nft_id: nft_id.clone(),
+            }),
+            history: Vector::new(StorageKey::BidHistory {
+                nft_id: nft_id.clone(),
+                listed_at: env::block_timestamp(),
+            }),
+            h_bid: params.minimum_bid,
+            minimum_bid: params.minimum_bid,
+            expiry,
+            push_nft: params.push_nft,
+            bid_interval: time::saturating_secs_to_nanos(params.bid_interval.unwrap_or(0)),
+            next_bid_sequence: 0,
+            kind: params.kind,
+            fees_accrued: NearToken::from_yoctonear(0),
+            bids_escrowed: NearToken::from_yoctonear(0),
+            storage_usage: 0,
+            on_unsold: params.on_unsold.unwrap_or_default(),
+            asset,
+            stake: NearToken::from_yoctonear(0),
+            dispute_window: time::saturating_secs_to_nanos(params.dispute_window.unwrap_or(0)),
+            proceeds_delay: time::saturating_secs_to_nanos(params.proceeds_delay.unwrap_or(0)),
+            settlement_delay: listing::settlement_delay(params.settlement_delay.unwrap_or(0))
+                .unwrap_or_else(|err| env::panic_str(&err.to_string())),
+            approval_id: None,
+            escrowless: false,
+            listed_at: env::block_timestamp(),
+            pay_on_win: params.pay_on_win,
+            pending_payment: None,
+            payment: params.payment_token,
+            activity: 0,
+            activity_at: 0,
+            mint_metadata: LazyOption::new(
+                StorageKey::MintMetadata {
+                    nft_id: nft_id.clone(),
+                },
+                None,
+            ),
+            fee_policy,
+            max_bid: params.max_bid,
+        };
+        self.auctions_by_nft
+            .entry(auction.nft.clone())
+            .or_insert_with(|| {
+                IterableSet::new(StorageKey::AuctionsOfNft {
+                    nft: auction.nft.clone(),
+                })
+            })
+            .insert(nft_id.clone());
+        self.internal_index_expiry(&nft_id, auction.expiry);
+        *self
+            .active_listings
+            .entry(auction.owner.clone())
+            .or_insert(0) += 1;
+        // Escrowless NFTs stay with their owner and lazily minted ones don't exist yet
+        if matches!(auction.asset, AuctionedAsset::Nft) && !params.escrowless {
+            self.internal_refresh_royalties(&auction.nft, &auction.token_id);
+        }
+        self.internal_announce(format!(
+            "New auction of {} from {}, bids from {}",
+            auction.token_id, auction.nft, auction.minimum_bid
+        ));
+        AuctionEvent::AuctionStarted {
+            nft: auction.nft.clone(),
+            token_id: auction.token_id.clone(),
+            owner: auction.owner.clone(),
+            minimum_bid: auction.minimum_bid,
+            expiry: auction.expiry.into(),
+        }
+        .emit();
+        auction.lot.flush();
+        self.auctions.insert(nft_id.clone(), auction);
+
+        // Collections write lazily, flush to measure the listing
+        self.auctions.flush();
+        self.auctions_by_nft.flush();
+        let storage_usage = env::storage_usage() - initial_storage_usage;
+        self.storage_usage += storage_usage;
+        let auction = self
+            .auctions
+            .get_mut(&nft_id)
+            .expect("auction was just inserted");
+        auction.storage_usage = storage_usage;
+        auction
+    }
+
+    fn internal_plan_settlement(auction: &Auction) -> Payout {
+        let mut nfts: Vec<TokenId> = auction.nfts().cloned().collect();
+        let Some(outcome) = auction
+            .kind
+            .engine()
+            .plan_settlement(&mut auction.settlement_bids(), &auction.engine_lot())
+        else {
+            return Payout {
+                winners: Vec::new(),
+                nfts,
+                proceeds: 0,
+                refunds: Vec::new(),
+            };
+        };
+        // The winner of a collection auction gets the NFT they picked
+        if let [winner] = outcome.winners.as_slice() {
+            let pick = auction.bids.get(winner).and_then(|bid| bid.pick.clone());
+            if let Some(position) = pick.and_then(|pick| nfts.iter().position(|t| *t == pick)) {
+                nfts.swap(0, position);
+            }
+        }
+        Payout {
+            proceeds: outcome.price.saturating_mul(outcome.winners.len() as u128),
+            winners: outcome.winners,
+            nfts,
+            refunds: auction.escrowed_refunds(outcome.refunds),
+        }
+    }
+
+    /// Checks that `lead` is in an auction by `owner_id` that `token_id` can join, then
+    /// moves `token_id` into its lot
+    fn internal_add_to_lot(
+        &mut self,
+        owner_id: AccountId,
+        nft: AccountId,
+        lead: TokenId,
+        token_id: TokenId,
+        approval_id: u64,
+    ) -> PromiseOrValue<String> {
+        // Validations
+        let Some(auction) = self
+            .auctions
+            .get(&NFTId::new(&nft, &lead))
+            .filter(|auction| matches!(auction.asset, AuctionedAsset::Nft))
+        else {
+            env::panic_str("this nft is not in auction")
+        };
+        require!(
+            auction.kind.has_lot(),
+            "only uniform and collection auctions have lots"
+        );
+        require!(
+            auction.owner == owner_id,
+            "only the owner of the auction can add to its lot"
+        );
+        require!(
+            !listing::is_expired(env::block_timestamp(), auction.expiry),
+            "auction is over"
+        );
+        self.internal_check_listable(&owner_id, &nft, &token_id)
+            .unwrap_or_else(|err| env::panic_str(&err));
+
+        // Operations
+        let promise = ext_nft_core::ext(nft.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .nft_transfer(
+                env::current_account_id(),
+                token_id.clone().into(),
+                Some(approval_id),
+                Some("Added to auction lot".into()),
+            )
+            .then(Self::ext(env::current_account_id()).add_to_lot(owner_id, nft, lead, token_id));
+        PromiseOrValue::Promise(promise)
+    }
+
+    /// Places the bid of `make_bid` on the auction of `nft_id`
+    fn internal_make_bid_call(
+        &mut self,
+        nft_id: NFTId,
+        amount: NearToken,
+        expires_at: Option<U64>,
+        use_balance: Option<bool>,
+        memo: Option<String>,
+    ) -> PromiseOrValue<()> {
+        let bidder = env::predecessor_account_id();
+        let use_balance = use_balance.unwrap_or(false);
+        let deposit = self.internal_bid_funds(&bidder, use_balance);
+        if let Some(registry) = self.internal_compliance_registry(amount) {
+            // Fail early, the bid is validated again once the registry answers
+            let Some(auction) = self.auctions.get(&nft_id) else {
+                env::panic_str("this nft is not in auction")
+            };
+            Self::internal_validate_bid(
+                auction,
+                &bidder,
+                amount,
+                expires_at.map(u64::from),
+                deposit,
+                None,
+            )
+            .unwrap_or_else(|err| env::panic_str(&err));
+            if let Some(memo) = &memo {
+                validate_memo(memo).unwrap_or_else(|err| env::panic_str(&err.to_string()));
+            }
+            return PromiseOrValue::Promise(Self::internal_check_compliance(
+                registry,
+                bidder,
+                auction.nft.clone(),
+                auction.token_id.clone(),
+                CheckedBid::Make {
+                    amount,
+                    expires_at,
+                    memo,
+                },
+                deposit,
+                use_balance,
+            ));
+        }
+        let taken = self
+            .internal_make_bid_with_memo(
+                bidder.clone(),
+                &nft_id,
+                amount,
+                expires_at.map(u64::from),
+                deposit,
+                memo,
+            )
+            .unwrap_or_else(|err| env::panic_str(&err));
+        self.internal_return_funds(bidder, deposit.saturating_sub(taken), use_balance);
+        PromiseOrValue::Value(())
+    }
+
+    /// Marks the lapsed bid of `bidder` on the auction of `nft_id` as refunded, returning the
+    /// token it was paid in, `None` for NEAR, and what it escrowed for the caller to send back
+    fn internal_withdraw_lapsed_bid(
+        &mut self,
+        nft_id: &NFTId,
+        bidder: &AccountId,
+    ) -> Result<(Option<PaymentToken>, NearToken), &'static str> {
+        // Validations
+        let Some(auction) = self.auctions.get_mut(nft_id) else {
+            return Err("this nft is not in auction");
+        };
+        let Some(bid) = auction.bids.get_mut(bidder).filter(|bid| !bid.paid) else {
+            return Err("bidder has no bid to withdraw");
+        };
+        if !is_lapsed(
+            env::block_timestamp(),
+            bid.expires_at,
+            bid.amount.as_yoctonear(),
+            auction.h_bid.as_yoctonear(),
+        ) {
+            return Err("bid has not lapsed, it is either not expired or still the highest bid");
+        }
+
+        // Operations
+        bid.paid = true;
+        let amount = bid.escrowed();
+        accounting::sub(&mut auction.bids_escrowed, amount);
+        let payment = auction.payment.clone();
+        self.internal_remove_active_bid(bidder, nft_id);
+        Ok((payment, amount))
+    }
+
+    /// Settles the auction of `nft_id` if it expired
+    fn internal_end_auction(&mut self, nft_id: &NFTId) -> PromiseOrValue<()> {
+        // Validations
+        let Some(auction) = self.auctions.get(nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+        let now = env::block_timestamp();
+        require!(
+            listing::is_expired(now, auction.expiry),
+            "cannot end, auction is still ongoing"
+        );
+        require!(
+            listing::is_expired(
+                now,
+                listing::settles_at(auction.expiry, auction.settlement_delay)
+            ),
+            "cannot end yet, auction is in its settlement delay"
+        );
+
+        // Operations
+        match self.internal_end(nft_id) {
+            Some(promise) => PromiseOrValue::Promise(promise),
+            None => PromiseOrValue::Value(()),
+        }
+    }
+
+    /// Checks `params` against the listing rules, returning the expiry of an auction started now
+    /// or every rule that was violated
+    fn internal_check_listing(
+        &self,
+        nft: &AccountId,
+        params: &AuctionParams,
+    ) -> Result<u64, Vec<String>> {
+        let mut violations = Vec::new();
+        let expiry = listing::expiry(env::block_timestamp(), params.timespan)
+            .map_err(|err| violations.push(err.to_string()));
+        if let Err(err) =
+            listing::check_min_timespan(params.timespan, self.internal_min_timespan(nft))
+        {
+            violations.push(err.to_string());
+        }
+        let kind = params.kind.name();
+        if self.disabled_kinds.contains(&kind) {
+            violations.push(format!("{} auctions are disabled", kind.as_str()));
+        }
+        if params
+            .max_bid
+            .is_some_and(|max_bid| max_bid <= params.minimum_bid)
+        {
+            violations.push("`max_bid` has to exceed `minimum_bid`".into());
+        }
+        if let Some(rules) = params.kind.penny_rules() {
+            if let Err(err) = penny::validate_rules(&rules) {
+                violations.push(err.to_string());
+            }
+        }
+        if let Some(curve) = params.kind.curve() {
+            if let Err(err) = curve::validate_curve(&curve) {
+                violations.push(err.to_string());
+            }
+        }
+        let countdown = match params.kind {
+            AuctionKind::Penny { countdown, .. } => Some(countdown),
+            _ => None,
+        };
+        let durations = [
+            ("bid_interval", params.bid_interval),
+            ("dispute_window", params.dispute_window),
+            ("proceeds_delay", params.proceeds_delay),
+            ("countdown", countdown),
+            (
+                "payment_window",
+                params.pay_on_win.as_ref().map(|pay| pay.payment_window),
+            ),
+        ];
+        for (name, secs) in durations {
+            if secs.is_some_and(|secs| time::secs_to_nanos(secs).is_none()) {
+                violations.push(format!("`{name}` is too long"));
+            }
+        }
+        if let Err(err) = listing::settlement_delay(params.settlement_delay.unwrap_or(0)) {
+            violations.push(err.to_string());
+        }
+        if params.dispute_window.is_some_and(|window| window > 0) && self.arbiter.is_none() {
+            violations.push("disputes need an arbiter, none is set".into());
+        }
+        if let Some(UnsoldAction::Fractionalize { after: 0, .. }) = params.on_unsold {
+            violations.push("fractionalizing requires `after` to be at least 1".into());
+        }
+        if let Some(pay_on_win) = &params.pay_on_win {
+            if let Err(err) = pay_on_win::validate_rules(&pay_on_win.rules()) {
+                violations.push(err.to_string());
+            }
+            if !matches!(params.kind, AuctionKind::English) {
+                violations.push("only english auctions can be pay-on-win".into());
+            }
+        }
+        if params.escrowless && params.kind.has_lot() {
+            violations.push("escrowless auctions can't have a lot".into());
+        }
+        if params.escrowless && params.on_unsold.is_some() {
+            violations.push("escrowless auctions leave unsold nfts with their owner".into());
+        }
+        if let Err(rejection) = self.internal_check_payment_token(params) {
+            violations.push(rejection.to_string());
+        }
+        if let Err(err) = self.internal_fee_policy(params) {
+            violations.push(err);
+        }
+        if params.payment_token.is_some() {
+            if params.fee_policy.is_some() {
+                violations
+                    .push("auctions taking bids in tokens pay the token's fee instead".into());
+            }
+            if !matches!(params.kind, AuctionKind::English) {
+                violations.push("only english auctions can take bids in tokens".into());
+            }
+            if params.pay_on_win.is_some() {
+                violations.push("auctions taking bids in tokens can't be pay-on-win".into());
+            }
+            if params.escrowless || params.push_nft {
+                violations.push("auctions taking bids in tokens have their nft claimed".into());
+            }
+            if params.dispute_window.is_some_and(|window| window > 0)
+                || params.proceeds_delay.is_some_and(|delay| delay > 0)
+            {
+                violations
+                    .push("auctions taking bids in tokens pay their proceeds right away".into());
+            }
+        }
+        match expiry {
+            Ok(expiry) if violations.is_empty() => Ok(expiry),
+            _ => Err(violations),
+        }
+    }
+
+    /// Checks whether `bidder`, who has `funds` of `payment` available, may bid `amount` on
+    /// `auction`
+    fn internal_validate_bid(
+        auction: &Auction,
+        bidder: &AccountId,
+        amount: NearToken,
+        expires_at: Option<u64>,
+        funds: NearToken,
+        payment: Option<&PaymentToken>,
+    ) -> Result<(), String> {
+        Self::internal_check_payment(auction, payment)?;
+        auction
+            .kind
+            .engine::<AccountId>()
+            .validate_bid(
+                &BidContext {
+                    h_bid: auction.h_bid.as_yoctonear(),
+                    expiry: auction.expiry,
+                    bid_interval: auction.bid_interval,
+                    now: env::block_timestamp(),
+                },
+                &BidAttempt {
+                    amount: amount.as_yoctonear(),
+                    deposit: funds.as_yoctonear(),
+                    already_bid: auction.bids.contains_key(bidder),
+                    expires_at,
+                    pay_on_win: auction.pay_on_win.as_ref().map(PayOnWin::rules),
+                },
+            )
+            .map_err(|err| err.to_string())
+    }
+
+    /// Places a bid of `amount` by `bidder`, who has `funds` of `payment`, a token or NEAR if
+    /// `None`, available to cover it, returning how much of the funds it took: what is
+    /// escrowed plus any burn fee
+    #[allow(clippy::too_many_arguments)]
+    fn internal_make_bid(
+        &mut self,
+        bidder: AccountId,
+        nft_id: &NFTId,
+        amount: NearToken,
+        expires_at: Option<u64>,
+        funds: NearToken,
+        payment: Option<&PaymentToken>,
+        origin: BidOrigin,
+    ) -> Result<NearToken, String> {
+        // Validations
+        let Some(auction) = self.auctions.get_mut(nft_id) else {
+            return Err("this nft is not in auction".into());
+        };
+        // Burn fees are in NEAR, bids paid in tokens don't pay them
+        let burn_fee = match payment {
+            Some(_) => NearToken::from_yoctonear(0),
+            None => Self::internal_bid_burn_fee(&self.bid_burn_fees, &auction.kind),
+        };
+        let Some(funds) = funds.checked_sub(burn_fee) else {
+            return Err("attached deposit does not cover the bid fee".into());
+        };
+        Self::internal_validate_bid(auction, &bidder, amount, expires_at, funds, payment)?;
+        Self::internal_check_max_bid(auction, self.max_bid, amount)?;
+        Self::internal_check_bid_capacity(&self.active_bids, self.max_active_bids, &bidder)?;
+
+        // Operations
+        let initial_storage_usage = env::storage_usage();
+        let now = env::block_timestamp();
+        let deposit = auction.pay_on_win.as_ref().map(|pay_on_win| {
+            NearToken::from_yoctonear(pay_on_win::required_deposit(
+                &pay_on_win.rules(),
+                amount.as_yoctonear(),
+            ))
+        });
+        let escrowed = deposit.unwrap_or(amount);
+        auction.bids.insert(
+            bidder.clone(),
+            Bid {
+                amount,
+                paid: false,
+                expires_at,
+                placed_at: now,
+                last_bid_at: now,
+                sequence: auction.next_bid_sequence,
+                pick: None,
+                deposit,
+                fees_paid: NearToken::from_yoctonear(0),
+                payment: payment.cloned(),
+                origin,
+                memo: None,
+            }
+            .into(),
+        );
+        auction.next_bid_sequence += 1;
+        auction.record_bid(&bidder, amount, now);
+        AuctionEvent::BidPlaced {
+            nft: auction.nft.clone(),
+            token_id: auction.token_id.clone(),
+            bidder: bidder.clone(),
+            amount,
+        }
+        .emit();
+        Self::internal_update_trending(&mut self.trending, nft_id, auction.activity());
+
+        // Collections write lazily, flush to measure the bid
+        auction.bids.flush();
+        auction.history.flush();
+        let storage_usage = env::storage_usage() - initial_storage_usage;
+        auction.storage_usage += storage_usage;
+        self.storage_usage += storage_usage;
+        accounting::add(&mut auction.bids_escrowed, escrowed);
+        if payment.is_none() {
+            accounting::add(&mut self.bids_escrowed, escrowed);
+        }
+        self.internal_burn(burn_fee);
+        self.internal_add_active_bid(&bidder, nft_id);
+        Ok(escrowed.saturating_add(burn_fee))
+    }
+
+    /// Places a NEAR bid as `internal_make_bid` does with `memo` left on it, whose storage is paid
+    /// out of `funds` as well, returning how much of the funds it took
+    fn internal_make_bid_with_memo(
+        &mut self,
+        bidder: AccountId,
+        nft_id: &NFTId,
+        amount: NearToken,
+        expires_at: Option<u64>,
+        funds: NearToken,
+        memo: Option<String>,
+    ) -> Result<NearToken, String> {
+        let Some(memo) = memo else {
+            return self.internal_make_bid(
+                bidder,
+                nft_id,
+                amount,
+                expires_at,
+                funds,
+                None,
+                BidOrigin::Direct,
+            );
+        };
+        let bytes = validate_memo(&memo).map_err(|err| err.to_string())?;
+        let cost = env::storage_byte_cost().saturating_mul(bytes.into());
+        let Some(funds) = funds.checked_sub(cost) else {
+            return Err("attached deposit does not cover the storage of the memo".into());
+        };
+        let taken = self.internal_make_bid(
+            bidder.clone(),
+            nft_id,
+            amount,
+            expires_at,
+            funds,
+            None,
+            BidOrigin::Direct,
+        )?;
+        let auction = self.auctions.get_mut(nft_id).expect("auction exists");
+        auction.bids.get_mut(&bidder).expect("bid exists").memo = Some(memo);
+        auction.storage_usage += bytes;
+        self.storage_usage += bytes;
+        Ok(taken.saturating_add(cost))
+    }
+
+    /// Puts the auction of `nft_id` on the `trending` board with its current `activity`
+    fn internal_update_trending(
+        trending: &mut Vec<TrendingEntry>,
+        nft_id: &NFTId,
+        activity: Activity,
+    ) {
+        let mut board: Vec<(NFTId, Activity)> = trending
+            .drain(..)
+            .map(|entry| (entry.nft_id.clone(), entry.activity()))
+            .collect();
+        trending::update_board(
+            &mut board,
+            nft_id.clone(),
+            activity,
+            activity.at,
+            TRENDING_CAPACITY,
+        );
+        trending.extend(
+            board
+                .into_iter()
+                .map(|(nft_id, activity)| TrendingEntry::new(nft_id, activity)),
+        );
+    }
+
+    /// Checks that `bidder` can place a bid in one more auction
+    fn internal_check_bid_capacity(
+        active_bids: &LookupMap<AccountId, u32>,
+        max_active_bids: Option<u32>,
+        bidder: &AccountId,
+    ) -> Result<(), String> {
+        let active = active_bids.get(bidder).copied().unwrap_or(0);
+        match max_active_bids {
+            Some(max) if active >= max => Err(format!(
+                "bidder already has {active} active bids, the most allowed"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a bid of `amount` does not exceed the cap of `auction`, its own or else
+    /// `max_bid` if it takes NEAR
+    fn internal_check_max_bid(
+        auction: &Auction,
+        max_bid: Option<NearToken>,
+        amount: NearToken,
+    ) -> Result<(), String> {
+        let max_bid = auction
+            .max_bid
+            .or(max_bid.filter(|_| auction.payment.is_none()));
+        match max_bid {
+            Some(max) if amount > max => Err(match auction.payment {
+                None => format!(
+                    "bid of {} exceeds the maximum bid of {}",
+                    amount.exact_amount_display(),
+                    max.exact_amount_display()
+                ),
+                Some(_) => format!(
+                    "bid of {} exceeds the maximum bid of {} in the auction's token",
+                    amount.as_yoctonear(),
+                    max.as_yoctonear()
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The fee burned on every bid in an auction of `kind`
+    fn internal_bid_burn_fee(
+        bid_burn_fees: &[(AuctionKindName, NearToken)],
+        kind: &AuctionKind,
+    ) -> NearToken {
+        bid_burn_fees
+            .iter()
+            .find(|(burned, _)| *burned == kind.name())
+            .map_or(NearToken::from_yoctonear(0), |(_, fee)| *fee)
+    }
+
+    /// Sets `fee` aside to be burned
+    fn internal_burn(&mut self, fee: NearToken) {
+        accounting::add(&mut self.burn_pending, fee);
+        accounting::add(&mut self.burned_total, fee);
+    }
+
+    /// Checks that `seller` can start one more auction
+    fn internal_check_listing_capacity(&self, seller: &AccountId) -> Result<(), String> {
+        let active = self.active_listings.get(seller).copied().unwrap_or(0);
+        match self.max_active_listings {
+            Some(max) if active >= max => Err(format!(
+                "seller already has {active} active auctions, the most allowed"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn internal_add_active_bid(&mut self, bidder: &AccountId, nft_id: &NFTId) {
+        *self.active_bids.entry(bidder.clone()).or_insert(0) += 1;
+        self.bids_outstanding += 1;
+        self.auctions_by_bidder
+            .entry(bidder.clone())
+            .or_insert_with(|| {
+                IterableSet::new(StorageKey::AuctionsOfBidder {
+                    bidder: bidder.clone(),
+                })
+            })
+            .insert(nft_id.clone());
+    }
+
+    /// Called once a bid is refunded or its auction is over
+    fn internal_remove_active_bid(&mut self, bidder: &AccountId, nft_id: &NFTId) {
+        if let Some(nft_ids) = self.auctions_by_bidder.get_mut(bidder) {
+            nft_ids.remove(nft_id);
+            if nft_ids.is_empty() {
+                self.auctions_by_bidder.remove(bidder);
+            }
+        }
+        let Some(active) = self.active_bids.get_mut(bidder) else {
+            return;
+        };
+        *active = active.saturating_sub(1);
+        if *active == 0 {
+            self.active_bids.remove(bidder);
+        }
+        self.bids_outstanding = self.bids_outstanding.saturating_sub(1);
+    }
+
+    /// Removes an auction and pays out according to its bids
+    fn internal_settle(&mut self, nft_id: &NFTId) -> Option<Promise> {
+        let auction = self.auctions.get(nft_id).expect("auction exists");
+        let payout = Self::internal_plan_settlement(auction);
+        let auction = self.internal_remove_auction(nft_id);
+        // Every winner pays the same
+        let price = NearToken::from_yoctonear(
+            payout
+                .proceeds
+                .checked_div(payout.winners.len() as u128)
+                .unwrap_or(0),
+        );
+        let outcomes = payout.nfts.iter().enumerate().map(|(i, token_id)| {
+            let outcome = match payout.winners.get(i) {
+                Some(buyer) => AuctionOutcome::Sold {
+                    buyer: buyer.clone(),
+                    price,
+                },
+                None => AuctionOutcome::Unsold,
+            };
+            (token_id.clone(), outcome)
+        });
+        self.internal_record_provenance(&auction, outcomes.collect());
+        AuctionEvent::AuctionSettled {
+            nft: auction.nft.clone(),
+            token_id: auction.token_id.clone(),
+            owner: auction.owner.clone(),
+            winners: payout.winners.clone(),
+            price,
+        }
+        .emit();
+        let returned = auction.fees_accrued.saturating_add(auction.stake);
+        if !returned.is_zero() {
+            self.internal_credit(auction.owner.clone(), returned);
+        }
+        let fractionalizer = if payout.winners.is_empty() {
+            self.internal_record_unsold(nft_id, &auction.on_unsold)
+        } else {
+            self.unsold_streaks.remove(nft_id);
+            self.internal_announce(format!(
+                "Auction of {} from {} won by {} for {}",
+                auction.token_id,
+                auction.nft,
+                payout
+                    .winners
+                    .iter()
+                    .map(|winner| winner.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                NearToken::from_yoctonear(payout.proceeds)
+            ));
+            None
+        };
+        self.internal_pay_out(
+            auction,
+            payout,
+            fractionalizer,
+            "Auction ended without bids",
+        )
+    }
+
+    /// Removes an auction that ends without a winner, refunding every outstanding bid
+    ///
+    /// If `slash_owner` is set, the owner's stake and the bid fees collected are shared among the
+    /// bidders, or returned to the owner if there are none. Otherwise every bidder gets the bid
+    /// fees they paid back and the owner only their stake. `nfts` go back to the owner with
+    /// `memo`.
+    fn internal_cancel(
+        &mut self,
+        nft_id: &NFTId,
+        nfts: Vec<TokenId>,
+        memo: &str,
+        slash_owner: bool,
+    ) -> Option<Promise> {
+        let auction = self.auctions.get(nft_id).expect("auction exists");
+        require!(
+            !matches!(
+                auction.asset,
+                AuctionedAsset::LazyMint { minting: true, .. }
+            ),
+            "the nft of this auction is being minted to its winner"
+        );
+        let slashed = auction.stake.saturating_add(auction.fees_accrued);
+        let mut refunds = auction.escrowed_refunds(plan_cancellation(
+            auction.settlement_bids(),
+            if slash_owner {
+                slashed.as_yoctonear()
+            } else {
+                0
+            },
+        ));
+        if !slash_owner {
+            for (bidder, bid) in auction.bids.iter() {
+                let fees_paid = bid.fees_paid.as_yoctonear();
+                if fees_paid == 0 {
+                    continue;
+                }
+                match refunds.iter_mut().find(|(refunded, _)| refunded == bidder) {
+                    Some((_, amount)) => *amount += fees_paid,
+                    None => refunds.push((bidder.clone(), fees_paid)),
+                }
+            }
+        }
+        let payout = Payout {
+            winners: Vec::new(),
+            nfts,
+            proceeds: 0,
+            refunds,
+        };
+        let held: Vec<TokenId> = auction.nfts().cloned().collect();
+        let auction = self.internal_remove_auction(nft_id);
+        let outcomes = held
+            .into_iter()
+            .map(|token_id| (token_id, AuctionOutcome::Cancelled));
+        self.internal_record_provenance(&auction, outcomes.collect());
+        AuctionEvent::AuctionCancelled {
+            nft: auction.nft.clone(),
+            token_id: auction.token_id.clone(),
+            owner: auction.owner.clone(),
+        }
+        .emit();
+        if !slash_owner {
+            self.internal_credit(auction.owner.clone(), auction.stake);
+        } else if payout.refunds.is_empty() {
+            self.internal_credit(auction.owner.clone(), slashed);
+        }
+        self.internal_pay_out(auction, payout, None, memo)
+    }
+
+    /// Settles an expired auction, once its NFT is checked to still be there if it sells one
+    ///
+    /// Pay-on-win auctions wait for their winner to pay in full first.
+    fn internal_end(&mut self, nft_id: &NFTId) -> Option<Promise> {
+        if self.internal_await_payment(nft_id) {
+            return None;
+        }
+        match self.auctions.get(nft_id).expect("auction exists").asset {
+            // Don't settle into a void if the NFT was burned or moved meanwhile
+            AuctionedAsset::Nft => Some(self.internal_check_escrow(nft_id, true)),
+            AuctionedAsset::Ft { .. } => self.internal_settle(nft_id),
+            AuctionedAsset::LazyMint { .. } => self.internal_mint_to_winner(nft_id),
+        }
+    }
+
+    /// Has the winner of a pay-on-win auction pay the rest of their bid, returning whether the
+    /// auction is waiting on them
+    ///
+    /// A winner that let their payment window pass loses the win and their deposit, which is split
+    /// between the seller and the protocol fees. The next highest bid wins instead.
+    fn internal_await_payment(&mut self, nft_id: &NFTId) -> bool {
+        let now = env::block_timestamp();
+        let auction = self.auctions.get_mut(nft_id).expect("auction exists");
+        let Some(rules) = auction.pay_on_win.as_ref().map(PayOnWin::rules) else {
+            return false;
+        };
+        if let Some(pending) = &auction.pending_payment {
+            if !listing::is_expired(now, pending.due) {
+                return true;
+            }
+            let defaulted = pending.winner.clone();
+            auction.pending_payment = None;
+            self.payments_pending -= 1;
+            let bid = auction.bids.get_mut(&defaulted).expect("winner has a bid");
+            bid.paid = true;
+            let slashed = bid.escrowed();
+            accounting::sub(&mut auction.bids_escrowed, slashed);
+            accounting::sub(&mut self.bids_escrowed, slashed);
+            let (seller_share, treasury_share) =
+                split_slash(slashed.as_yoctonear(), self.slash_treasury_bps);
+            let (seller_share, treasury_share) = (
+                NearToken::from_yoctonear(seller_share),
+                NearToken::from_yoctonear(treasury_share),
+            );
+            AuctionEvent::WinnerDefaulted {
+                nft: auction.nft.clone(),
+                token_id: auction.token_id.clone(),
+                winner: defaulted.clone(),
+                slashed,
+                seller_share,
+                treasury_share,
+            }
+            .emit();
+            let owner = auction.owner.clone();
+            self.internal_remove_active_bid(&defaulted, nft_id);
+            self.internal_credit(owner, seller_share);
+            self.internal_collect_protocol_fee(treasury_share);
+        }
+
+        let auction = self.auctions.get_mut(nft_id).expect("auction exists");
+        let Settlement::Sold { winner, .. } = plan_settlement(auction.settlement_bids()) else {
+            return false;
+        };
+        let bid = auction.bids.get(&winner).expect("winner has a bid");
+        let amount_due = bid.amount.saturating_sub(bid.escrowed());
+        if amount_due.is_zero() {
+            return false;
+        }
+        let due = now.saturating_add(rules.payment_window);
+        AuctionEvent::PaymentDue {
+            nft: auction.nft.clone(),
+            token_id: auction.token_id.clone(),
+            winner: winner.clone(),
+            amount_due,
+            due: due.into(),
+        }
+        .emit();
+        auction.pending_payment = Some(PendingPayment { winner, due });
+        self.payments_pending += 1;
+        true
+    }
+
+    /// Asks the NFT contract whether the contract still owns the next NFT an auction sells, or
+    /// is still approved to transfer it if the auction is escrowless, to cancel the auction if
+    /// not
+    fn internal_check_escrow(&self, nft_id: &NFTId, settle: bool) -> Promise {
+        let auction = self.auctions.get(nft_id).expect("auction exists");
+        let next = auction
+            .nfts()
+            .next()
+            .expect("auction has an nft left")
+            .clone();
+        let callback = Self::ext(env::current_account_id());
+        let (nft, token_id) = (auction.nft.clone(), auction.token_id.clone());
+        match auction.approval_id.filter(|_| auction.escrowless) {
+            Some(approval_id) => ext_nft_approval::ext(nft.clone())
+                .nft_is_approved(next.into(), env::current_account_id(), Some(approval_id))
+                .then(callback.on_approval_checked(nft, token_id, settle)),
+            None => ext_nft_core::ext(nft.clone())
+                .nft_token(next.into())
+                .then(callback.on_escrow_checked(nft, token_id, settle)),
+        }
+    }
+
+    /// Cancels the auction unless its NFT is still `held`, otherwise settles it if `settle` is
+    /// set, returning whether the auction went on
+    fn internal_escrow_checked(&mut self, nft_id: &NFTId, held: bool, settle: bool) -> bool {
+        // Settled or cancelled while the check was in flight
+        let Some(auction) = self.auctions.get(nft_id) else {
+            return false;
+        };
+        if !held {
+            // The lot's other NFTs are still held and go back to the owner
+            let nfts = auction.nfts().skip(1).cloned().collect();
+            AuctionEvent::EscrowLost {
+                nft: auction.nft.clone(),
+                token_id: auction.token_id.clone(),
+                owner: auction.owner.clone(),
+            }
+            .emit();
+            self.internal_cancel(nft_id, nfts, "Auction cancelled, its NFT left escrow", true);
+            return false;
+        }
+        if settle {
+            self.internal_settle(nft_id);
+        }
+        true
+    }
+
+    /// Credits the owner with the proceeds, hands each NFT to its winner, or lets them claim it,
+    /// and refunds the bidders
+    ///
+    /// NFTs left without a winner go into `fractionalizer` if there is one, otherwise back to the
+    /// owner with `unsold_memo`.
+    fn internal_pay_out(
+        &mut self,
+        auction: Auction,
+        payout: Payout,
+        fractionalizer: Option<AccountId>,
+        unsold_memo: &str,
+    ) -> Option<Promise> {
+        if !payout.winners.is_empty() {
+            self.internal_reputation(&auction.owner).auctions_completed += 1;
+        }
+        for winner in &payout.winners {
+            self.internal_reputation(winner).bids_honored += 1;
+        }
+
+        // Every winner pays the same, the seller gets it less what the fee policy takes
+        let paid = NearToken::from_yoctonear(
+            payout
+                .proceeds
+                .checked_div(payout.winners.len() as u128)
+                .unwrap_or(0),
+        );
+        // Sales in a payment token pay its own fee instead, and no royalties, which are in NEAR
+        let token_fee = auction
+            .payment
+            .as_ref()
+            .map_or(NearToken::from_yoctonear(0), |token| {
+                self.internal_token_fee(token, paid)
+            });
+        let token_fees = token_fee.saturating_mul(payout.winners.len() as u128);
+        // Fungible tokens pay no royalties either
+        let fee_policy = match (&auction.payment, &auction.asset) {
+            (Some(_), _) => FeePolicyConfig {
+                no_royalties: true,
+                ..Default::default()
+            },
+            (None, AuctionedAsset::Ft { .. }) => FeePolicyConfig {
+                no_royalties: true,
+                ..auction.fee_policy.clone()
+            },
+            (None, _) => auction.fee_policy.clone(),
+        };
+        let payments: Vec<(AccountId, NearToken)> = payout
+            .winners
+            .iter()
+            .zip(&payout.nfts)
+            .map(|(winner, token_id)| {
+                let fee = match auction.asset {
+                    // These sales don't count towards NEAR volume either
+                    _ if auction.payment.is_some() => token_fee,
+                    AuctionedAsset::Nft | AuctionedAsset::LazyMint { .. } => self
+                        .internal_record_trade(
+                            &fee_policy,
+                            &auction.nft,
+                            token_id,
+                            &auction.owner,
+                            winner,
+                            paid,
+                        ),
+                    AuctionedAsset::Ft { .. } => {
+                        let fee = NearToken::from_yoctonear(0);
+                        self.internal_add_volume(paid);
+                        let split = self.internal_fee_split(&fee_policy, &auction.nft, paid, fee);
+                        let charged = NearToken::from_yoctonear(split.marketplace);
+                        self.internal_record_stats(&auction.owner, winner, paid, charged);
+                        fee
+                    }
+                };
+                if auction.payment.is_none() {
+                    self.internal_record_win(winner, paid);
+                }
+                (winner.clone(), fee)
+            })
+            .collect();
+        let proceeds = NearToken::from_yoctonear(payout.proceeds);
+
+        // Owner withdraws the bid-amount whenever they like, or once the proceeds are released.
+        // Held proceeds keep their fees too, so buyers can be refunded in full, and NFTs handed
+        // over at settlement may fail to arrive, so their proceeds wait for them.
+        let hold_for = auction.dispute_window.max(auction.proceeds_delay);
+        let handed_over = matches!(auction.asset, AuctionedAsset::Nft)
+            && (auction.push_nft || auction.escrowless);
+        let mut transfers = Vec::new();
+        let escrow_id = if !proceeds.is_zero() && (hold_for > 0 || handed_over) {
+            let now = env::block_timestamp();
+            Some(
+                self.internal_hold_proceeds(HeldProceeds {
+                    nft: auction.nft.clone(),
+                    token_id: auction.token_id.clone(),
+                    seller: auction.owner.clone(),
+                    payments: payments
+                        .iter()
+                        .map(|(buyer, protocol_fee)| HeldPayment {
+                            buyer: buyer.clone(),
+                            amount: paid,
+                            protocol_fee: *protocol_fee,
+                        })
+                        .collect(),
+                    undelivered: if handed_over {
+                        payments.into_iter().map(|(buyer, _)| buyer).collect()
+                    } else {
+                        Vec::new()
+                    },
+                    release_at: now.saturating_add(hold_for),
+                    dispute_until: now.saturating_add(auction.dispute_window),
+                    frozen: false,
+                    fee_policy,
+                }),
+            )
+        } else {
+            match &auction.payment {
+                _ if proceeds.is_zero() => {}
+                Some(payment) => transfers.push(self.internal_token_payout(
+                    payment,
+                    auction.owner.clone(),
+                    U128(proceeds.saturating_sub(token_fees).as_yoctonear()),
+                    "Auction proceeds",
+                )),
+                None => {
+                    let mut owed = NearToken::from_yoctonear(0);
+                    for (_, fee) in &payments {
+                        let left = self.internal_charge_fees(&fee_policy, &auction.nft, paid, *fee);
+                        owed = owed.saturating_add(left);
+                    }
+                    self.internal_credit(auction.owner.clone(), owed);
+                }
+            }
+            if let Some(payment) = auction.payment.as_ref().filter(|_| !token_fees.is_zero()) {
+                transfers.push(self.internal_token_payout(
+                    payment,
+                    self.owner(),
+                    U128(token_fees.as_yoctonear()),
+                    "Payment token fee",
+                ));
+            }
+            None
+        };
+
+        // Hand each NFT to its winner, or let them claim it, and return unsold ones to the owner
+        let mut winners = payout.winners.into_iter();
+        match auction.asset {
+            // Fungible tokens can't be claimed, they are always handed over
+            AuctionedAsset::Ft { amount } => {
+                let (receiver_id, memo) = match winners.next() {
+                    Some(winner) => (winner, "Auction won"),
+                    None => (auction.owner.clone(), unsold_memo),
+                };
+                transfers.push(self.internal_ft_payout(
+                    auction.nft.clone(),
+                    receiver_id,
+                    amount,
+                    memo,
+                ));
+            }
+            // The NFT never left the owner, it is only transferred if there is a winner
+            AuctionedAsset::Nft if auction.escrowless => {
+                if let Some(winner) = winners.next() {
+                    let claim = NftClaim {
+                        nft: auction.nft.clone(),
+                        token_id: auction.token_id.clone(),
+                        winner: winner.clone(),
+                        escrow_id,
+                    };
+                    let transfer = self.internal_nft_transfer_approved(
+                        auction.nft.clone(),
+                        winner,
+                        auction.token_id.clone(),
+                        auction.approval_id,
+                    );
+                    transfers.push(Self::internal_track_delivery(transfer, claim, false));
+                }
+            }
+            // The winner already got the NFT minted to them, without one it was never minted
+            AuctionedAsset::LazyMint { deposit, .. } => {
+                if winners.next().is_none() {
+                    accounting::sub(&mut self.mint_deposits_held, deposit);
+                    self.internal_credit(auction.owner.clone(), deposit);
+                }
+            }
+            AuctionedAsset::Nft => {
+                for token_id in payout.nfts {
+                    match winners.next() {
+                        Some(winner) => {
+                            let claim = NftClaim {
+                                nft: auction.nft.clone(),
+                                token_id: token_id.clone(),
+                                winner: winner.clone(),
+                                escrow_id,
+                            };
+                            let transfer = self.internal_award_nft(
+                                auction.nft.clone(),
+                                token_id,
+                                winner,
+                                auction.push_nft,
+                            );
+                            transfers.extend(transfer.map(|transfer| {
+                                Self::internal_track_delivery(transfer, claim, true)
+                            }));
+                        }
+                        None => transfers.push(match &fractionalizer {
+                            Some(fractionalizer) => self.internal_fractionalize(
+                                auction.nft.clone(),
+                                token_id,
+                                auction.owner.clone(),
+                                fractionalizer.clone(),
+                            ),
+                            None => self.internal_nft_transfer(
+                                auction.nft.clone(),
+                                auction.owner.clone(),
+                                token_id,
+                                unsold_memo,
+                            ),
+                        }),
+                    }
+                }
+            }
+        }
+
+        // Refund all bidders that didn't win the bid, or paid more than the price
+        // (called 0 or more times)
+        let refunds: Vec<Promise> = payout
+            .refunds
+            .into_iter()
+            .map(|(acc_id, amount)| match &auction.payment {
+                Some(payment) => {
+                    self.internal_token_transfer(payment, acc_id, U128(amount), "Bid refunded")
+                }
+                None => Self::internal_pay(acc_id, NearToken::from_yoctonear(amount)),
+            })
+            .collect();
+        // Transfers are chains of their own and can't be chained after one another, every one
+        // but the last is scheduled as it is dropped
+        transfers.into_iter().chain(refunds).last()
+    }
+
+    /// Transfers an NFT held by or approved to the contract, paying the required yoctoNEAR itself
+    fn internal_nft_transfer(
+        &mut self,
+        nft: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        memo: &str,
+    ) -> Promise {
+        let deposit = NearToken::from_yoctonear(1);
+        accounting::add(&mut self.nft_deposits_paid, deposit);
+        ext_nft_core::ext(nft)
+            .with_attached_deposit(deposit)
+            .nft_transfer(receiver_id, token_id.into(), None, Some(memo.into()))
+    }
+
+    /// Transfers an NFT the contract was approved on with `approval_id`, paying the required
+    /// yoctoNEAR itself
+    fn internal_nft_transfer_approved(
+        &mut self,
+        nft: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+    ) -> Promise {
+        let deposit = NearToken::from_yoctonear(1);
+        accounting::add(&mut self.nft_deposits_paid, deposit);
+        ext_nft_core::ext(nft)
+            .with_attached_deposit(deposit)
+            .nft_transfer(
+                receiver_id,
+                token_id.into(),
+                approval_id,
+                Some("Auction won".into()),
+            )
+    }
+
+    /// Has the proceeds held under `claim.escrow_id`, if any, wait for `transfer` to deliver the
+    /// NFT of `claim`, `escrowed` being whether the contract holds the NFT
+    fn internal_track_delivery(transfer: Promise, claim: NftClaim, escrowed: bool) -> Promise {
+        match claim.escrow_id {
+            Some(_) => transfer
+                .then(Self::ext(env::current_account_id()).on_nft_delivered(claim, escrowed)),
+            None => transfer,
+        }
+    }
+
+    /// Hands an NFT held by the contract to `winner` if `push_nft` is set, otherwise lets them
+    /// claim it
+    fn internal_award_nft(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        winner: AccountId,
+        push_nft: bool,
+    ) -> Option<Promise> {
+        if push_nft {
+            return Some(self.internal_nft_transfer(nft, winner, token_id, "Auction won"));
+        }
+        self.nft_claims.insert(
+            NFTId::new(&nft, &token_id),
+            NftClaim {
+                nft,
+                token_id,
+                winner,
+                escrow_id: None,
+            },
+        );
+        None
+    }
+
+    /// Counts another listing of an NFT that ended without a winner, returning the fractionalizer
+    /// to deposit its NFTs into if that was the last one `on_unsold` allows
+    fn internal_record_unsold(
+        &mut self,
+        nft_id: &NFTId,
+        on_unsold: &UnsoldAction,
+    ) -> Option<AccountId> {
+        let streak = self.unsold_streaks.get(nft_id).copied().unwrap_or(0) + 1;
+        match on_unsold {
+            UnsoldAction::Fractionalize {
+                fractionalizer,
+                after,
+            } if streak >= *after => {
+                self.unsold_streaks.remove(nft_id);
+                Some(fractionalizer.clone())
+            }
+            _ => {
+                self.unsold_streaks.insert(nft_id.clone(), streak);
+                None
+            }
+        }
+    }
+
+    /// Deposits an NFT held by the contract into `fractionalizer` on behalf of `owner`
+    fn internal_fractionalize(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        owner: AccountId,
+        fractionalizer: AccountId,
+    ) -> Promise {
+        let deposit = NearToken::from_yoctonear(1);
+        accounting::add(&mut self.nft_deposits_paid, deposit);
+        let msg = serde_json::to_string(&FractionalizeMsg {
+            shares_receiver_id: owner.clone(),
+        })
+        .expect("fractionalize msg is serializable");
+        ext_nft_core::ext(nft.clone())
+            .with_attached_deposit(deposit)
+            .nft_transfer_call(
+                fractionalizer,
+                token_id.clone().into(),
+                None,
+                Some("Auction ended without a winner".into()),
+                msg,
+            )
+            .then(Self::ext(env::current_account_id()).on_fractionalize(nft, token_id, owner))
+    }
+
+    /// Transfers fungible tokens held by the contract, paying the required yoctoNEAR itself and
+    /// recording them as owed if the transfer fails
+    ///
+    /// The receiver has to be registered with the token, see `internal_ft_payout` for payees
+    /// that may not be.
+    pub(crate) fn internal_ft_transfer(
+        &mut self,
+        ft: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: &str,
+    ) -> Promise {
+        let deposit = NearToken::from_yoctonear(1);
+        accounting::add(&mut self.nft_deposits_paid, deposit);
+        ext_ft_core::ext(ft.clone())
+            .with_attached_deposit(deposit)
+            .ft_transfer(receiver_id.clone(), amount, Some(memo.into()))
+            // Fails if the receiver isn't registered with the token
+            .then(
+                Self::ext(env::current_account_id())
+                    .on_payout(receiver_id, OwedPayout::Ft { ft, amount }),
+            )
+    }
+
+    /// Records a trade of an NFT for `price` under `policy` and adds it to the volume, returning
+    /// the quick-flip fee it is charged
+    fn internal_record_trade(
+        &mut self,
+        policy: &FeePolicyConfig,
+        nft: &AccountId,
+        token_id: &TokenId,
+        seller: &AccountId,
+        buyer: &AccountId,
+        price: NearToken,
+    ) -> NearToken {
+        self.internal_add_volume(price);
+        let fee = self.internal_charge_quick_flip_fee(nft, token_id, seller, buyer, price);
+        let split = self.internal_fee_split(policy, nft, price, fee);
+        let charged = NearToken::from_yoctonear(split.marketplace);
+        self.internal_record_stats(seller, buyer, price, charged);
+        fee
+    }
+
+    /// Adds a sale for `price` to the volume, in total and of the current day and week
+    fn internal_add_volume(&mut self, price: NearToken) {
+        let now = env::block_timestamp();
+        accounting::add(&mut self.volume, price);
+        volume::record(
+            &mut self.daily_volume,
+            volume::DAY,
+            DAILY_VOLUME_KEPT,
+            now,
+            price.as_yoctonear(),
+        );
+        volume::record(
+            &mut self.weekly_volume,
+            volume::WEEK,
+            WEEKLY_VOLUME_KEPT,
+            now,
+            price.as_yoctonear(),
+        );
+    }
+
+    /// Adds a sale for `price`, of which `fee` went to the protocol, to the stats of both sides
+    fn internal_record_stats(
+        &mut self,
+        seller: &AccountId,
+        buyer: &AccountId,
+        price: NearToken,
+        fee: NearToken,
+    ) {
+        let stats = self.account_stats.entry(seller.clone()).or_default();
+        stats.sold += 1;
+        stats.earned = stats.earned.saturating_add(price.saturating_sub(fee));
+        stats.fees_paid = stats.fees_paid.saturating_add(fee);
+        let stats = self.account_stats.entry(buyer.clone()).or_default();
+        stats.won += 1;
+        stats.spent = stats.spent.saturating_add(price);
+    }
+
+    /// The quick-flip fee a trade of an NFT for `price` is charged, if any
+    fn internal_charge_quick_flip_fee(
+        &mut self,
+        nft: &AccountId,
+        token_id: &TokenId,
+        seller: &AccountId,
+        buyer: &AccountId,
+        price: NearToken,
+    ) -> NearToken {
+        let Some(mut rules) = self.wash_trade_rules.as_ref().map(WashTradeConfig::rules) else {
+            return NearToken::from_yoctonear(0);
+        };
+        if let Some(fee_bps) = self
+            .collection_policies
+            .get(nft)
+            .and_then(|policy| policy.fee_bps)
+        {
+            rules.fee_bps = fee_bps;
+        }
+        let now = env::block_timestamp();
+        let nft_id = NFTId::new(nft, token_id);
+        let mut trades: Vec<Trade<AccountId>> = self
+            .trades
+            .get(&nft_id)
+            .into_iter()
+            .flatten()
+            .map(|trade| Trade {
+                seller: trade.seller.clone(),
+                buyer: trade.buyer.clone(),
+                at: trade.at,
+            })
+            .collect();
+        wash::prune(&mut trades, &rules, now);
+        let fee = NearToken::from_yoctonear(wash::quick_flip_fee(
+            &trades,
+            &rules,
+            seller,
+            buyer,
+            now,
+            price.as_yoctonear(),
+        ));
+        trades.push(Trade {
+            seller: seller.clone(),
+            buyer: buyer.clone(),
+            at: now,
+        });
+        self.trades.insert(
+            nft_id,
+            trades
+                .into_iter()
+                .map(|trade| TradeRecord {
+                    seller: trade.seller,
+                    buyer: trade.buyer,
+                    at: trade.at,
+                })
+                .collect(),
+        );
+        if !fee.is_zero() {
+            AuctionEvent::QuickFlipFeeCharged {
+                nft: nft.clone(),
+                token_id: token_id.clone(),
+                seller: seller.clone(),
+                buyer: buyer.clone(),
+                fee,
+            }
+            .emit();
+        }
+        fee
+    }
+
+    /// Adds the `paid` winning bid of `winner` to their winning volume and the leaderboard
+    fn internal_record_win(&mut self, winner: &AccountId, paid: NearToken) {
+        let volume = self
+            .winning_volumes
+            .entry(winner.clone())
+            .or_insert(NearToken::from_yoctonear(0));
+        accounting::add(volume, paid);
+        leaderboard::update(
+            &mut self.leaderboard,
+            winner.clone(),
+            volume.as_yoctonear(),
+            LEADERBOARD_CAPACITY,
+        );
+    }
+
+    /// The minimum `timespan` of listings of the NFTs of `nft`
+    fn internal_min_timespan(&self, nft: &AccountId) -> u64 {
+        self.collection_policies
+            .get(nft)
+            .and_then(|policy| policy.min_timespan)
+            .map_or(self.min_timespan, |min| {
+                time::saturating_secs_to_nanos(min.into())
+            })
+    }
+
+    /// Takes the listing stake out of the balance of `account_id`, who is about to list
+    fn internal_take_stake(&mut self, account_id: &AccountId) -> NearToken {
+        let stake = self.listing_stake;
+        if stake.is_zero() {
+            return stake;
+        }
+        let Some(balance) = self.balances.get_mut(account_id).filter(|b| **b >= stake) else {
+            env::panic_str("balance does not cover the listing stake, `deposit` it first")
+        };
+        *balance = balance.saturating_sub(stake);
+        if balance.is_zero() {
+            self.balances.remove(account_id);
+            self.balance_holders -= 1;
+        }
+        accounting::sub(&mut self.balances_total, stake);
+        accounting::add(&mut self.stakes_escrowed, stake);
+        stake
+    }
+
+    fn internal_reputation(&mut self, account_id: &AccountId) -> &mut Reputation {
+        self.reputations.entry(account_id.clone()).or_default()
+    }
+
+    /// Adds `amount` to the balance `account_id` can withdraw
+    fn internal_credit(&mut self, account_id: AccountId, amount: NearToken) {
+        if !self.balances.contains_key(&account_id) {
+            self.balance_holders += 1;
+        }
+        let balance = self
+            .balances
+            .entry(account_id)
+            .or_insert(NearToken::from_yoctonear(0));
+        *balance = balance.saturating_add(amount);
+        accounting::add(&mut self.balances_total, amount);
+    }
+
+    /// The attached deposit, plus the whole balance of `bidder` if `use_balance` is set
+    ///
+    /// The balance is taken out until `internal_return_funds` returns what the call did not use,
+    /// so bids can be placed without attaching anything, such as with a function-call access key.
+    fn internal_bid_funds(&mut self, bidder: &AccountId, use_balance: bool) -> NearToken {
+        let deposit = env::attached_deposit();
+        if !use_balance {
+            return deposit;
+        }
+        let balance = self.balance_of(bidder.clone());
+        if !balance.is_zero() {
+            self.internal_debit(bidder, balance);
+        }
+        deposit.saturating_add(balance)
+    }
+
+    /// Returns the `excess` of the funds of `bidder` the call did not use, to their balance if
+    /// they drew on it
+    pub(crate) fn internal_return_funds(
+        &mut self,
+        bidder: AccountId,
+        excess: NearToken,
+        use_balance: bool,
+    ) {
+        if excess.is_zero() {
+            return;
+        }
+        if use_balance {
+            self.internal_credit(bidder, excess);
+        } else {
+            Self::internal_pay(bidder, excess);
+        }
+    }
+
+    /// Takes `amount` out of the balance of `account_id`, which has to cover it
+    pub(crate) fn internal_debit(&mut self, account_id: &AccountId, amount: NearToken) {
+        let Some(balance) = self.balances.get_mut(account_id).filter(|b| **b >= amount) else {
+            env::panic_str("balance is too low")
+        };
+        *balance = balance.saturating_sub(amount);
+        if balance.is_zero() {
+            self.balances.remove(account_id);
+            self.balance_holders -= 1;
+        }
+        accounting::sub(&mut self.balances_total, amount);
+    }
+
+    /// Adds how the removed `auction` ended for each of its NFTs to their provenance
+    fn internal_record_provenance(
+        &mut self,
+        auction: &Auction,
+        outcomes: Vec<(TokenId, AuctionOutcome)>,
+    ) {
+        // Lots of fungible tokens have no provenance
+        if matches!(auction.asset, AuctionedAsset::Ft { .. }) {
+            return;
+        }
+        for (token_id, outcome) in outcomes {
+            self.internal_add_provenance(&auction.nft, &token_id, auction.provenance(outcome));
+        }
+    }
+
+    /// Adds `record` to the provenance of `token_id` of `nft`
+    fn internal_add_provenance(
+        &mut self,
+        nft: &AccountId,
+        token_id: &TokenId,
+        record: ProvenanceRecord,
+    ) {
+        let nft_id = NFTId::new(nft, token_id);
+        self.provenance
+            .entry(nft_id.clone())
+            .or_insert_with(|| Vector::new(StorageKey::ProvenanceOf { nft_id }))
+            .push(record);
+    }
+
+    /// Removes an auction along with all of its bids
+    fn internal_remove_auction(&mut self, nft_id: &NFTId) -> Auction {
+        let mut auction = self.auctions.remove(nft_id).expect("auction exists");
+        if let Some(active) = self.active_listings.get_mut(&auction.owner) {
+            *active = active.saturating_sub(1);
+            if *active == 0 {
+                self.active_listings.remove(&auction.owner);
+            }
+        }
+        if auction.pending_payment.is_some() {
+            self.payments_pending -= 1;
+        }
+        self.trending.retain(|entry| entry.nft_id != *nft_id);
+        if let Some(nft_ids) = self.auctions_by_nft.get_mut(&auction.nft) {
+            nft_ids.remove(nft_id);
+            if nft_ids.is_empty() {
+                self.auctions_by_nft.remove(&auction.nft);
+            }
+        }
+        self.internal_unindex_expiry(nft_id, auction.expiry);
+        let bidders: Vec<AccountId> = auction
+            .bids
+            .iter()
+            .filter(|(_, bid)| !bid.paid)
+            .map(|(bidder, _)| bidder.clone())
+            .collect();
+        for bidder in &bidders {
+            self.internal_remove_active_bid(bidder, nft_id);
+        }
+        // Paid out or refunded by whoever removes the auction, as are the stake and the bid fees
+        if auction.payment.is_none() {
+            accounting::sub(&mut self.bids_escrowed, auction.bids_escrowed);
+        }
+        accounting::sub(&mut self.stakes_escrowed, auction.stake);
+        accounting::sub(&mut self.bid_fees_escrowed, auction.fees_accrued);
+        auction.bids.clear();
+        // Bid histories are kept after the auction, see `get_bid_history`
+        if !auction.history.is_empty() {
+            let key = StorageKey::BidHistory {
+                nft_id: nft_id.clone(),
+                listed_at: auction.listed_at,
+            };
+            let history = std::mem::replace(&mut auction.history, Vector::new(key));
+            self.past_bid_histories
+                .insert((nft_id.clone(), auction.listed_at), history);
+        }
+        // Removed from storage once the auction is dropped
+        auction.lot.set(None);
+        auction.mint_metadata.set(None);
+        self.storage_usage -= auction.storage_usage;
+        auction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod events;
+    mod model;
+
+    use near_sdk::{
+        mock::MockAction,
+        test_utils::{accounts, get_created_receipts, VMContextBuilder},
+        testing_env, IntoStorageKey, PromiseResult,
+    };
+
+    use near_contract_standards::non_fungible_token::{metadata::TokenMetadata, Token};
+    use near_nft_auction_core::staking::EPOCH;
+
+    use super::*;
+
+    const MINUTE: u64 = 60 * 1_000_000_000;
+    const HOUR: u64 = 60 * MINUTE;
+    /// A minute, as durations are given to the contract
+    const MINUTE_SECS: u64 = 60;
+    /// An hour, as the `timespan` of listings
+    const HOUR_SECS: u64 = 60 * MINUTE_SECS;
+
+    fn contract_id() -> AccountId {
+        "auction.near".parse().unwrap()
+    }
+
+    fn nft() -> AccountId {
+        "nft.near".parse().unwrap()
+    }
+
+    fn token_id() -> TokenId {
+        "1".parse().unwrap()
+    }
+
+    fn near(amount: u128) -> NearToken {
+        NearToken::from_near(amount)
+    }
+
+    /// Context of a call to the contract by `caller`, who signs it too, at block time 0 with
+    /// nothing attached unless set
+    fn context(caller: &AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(contract_id())
+            .predecessor_account_id(caller.clone())
+            .signer_account_id(caller.clone());
+        builder
+    }
+
+    /// A contract with an english auction of `token_id` by alice, with a minimum bid of 1 NEAR,
+    /// started at block time 0 and ending an hour later
+    fn contract_with_auction() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        contract
+    }
+
+    /// NEAR the calls made since the context was last set transfer to `account_id`
+    fn transfers_to(account_id: &AccountId) -> Vec<NearToken> {
+        get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == *account_id)
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bids_are_escrowed() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.h_bid, near(2));
+        assert_eq!(auction.bid_count, 1);
+        assert_eq!(auction.bids_escrowed, near(2));
+        assert_eq!(contract.reconcile().bids_escrowed, near(2));
+    }
+
+    #[test]
+    fn listings_record_when_and_how_they_were_made() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).block_timestamp(MINUTE).build());
+        let pending = PendingListing {
+            owner_id: accounts(0),
+            expiry: U64(HOUR),
+            params: AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            stake: near(0),
+            approval_id: Some(U64(7)),
+        };
+        assert!(contract.on_escrow_nft_token(
+            nft(),
+            token_id(),
+            pending,
+            Ok(token_owned_by(&contract_id()))
+        ));
+
+        testing_env!(context(&accounts(5)).block_timestamp(3 * MINUTE).build());
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.nft, nft());
+        assert_eq!(auction.approval_id, Some(U64(7)));
+        assert!(!auction.escrowless);
+        assert_eq!(auction.listed_at, U64(MINUTE));
+        assert_eq!(auction.listing_age, U64(2 * MINUTE));
+    }
+
+    #[test]
+    fn escrowless_listings_keep_their_approval() {
+        let mut contract = Contract::default();
+        testing_env!(context(&nft()).build());
+        let msg = AuctionParams::builder(HOUR_SECS, near(1))
+            .escrowless(true)
+            .build()
+            .to_msg();
+        contract.nft_on_approve(token_id().into(), accounts(0), 7, msg);
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.approval_id, Some(U64(7)));
+        assert!(auction.escrowless);
+    }
+
+    #[test]
+    fn listings_last_their_timespan_in_seconds() {
+        let mut contract = Contract::default();
+        testing_env!(context(&nft()).block_timestamp(MINUTE).build());
+        let msg = AuctionParams::builder(HOUR_SECS, near(1))
+            .escrowless(true)
+            .build()
+            .to_msg();
+        contract.nft_on_approve(token_id().into(), accounts(0), 7, msg);
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.expiry, U64(MINUTE + HOUR));
+        assert_eq!(auction.expiry_iso, "1970-01-01T01:01:00Z");
+    }
+
+    #[test]
+    fn listing_durations_are_given_in_seconds() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_min_timespan(U64(HOUR_SECS));
+        assert_eq!(contract.min_timespan(), U64(HOUR_SECS));
+        let short = AuctionParams::builder(HOUR_SECS - 1, near(1))
+            .build()
+            .to_msg();
+        assert_eq!(
+            contract.validate_auction_params(nft(), short).violations,
+            vec![ListingError::TimespanTooShort.to_string()]
+        );
+        let endless = AuctionParams::builder(HOUR_SECS, near(1))
+            .bid_interval(u64::MAX)
+            .build()
+            .to_msg();
+        assert_eq!(
+            contract.validate_auction_params(nft(), endless).violations,
+            vec!["`bid_interval` is too long".to_string()]
+        );
+
+        testing_env!(context(&nft()).build());
+        let msg = AuctionParams::builder(HOUR_SECS, near(1))
+            .bid_interval(MINUTE_SECS)
+            .proceeds_delay(HOUR_SECS)
+            .escrowless(true)
+            .build()
+            .to_msg();
+        contract.nft_on_approve(token_id().into(), accounts(0), 7, msg);
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.bid_interval, U64(MINUTE));
+        assert_eq!(auction.proceeds_delay, U64(HOUR));
+    }
+
+    #[test]
+    #[should_panic(expected = "this nft is not in auction")]
+    fn auctions_are_not_ended_for_another_nft() {
+        let mut contract = contract_with_auction();
+        // As if another token's id hashed to the same key
+        let other: TokenId = "2".parse().unwrap();
+        let auction = contract
+            .auctions
+            .remove(&NFTId::new(&nft(), &token_id()))
+            .unwrap();
+        contract
+            .auctions
+            .insert(NFTId::new(&nft(), &other), auction);
+
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction(nft(), other);
+    }
+
+    #[test]
+    fn auctions_can_be_addressed_by_nft_id() {
+        let mut contract = contract_with_auction();
+        let nft_id = contract.get_auction(nft(), token_id()).nft_id;
+        assert_eq!(nft_id, NFTId::new(&nft(), &token_id()));
+
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(2))
+            .build());
+        contract.make_bid_by_id(nft_id.clone(), near(2), None, None, None);
+        assert_eq!(contract.get_auction_by_id(nft_id.clone()).h_bid, near(2));
+        assert_eq!(contract.get_bids_by_id(nft_id.clone(), None, None).len(), 1);
+
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction_by_id(nft_id);
+        testing_env!(context(&contract_id()).block_timestamp(HOUR).build());
+        assert!(contract.on_escrow_checked(
+            nft(),
+            token_id(),
+            true,
+            Ok(token_owned_by(&contract_id()))
+        ));
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(1)));
+    }
+
+    #[test]
+    fn lots_are_stored_apart_and_removed_with_their_auction() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .kind(AuctionKind::Uniform)
+                .build(),
+            None,
+        );
+        let mut contract = reloaded(contract);
+        let before = contract.storage_usage_of(nft(), token_id());
+        let lot: TokenId = "2".parse().unwrap();
+        contract.add_to_lot(accounts(0), nft(), token_id(), lot.clone(), Ok(()));
+
+        let lot_key = StorageKey::Lot {
+            nft_id: NFTId::new(&nft(), &token_id()),
+        }
+        .into_storage_key();
+        assert!(env::storage_has_key(&lot_key));
+        assert_eq!(contract.get_auction(nft(), token_id()).lot, vec![lot]);
+        assert!(contract.storage_usage_of(nft(), token_id()).bytes.0 > before.bytes.0);
+
+        testing_env!(context(&accounts(0)).block_timestamp(MINUTE).build());
+        contract.cancel_auction(nft(), token_id());
+        drop(contract);
+        assert!(!env::storage_has_key(&lot_key));
+    }
+
+    #[test]
+    fn auctions_without_lots_are_read_back_from_storage() {
+        let contract = reloaded(contract_with_auction());
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert!(auction.lot.is_empty());
+        assert!(auction.mint_metadata.is_none());
+    }
+
+    #[test]
+    fn bid_history_keeps_every_bid() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+        testing_env!(context(&accounts(1))
+            .block_timestamp(3 * MINUTE)
+            .attached_deposit(near(2))
+            .build());
+        contract.increase_bid(nft(), token_id(), Some(near(2)), None);
+
+        let records = |history: Vec<BidRecord>| -> Vec<(AccountId, NearToken, U64)> {
+            history
+                .into_iter()
+                .map(|record| (record.bidder, record.amount, record.placed_at))
+                .collect()
+        };
+        let placed = vec![
+            (accounts(1), near(2), U64(MINUTE)),
+            (accounts(2), near(3), U64(2 * MINUTE)),
+            (accounts(1), near(4), U64(3 * MINUTE)),
+        ];
+        assert_eq!(
+            records(contract.get_bid_history(nft(), token_id(), None, None, None)),
+            placed
+        );
+        let page = contract.get_bid_history(nft(), token_id(), None, Some(1), Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].bidder, accounts(2));
+
+        let listed_at = contract.get_auction(nft(), token_id()).listed_at;
+        end_auction(&mut contract, HOUR);
+        assert_eq!(
+            records(contract.get_bid_history(nft(), token_id(), Some(listed_at), None, None)),
+            placed
+        );
+    }
+
+    #[test]
+    fn provenance_lists_every_auction_of_an_nft() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        end_auction(&mut contract, HOUR);
+        // Written at the end of the call, before the NFT can be listed again
+        contract.auctions.flush();
+        testing_env!(context(&contract_id()).block_timestamp(2 * HOUR).build());
+        contract.start_auction(
+            accounts(1),
+            nft(),
+            token_id(),
+            3 * HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        testing_env!(context(&accounts(1)).block_timestamp(2 * HOUR).build());
+        contract.cancel_auction(nft(), token_id());
+
+        let provenance = contract.get_provenance(nft(), token_id(), None, None);
+        let records: Vec<(AccountId, U64, AuctionOutcome)> = provenance
+            .into_iter()
+            .map(|record| (record.seller, record.ended_at, record.outcome))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (
+                    accounts(0),
+                    U64(HOUR),
+                    AuctionOutcome::Sold {
+                        buyer: accounts(1),
+                        price: near(2),
+                    },
+                ),
+                (accounts(1), U64(2 * HOUR), AuctionOutcome::Cancelled),
+            ]
+        );
+        let other: TokenId = "2".parse().unwrap();
+        assert!(contract.get_provenance(nft(), other, None, None).is_empty());
+    }
+
+    #[test]
+    fn fee_policies_split_the_proceeds() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_fee_policy(
+            "charity".into(),
+            Some(FeePolicyConfig {
+                marketplace_bps: 250,
+                no_royalties: false,
+                referral: Some(FeeShare {
+                    account_id: accounts(3),
+                    bps: 100,
+                }),
+                charity: Some(FeeShare {
+                    account_id: accounts(4),
+                    bps: 500,
+                }),
+            }),
+        );
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .fee_policy("charity".into())
+                .build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(10), MINUTE);
+        end_auction(&mut contract, HOUR);
+
+        assert_eq!(
+            contract.balance_of(accounts(0)),
+            NearToken::from_millinear(9_150)
+        );
+        assert_eq!(
+            contract.balance_of(accounts(3)),
+            NearToken::from_millinear(100)
+        );
+        assert_eq!(
+            contract.balance_of(accounts(4)),
+            NearToken::from_millinear(500)
+        );
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(250));
+    }
+
+    #[test]
+    fn held_proceeds_are_split_once_released() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_fee_policy(
+            "referred".into(),
+            Some(FeePolicyConfig {
+                marketplace_bps: 500,
+                referral: Some(FeeShare {
+                    account_id: accounts(3),
+                    bps: 500,
+                }),
+                ..Default::default()
+            }),
+        );
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .fee_policy("referred".into())
+                .proceeds_delay(HOUR_SECS)
+                .build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(10), MINUTE);
+        end_auction(&mut contract, HOUR);
+        assert_eq!(contract.balance_of(accounts(3)), near(0));
+        assert!(contract.protocol_fees().is_zero());
+
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        contract.release_proceeds(U64(0));
+        assert_eq!(contract.balance_of(accounts(0)), near(9));
+        assert_eq!(
+            contract.balance_of(accounts(3)),
+            NearToken::from_millinear(500)
+        );
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(500));
+    }
+
+    #[test]
+    fn listings_pick_a_registered_fee_policy() {
+        let contract = Contract::default();
+        let msg = AuctionParams::builder(HOUR_SECS, near(1))
+            .fee_policy("missing".into())
+            .build()
+            .to_msg();
+        assert_eq!(
+            contract.validate_auction_params(nft(), msg).violations,
+            vec!["there is no fee policy named `missing`".to_string()]
+        );
+    }
+
+    #[test]
+    fn bids_record_how_they_were_placed() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+
+        let bids = contract.get_bids(nft(), token_id(), None, None);
+        assert_eq!(bids[0].placed_at, U64(MINUTE));
+        assert!(bids[0].payment.is_none());
+        assert_eq!(bids[0].origin, BidOrigin::Direct);
+    }
+
+    #[test]
+    fn bids_stored_before_versioning_are_upgraded() {
+        let bid = BidV1 {
+            amount: near(2),
+            paid: false,
+            expires_at: None,
+            placed_at: MINUTE,
+            last_bid_at: MINUTE,
+            sequence: 0,
+            pick: None,
+            deposit: None,
+            fees_paid: NearToken::from_yoctonear(0),
+        };
+        let stored = [vec![0], near_sdk::borsh::to_vec(&bid).unwrap()].concat();
+
+        let bid: VersionedBid = near_sdk::borsh::from_slice(&stored).unwrap();
+        assert_eq!(bid.amount, near(2));
+        assert_eq!(bid.origin, BidOrigin::Direct);
+        assert!(bid.memo.is_none());
+        assert_eq!(near_sdk::borsh::to_vec(&bid).unwrap()[0], 2);
+    }
+
+    #[test]
+    fn memos_are_shown_and_pay_for_their_storage() {
+        let mut contract = contract_with_auction();
+        let memo = "for the collection".to_string();
+        let cost = env::storage_byte_cost().saturating_mul(memo.len() as u128 + 4);
+        let required = contract.required_deposit(
+            nft(),
+            token_id(),
+            accounts(1),
+            Some(near(2)),
+            Some(memo.clone()),
+        );
+        assert_eq!(required.storage, cost);
+        assert_eq!(required.total, near(2).saturating_add(cost));
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(3))
+            .build());
+        contract.make_bid(nft(), token_id(), near(2), None, None, Some(memo.clone()));
+
+        assert_eq!(
+            transfers_to(&accounts(1)),
+            vec![near(1).saturating_sub(cost)]
+        );
+        let bids = contract.get_bids(nft(), token_id(), None, None);
+        assert_eq!(bids[0].memo, Some(memo));
+    }
+
+    #[test]
+    #[should_panic(expected = "bid memos can have at most 140 bytes")]
+    fn long_memos_are_rejected() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(3))
+            .build());
+        contract.make_bid(
+            nft(),
+            token_id(),
+            near(2),
+            None,
+            None,
+            Some("a".repeat(141)),
+        );
+    }
+
+    #[test]
+    fn excess_deposit_is_refunded() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(3))
+            .build());
+        contract.make_bid(nft(), token_id(), near(2), None, None, None);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(1)]);
+        assert_eq!(
+            contract.get_auction(nft(), token_id()).bids_escrowed,
+            near(2)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bid amount does not exceed previous bid or minimum bid amount")]
+    fn bids_have_to_exceed_the_minimum_bid() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(1), MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid amount does not exceed previous bid or minimum bid amount")]
+    fn bids_have_to_exceed_the_highest_bid() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "provided deposit does not cover bid amount")]
+    fn bids_have_to_be_covered_by_the_deposit() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(1))
+            .build());
+        contract.make_bid(nft(), token_id(), near(2), None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "bidder has already made a bid")]
+    fn bidders_bid_once() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        make_bid(&mut contract, &accounts(1), near(3), 2 * MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot bid, auction is over")]
+    fn bids_close_at_expiry() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), HOUR);
+    }
+
+    #[test]
+    #[should_panic(expected = "this nft is not in auction")]
+    fn bids_need_an_auction() {
+        let mut contract = Contract::default();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot end, auction is still ongoing")]
+    fn auctions_end_once_expired() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1)).block_timestamp(HOUR - 1).build());
+        contract.end_auction(nft(), token_id());
+    }
+
+    /// The auction of `contract_with_auction`, waiting an hour after expiry to be settled
+    fn contract_with_delayed_settlement() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .settlement_delay(HOUR_SECS)
+                .build(),
+            None,
+        );
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot end yet, auction is in its settlement delay")]
+    fn auctions_wait_out_their_settlement_delay() {
+        let mut contract = contract_with_delayed_settlement();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR - 1).build());
+        contract.end_auction(nft(), token_id());
+    }
+
+    #[test]
+    fn auctions_settle_after_their_settlement_delay() {
+        let mut contract = contract_with_delayed_settlement();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        assert_eq!(
+            contract.get_auction(nft(), token_id()).settles_at,
+            U64(2 * HOUR)
+        );
+
+        end_auction(&mut contract, 2 * HOUR);
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(1)));
+    }
+
+    #[test]
+    fn settlement_pays_the_seller_and_refunds_the_outbid() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+        end_auction(&mut contract, HOUR);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+        assert!(transfers_to(&accounts(2)).is_empty());
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(2)));
+        assert_eq!(contract.balance_of(accounts(0)), near(3));
+        assert_eq!(contract.reconcile().bids_escrowed, near(0));
+        assert!(contract.is_empty());
+    }
+
+    #[test]
+    fn auctions_without_bids_return_the_nft() {
+        let mut contract = contract_with_auction();
+        end_auction(&mut contract, HOUR);
+
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), None);
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert!(get_created_receipts()
+            .iter()
+            .any(|receipt| receipt.receiver_id == nft()));
+    }
+
+    #[test]
+    fn lapsed_bids_are_refunded() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(2))
+            .build());
+        contract.make_bid(
+            nft(),
+            token_id(),
+            near(2),
+            Some((10 * MINUTE).into()),
+            None,
+            None,
+        );
+        make_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+
+        testing_env!(context(&accounts(1)).block_timestamp(10 * MINUTE).build());
+        contract.withdraw_lapsed_bid(nft(), token_id());
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+        let bid = contract
+            .get_bid(accounts(1), nft(), token_id())
+            .expect("bid is kept");
+        assert!(bid.paid);
+        assert_eq!(
+            contract.get_auction(nft(), token_id()).bids_escrowed,
+            near(3)
+        );
+    }
+
+    #[test]
+    fn auction_lists_can_be_filtered() {
+        let mut contract = contract_with_auction();
+        let other: TokenId = "2".parse().unwrap();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            other.clone(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+
+        let listed = |contract: &Contract, filter: AuctionFilter| -> Vec<TokenId> {
+            contract
+                .auctions_by_nft_contract(nft(), None, None, Some(filter))
+                .auctions
+                .into_iter()
+                .map(|auction| auction.token_id)
+                .collect()
+        };
+        let priced = AuctionFilter {
+            min_bid: Some(near(2)),
+            ..AuctionFilter::default()
+        };
+        assert_eq!(listed(&contract, priced), vec![token_id()]);
+        let cheap = AuctionFilter {
+            max_bid: Some(near(2)),
+            paid_in: Some(PaidIn::Near),
+            ..AuctionFilter::default()
+        };
+        assert_eq!(listed(&contract, cheap.clone()), vec![other.clone()]);
+        // Pages are cut from every auction before filtering
+        let first = contract.auctions_by_nft_contract(nft(), None, Some(1), Some(cheap.clone()));
+        assert!(first.auctions.is_empty());
+        assert_eq!(first.next_index, Some(1));
+        let second =
+            contract.auctions_by_nft_contract(nft(), first.next_index, Some(1), Some(cheap));
+        assert_eq!(second.auctions[0].token_id, other);
+        assert_eq!(second.next_index, None);
+        let in_tokens = AuctionFilter {
+            paid_in: Some(PaidIn::Token(PaymentToken::Ft {
+                contract: accounts(4),
+            })),
+            ..AuctionFilter::default()
+        };
+        assert!(listed(&contract, in_tokens).is_empty());
+
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        let expired = AuctionFilter {
+            status: Some(AuctionStatus::Expired),
+            ..AuctionFilter::default()
+        };
+        assert_eq!(listed(&contract, expired).len(), 2);
+    }
+
+    #[test]
+    fn contracts_without_auctions_list_none_by_expiry() {
+        testing_env!(context(&contract_id()).build());
+        let contract = reloaded(Contract::default());
+
+        assert!(contract.auctions_ending_soon(None).is_empty());
+    }
+
+    #[test]
+    fn gc_settles_only_stale_auctions() {
+        let mut contract = contract_with_auction();
+        contract.set_gc_staleness(U64(HOUR_SECS));
+        for (token_id, expiry) in [("2", 2 * HOUR), ("3", 5 * HOUR)] {
+            contract.start_auction(
+                accounts(0),
+                nft(),
+                token_id.parse().unwrap(),
+                expiry,
+                AuctionParams::builder(HOUR_SECS, near(1)).build(),
+                None,
+            );
+        }
+
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR + 1).build());
+        assert_eq!(contract.gc(10), 1);
+        testing_env!(context(&accounts(5)).block_timestamp(4 * HOUR + 1).build());
+        assert_eq!(contract.gc(1), 1);
+        assert_eq!(contract.gc(10), 2);
+    }
+
+    #[test]
+    fn auctions_are_listed_by_expiry() {
+        let mut contract = contract_with_auction();
+        let (late, early): (TokenId, TokenId) = ("2".parse().unwrap(), "3".parse().unwrap());
+        testing_env!(context(&contract_id()).build());
+        for (token_id, expiry) in [(&late, 3 * HOUR), (&early, 30 * MINUTE)] {
+            contract.start_auction(
+                accounts(0),
+                nft(),
+                token_id.clone(),
+                expiry,
+                AuctionParams::builder(HOUR_SECS, near(1)).build(),
+                None,
+            );
+        }
+        let token_ids = |auctions: Vec<AuctionView>| -> Vec<TokenId> {
+            auctions
+                .into_iter()
+                .map(|auction| auction.token_id)
+                .collect()
+        };
+        assert_eq!(
+            token_ids(contract.auctions_ending_soon(None)),
+            vec![early.clone(), token_id(), late.clone()]
+        );
+        assert_eq!(
+            token_ids(contract.auctions_ending_between(U64(HOUR), U64(2 * HOUR), None, None)),
+            vec![token_id()]
+        );
+
+        end_auction(&mut contract, HOUR);
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        assert_eq!(
+            token_ids(contract.auctions_ending_soon(Some(1))),
+            vec![late]
+        );
+        assert_eq!(
+            token_ids(contract.auctions_ending_between(U64(0), U64(2 * HOUR), None, None)),
+            vec![early]
+        );
+    }
+
+    #[test]
+    fn outbid_bids_can_be_reduced() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(4), 2 * MINUTE);
+
+        testing_env!(context(&accounts(1)).block_timestamp(3 * MINUTE).build());
+        contract.reduce_bid(nft(), token_id(), near(1));
+        assert_eq!(transfers_to(&accounts(1)), vec![near(1)]);
+        let bid = contract.get_bid(accounts(1), nft(), token_id()).unwrap();
+        assert_eq!(bid.amount, near(2));
+        assert_eq!(
+            contract.get_auction(nft(), token_id()).bids_escrowed,
+            near(6)
+        );
+        let history = contract.get_bid_history(nft(), token_id(), None, Some(2), None);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].bidder, accounts(1));
+        assert_eq!(history[0].amount, near(2));
+        assert_eq!(history[0].placed_at, U64(3 * MINUTE));
+    }
+
+    #[test]
+    #[should_panic(expected = "the reduced bid has to exceed the minimum bid")]
+    fn bids_are_not_reduced_to_the_minimum() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(4), 2 * MINUTE);
+        testing_env!(context(&accounts(1)).block_timestamp(3 * MINUTE).build());
+        contract.reduce_bid(nft(), token_id(), near(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "bids that are winning can't be reduced")]
+    fn the_highest_bid_is_not_reduced() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        testing_env!(context(&accounts(1)).block_timestamp(2 * MINUTE).build());
+        contract.reduce_bid(nft(), token_id(), near(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "bid of 4 NEAR exceeds the maximum bid of 3 NEAR")]
+    fn bids_are_capped_globally() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&contract_id()).build());
+        contract.set_max_bid(Some(near(3)));
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(4), 2 * MINUTE);
+    }
+
+    #[test]
+    fn sellers_cap_bids_in_their_auctions() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_max_bid(Some(near(3)));
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .max_bid(near(5))
+                .build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(5), MINUTE);
+
+        let simulated = contract.simulate_bid(nft(), token_id(), accounts(2), near(6));
+        assert_eq!(
+            simulated.reason.as_deref(),
+            Some("bid of 6 NEAR exceeds the maximum bid of 5 NEAR")
+        );
+
+        let capped = AuctionParams::builder(HOUR_SECS, near(1))
+            .max_bid(near(1))
+            .build()
+            .to_msg();
+        assert_eq!(
+            contract.validate_auction_params(nft(), capped).violations,
+            vec!["`max_bid` has to exceed `minimum_bid`".to_string()]
+        );
+    }
+
+    #[test]
+    fn every_lapsed_bid_is_refunded_at_once() {
+        let mut contract = contract_with_auction();
+        let other: TokenId = "2".parse().unwrap();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            other.clone(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        for token_id in [token_id(), other.clone()] {
+            testing_env!(context(&accounts(1))
+                .block_timestamp(MINUTE)
+                .attached_deposit(near(2))
+                .build());
+            contract.make_bid(
+                nft(),
+                token_id,
+                near(2),
+                Some((10 * MINUTE).into()),
+                None,
+                None,
+            );
+        }
+        make_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+
+        // The bid on the other auction is still the highest
+        testing_env!(context(&accounts(1)).block_timestamp(10 * MINUTE).build());
+        assert_eq!(contract.withdraw_all_bids(None), 1);
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+        assert_eq!(contract.bid_capacity_of(accounts(1)).active, 1);
+        assert!(!contract.get_bid(accounts(1), nft(), other).unwrap().paid);
+
+        testing_env!(context(&accounts(1)).block_timestamp(10 * MINUTE).build());
+        assert_eq!(contract.withdraw_all_bids(None), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid has not lapsed")]
+    fn highest_bids_do_not_lapse() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(2))
+            .build());
+        contract.make_bid(
+            nft(),
+            token_id(),
+            near(2),
+            Some((10 * MINUTE).into()),
+            None,
+            None,
+        );
+
+        testing_env!(context(&accounts(1)).block_timestamp(10 * MINUTE).build());
+        contract.withdraw_lapsed_bid(nft(), token_id());
+    }
+
+    #[test]
+    fn cancelling_refunds_every_bid() {
+        let mut contract = contract_with_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+
+        testing_env!(context(&accounts(0)).block_timestamp(3 * MINUTE).build());
+        contract.cancel_auction(nft(), token_id());
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+        assert_eq!(transfers_to(&accounts(2)), vec![near(3)]);
+        assert!(contract.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "only the owner of the auction can cancel it")]
+    fn only_the_seller_cancels() {
+        let mut contract = contract_with_auction();
+        testing_env!(context(&accounts(1)).block_timestamp(MINUTE).build());
+        contract.cancel_auction(nft(), token_id());
+    }
+
+    #[test]
+    fn listings_are_validated() {
+        let contract = Contract::default();
+        testing_env!(context(&accounts(0)).build());
+
+        let valid = AuctionParams::builder(HOUR_SECS, near(1)).build().to_msg();
+        assert!(contract
+            .validate_auction_params(nft(), valid)
+            .violations
+            .is_empty());
+
+        let instant = AuctionParams::builder(0, near(1)).build().to_msg();
+        assert_eq!(
+            contract.validate_auction_params(nft(), instant).violations,
+            vec![ListingError::ZeroTimespan.to_string()]
+        );
+
+        let stalled = AuctionParams::builder(HOUR_SECS, near(1))
+            .settlement_delay(7 * 24 * HOUR_SECS + 1)
+            .build()
+            .to_msg();
+        assert_eq!(
+            contract.validate_auction_params(nft(), stalled).violations,
+            vec![ListingError::SettlementDelayTooLong.to_string()]
+        );
+
+        let garbled = contract.validate_auction_params(nft(), "{}".into());
+        assert!(garbled.params.is_none());
+        assert_eq!(garbled.violations.len(), 1);
+    }
+
+    /// `contract` as the next call reads it from storage
+    fn reloaded(contract: Contract) -> Contract {
+        env::state_write(&contract);
+        // Collections write their entries when dropped
+        drop(contract);
+        env::state_read().expect("state was just written")
+    }
+
+    /// Ends the auction at `now` and answers the escrow check with the NFT still held
+    fn end_auction(contract: &mut Contract, now: u64) {
+        testing_env!(context(&accounts(5)).block_timestamp(now).build());
+        contract.end_auction(nft(), token_id());
+        testing_env!(context(&contract_id()).block_timestamp(now).build());
+        assert!(contract.on_escrow_checked(
+            nft(),
+            token_id(),
+            true,
+            Ok(token_owned_by(&contract_id()))
+        ));
+    }
+
+    fn make_bid(contract: &mut Contract, bidder: &AccountId, amount: NearToken, now: u64) {
+        testing_env!(context(bidder)
+            .block_timestamp(now)
+            .attached_deposit(amount)
+            .build());
+        contract.make_bid(nft(), token_id(), amount, None, None, None);
+    }
+
+    /// A contract with a penny auction of `token_id` by alice, starting at 1 NEAR and ending an
+    /// hour later, where every bid costs 1 NEAR, raises the price by 1 NEAR and leaves at least
+    /// ten minutes on the clock
+    fn contract_with_penny_auction() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .kind(AuctionKind::Penny {
+                    bid_fee: near(1),
+                    increment: near(1),
+                    countdown: 10 * MINUTE_SECS,
+                })
+                .build(),
+            None,
+        );
+        contract
+    }
+
+    fn penny_bid(contract: &mut Contract, bidder: &AccountId, deposit: NearToken, now: u64) {
+        testing_env!(context(bidder)
+            .block_timestamp(now)
+            .attached_deposit(deposit)
+            .build());
+        contract.penny_bid(nft(), token_id(), None);
+    }
+
+    #[test]
+    fn penny_bids_charge_the_fee_and_reset_the_clock() {
+        let mut contract = contract_with_penny_auction();
+        penny_bid(&mut contract, &accounts(1), near(5), 55 * MINUTE);
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.h_bid, near(2));
+        assert_eq!(auction.fees_accrued, near(1));
+        assert_eq!(auction.expiry, U64(65 * MINUTE));
+        assert_eq!(contract.fees_accrued(), near(1));
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+    }
+
+    #[test]
+    fn penny_bids_only_pay_for_what_the_bid_grows_by() {
+        let mut contract = contract_with_penny_auction();
+        penny_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        penny_bid(&mut contract, &accounts(2), near(4), 2 * MINUTE);
+        // Raising their bid of 2 NEAR to 4 NEAR takes 2 NEAR plus the fee
+        penny_bid(&mut contract, &accounts(1), near(3), 3 * MINUTE);
+
+        assert!(transfers_to(&accounts(1)).is_empty());
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.h_bid, near(4));
+        assert_eq!(auction.fees_accrued, near(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "provided deposit does not cover bid amount")]
+    fn penny_bids_have_to_cover_the_fee() {
+        let mut contract = contract_with_penny_auction();
+        penny_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "this is a penny auction, bid with `penny_bid`")]
+    fn penny_auctions_only_take_penny_bids() {
+        let mut contract = contract_with_penny_auction();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(2))
+            .build());
+        contract.make_bid(nft(), token_id(), near(2), None, None, None);
+    }
+
+    #[test]
+    fn penny_auction_fees_go_to_the_seller() {
+        let mut contract = contract_with_penny_auction();
+        penny_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        penny_bid(&mut contract, &accounts(2), near(4), 2 * MINUTE);
+
+        end_auction(&mut contract, HOUR);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+        assert_eq!(contract.balance_of(accounts(0)), near(5));
+    }
+
+    /// A contract with an auction of `kind` selling `token_id` and token 2 by alice, with a
+    /// minimum bid of 1 NEAR, started at block time 0 and ending an hour later
+    fn contract_with_lot(kind: AuctionKind) -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .kind(kind)
+                .build(),
+            None,
+        );
+        contract.add_to_lot(accounts(0), nft(), token_id(), "2".parse().unwrap(), Ok(()));
+        contract
+    }
+
+    fn contract_with_uniform_auction() -> Contract {
+        contract_with_lot(AuctionKind::Uniform)
+    }
+
+    #[test]
+    fn uniform_auctions_charge_every_winner_the_lowest_winning_bid() {
+        let mut contract = contract_with_uniform_auction();
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        make_bid(&mut contract, &accounts(2), near(5), 2 * MINUTE);
+        make_bid(&mut contract, &accounts(3), near(4), 3 * MINUTE);
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(4));
+
+        end_auction(&mut contract, HOUR);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(3)]);
+        assert_eq!(transfers_to(&accounts(2)), vec![near(1)]);
+        assert!(transfers_to(&accounts(3)).is_empty());
+        assert_eq!(contract.balance_of(accounts(0)), near(8));
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(2)));
+        assert_eq!(
+            contract.nft_claim_of(nft(), "2".parse().unwrap()),
+            Some(accounts(3))
+        );
+    }
+
+    #[test]
+    fn lots_only_grow_by_nfts_that_were_transferred() {
+        let mut contract = contract_with_uniform_auction();
+        contract.add_to_lot(
+            accounts(0),
+            nft(),
+            token_id(),
+            "3".parse().unwrap(),
+            Err(PromiseError::Failed),
+        );
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.lot, vec!["2".to_string()]);
+        assert_eq!(auction.h_bid, near(1));
+    }
+
+    #[test]
+    fn lots_arriving_after_the_auction_ended_are_returned() {
+        let mut contract = contract_with_uniform_auction();
+        end_auction(&mut contract, HOUR);
+
+        testing_env!(context(&contract_id()).block_timestamp(HOUR).build());
+        contract.add_to_lot(
+            accounts(0),
+            nft(),
+            token_id(),
+            "3".parse().unwrap(),
+            Err(PromiseError::Failed),
+        );
+        assert!(get_created_receipts().is_empty());
+        contract.add_to_lot(accounts(0), nft(), token_id(), "3".parse().unwrap(), Ok(()));
+        assert!(get_created_receipts()
+            .iter()
+            .any(|receipt| receipt.receiver_id == nft()));
+    }
+
+    #[test]
+    fn simulated_bids_raise_uniform_auctions_to_their_lowest_winning_bid() {
+        let mut contract = contract_with_uniform_auction();
+
+        // One bid leaves a unit to whoever bids the minimum
+        let simulated = contract.simulate_bid(nft(), token_id(), accounts(1), near(3));
+        assert!(simulated.accepted);
+        assert_eq!(
+            simulated.min_next_bid,
+            near(1).saturating_add(NearToken::from_yoctonear(1))
+        );
+
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        let simulated = contract.simulate_bid(nft(), token_id(), accounts(2), near(5));
+        assert!(simulated.accepted);
+        assert_eq!(
+            simulated.min_next_bid,
+            near(3).saturating_add(NearToken::from_yoctonear(1))
+        );
+    }
+
+    fn make_offer(
+        contract: &mut Contract,
+        offerer: &AccountId,
+        amount: NearToken,
+        expires_at: Option<u64>,
+    ) -> U64 {
+        testing_env!(context(offerer)
+            .block_timestamp(MINUTE)
+            .attached_deposit(amount)
+            .build());
+        contract.make_offer(nft(), token_id(), expires_at.map(U64))
+    }
+
+    #[test]
+    fn offers_escrow_their_deposit_until_withdrawn() {
+        let mut contract = Contract::default();
+        let first = make_offer(&mut contract, &accounts(1), near(2), None);
+        let second = make_offer(&mut contract, &accounts(2), near(3), Some(HOUR));
+        assert_ne!(first, second);
+
+        let offers = contract.get_offers(nft(), token_id(), None, None);
+        assert_eq!(offers.len(), 2);
+        assert_eq!(offers[0].offerer, accounts(1));
+        assert_eq!(offers[0].amount, near(2));
+        assert_eq!(offers[1].expires_at, Some(U64(HOUR)));
+
+        testing_env!(context(&accounts(1)).block_timestamp(2 * MINUTE).build());
+        contract.withdraw_offer(nft(), token_id(), first);
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+        assert_eq!(contract.get_offers(nft(), token_id(), None, None).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "caller has no such offer on this nft")]
+    fn offers_can_only_be_withdrawn_by_their_offerer() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        testing_env!(context(&accounts(2)).build());
+        contract.withdraw_offer(nft(), token_id(), offer_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "offer expiry must be in the future")]
+    fn offers_cannot_expire_in_the_past() {
+        make_offer(
+            &mut Contract::default(),
+            &accounts(1),
+            near(2),
+            Some(MINUTE),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "this nft is in auction, bid on it instead")]
+    fn offers_cannot_be_made_on_nfts_in_auction() {
+        let mut contract = contract_with_uniform_auction();
+        make_offer(&mut contract, &accounts(1), near(2), None);
+    }
+
+    fn token_owned_by(owner_id: &AccountId) -> Option<Token> {
+        Some(Token {
+            token_id: token_id().into(),
+            owner_id: owner_id.clone(),
+            metadata: None,
+            approved_account_ids: None,
+        })
+    }
+
+    /// Accepts `offer_id` as alice, returning the offer held aside while the swap is in flight
+    fn accept_offer(contract: &mut Contract, offer_id: U64) -> Offer {
+        let held = contract.get_offers(nft(), token_id(), None, None);
+        let held = held
+            .iter()
+            .find(|offer| offer.offer_id == offer_id)
+            .unwrap();
+        let offer = Offer {
+            offerer: held.offerer.clone(),
+            amount: held.amount,
+            expires_at: held.expires_at.map(u64::from),
+            made_at: held.made_at.into(),
+            counter: None,
+        };
+        testing_env!(context(&accounts(0)).block_timestamp(2 * MINUTE).build());
+        contract.accept_offer(nft(), token_id(), offer_id);
+        offer
+    }
+
+    #[test]
+    fn accepted_offers_pay_the_owner_and_refund_the_others() {
+        let mut contract = Contract::default();
+        let accepted = make_offer(&mut contract, &accounts(1), near(2), None);
+        make_offer(&mut contract, &accounts(2), near(1), None);
+        let offer = accept_offer(&mut contract, accepted);
+        assert_eq!(contract.get_offers(nft(), token_id(), None, None).len(), 1);
+
+        testing_env!(context(&contract_id()).build());
+        contract.on_offer_nft_token(
+            nft(),
+            token_id(),
+            accepted,
+            Offer {
+                offerer: offer.offerer.clone(),
+                counter: None,
+                ..offer
+            },
+            accounts(0),
+            Ok(token_owned_by(&accounts(0))),
+        );
+        assert!(get_created_receipts()
+            .iter()
+            .any(|receipt| receipt.receiver_id == nft()));
+
+        testing_env!(context(&contract_id()).build());
+        assert!(contract.on_offer_accepted(
+            nft(),
+            token_id(),
+            accepted,
+            offer,
+            accounts(0),
+            Ok(())
+        ));
+        assert_eq!(contract.balance_of(accounts(0)), near(2));
+        assert_eq!(transfers_to(&accounts(2)), vec![near(1)]);
+        assert!(contract
+            .get_offers(nft(), token_id(), None, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn offers_are_restored_if_the_caller_does_not_own_the_nft() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        let offer = accept_offer(&mut contract, offer_id);
+
+        testing_env!(context(&contract_id()).build());
+        contract.on_offer_nft_token(
+            nft(),
+            token_id(),
+            offer_id,
+            offer,
+            accounts(0),
+            Ok(token_owned_by(&accounts(3))),
+        );
+        assert!(get_created_receipts().is_empty());
+        let offers = contract.get_offers(nft(), token_id(), None, None);
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].offer_id, offer_id);
+    }
+
+    #[test]
+    fn offers_are_restored_if_the_transfer_fails() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        let offer = accept_offer(&mut contract, offer_id);
+
+        testing_env!(context(&contract_id()).build());
+        assert!(!contract.on_offer_accepted(
+            nft(),
+            token_id(),
+            offer_id,
+            offer,
+            accounts(0),
+            Err(PromiseError::Failed)
+        ));
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert_eq!(contract.get_offers(nft(), token_id(), None, None).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "offer has expired")]
+    fn expired_offers_cannot_be_accepted() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), Some(2 * MINUTE));
+        accept_offer(&mut contract, offer_id);
+    }
+
+    /// Counters `offer_id` as alice with `price` until an hour in, once the NFT contract confirmed
+    /// she owns the NFT
+    fn counter_offer(contract: &mut Contract, offer_id: U64, price: NearToken) -> bool {
+        testing_env!(context(&accounts(0)).block_timestamp(2 * MINUTE).build());
+        contract.counter_offer(nft(), token_id(), offer_id, price, U64(HOUR));
+        testing_env!(context(&contract_id()).block_timestamp(2 * MINUTE).build());
+        contract.on_counter_nft_token(
+            nft(),
+            token_id(),
+            offer_id,
+            Counteroffer {
+                seller: accounts(0),
+                price,
+                expires_at: HOUR,
+            },
+            Ok(token_owned_by(&accounts(0))),
+        )
+    }
+
+    #[test]
+    fn counteroffers_are_recorded_for_the_nft_owner() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        assert!(counter_offer(&mut contract, offer_id, near(3)));
+
+        let offers = contract.get_offers(nft(), token_id(), None, None);
+        let counter = offers[0].counter.as_ref().unwrap();
+        assert_eq!(counter.seller, accounts(0));
+        assert_eq!(counter.price, near(3));
+        assert_eq!(counter.expires_at, U64(HOUR));
+    }
+
+    #[test]
+    fn counteroffers_are_ignored_unless_the_seller_owns_the_nft() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        testing_env!(context(&contract_id()).build());
+        assert!(!contract.on_counter_nft_token(
+            nft(),
+            token_id(),
+            offer_id,
+            Counteroffer {
+                seller: accounts(0),
+                price: near(3),
+                expires_at: HOUR,
+            },
+            Ok(token_owned_by(&accounts(3))),
+        ));
+        assert!(contract.get_offers(nft(), token_id(), None, None)[0]
+            .counter
+            .is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "counter price must exceed the amount offered")]
+    fn counteroffers_must_ask_for_more() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        counter_offer(&mut contract, offer_id, near(2));
+    }
+
+    #[test]
+    fn accepting_a_counteroffer_tops_up_the_escrow() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        counter_offer(&mut contract, offer_id, near(3));
+
+        testing_env!(context(&accounts(1))
+            .block_timestamp(3 * MINUTE)
+            .attached_deposit(near(2))
+            .build());
+        contract.accept_counteroffer(nft(), token_id(), offer_id);
+        // The excess is refunded and the offer is held aside for the swap
+        assert_eq!(transfers_to(&accounts(1)), vec![near(1)]);
+        assert!(contract
+            .get_offers(nft(), token_id(), None, None)
+            .is_empty());
+
+        testing_env!(context(&contract_id()).build());
+        let offer = Offer {
+            offerer: accounts(1),
+            amount: near(3),
+            expires_at: None,
+            made_at: MINUTE,
+            counter: None,
+        };
+        contract.on_offer_accepted(nft(), token_id(), offer_id, offer, accounts(0), Ok(()));
+        assert_eq!(contract.balance_of(accounts(0)), near(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "attached deposit does not cover the counter price")]
+    fn accepting_a_counteroffer_needs_the_difference() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        counter_offer(&mut contract, offer_id, near(3));
+        testing_env!(context(&accounts(1))
+            .block_timestamp(3 * MINUTE)
+            .attached_deposit(near(1).saturating_sub(NearToken::from_yoctonear(1)))
+            .build());
+        contract.accept_counteroffer(nft(), token_id(), offer_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "this offer has not been countered")]
+    fn only_countered_offers_can_be_accepted_by_their_offerer() {
+        let mut contract = Contract::default();
+        let offer_id = make_offer(&mut contract, &accounts(1), near(2), None);
+        testing_env!(context(&accounts(1)).attached_deposit(near(1)).build());
+        contract.accept_counteroffer(nft(), token_id(), offer_id);
+    }
+
+    fn contract_with_bonding_curve() -> Contract {
+        contract_with_lot(AuctionKind::BondingCurve {
+            base_price: near(2),
+            slope: near(1),
+        })
+    }
+
+    fn buy(contract: &mut Contract, buyer: &AccountId, deposit: NearToken) -> TokenId {
+        testing_env!(context(buyer)
+            .block_timestamp(MINUTE)
+            .attached_deposit(deposit)
+            .build());
+        let PromiseOrValue::Value(Some(bought)) = contract.buy(nft(), token_id(), None) else {
+            panic!("purchase was not made right away");
+        };
+        bought
+    }
+
+    #[test]
+    fn bonding_curves_sell_each_nft_for_more_until_sold_out() {
+        let mut contract = contract_with_bonding_curve();
+        assert_eq!(buy(&mut contract, &accounts(1), near(2)), token_id());
+        assert_eq!(contract.get_auction(nft(), token_id()).sold, 1);
+        assert_eq!(
+            buy(&mut contract, &accounts(2), near(5)),
+            "2".parse::<TokenId>().unwrap()
+        );
+        // The price was 3 NEAR
+        assert_eq!(transfers_to(&accounts(2)), vec![near(2)]);
+
+        assert_eq!(contract.balance_of(accounts(0)), near(5));
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(1)));
+        assert_eq!(
+            contract.nft_claim_of(nft(), "2".parse().unwrap()),
+            Some(accounts(2))
+        );
+        assert!(!contract
+            .auctions
+            .contains_key(&NFTId::new(&nft(), &token_id())));
+    }
+
+    #[test]
+    #[should_panic(expected = "provided deposit does not cover the quoted price")]
+    fn bonding_curves_charge_the_quoted_price() {
+        let mut contract = contract_with_bonding_curve();
+        buy(&mut contract, &accounts(1), near(2));
+        buy(&mut contract, &accounts(2), near(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "this is a bonding-curve sale, buy with `buy`")]
+    fn bonding_curves_take_no_bids() {
+        let mut contract = contract_with_bonding_curve();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "this is not a bonding-curve sale")]
+    fn only_bonding_curves_sell_through_buy() {
+        let mut contract = contract_with_uniform_auction();
+        buy(&mut contract, &accounts(1), near(2));
+    }
+
+    #[test]
+    fn winners_can_relist_an_unclaimed_nft() {
+        let mut contract = contract_with_bonding_curve();
+        buy(&mut contract, &accounts(1), near(2));
+        buy(&mut contract, &accounts(2), near(3));
+        let mut contract = reloaded(contract);
+
+        testing_env!(context(&accounts(1)).block_timestamp(2 * MINUTE).build());
+        contract.relist_won(
+            nft(),
+            token_id(),
+            AuctionParams::builder(HOUR_SECS, near(3)).build(),
+        );
+
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), None);
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.owner, accounts(1));
+        assert_eq!(auction.minimum_bid, near(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "only the winner of the auction can relist this nft")]
+    fn only_winners_can_relist() {
+        let mut contract = contract_with_bonding_curve();
+        buy(&mut contract, &accounts(1), near(2));
+
+        testing_env!(context(&accounts(2)).block_timestamp(2 * MINUTE).build());
+        contract.relist_won(
+            nft(),
+            token_id(),
+            AuctionParams::builder(HOUR_SECS, near(3)).build(),
+        );
+    }
+
+    fn arbiter() -> AccountId {
+        "arbiter.near".parse().unwrap()
+    }
+
+    /// A contract whose auction of `token_id` by alice, with an hour long dispute window, bob
+    /// won for 3 NEAR
+    fn contract_with_disputable_sale() -> Contract {
+        disputable_sale(Contract::default())
+    }
+
+    /// `contract` once bob won alice's auction of `token_id`, with an hour long dispute window,
+    /// for 3 NEAR
+    fn disputable_sale(mut contract: Contract) -> Contract {
+        testing_env!(context(&contract_id()).build());
+        contract.set_arbiter(Some(arbiter()));
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .dispute_window(HOUR_SECS)
+                .build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        end_auction(&mut contract, HOUR);
+        contract
+    }
+
+    #[test]
+    fn disputable_proceeds_are_released_once_the_window_is_over() {
+        let mut contract = contract_with_disputable_sale();
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        let held = contract.get_held_proceeds(U64(0)).unwrap();
+        assert_eq!(held.payments.len(), 1);
+        assert_eq!(held.payments[0].buyer, accounts(1));
+        assert_eq!(held.payments[0].amount, near(3));
+        assert_eq!(held.release_at, U64(2 * HOUR));
+
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        contract.release_proceeds(U64(0));
+        assert_eq!(contract.balance_of(accounts(0)), near(3));
+        assert!(contract.get_held_proceeds(U64(0)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "proceeds are not due yet")]
+    fn disputable_proceeds_are_held_during_the_window() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR + MINUTE).build());
+        contract.release_proceeds(U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "proceeds are frozen by a dispute")]
+    fn frozen_proceeds_are_not_released() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&arbiter()).block_timestamp(HOUR + MINUTE).build());
+        contract.freeze_payout(U64(0));
+        assert!(contract.get_held_proceeds(U64(0)).unwrap().frozen);
+
+        testing_env!(context(&accounts(5)).block_timestamp(3 * HOUR).build());
+        contract.release_proceeds(U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "only the arbiter can do this")]
+    fn only_the_arbiter_freezes_proceeds() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&accounts(1)).block_timestamp(HOUR + MINUTE).build());
+        contract.freeze_payout(U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "dispute window is over")]
+    fn proceeds_cannot_be_frozen_after_the_window() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&arbiter()).block_timestamp(2 * HOUR).build());
+        contract.freeze_payout(U64(0));
+    }
+
+    #[test]
+    fn disputes_resolved_for_the_buyers_refund_what_they_paid() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&arbiter()).block_timestamp(HOUR + MINUTE).build());
+        contract.freeze_payout(U64(0));
+        contract.resolve_dispute(U64(0), true);
+
+        assert_eq!(contract.balance_of(accounts(1)), near(3));
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert!(contract.get_held_proceeds(U64(0)).is_none());
+    }
+
+    #[test]
+    fn disputes_resolved_for_the_seller_pay_the_seller() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&arbiter()).block_timestamp(HOUR + MINUTE).build());
+        contract.freeze_payout(U64(0));
+        contract.resolve_dispute(U64(0), false);
+
+        assert_eq!(contract.balance_of(accounts(0)), near(3));
+        assert_eq!(contract.balance_of(accounts(1)), near(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "there are no such frozen proceeds")]
+    fn only_frozen_proceeds_are_resolved() {
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&arbiter()).block_timestamp(HOUR + MINUTE).build());
+        contract.resolve_dispute(U64(0), true);
+    }
+
+    /// A contract charging a 10% quick-flip fee on every trade
+    fn contract_charging_quick_flips() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_wash_trade_rules(Some(WashTradeConfig {
+            window: U64(HOUR_SECS),
+            free_trades: 0,
+            fee_bps: 1_000,
+        }));
+        contract
+    }
+
+    #[test]
+    fn quick_flip_fees_are_held_with_the_proceeds() {
+        let mut contract = disputable_sale(contract_charging_quick_flips());
+        assert_eq!(contract.protocol_fees(), near(0));
+        let held = contract.get_held_proceeds(U64(0)).unwrap();
+        assert_eq!(held.payments.len(), 1);
+        assert_eq!(held.payments[0].amount, near(3));
+        assert_eq!(
+            held.payments[0].protocol_fee,
+            NearToken::from_millinear(300)
+        );
+
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        contract.release_proceeds(U64(0));
+        assert_eq!(
+            contract.balance_of(accounts(0)),
+            NearToken::from_millinear(2_700)
+        );
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(300));
+    }
+
+    #[test]
+    fn refunded_disputes_return_the_quick_flip_fee_too() {
+        let mut contract = disputable_sale(contract_charging_quick_flips());
+        testing_env!(context(&arbiter()).block_timestamp(HOUR + MINUTE).build());
+        contract.freeze_payout(U64(0));
+        contract.resolve_dispute(U64(0), true);
+
+        assert_eq!(contract.balance_of(accounts(1)), near(3));
+        assert_eq!(contract.protocol_fees(), near(0));
+    }
+
+    #[test]
+    fn protocol_fees_that_fail_to_transfer_are_owed() {
+        let mut contract = contract_charging_quick_flips();
+        contract.internal_collect_protocol_fee(near(1));
+        contract.withdraw_protocol_fees(accounts(4));
+        assert_eq!(contract.protocol_fees(), near(0));
+
+        contract.on_payout(
+            accounts(4),
+            OwedPayout::Near { amount: near(1) },
+            Err(PromiseError::Failed),
+        );
+        assert_eq!(contract.owed_to(accounts(4)).len(), 1);
+    }
+
+    /// `contract` once bob won alice's auction of `token_id` for 3 NEAR, the NFT being handed
+    /// over to him at settlement
+    fn handed_over_sale(mut contract: Contract) -> Contract {
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .push_nft(true)
+                .build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        end_auction(&mut contract, HOUR);
+        contract
+    }
+
+    fn delivered_claim() -> NftClaim {
+        NftClaim {
+            nft: nft(),
+            token_id: token_id(),
+            winner: accounts(1),
+            escrow_id: Some(0),
+        }
+    }
+
+    #[test]
+    fn proceeds_of_handed_over_nfts_are_released_once_delivered() {
+        let mut contract = handed_over_sale(contract_charging_quick_flips());
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert_eq!(
+            contract.get_held_proceeds(U64(0)).unwrap().undelivered,
+            vec![accounts(1)]
+        );
+
+        assert!(contract.on_nft_delivered(delivered_claim(), true, Ok(())));
+        assert!(contract.get_held_proceeds(U64(0)).is_none());
+        assert_eq!(
+            contract.balance_of(accounts(0)),
+            NearToken::from_millinear(2_700)
+        );
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(300));
+    }
+
+    #[test]
+    fn escrowed_nfts_that_failed_to_arrive_can_be_claimed() {
+        let mut contract = handed_over_sale(Contract::default());
+        assert!(!contract.on_nft_delivered(delivered_claim(), true, Err(PromiseError::Failed)));
+
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(1)));
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert!(contract.get_held_proceeds(U64(0)).is_some());
+    }
+
+    #[test]
+    fn buyers_are_refunded_in_full_if_their_nft_cannot_arrive() {
+        let mut contract = handed_over_sale(contract_charging_quick_flips());
+        assert!(!contract.on_nft_delivered(delivered_claim(), false, Err(PromiseError::Failed)));
+
+        assert_eq!(contract.balance_of(accounts(1)), near(3));
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert_eq!(contract.protocol_fees(), near(0));
+        assert!(contract.get_held_proceeds(U64(0)).is_none());
+    }
+
+    /// A contract with a pay-on-win auction of `token_id` by alice, with a minimum bid of 1 NEAR
+    /// and ending an hour later, where bids escrow 10% and the winner has an hour to pay the rest
+    fn contract_with_pay_on_win_auction() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .pay_on_win(PayOnWin {
+                    deposit_bps: 1_000,
+                    payment_window: HOUR_SECS,
+                })
+                .build(),
+            None,
+        );
+        contract
+    }
+
+    fn complete_purchase(contract: &mut Contract, buyer: &AccountId, deposit: NearToken, now: u64) {
+        testing_env!(context(buyer)
+            .block_timestamp(now)
+            .attached_deposit(deposit)
+            .build());
+        contract.complete_purchase(nft(), token_id(), None);
+    }
+
+    #[test]
+    fn pay_on_win_bids_escrow_only_their_deposit() {
+        let mut contract = contract_with_pay_on_win_auction();
+        make_bid(&mut contract, &accounts(1), near(10), MINUTE);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(9)]);
+        let bids = contract.get_bids(nft(), token_id(), None, None);
+        assert_eq!(bids[0].deposit, Some(near(1)));
+        assert_eq!(contract.reconcile().bids_escrowed, near(1));
+    }
+
+    #[test]
+    fn pay_on_win_winners_pay_the_rest_to_settle() {
+        let mut contract = contract_with_pay_on_win_auction();
+        make_bid(&mut contract, &accounts(1), near(10), MINUTE);
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction(nft(), token_id());
+        let pending = contract
+            .get_auction(nft(), token_id())
+            .pending_payment
+            .unwrap();
+        assert_eq!(pending.winner, accounts(1));
+        assert_eq!(pending.due, U64(2 * HOUR));
+
+        // Anything beyond the rest of the bid is returned
+        complete_purchase(&mut contract, &accounts(1), near(10), HOUR + MINUTE);
+        assert_eq!(transfers_to(&accounts(1)), vec![near(1)]);
+        testing_env!(context(&contract_id())
+            .block_timestamp(HOUR + MINUTE)
+            .build());
+        assert!(contract.on_escrow_checked(
+            nft(),
+            token_id(),
+            true,
+            Ok(token_owned_by(&contract_id()))
+        ));
+        assert_eq!(contract.balance_of(accounts(0)), near(10));
+        assert_eq!(contract.nft_claim_of(nft(), token_id()), Some(accounts(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "attached deposit does not cover the rest of the bid")]
+    fn pay_on_win_winners_pay_all_of_the_rest() {
+        let mut contract = contract_with_pay_on_win_auction();
+        make_bid(&mut contract, &accounts(1), near(10), MINUTE);
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction(nft(), token_id());
+        complete_purchase(&mut contract, &accounts(1), near(8), HOUR + MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "caller has no payment due on this auction")]
+    fn only_pay_on_win_winners_complete_the_purchase() {
+        let mut contract = contract_with_pay_on_win_auction();
+        make_bid(&mut contract, &accounts(2), near(5), MINUTE);
+        make_bid(&mut contract, &accounts(1), near(10), 2 * MINUTE);
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction(nft(), token_id());
+        complete_purchase(&mut contract, &accounts(2), near(5), HOUR + MINUTE);
+    }
+
+    #[test]
+    fn marketplace_metadata_lists_the_allowed_payment_tokens() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.internal_collect_protocol_fee(near(1));
+        let config = || PaymentTokenConfig {
+            symbol: "WETH".into(),
+            decimals: 18,
+            min_bid_unit: U128(1),
+            fee: TokenFee::Waived,
+        };
+        contract.set_payment_token(
+            PaymentToken::Ft {
+                contract: accounts(4),
+            },
+            Some(config()),
+        );
+        contract.set_payment_token(
+            PaymentToken::Mt {
+                contract: accounts(5),
+                token_id: "gold".parse().unwrap(),
+            },
+            Some(config()),
+        );
+
+        let metadata = contract.nft_marketplace_metadata();
+        assert_eq!(
+            metadata.payment_tokens,
+            vec!["near".to_string(), "eugene".into(), "fargo:gold".into()]
+        );
+        assert!(metadata.standards.contains(&"nep245".into()));
+        assert!(metadata.receivers.contains(&"mt_on_transfer".into()));
+    }
+
+    #[test]
+    fn pay_on_win_winners_that_miss_the_window_lose_the_win_and_their_deposit() {
+        let mut contract = contract_with_pay_on_win_auction();
+        make_bid(&mut contract, &accounts(2), near(5), MINUTE);
+        make_bid(&mut contract, &accounts(1), near(10), 2 * MINUTE);
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction(nft(), token_id());
+
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        contract.end_auction(nft(), token_id());
+        assert!(transfers_to(&accounts(1)).is_empty());
+        assert_eq!(
+            contract.balance_of(accounts(0)),
+            NearToken::from_millinear(500)
+        );
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(500));
+        assert_eq!(
+            contract.reconcile().bids_escrowed,
+            NearToken::from_millinear(500)
+        );
+        let pending = contract
+            .get_auction(nft(), token_id())
+            .pending_payment
+            .unwrap();
+        assert_eq!(pending.winner, accounts(2));
+        assert_eq!(pending.due, U64(3 * HOUR));
+    }
+
+    #[test]
+    #[should_panic(expected = "slash treasury share must be at most 10000 bps")]
+    fn slashed_deposits_go_to_the_treasury_at_most_in_full() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_slash_treasury_bps(10_001);
+    }
+
+    #[test]
+    #[should_panic(expected = "payment window is over")]
+    fn pay_on_win_purchases_cannot_complete_after_the_window() {
+        let mut contract = contract_with_pay_on_win_auction();
+        make_bid(&mut contract, &accounts(1), near(10), MINUTE);
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_auction(nft(), token_id());
+        complete_purchase(&mut contract, &accounts(1), near(9), 2 * HOUR);
+    }
+
+    fn dao() -> AccountId {
+        "dao.near".parse().unwrap()
+    }
+
+    #[test]
+    fn owners_hand_the_settings_over() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        assert_eq!(contract.owner(), contract_id());
+        contract.set_owner(dao());
+
+        testing_env!(context(&dao()).build());
+        contract.set_listing_stake(near(1));
+        assert_eq!(contract.owner(), dao());
+        assert_eq!(contract.listing_stake(), near(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "only the owner can call this method")]
+    fn only_the_owner_changes_settings() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_owner(dao());
+        contract.set_listing_stake(near(1));
+    }
+
+    /// A contract where two of carol, dan and eve have to confirm privileged actions
+    fn contract_with_admins() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_admins(vec![accounts(2), accounts(3), accounts(4)], 2);
+        contract
+    }
+
+    fn propose_action(contract: &mut Contract, admin: &AccountId, action: AdminAction) -> bool {
+        testing_env!(context(admin).build());
+        matches!(contract.propose_action(action), PromiseOrValue::Promise(_))
+    }
+
+    fn confirm_action(contract: &mut Contract, admin: &AccountId, action_id: u64) -> bool {
+        testing_env!(context(admin).build());
+        matches!(
+            contract.confirm_action(U64(action_id)),
+            PromiseOrValue::Promise(_)
+        )
+    }
+
+    fn withdrawal_to_dao() -> AdminAction {
+        AdminAction::WithdrawProtocolFees { receiver_id: dao() }
+    }
+
+    #[test]
+    fn admin_actions_run_once_enough_admins_confirmed() {
+        let mut contract = contract_with_admins();
+        contract.internal_collect_protocol_fee(near(1));
+
+        assert!(!propose_action(
+            &mut contract,
+            &accounts(2),
+            withdrawal_to_dao()
+        ));
+        assert_eq!(contract.protocol_fees(), near(1));
+        let pending = contract.get_pending_action(U64(0)).unwrap();
+        assert_eq!(pending.proposer, accounts(2));
+        assert_eq!(pending.confirmations, vec![accounts(2)]);
+
+        assert!(confirm_action(&mut contract, &accounts(3), 0));
+        assert_eq!(transfers_to(&dao()), vec![near(1)]);
+        assert_eq!(contract.protocol_fees(), near(0));
+        assert!(contract.get_pending_action(U64(0)).is_none());
+    }
+
+    #[test]
+    fn admin_actions_confirmed_by_their_proposer_alone_run_right_away() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_admins(vec![accounts(2)], 1);
+        contract.internal_collect_protocol_fee(near(1));
+
+        assert!(propose_action(
+            &mut contract,
+            &accounts(2),
+            withdrawal_to_dao()
+        ));
+        assert_eq!(transfers_to(&dao()), vec![near(1)]);
+    }
+
+    #[test]
+    fn admins_cancel_auctions_refunding_every_bid() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_admins(vec![accounts(2)], 1);
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        let cancel = AdminAction::CancelAuction {
+            nft: nft(),
+            token_id: token_id(),
+        };
+        assert!(propose_action(&mut contract, &accounts(2), cancel));
+        assert_eq!(transfers_to(&accounts(1)), vec![near(3)]);
+        assert_eq!(contract.counts().auctions, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "admins are set, propose this as an action instead")]
+    fn settings_guarded_by_admins_cannot_be_set_directly() {
+        let mut contract = contract_with_admins();
+        contract.set_arbiter(Some(arbiter()));
+    }
+
+    #[test]
+    fn admins_replace_the_arbiter_through_an_action() {
+        let mut contract = contract_with_admins();
+        let action = AdminAction::SetArbiter {
+            arbiter: Some(arbiter()),
+        };
+        propose_action(&mut contract, &accounts(2), action);
+        confirm_action(&mut contract, &accounts(4), 0);
+        assert_eq!(contract.arbiter(), Some(arbiter()));
+    }
+
+    #[test]
+    #[should_panic(expected = "only admins can propose or confirm actions")]
+    fn only_admins_propose_actions() {
+        let mut contract = contract_with_admins();
+        propose_action(&mut contract, &accounts(1), withdrawal_to_dao());
+    }
+
+    #[test]
+    #[should_panic(expected = "caller already confirmed this action")]
+    fn admins_confirm_an_action_once() {
+        let mut contract = contract_with_admins();
+        propose_action(&mut contract, &accounts(2), withdrawal_to_dao());
+        confirm_action(&mut contract, &accounts(2), 0);
+    }
+
+    #[test]
+    fn proposers_revoke_their_actions() {
+        let mut contract = contract_with_admins();
+        propose_action(&mut contract, &accounts(2), withdrawal_to_dao());
+        contract.revoke_action(U64(0));
+        assert!(contract.get_pending_action(U64(0)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "only the proposer can revoke an action")]
+    fn only_the_proposer_revokes_an_action() {
+        let mut contract = contract_with_admins();
+        propose_action(&mut contract, &accounts(2), withdrawal_to_dao());
+        testing_env!(context(&accounts(3)).build());
+        contract.revoke_action(U64(0));
+    }
+
+    #[test]
+    fn upgrades_migrate_the_state_they_versioned() {
+        let mut contract = contract_with_penny_auction();
+        contract.upgrade(vec![0].into());
+        env::state_write(&contract);
+        // Collections write their entries when dropped
+        drop(contract);
+
+        let migrated = Contract::migrate();
+        assert_eq!(migrated.counts().auctions, 1);
+        assert_eq!(migrated.get_auction(nft(), token_id()).minimum_bid, near(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "this code was not approved by the admins")]
+    fn upgrades_deploy_only_code_the_admins_approved() {
+        let mut contract = contract_with_admins();
+        let action = AdminAction::Upgrade {
+            code_hash: env::sha256_array(&[1]).into(),
+        };
+        propose_action(&mut contract, &accounts(2), action);
+        confirm_action(&mut contract, &accounts(3), 0);
+        contract.upgrade(vec![0].into());
+    }
+
+    /// Stores the state of the first deployed code, with an auction of `token_id` by alice
+    /// ending an hour in
+    fn write_baseline_state() {
+        testing_env!(context(&contract_id()).build());
+        let mut auctions = IterableMap::new(upgrade::LEGACY_AUCTIONS_PREFIX);
+        auctions.insert(
+            NFTId::new(&nft(), &token_id()),
+            upgrade::LegacyAuction {
+                owner: accounts(0),
+                bids: IterableMap::new(upgrade::LEGACY_AUCTIONS_PREFIX),
+                h_bid: near(1),
+                expiry: HOUR,
+            },
+        );
+        let baseline = upgrade::BaselineContract { auctions };
+        env::state_write(&baseline);
+        drop(baseline);
+    }
+
+    #[test]
+    fn baseline_state_is_migrated_and_its_auctions_returned_once_over() {
+        write_baseline_state();
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.counts().auctions, 0);
+        assert_eq!(env::storage_read(b"STATE_VERSION"), Some(vec![1]));
+
+        testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+        contract.end_legacy_auction(nft(), token_id());
+        let transfers: Vec<_> = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == nft())
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } => Some((String::from_utf8(method_name).unwrap(), args)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].0, "nft_transfer");
+        let args: serde_json::Value = serde_json::from_slice(&transfers[0].1).unwrap();
+        assert_eq!(args["receiver_id"], "alice");
+        assert_eq!(args["token_id"], "1");
+        assert!(contract.legacy_auctions.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot end, auction is still ongoing")]
+    fn baseline_auctions_are_only_returned_once_over() {
+        write_baseline_state();
+        let mut contract = Contract::migrate();
+        testing_env!(context(&accounts(5)).block_timestamp(MINUTE).build());
+        contract.end_legacy_auction(nft(), token_id());
+    }
+
+    fn registry() -> AccountId {
+        "kyc.near".parse().unwrap()
+    }
+
+    /// `contract` checking bids and purchases above 1 NEAR with the compliance registry
+    fn checking_compliance(mut contract: Contract) -> Contract {
+        testing_env!(context(&contract_id()).build());
+        contract.set_compliance(Some(registry()), Some(near(1)));
+        contract
+    }
+
+    /// A contract with an english auction of `token_id` by alice, with a minimum bid of 1 NEAR,
+    /// checking bids above 1 NEAR with the compliance registry
+    fn contract_checking_compliance() -> Contract {
+        let mut contract = checking_compliance(Contract::default());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        contract
+    }
+
+    fn answer_compliance_check(
+        contract: &mut Contract,
+        bidder: &AccountId,
+        bid: CheckedBid,
+        deposit: NearToken,
+        compliant: Result<bool, PromiseError>,
+    ) -> bool {
+        testing_env!(context(&contract_id()).block_timestamp(MINUTE).build());
+        contract.on_compliance_checked(
+            bidder.clone(),
+            nft(),
+            token_id(),
+            bid,
+            deposit,
+            false,
+            compliant,
+        )
+    }
+
+    #[test]
+    fn large_bids_wait_for_the_compliance_registry() {
+        let mut contract = contract_checking_compliance();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(3))
+            .build());
+        let checked = contract.make_bid(nft(), token_id(), near(3), None, None, None);
+        assert!(matches!(checked, PromiseOrValue::Promise(_)));
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+
+        let bid = CheckedBid::Make {
+            amount: near(3),
+            expires_at: None,
+            memo: None,
+        };
+        assert!(answer_compliance_check(
+            &mut contract,
+            &accounts(1),
+            bid,
+            near(3),
+            Ok(true)
+        ));
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(3));
+    }
+
+    #[test]
+    fn bidders_the_registry_rejects_are_refunded() {
+        let mut contract = contract_checking_compliance();
+        let bid = CheckedBid::Make {
+            amount: near(3),
+            expires_at: None,
+            memo: None,
+        };
+        assert!(!answer_compliance_check(
+            &mut contract,
+            &accounts(1),
+            bid,
+            near(3),
+            Ok(false)
+        ));
+        assert_eq!(transfers_to(&accounts(1)), vec![near(3)]);
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+    }
+
+    #[test]
+    fn penny_bids_raising_the_price_above_the_threshold_wait_for_the_registry() {
+        let mut contract = checking_compliance(contract_with_penny_auction());
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(5))
+            .build());
+        let checked = contract.penny_bid(nft(), token_id(), None);
+        assert!(matches!(checked, PromiseOrValue::Promise(_)));
+
+        let bid = CheckedBid::Penny;
+        assert!(answer_compliance_check(
+            &mut contract,
+            &accounts(1),
+            bid,
+            near(5),
+            Ok(true)
+        ));
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(2));
+        assert_eq!(transfers_to(&accounts(1)), vec![near(2)]);
+    }
+
+    #[test]
+    fn large_purchases_wait_for_the_compliance_registry() {
+        let mut contract = checking_compliance(contract_with_bonding_curve());
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(5))
+            .build());
+        let checked = contract.buy(nft(), token_id(), None);
+        assert!(matches!(checked, PromiseOrValue::Promise(_)));
+
+        testing_env!(context(&contract_id()).block_timestamp(MINUTE).build());
+        let bought = contract.on_purchase_compliance_checked(
+            accounts(1),
+            nft(),
+            token_id(),
+            near(5),
+            false,
+            Ok(true),
+        );
+        assert!(bought.is_some());
+        assert_eq!(contract.balance_of(accounts(0)), near(2));
+        assert_eq!(transfers_to(&accounts(1)), vec![near(3)]);
+
+        let rejected = contract.on_purchase_compliance_checked(
+            accounts(2),
+            nft(),
+            token_id(),
+            near(5),
+            false,
+            Err(PromiseError::Failed),
+        );
+        assert!(rejected.is_none());
+        assert_eq!(transfers_to(&accounts(2)), vec![near(5)]);
+    }
+
+    /// `contract` once the penny bid of alice burned 1 NEAR on top of its cost
+    fn burning_penny_bid_fees(mut contract: Contract) -> Contract {
+        testing_env!(context(&contract_id()).build());
+        contract.set_bid_burn_fee(AuctionKindName::Penny, Some(near(1)));
+        penny_bid(&mut contract, &accounts(1), near(5), MINUTE);
+        contract
+    }
+
+    #[test]
+    fn bid_fees_are_burned_on_top_of_the_bid() {
+        let mut contract = burning_penny_bid_fees(contract_with_penny_auction());
+        assert_eq!(transfers_to(&accounts(1)), vec![near(1)]);
+        assert_eq!(
+            contract.get_auction(nft(), token_id()).fees_accrued,
+            near(1)
+        );
+        assert_eq!(contract.reconcile().burn_pending, near(1));
+
+        testing_env!(context(&contract_id()).build());
+        contract.set_burn_account(Some(accounts(5)));
+        testing_env!(context(&accounts(4)).build());
+        contract.burn();
+        assert_eq!(transfers_to(&accounts(5)), vec![near(1)]);
+        assert_eq!(contract.reconcile().burn_pending, near(0));
+    }
+
+    #[test]
+    fn burned_fees_that_fail_to_transfer_are_owed() {
+        let mut contract = burning_penny_bid_fees(contract_with_penny_auction());
+        testing_env!(context(&contract_id()).build());
+        contract.set_burn_account(Some(accounts(5)));
+        contract.burn();
+
+        contract.on_payout(
+            accounts(5),
+            OwedPayout::Near { amount: near(1) },
+            Err(PromiseError::Failed),
+        );
+        assert_eq!(contract.owed_to(accounts(5)).len(), 1);
+        assert_eq!(contract.reconcile().owed, near(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "no burn account is set")]
+    fn bid_fees_are_only_burned_into_a_burn_account() {
+        let mut contract = burning_penny_bid_fees(contract_with_penny_auction());
+        contract.burn();
+    }
+
+    fn stake_token() -> AccountId {
+        "stake.near".parse().unwrap()
+    }
+
+    /// A contract sharing half of the protocol fees with the stakers of `stake_token`, alice
+    /// having staked 100 of it and 2 NEAR of fees having been collected since
+    fn contract_sharing_fees_with_stakers() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_staking(Some(stake_token()), 5_000);
+        testing_env!(context(&stake_token()).build());
+        contract.ft_on_transfer(accounts(0), U128(100), String::new());
+        contract.internal_collect_protocol_fee(near(2));
+        contract
+    }
+
+    #[test]
+    fn stakers_claim_their_share_of_the_fees_once_distributed() {
+        let mut contract = contract_sharing_fees_with_stakers();
+        assert_eq!(contract.protocol_fees(), near(1));
+        assert_eq!(contract.staking().undistributed, near(1));
+        let reconciliation = contract.reconcile();
+        assert_eq!(reconciliation.staking_rewards, near(1));
+        assert_eq!(reconciliation.liabilities, near(2));
+
+        testing_env!(context(&accounts(4)).block_timestamp(EPOCH).build());
+        contract.distribute_rewards();
+        assert_eq!(contract.staking().undistributed, near(0));
+        assert_eq!(contract.staker(accounts(0)).unwrap().unclaimed, near(1));
+
+        testing_env!(context(&accounts(0)).block_timestamp(EPOCH).build());
+        contract.claim_rewards();
+        assert_eq!(transfers_to(&accounts(0)), vec![near(1)]);
+        assert_eq!(contract.staker(accounts(0)).unwrap().unclaimed, near(0));
+        assert_eq!(contract.reconcile().staking_rewards, near(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "the epoch has not ended yet")]
+    fn rewards_are_only_distributed_once_the_epoch_ended() {
+        let mut contract = contract_sharing_fees_with_stakers();
+        testing_env!(context(&accounts(4)).block_timestamp(EPOCH - 1).build());
+        contract.distribute_rewards();
+    }
+
+    #[test]
+    #[should_panic(expected = "there are no rewards to claim")]
+    fn undistributed_fees_cannot_be_claimed() {
+        let mut contract = contract_sharing_fees_with_stakers();
+        testing_env!(context(&accounts(0)).build());
+        contract.claim_rewards();
+    }
+
+    #[test]
+    fn stakes_are_sent_back_once_unlocked() {
+        let mut contract = contract_sharing_fees_with_stakers();
+        testing_env!(context(&accounts(0))
+            .block_timestamp(EPOCH)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.unstake(U128(40));
+
+        let transfers: Vec<_> = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == stake_token())
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } => Some((String::from_utf8(method_name).unwrap(), args)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].0, "ft_transfer");
+        let args: serde_json::Value = serde_json::from_slice(&transfers[0].1).unwrap();
+        assert_eq!(args["receiver_id"], "alice");
+        assert_eq!(args["amount"], "40");
+        assert_eq!(contract.staking().staked, U128(60));
+    }
+
+    #[test]
+    #[should_panic(expected = "stake is locked for an epoch after it was last added to")]
+    fn stakes_are_locked_for_an_epoch() {
+        let mut contract = contract_sharing_fees_with_stakers();
+        testing_env!(context(&accounts(0))
+            .block_timestamp(EPOCH - 1)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.unstake(U128(40));
+    }
+
+    #[test]
+    #[should_panic(expected = "admins are set, propose this as an action instead")]
+    fn staking_is_set_through_actions_once_there_are_admins() {
+        let mut contract = contract_with_admins();
+        testing_env!(context(&contract_id()).build());
+        contract.set_staking(Some(stake_token()), 5_000);
+    }
+
+    #[test]
+    fn admins_set_staking_through_actions() {
+        let mut contract = contract_with_admins();
+        let action = AdminAction::SetStaking {
+            stake_token: Some(stake_token()),
+            share_bps: 5_000,
+        };
+        propose_action(&mut contract, &accounts(2), action);
+        confirm_action(&mut contract, &accounts(3), 0);
+        assert_eq!(contract.staking().stake_token, Some(stake_token()));
+        assert_eq!(contract.staking().share_bps, 5_000);
+    }
+
+    fn moderator() -> AccountId {
+        "moderator.near".parse().unwrap()
+    }
+
+    /// Has a moderator take down the auction of `token_id` in `contract`
+    fn take_down(contract: &mut Contract, token_id: TokenId, blacklist_seller: bool) {
+        testing_env!(context(&contract_id()).build());
+        contract.set_moderators(vec![moderator()]);
+        testing_env!(context(&moderator()).build());
+        contract.take_down(nft(), token_id, "infringing".into(), blacklist_seller);
+    }
+
+    #[test]
+    fn takedowns_refund_the_bids_and_their_fees() {
+        let mut contract = contract_with_penny_auction();
+        penny_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        penny_bid(&mut contract, &accounts(2), near(4), 2 * MINUTE);
+        take_down(&mut contract, token_id(), false);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(3)]);
+        assert_eq!(transfers_to(&accounts(2)), vec![near(4)]);
+        assert!(get_created_receipts()
+            .iter()
+            .any(|receipt| receipt.receiver_id == nft()));
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert_eq!(contract.reconcile().bid_fees_escrowed, near(0));
+        let takedown = contract.takedown(nft(), token_id()).unwrap();
+        assert_eq!(takedown.moderator, moderator());
+        assert_eq!(takedown.seller, accounts(0));
+        assert!(!contract.is_blacklisted(accounts(0)));
+    }
+
+    #[test]
+    fn admin_cancellations_refund_the_bids_and_their_fees() {
+        let mut contract = contract_with_penny_auction();
+        penny_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        testing_env!(context(&contract_id()).build());
+        contract.set_admins(vec![accounts(2), accounts(3), accounts(4)], 2);
+        let action = AdminAction::CancelAuction {
+            nft: nft(),
+            token_id: token_id(),
+        };
+        propose_action(&mut contract, &accounts(2), action);
+        confirm_action(&mut contract, &accounts(3), 0);
+
+        assert_eq!(transfers_to(&accounts(1)), vec![near(3)]);
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "this nft was taken down by a moderator")]
+    fn taken_down_nfts_cannot_be_listed_again() {
+        let mut contract = contract_with_penny_auction();
+        take_down(&mut contract, token_id(), false);
+
+        testing_env!(context(&nft()).build());
+        let params = AuctionParams::builder(HOUR_SECS, near(1)).build();
+        contract.nft_on_approve(
+            token_id().into(),
+            accounts(0),
+            0,
+            serde_json::to_string(&params).unwrap(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "this nft was taken down by a moderator")]
+    fn taken_down_nfts_cannot_be_added_to_lots() {
+        let mut contract = contract_with_uniform_auction();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            "9".parse().unwrap(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        take_down(&mut contract, "9".parse().unwrap(), false);
+
+        testing_env!(context(&nft()).build());
+        contract.nft_on_approve(
+            "9".into(),
+            accounts(0),
+            0,
+            serde_json::json!({ "lot": token_id() }).to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "seller is blacklisted")]
+    fn blacklisted_sellers_cannot_add_to_lots() {
+        let mut contract = contract_with_uniform_auction();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            "9".parse().unwrap(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        take_down(&mut contract, "9".parse().unwrap(), true);
+
+        testing_env!(context(&nft()).build());
+        contract.nft_on_approve(
+            "10".into(),
+            accounts(0),
+            0,
+            serde_json::json!({ "lot": token_id() }).to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "only moderators can do this")]
+    fn only_moderators_take_down_auctions() {
+        let mut contract = contract_with_penny_auction();
+        testing_env!(context(&accounts(1)).build());
+        contract.take_down(nft(), token_id(), "infringing".into(), false);
+    }
+
+    #[test]
+    #[should_panic(expected = "admins are set, propose this as an action instead")]
+    fn moderators_are_set_through_actions_once_there_are_admins() {
+        let mut contract = contract_with_admins();
+        testing_env!(context(&contract_id()).build());
+        contract.set_moderators(vec![moderator()]);
+    }
+
+    #[test]
+    fn admins_set_moderators_through_actions() {
+        let mut contract = contract_with_admins();
+        let action = AdminAction::SetModerators {
+            moderators: vec![moderator()],
+        };
+        propose_action(&mut contract, &accounts(2), action);
+        confirm_action(&mut contract, &accounts(3), 0);
+        assert_eq!(contract.moderators(), vec![moderator()]);
+    }
+
+    #[test]
+    fn only_unregistered_payees_are_registered_with_fungible_tokens() {
+        use near_contract_standards::storage_management::StorageBalance;
+
+        let mut contract = Contract {
+            protocol_fees: near(1),
+            ..Contract::default()
+        };
+        testing_env!(context(&contract_id()).build());
+        let storage_deposits = || {
+            get_created_receipts()
+                .into_iter()
+                .filter(|receipt| receipt.receiver_id == accounts(4))
+                .flat_map(|receipt| receipt.actions)
+                .filter(|action| {
+                    matches!(action, MockAction::FunctionCallWeight { method_name, .. }
+                        if method_name == b"storage_deposit")
+                })
+                .count()
+        };
+
+        contract.internal_ft_transfer(accounts(4), accounts(1), U128(5), "Refund");
+        assert_eq!(storage_deposits(), 0);
+
+        testing_env!(context(&contract_id()).build());
+        let registered = || StorageBalance {
+            total: NearToken::from_millinear(2),
+            available: NearToken::from_yoctonear(0),
+        };
+        contract.on_ft_storage_balance(
+            accounts(4),
+            accounts(1),
+            U128(5),
+            "Auction proceeds".into(),
+            Ok(Some(registered())),
+        );
+        assert_eq!(storage_deposits(), 0);
+        assert_eq!(contract.protocol_fees(), near(1));
+
+        testing_env!(context(&contract_id()).build());
+        contract.on_ft_storage_balance(
+            accounts(4),
+            accounts(1),
+            U128(5),
+            "Auction proceeds".into(),
+            Ok(None),
+        );
+        assert_eq!(storage_deposits(), 1);
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(987));
+
+        // The token keeps what registering took and refunds the rest
+        contract.on_ft_storage_deposit(
+            accounts(4),
+            accounts(1),
+            U128(5),
+            "Auction proceeds".into(),
+            Ok(registered()),
+        );
+        let totals = contract.accounting_totals();
+        assert_eq!(totals.ft_storage_paid, NearToken::from_millinear(2));
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(998));
+    }
+
+    fn solver() -> AccountId {
+        "solver.near".parse().unwrap()
+    }
+
+    fn intent_framework() -> AccountId {
+        "intents.near".parse().unwrap()
+    }
+
+    fn intent_key() -> ed25519_dalek::SigningKey {
+        ed25519_dalek::SigningKey::from_bytes(&[7; 32])
+    }
+
+    /// A contract with an auction of `token_id` by alice, bob having deposited 5 NEAR and set
+    /// the intent key
+    fn contract_with_intent_key() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        testing_env!(context(&accounts(1)).attached_deposit(near(5)).build());
+        contract.deposit();
+        let public_key = intent_key().verifying_key().to_bytes().to_vec();
+        contract.set_intent_key(Some(
+            PublicKey::from_parts(near_sdk::CurveType::ED25519, public_key).unwrap(),
+        ));
+        contract
+    }
+
+    /// Bob's intent to bid up to 3 NEAR on `token_id`, paying the solver 1 NEAR
+    fn bid_intent() -> BidIntent {
+        BidIntent {
+            signer_id: accounts(1),
+            nft: nft(),
+            token_id: token_id(),
+            max_amount: near(3),
+            solver_fee: near(1),
+            nonce: U64(1),
+            deadline: U64(HOUR),
+            settle_to: Some(intent_framework()),
+        }
+    }
+
+    /// `intent` signed by `key` as a NEP-413 message to `recipient`
+    fn signed_intent(
+        intent: &BidIntent,
+        key: &ed25519_dalek::SigningKey,
+        recipient: &AccountId,
+    ) -> SignedBidIntent {
+        use ed25519_dalek::Signer;
+
+        let message = serde_json::to_string(intent).unwrap();
+        let nonce = [1; 32];
+        let mut payload = near_sdk::borsh::to_vec(&(2_u32.pow(31) + 413)).unwrap();
+        payload.extend(
+            near_sdk::borsh::to_vec(&(
+                message.clone(),
+                nonce,
+                recipient.to_string(),
+                None::<String>,
+            ))
+            .unwrap(),
+        );
+        let signature = key.sign(&env::sha256(&payload));
+        SignedBidIntent {
+            message,
+            nonce: nonce.to_vec().into(),
+            recipient: recipient.to_string(),
+            callback_url: None,
+            signature: signature.to_bytes().to_vec().into(),
+        }
+    }
+
+    fn fulfill_bid_intent(contract: &mut Contract, signed: SignedBidIntent, now: u64) {
+        testing_env!(context(&solver()).block_timestamp(now).build());
+        contract.fulfill_bid_intent(signed, near(2));
+    }
+
+    #[test]
+    fn solvers_bid_for_the_signers_of_intents() {
+        let mut contract = contract_with_intent_key();
+        let signed = signed_intent(&bid_intent(), &intent_key(), &contract_id());
+        fulfill_bid_intent(&mut contract, signed, MINUTE);
+
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(2));
+        assert_eq!(contract.balance_of(accounts(1)), near(2));
+        assert_eq!(contract.balance_of(solver()), near(1));
+        assert_eq!(contract.intent_nonce(accounts(1)), Some(U64(1)));
+        let notified: Vec<_> = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == intent_framework())
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, .. } => {
+                    Some(String::from_utf8(method_name).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(notified, vec!["on_bid_intent_settled"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid intent signature")]
+    fn intents_are_only_fulfilled_if_signed_with_the_intent_key() {
+        let mut contract = contract_with_intent_key();
+        let other_key = ed25519_dalek::SigningKey::from_bytes(&[8; 32]);
+        let signed = signed_intent(&bid_intent(), &other_key, &contract_id());
+        fulfill_bid_intent(&mut contract, signed, MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid intent signature")]
+    fn intents_are_only_fulfilled_as_signed() {
+        let mut contract = contract_with_intent_key();
+        let mut signed = signed_intent(&bid_intent(), &intent_key(), &contract_id());
+        let intent = BidIntent {
+            max_amount: near(5),
+            ..bid_intent()
+        };
+        signed.message = serde_json::to_string(&intent).unwrap();
+        fulfill_bid_intent(&mut contract, signed, MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "intent nonce must exceed the last one used by the signer")]
+    fn intents_are_only_fulfilled_once() {
+        let mut contract = contract_with_intent_key();
+        let signed = || signed_intent(&bid_intent(), &intent_key(), &contract_id());
+        fulfill_bid_intent(&mut contract, signed(), MINUTE);
+        fulfill_bid_intent(&mut contract, signed(), 2 * MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "intent has expired")]
+    fn intents_are_only_fulfilled_until_their_deadline() {
+        let mut contract = contract_with_intent_key();
+        let intent = BidIntent {
+            deadline: U64(MINUTE),
+            ..bid_intent()
+        };
+        let signed = signed_intent(&intent, &intent_key(), &contract_id());
+        fulfill_bid_intent(&mut contract, signed, MINUTE);
+    }
+
+    #[test]
+    #[should_panic(expected = "intent is for another contract")]
+    fn intents_are_only_fulfilled_by_the_contract_they_were_signed_for() {
+        let mut contract = contract_with_intent_key();
+        let other = "other.near".parse().unwrap();
+        let signed = signed_intent(&bid_intent(), &intent_key(), &other);
+        fulfill_bid_intent(&mut contract, signed, MINUTE);
+    }
+
+    /// A contract with an auction of `token_id` by alice, bob having deposited 5 NEAR
+    fn contract_with_funded_bidder() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        testing_env!(context(&accounts(1)).attached_deposit(near(5)).build());
+        contract.deposit();
+        contract
+    }
+
+    #[test]
+    fn bids_are_funded_from_the_balance_with_nothing_attached() {
+        let mut contract = contract_with_funded_bidder();
+        testing_env!(context(&accounts(1)).block_timestamp(MINUTE).build());
+        contract.make_bid(nft(), token_id(), near(2), None, Some(true), None);
+
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(2));
+        assert_eq!(contract.balance_of(accounts(1)), near(3));
+        assert!(transfers_to(&accounts(1)).is_empty());
+
+        testing_env!(context(&accounts(1)).block_timestamp(2 * MINUTE).build());
+        contract.increase_bid(nft(), token_id(), Some(near(1)), Some(true));
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(3));
+        assert_eq!(contract.balance_of(accounts(1)), near(2));
+        assert!(transfers_to(&accounts(1)).is_empty());
+    }
+
+    #[test]
+    fn what_balance_funded_bids_leave_goes_back_to_the_balance() {
+        let mut contract = contract_with_funded_bidder();
+        testing_env!(context(&accounts(1))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(1))
+            .build());
+        let request = |token_id: &str| BidRequest {
+            nft: nft(),
+            token_id: token_id.parse().unwrap(),
+            amount: near(2),
+            expires_at: None,
+        };
+        let results = contract.make_bids(vec![request("1"), request("2")], Some(true));
+
+        assert!(results[0].error.is_none());
+        assert_eq!(
+            results[1].error.as_deref(),
+            Some("this nft is not in auction")
+        );
+        assert_eq!(contract.balance_of(accounts(1)), near(4));
+        assert!(transfers_to(&accounts(1)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "provided deposit does not cover bid amount")]
+    fn contracts_cannot_bid_with_the_balance_of_the_account_calling_them() {
+        let mut contract = contract_with_funded_bidder();
+        // Bob signed a call to another contract, which calls on to bid with his balance
+        testing_env!(context(&accounts(1))
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(MINUTE)
+            .build());
+        contract.make_bid(nft(), token_id(), near(2), None, Some(true), None);
+    }
+
+    #[test]
+    fn bids_are_placed_and_refunded_for_the_calling_account() {
+        let mut contract = contract_with_funded_bidder();
+        testing_env!(context(&accounts(1))
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(MINUTE)
+            .attached_deposit(near(3))
+            .build());
+        contract.make_bid(nft(), token_id(), near(2), None, None, None);
+
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+        assert!(contract.get_bid(accounts(3), nft(), token_id()).is_some());
+        assert_eq!(transfers_to(&accounts(3)), vec![near(1)]);
+        assert!(transfers_to(&accounts(1)).is_empty());
+        assert_eq!(contract.balance_of(accounts(1)), near(5));
+    }
+
+    #[test]
+    fn rejected_balance_funded_bids_go_back_to_the_balance() {
+        let mut contract = checking_compliance(contract_with_funded_bidder());
+        testing_env!(context(&accounts(1)).block_timestamp(MINUTE).build());
+        let checked = contract.make_bid(nft(), token_id(), near(3), None, Some(true), None);
+        assert!(matches!(checked, PromiseOrValue::Promise(_)));
+        assert_eq!(contract.balance_of(accounts(1)), near(0));
+
+        testing_env!(context(&contract_id()).block_timestamp(MINUTE).build());
+        let bid = CheckedBid::Make {
+            amount: near(3),
+            expires_at: None,
+            memo: None,
+        };
+        contract.on_compliance_checked(
+            accounts(1),
+            nft(),
+            token_id(),
+            bid,
+            near(5),
+            true,
+            Ok(false),
+        );
+        assert_eq!(contract.balance_of(accounts(1)), near(5));
+        assert!(transfers_to(&accounts(1)).is_empty());
+    }
+
+    fn minter() -> AccountId {
+        "minter.near".parse().unwrap()
+    }
+
+    /// Sets `context` as that of a callback receiving `result`
+    fn callback_env(context: VMContextBuilder, result: PromiseResult) {
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    /// A contract with a listing stake of 1 NEAR where bob deposited the stake and the minter
+    /// authorized him
+    fn contract_with_authorized_seller() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_listing_stake(near(1));
+        testing_env!(context(&accounts(1)).attached_deposit(near(1)).build());
+        contract.deposit();
+        testing_env!(context(&minter()).build());
+        contract.authorize_seller(accounts(1), true);
+        contract
+    }
+
+    fn mint_and_list(contract: &mut Contract, seller: &AccountId) -> Promise {
+        testing_env!(context(seller).attached_deposit(near(1)).build());
+        contract.mint_and_list(
+            minter(),
+            token_id(),
+            TokenMetadata::default(),
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+        )
+    }
+
+    #[test]
+    fn authorized_sellers_mint_and_list() {
+        let mut contract = contract_with_authorized_seller();
+        assert!(contract.is_authorized_seller(minter(), accounts(1)));
+        mint_and_list(&mut contract, &accounts(1));
+
+        assert_eq!(contract.balance_of(accounts(1)), near(0));
+        assert_eq!(contract.reconcile().stakes_escrowed, near(1));
+        assert!(get_created_receipts().iter().any(|receipt| {
+            receipt.receiver_id == minter()
+                && receipt.actions.iter().any(|action| {
+                    matches!(
+                        action,
+                        MockAction::FunctionCallWeight { method_name, .. }
+                            if method_name == b"nft_mint"
+                    )
+                })
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "the minter did not authorize this seller")]
+    fn sellers_cannot_mint_and_list_without_the_minters_authorization() {
+        let mut contract = contract_with_authorized_seller();
+        testing_env!(context(&accounts(2)).attached_deposit(near(1)).build());
+        contract.deposit();
+        mint_and_list(&mut contract, &accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "the minter did not authorize this seller")]
+    fn minters_revoke_their_authorization() {
+        let mut contract = contract_with_authorized_seller();
+        testing_env!(context(&minter()).build());
+        contract.authorize_seller(accounts(1), false);
+        mint_and_list(&mut contract, &accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "seller is blacklisted")]
+    fn blacklisted_sellers_cannot_mint_and_list() {
+        let mut contract = contract_with_authorized_seller();
+        testing_env!(context(&contract_id()).build());
+        contract.set_moderators(vec![moderator()]);
+        testing_env!(context(&moderator()).build());
+        contract.set_blacklisted(accounts(1), true);
+        mint_and_list(&mut contract, &accounts(1));
+    }
+
+    #[test]
+    fn failed_mints_return_the_deposit_and_the_stake() {
+        let mut contract = contract_with_authorized_seller();
+        mint_and_list(&mut contract, &accounts(1));
+
+        callback_env(context(&contract_id()), PromiseResult::Failed);
+        let pending = PendingMint {
+            owner_id: accounts(1),
+            expiry: HOUR.into(),
+            params: AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            stake: near(1),
+            deposit: near(1),
+        };
+        let listed = contract.on_nft_minted(minter(), token_id(), pending);
+
+        assert!(matches!(listed, PromiseOrValue::Value(false)));
+        assert_eq!(contract.balance_of(accounts(1)), near(2));
+        assert_eq!(contract.reconcile().stakes_escrowed, near(0));
+    }
+
+    /// Has bob list `token_id` of the minter before it is minted, with a deposit of 1 NEAR
+    fn list_lazy_mint(contract: &mut Contract) {
+        testing_env!(context(&accounts(1)).attached_deposit(near(1)).build());
+        contract.list_lazy_mint(
+            minter(),
+            token_id(),
+            TokenMetadata::default(),
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+        );
+        testing_env!(context(&contract_id()).build());
+        let pending = PendingMint {
+            owner_id: accounts(1),
+            expiry: HOUR.into(),
+            params: AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            stake: near(1),
+            deposit: near(1),
+        };
+        let metadata = TokenMetadata::default();
+        assert!(contract.on_lazy_mint_checked(minter(), token_id(), metadata, pending, Ok(None)));
+    }
+
+    #[test]
+    #[should_panic(expected = "the minter did not authorize this seller")]
+    fn sellers_cannot_list_lazy_mints_without_the_minters_authorization() {
+        let mut contract = contract_with_authorized_seller();
+        testing_env!(context(&minter()).build());
+        contract.authorize_seller(accounts(1), false);
+        list_lazy_mint(&mut contract);
+    }
+
+    #[test]
+    fn lazy_mint_deposits_are_held_until_the_auction_ends() {
+        let mut contract = contract_with_authorized_seller();
+        list_lazy_mint(&mut contract);
+        assert_eq!(contract.reconcile().mint_deposits_held, near(1));
+        assert_eq!(contract.reconcile().stakes_escrowed, near(1));
+
+        testing_env!(context(&contract_id()).build());
+        contract.set_moderators(vec![moderator()]);
+        testing_env!(context(&moderator()).build());
+        contract.take_down(minter(), token_id(), "infringing".into(), false);
+        assert_eq!(contract.reconcile().mint_deposits_held, near(0));
+        assert_eq!(contract.reconcile().stakes_escrowed, near(0));
+        assert_eq!(contract.balance_of(accounts(1)), near(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "this nft was taken down by a moderator")]
+    fn taken_down_lazy_mints_cannot_be_listed_again() {
+        let mut contract = contract_with_authorized_seller();
+        list_lazy_mint(&mut contract);
+        testing_env!(context(&contract_id()).build());
+        contract.set_moderators(vec![moderator()]);
+        testing_env!(context(&moderator()).build());
+        contract.take_down(minter(), token_id(), "infringing".into(), false);
+        list_lazy_mint(&mut contract);
+    }
+
+    #[test]
+    fn creators_listing_first_keep_their_royalty() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_royalties(true, U64(HOUR_SECS));
+        // The creator lists their own NFT, which the contract then owns
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        let queried = get_created_receipts().into_iter().any(|receipt| {
+            receipt.receiver_id == nft()
+                && receipt.actions.iter().any(|action| {
+                    matches!(action, MockAction::FunctionCallWeight { method_name, .. }
+                        if method_name == b"nft_payout")
+                })
+        });
+        assert!(queried);
+
+        let payout = [(contract_id(), 9_000), (accounts(0), 1_000)]
+            .into_iter()
+            .map(|(account_id, share)| (account_id, U128(share)))
+            .collect();
+        assert!(contract.on_nft_payout(nft(), Ok(royalties::NftPayout { payout })));
+        assert_eq!(
+            contract.royalty_split(nft()).unwrap().shares,
+            vec![(accounts(0), 1_000)]
+        );
+    }
+
+    /// A contract charging a 10% quick-flip fee whose collection pays carol a 10% royalty
+    fn contract_paying_royalties() -> Contract {
+        let mut contract = contract_charging_quick_flips();
+        contract.set_royalties(true, U64(HOUR_SECS));
+        let payout = [(contract_id(), 9_000), (accounts(2), 1_000)]
+            .into_iter()
+            .map(|(account_id, share)| (account_id, U128(share)))
+            .collect();
+        assert!(contract.on_nft_payout(nft(), Ok(royalties::NftPayout { payout })));
+        contract
+    }
+
+    #[test]
+    fn royalties_come_out_of_the_proceeds_after_fees() {
+        let mut contract = contract_paying_royalties();
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1)).build(),
+            None,
+        );
+        make_bid(&mut contract, &accounts(1), near(3), MINUTE);
+        end_auction(&mut contract, HOUR);
+
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(300));
+        assert_eq!(
+            contract.balance_of(accounts(2)),
+            NearToken::from_millinear(270)
+        );
+        assert_eq!(
+            contract.balance_of(accounts(0)),
+            NearToken::from_millinear(2_430)
+        );
+    }
+
+    #[test]
+    fn royalties_on_held_proceeds_are_paid_once_released() {
+        let mut contract = disputable_sale(contract_paying_royalties());
+        assert_eq!(contract.balance_of(accounts(2)), near(0));
+
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        contract.release_proceeds(U64(0));
+        assert_eq!(contract.protocol_fees(), NearToken::from_millinear(300));
+        assert_eq!(
+            contract.balance_of(accounts(2)),
+            NearToken::from_millinear(270)
+        );
+        assert_eq!(
+            contract.balance_of(accounts(0)),
+            NearToken::from_millinear(2_430)
+        );
+    }
+
+    fn mt() -> AccountId {
+        "mt.near".parse().unwrap()
+    }
+
+    fn gold() -> PaymentToken {
+        PaymentToken::Mt {
+            contract: mt(),
+            token_id: "gold".parse().unwrap(),
+        }
+    }
+
+    /// A contract with an english auction of `token_id` by alice taking bids in gold, with a
+    /// minimum bid of 1 gold
+    fn contract_with_mt_auction() -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .payment_token(gold())
+                .build(),
+            None,
+        );
+        contract
+    }
+
+    /// Has `bidder` bid `amount` of gold at `now` with `mt_transfer_call`
+    fn mt_bid(
+        contract: &mut Contract,
+        bidder: &AccountId,
+        amount: NearToken,
+        now: u64,
+    ) -> PromiseOrValue<Vec<U128>> {
+        testing_env!(context(&mt()).block_timestamp(now).build());
+        let msg = serde_json::json!({ "nft": nft(), "token_id": token_id() }).to_string();
+        contract.mt_on_transfer(
+            bidder.clone(),
+            vec![bidder.clone()],
+            vec!["gold".parse().unwrap()],
+            vec![U128(amount.as_yoctonear())],
+            msg,
+        )
+    }
+
+    /// Gold the calls made since the context was last set transfer, by receiver
+    fn gold_transfers() -> Vec<(AccountId, U128)> {
+        get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == mt())
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } if method_name == b"mt_transfer" => {
+                    let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                    Some((
+                        args["receiver_id"].as_str().unwrap().parse().unwrap(),
+                        serde_json::from_value(args["amount"].clone()).unwrap(),
+                    ))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bids_paid_in_a_multi_token_are_refunded_in_it() {
+        let mut contract = contract_with_mt_auction();
+        let placed = mt_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        assert!(matches!(placed, PromiseOrValue::Value(unused) if unused == vec![U128(0)]));
+        mt_bid(&mut contract, &accounts(2), near(3), 2 * MINUTE);
+
+        let auction = contract.get_auction(nft(), token_id());
+        assert_eq!(auction.h_bid, near(3));
+        assert!(auction.payment == Some(gold()));
+        assert_eq!(contract.reconcile().bids_escrowed, near(0));
+
+        end_auction(&mut contract, HOUR);
+        assert_eq!(
+            gold_transfers(),
+            vec![
+                (accounts(0), U128(near(3).as_yoctonear())),
+                (accounts(1), U128(near(2).as_yoctonear())),
+            ]
+        );
+        assert_eq!(contract.balance_of(accounts(0)), near(0));
+        assert_eq!(contract.protocol_fees(), near(0));
+    }
+
+    #[test]
+    fn multi_token_transfers_top_up_the_bid() {
+        let mut contract = contract_with_mt_auction();
+        mt_bid(&mut contract, &accounts(1), near(2), MINUTE);
+        mt_bid(&mut contract, &accounts(1), near(1), 2 * MINUTE);
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(3));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "this auction takes bids in a multi-token, bid with `mt_transfer_call`"
+    )]
+    fn auctions_taking_a_multi_token_reject_near_bids() {
+        let mut contract = contract_with_mt_auction();
+        make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "this auction takes bids in a multi-token, bid with `mt_transfer_call`"
+    )]
+    fn auctions_taking_a_multi_token_reject_other_tokens() {
+        let mut contract = contract_with_mt_auction();
+        testing_env!(context(&"other-mt.near".parse().unwrap())
+            .block_timestamp(MINUTE)
+            .build());
+        let msg = serde_json::json!({ "nft": nft(), "token_id": token_id() }).to_string();
+        contract.mt_on_transfer(
+            accounts(1),
+            vec![accounts(1)],
+            vec!["gold".parse().unwrap()],
+            vec![U128(near(2).as_yoctonear())],
+            msg,
+        );
+    }
+
+    #[test]
+    fn multi_token_bids_wait_for_the_compliance_registry() {
+        let mut contract = checking_compliance(contract_with_mt_auction());
+        // However small, token amounts can't be compared to the threshold in NEAR
+        let checked = mt_bid(
+            &mut contract,
+            &accounts(1),
+            NearToken::from_yoctonear(1),
+            MINUTE,
+        );
+        assert!(matches!(checked, PromiseOrValue::Promise(_)));
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+
+        testing_env!(context(&contract_id()).block_timestamp(MINUTE).build());
+        let msg = || TokenBidMsg {
+            nft: nft(),
+            token_id: token_id(),
+            expires_at: None,
+        };
+        let amount = U128(near(2).as_yoctonear());
+        let unused =
+            contract.on_mt_bid_compliance_checked(accounts(1), gold(), amount, msg(), Ok(false));
+        assert_eq!(unused, vec![amount]);
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+
+        let unused =
+            contract.on_mt_bid_compliance_checked(accounts(1), gold(), amount, msg(), Ok(true));
+        assert_eq!(unused, vec![U128(0)]);
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(2));
+    }
+
+    fn weth() -> AccountId {
+        "weth.near".parse().unwrap()
+    }
+
+    /// `milli` thousandths of a wETH, which has 18 decimals
+    fn weth_units(milli: u128) -> NearToken {
+        NearToken::from_yoctonear(milli * 1_000_000_000_000_000)
+    }
+
+    /// wETH taking bids in thousandths, its sales paying `fee`
+    fn weth_config(fee: TokenFee) -> PaymentTokenConfig {
+        PaymentTokenConfig {
+            symbol: "WETH".into(),
+            decimals: 18,
+            min_bid_unit: U128(weth_units(1).as_yoctonear()),
+            fee,
+        }
+    }
+
+    /// A contract allowing wETH with `fee` and an english auction of `token_id` by alice taking
+    /// bids in it, with a minimum bid of 1 wETH
+    fn contract_with_weth_auction(fee: TokenFee) -> Contract {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.internal_collect_protocol_fee(near(1));
+        contract.set_payment_token(
+            PaymentToken::Ft { contract: weth() },
+            Some(weth_config(fee)),
+        );
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            token_id(),
+            HOUR,
+            AuctionParams::builder(HOUR_SECS, weth_units(1_000))
+                .payment_token(PaymentToken::Ft { contract: weth() })
+                .build(),
+            None,
+        );
+        contract
+    }
+
+    /// Has `bidder` bid `amount` of wETH at `now` with `ft_transfer_call`
+    fn ft_bid(
+        contract: &mut Contract,
+        bidder: &AccountId,
+        amount: NearToken,
+        now: u64,
+    ) -> PromiseOrValue<U128> {
+        testing_env!(context(&weth()).block_timestamp(now).build());
+        let msg = serde_json::json!({ "nft": nft(), "token_id": token_id() }).to_string();
+        contract.ft_on_transfer(bidder.clone(), U128(amount.as_yoctonear()), msg)
+    }
+
+    #[test]
+    fn allowing_a_fungible_token_pays_its_storage_out_of_the_protocol_fees() {
+        let mut contract = contract_with_weth_auction(TokenFee::Waived);
+        assert_eq!(
+            contract.protocol_fees(),
+            near(1).saturating_sub(FT_STORAGE_DEPOSIT)
+        );
+
+        // The contract was registered already and got the whole deposit back
+        testing_env!(context(&contract_id()).build());
+        contract.on_payment_token_registered(Err(PromiseError::Failed));
+        assert_eq!(contract.protocol_fees(), near(1));
+        assert_eq!(contract.accounting_totals().ft_storage_paid, near(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "the protocol fees don't cover the storage deposit")]
+    fn fungible_tokens_cannot_be_allowed_without_the_fees_for_storage() {
+        let mut contract = Contract::default();
+        testing_env!(context(&contract_id()).build());
+        contract.set_payment_token(
+            PaymentToken::Ft { contract: weth() },
+            Some(weth_config(TokenFee::Waived)),
+        );
+    }
+
+    #[test]
+    fn bids_paid_in_a_payment_token_show_in_whole_tokens() {
+        let mut contract = contract_with_weth_auction(TokenFee::Waived);
+        let placed = ft_bid(&mut contract, &accounts(1), weth_units(1_500), MINUTE);
+        assert!(matches!(placed, PromiseOrValue::Value(U128(0))));
+
+        let display = contract.get_auction(nft(), token_id()).display.unwrap();
+        assert_eq!(display.symbol, "WETH");
+        assert_eq!(display.h_bid, "1.5");
+        assert_eq!(display.minimum_bid, "1");
+        assert_eq!(contract.reconcile().bids_escrowed, near(0));
+    }
+
+    #[test]
+    fn sales_in_a_payment_token_pay_its_fee_in_it() {
+        let mut contract = contract_with_weth_auction(TokenFee::Bps { bps: 100 });
+        ft_bid(&mut contract, &accounts(1), weth_units(2_000), MINUTE);
+        end_auction(&mut contract, HOUR);
+
+        // Payees are paid once it is known whether they are registered with wETH
+        let payouts: Vec<(AccountId, U128)> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } if method_name == b"on_ft_storage_balance" => {
+                    let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                    Some((
+                        args["receiver_id"].as_str().unwrap().parse().unwrap(),
+                        serde_json::from_value(args["amount"].clone()).unwrap(),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            payouts,
+            vec![
+                (accounts(0), U128(weth_units(1_980).as_yoctonear())),
+                (contract.owner(), U128(weth_units(20).as_yoctonear())),
+            ]
+        );
+        assert_eq!(
+            contract.protocol_fees(),
+            near(1).saturating_sub(FT_STORAGE_DEPOSIT)
+        );
+    }
+
+    #[test]
+    fn fungible_token_bids_wait_for_the_compliance_registry() {
+        let mut contract = checking_compliance(contract_with_weth_auction(TokenFee::Waived));
+        let checked = ft_bid(
+            &mut contract,
+            &accounts(1),
+            NearToken::from_yoctonear(1),
+            MINUTE,
+        );
+        assert!(matches!(checked, PromiseOrValue::Promise(_)));
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+
+        testing_env!(context(&contract_id()).block_timestamp(MINUTE).build());
+        let msg = || TokenBidMsg {
+            nft: nft(),
+            token_id: token_id(),
+            expires_at: None,
+        };
+        let payment = || PaymentToken::Ft { contract: weth() };
+        let amount = U128(near(2).as_yoctonear());
+        let unused =
+            contract.on_ft_bid_compliance_checked(accounts(1), payment(), amount, msg(), Ok(false));
+        assert_eq!(unused, amount);
+        assert!(contract.get_bid(accounts(1), nft(), token_id()).is_none());
+
+        let unused =
+            contract.on_ft_bid_compliance_checked(accounts(1), payment(), amount, msg(), Ok(true));
+        assert_eq!(unused, U128(0));
+        assert_eq!(contract.get_auction(nft(), token_id()).h_bid, near(2));
+    }
+
+    #[test]
+    fn reconcile_sums_everything_the_contract_owes() {
+        // Bob's 3 NEAR for alice's NFT are held
+        let mut contract = contract_with_disputable_sale();
+        testing_env!(context(&contract_id()).build());
+        contract.internal_collect_protocol_fee(near(1));
+        contract.on_payout(
+            accounts(4),
+            OwedPayout::Near { amount: near(2) },
+            Err(PromiseError::Failed),
+        );
+        contract.set_listing_stake(near(1));
+
+        // Alice stakes 1 of the 3 NEAR she deposited on a penny auction, which carol bids on
+        testing_env!(context(&accounts(0)).attached_deposit(near(3)).build());
+        contract.deposit();
+        let stake = contract.internal_take_stake(&accounts(0));
+        testing_env!(context(&contract_id()).build());
+        contract.start_auction(
+            accounts(0),
+            nft(),
+            "2".parse().unwrap(),
+            2 * HOUR,
+            AuctionParams::builder(HOUR_SECS, near(1))
+                .kind(AuctionKind::Penny {
+                    bid_fee: near(1),
+                    increment: near(1),
+                    countdown: 10 * MINUTE_SECS,
+                })
+                .build(),
+            Some(stake),
+        );
+        testing_env!(context(&accounts(2)).attached_deposit(near(3)).build());
+        contract.penny_bid(nft(), "2".parse().unwrap(), None);
+
+        // Dan offers 2 NEAR for another NFT
+        testing_env!(context(&accounts(3)).attached_deposit(near(2)).build());
+        contract.make_offer(nft(), "3".parse().unwrap(), None);
+
+        let reconciliation = contract.reconcile();
+        assert_eq!(reconciliation.bids_escrowed, near(2));
+        assert_eq!(reconciliation.offers_escrowed, near(2));
+        assert_eq!(reconciliation.stakes_escrowed, near(1));
+        assert_eq!(reconciliation.bid_fees_escrowed, near(1));
+        assert_eq!(reconciliation.proceeds_held, near(3));
+        assert_eq!(reconciliation.balances, near(2));
+        assert_eq!(reconciliation.owed, near(2));
+        assert_eq!(reconciliation.protocol_fees, near(1));
+        assert_eq!(reconciliation.liabilities, near(14));
+
+        // Paying out moves NEAR between the liabilities or out of the contract
+        testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+        contract.release_proceeds(U64(0));
+        testing_env!(context(&accounts(0)).build());
+        contract.withdraw();
+        let reconciliation = contract.reconcile();
+        assert_eq!(reconciliation.proceeds_held, near(0));
+        assert_eq!(reconciliation.balances, near(0));
+        assert_eq!(reconciliation.liabilities, near(9));
+    }
+
+    #[test]
+    #[should_panic(expected = "token ids can't contain ' '")]
+    fn malformed_token_ids_are_not_listed() {
+        let mut contract = Contract::default();
+        testing_env!(context(&nft()).build());
+        let msg = AuctionParams::builder(HOUR_SECS, near(1)).build().to_msg();
+        contract.nft_on_approve("one two".into(), accounts(0), 1, msg);
+    }
+
+    #[test]
+    fn malformed_token_ids_are_rejected_in_arguments() {
+        let args = |token_id: &str| {
+            serde_json::from_value::<NftClaim>(serde_json::json!({
+                "nft": nft(),
+                "token_id": token_id,
+                "winner": accounts(1),
+                "escrow_id": null,
+            }))
+        };
+        assert_eq!(args("1").unwrap().token_id, token_id());
+        assert!(args("").is_err());
+        assert!(args(&"1".repeat(257)).is_err());
+    }
 }
@@ -0,0 +1,330 @@
+//! Minting an NFT straight into an auction, or only once it is won
+//!
+//! With `mint_and_list` the contract has the minter mint the NFT to itself and then lists it as
+//! if the seller had approved it. If minting fails, the seller gets back the deposit and their
+//! listing stake.
+//!
+//! With `list_lazy_mint` nothing is minted until the auction ends. The minter then mints the NFT
+//! straight to the winner, so it is never escrowed, and if minting fails the auction is cancelled
+//! and every bid refunded.
+//!
+//! Minters usually let only this contract call `nft_mint`, so either way the minter has to
+//! authorize every seller that lists through it with `authorize_seller`. Otherwise anyone could
+//! mint its NFTs.
+
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_sdk::{
+    env, ext_contract, json_types::U64, near, require, AccountId, NearToken, Promise, PromiseError,
+    PromiseResult,
+};
+
+use crate::*;
+
+/// The minting method of NFT contracts such as the NEP-171 example
+#[ext_contract(ext_nft_minter)]
+pub trait NftMinter {
+    fn nft_mint(
+        &mut self,
+        token_id: TokenId,
+        token_owner_id: AccountId,
+        token_metadata: TokenMetadata,
+    ) -> Token;
+}
+
+/// A listing waiting for its NFT to be minted
+#[near(serializers = [json])]
+pub struct PendingMint {
+    pub owner_id: AccountId,
+    pub expiry: U64,
+    pub params: AuctionParams,
+    pub stake: NearToken,
+    /// Attached to `nft_mint`
+    pub deposit: NearToken,
+}
+
+#[near]
+impl Contract {
+    /// Lets `seller` have this contract mint NFTs of the caller, a minter, and list them for
+    /// auction, or stops letting them
+    pub fn authorize_seller(&mut self, seller: AccountId, authorized: bool) {
+        let minter = env::predecessor_account_id();
+        if authorized {
+            self.seller_authorizations.insert((minter, seller));
+        } else {
+            self.seller_authorizations.remove(&(minter, seller));
+        }
+    }
+
+    pub fn is_authorized_seller(&self, minter: AccountId, seller: AccountId) -> bool {
+        self.seller_authorizations.contains(&(minter, seller))
+    }
+
+    /// Has `minter` mint `token_id` with `token_metadata` and lists it for auction with `params`,
+    /// the caller being the seller
+    ///
+    /// The attached deposit pays for minting. The minter refunds what it does not take to this
+    /// contract, so attach exactly what it charges.
+    #[payable]
+    pub fn mint_and_list(
+        &mut self,
+        minter: AccountId,
+        token_id: TokenId,
+        token_metadata: TokenMetadata,
+        params: AuctionParams,
+    ) -> Promise {
+        // Validations
+        let owner_id = env::predecessor_account_id();
+        self.internal_check_minting(&minter, &owner_id, &token_id);
+        let expiry = self
+            .internal_check_listing(&minter, &params)
+            .unwrap_or_else(|violations| env::panic_str(&violations.join(", ")));
+        require!(
+            !params.escrowless,
+            "minted nfts are always escrowed by the contract"
+        );
+        require!(
+            !params.kind.has_lot(),
+            "minted nfts are listed on their own"
+        );
+
+        // Operations
+        let stake = self.internal_take_stake(&owner_id);
+        let deposit = env::attached_deposit();
+        ext_nft_minter::ext(minter.clone())
+            .with_attached_deposit(deposit)
+            .nft_mint(token_id.clone(), env::current_account_id(), token_metadata)
+            .then(Self::ext(env::current_account_id()).on_nft_minted(
+                minter,
+                token_id,
+                PendingMint {
+                    owner_id,
+                    expiry: expiry.into(),
+                    params,
+                    stake,
+                    deposit,
+                },
+            ))
+    }
+
+    /// Checks that the minted NFT arrived and starts its auction, or returns the deposit and the
+    /// stake to the seller if minting failed
+    #[private]
+    pub fn on_nft_minted(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        pending: PendingMint,
+    ) -> PromiseOrValue<bool> {
+        let PendingMint {
+            owner_id,
+            expiry,
+            params,
+            stake,
+            deposit,
+        } = pending;
+        // Not deserialized, minters return the token or nothing at all
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            // The failed mint refunded the deposit to this contract
+            accounting::sub(&mut self.stakes_escrowed, stake);
+            self.internal_credit(owner_id, stake.saturating_add(deposit));
+            return PromiseOrValue::Value(false);
+        }
+        PromiseOrValue::Promise(
+            ext_nft_core::ext(nft.clone())
+                .nft_token(token_id.clone().into())
+                .then(Self::ext(env::current_account_id()).on_escrow_nft_token(
+                    nft,
+                    token_id,
+                    PendingListing {
+                        owner_id,
+                        expiry,
+                        params,
+                        stake,
+                        approval_id: None,
+                    },
+                )),
+        )
+    }
+
+    /// Lists `token_id` of `minter` for auction with `params` before it is minted, the caller
+    /// being the seller
+    ///
+    /// The attached deposit is held to pay for minting the NFT to the winner, and goes back to
+    /// the seller if there is none. The NFT must not exist yet.
+    #[payable]
+    pub fn list_lazy_mint(
+        &mut self,
+        minter: AccountId,
+        token_id: TokenId,
+        token_metadata: TokenMetadata,
+        params: AuctionParams,
+    ) -> Promise {
+        // Validations
+        let owner_id = env::predecessor_account_id();
+        self.internal_check_minting(&minter, &owner_id, &token_id);
+        let expiry = self
+            .internal_check_listing(&minter, &params)
+            .unwrap_or_else(|violations| env::panic_str(&violations.join(", ")));
+        require!(
+            !params.escrowless,
+            "lazily minted nfts are never escrowed, there is nothing to approve"
+        );
+        require!(
+            !params.kind.has_lot() && params.kind.curve().is_none(),
+            "lazily minted nfts are auctioned on their own"
+        );
+        require!(
+            matches!(params.on_unsold, None | Some(UnsoldAction::ReturnToOwner)),
+            "lazily minted nfts are not minted if unsold"
+        );
+        require!(
+            !self.auctions.contains_key(&NFTId::new(&minter, &token_id)),
+            "this nft is already in auction"
+        );
+
+        // Operations
+        let stake = self.internal_take_stake(&owner_id);
+        ext_nft_core::ext(minter.clone())
+            .nft_token(token_id.clone().into())
+            .then(Self::ext(env::current_account_id()).on_lazy_mint_checked(
+                minter,
+                token_id,
+                token_metadata,
+                PendingMint {
+                    owner_id,
+                    expiry: expiry.into(),
+                    params,
+                    stake,
+                    deposit: env::attached_deposit(),
+                },
+            ))
+    }
+
+    /// Starts the auction of a lazily minted NFT if it does not exist yet, returns the deposit
+    /// and the stake to the seller otherwise
+    #[private]
+    pub fn on_lazy_mint_checked(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        token_metadata: TokenMetadata,
+        pending: PendingMint,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> bool {
+        let PendingMint {
+            owner_id,
+            expiry,
+            params,
+            stake,
+            deposit,
+        } = pending;
+        let nft_id = NFTId::new(&nft, &token_id);
+        if !matches!(token, Ok(None)) || self.auctions.contains_key(&nft_id) {
+            accounting::sub(&mut self.stakes_escrowed, stake);
+            self.internal_credit(owner_id, stake.saturating_add(deposit));
+            return false;
+        }
+        accounting::add(&mut self.mint_deposits_held, deposit);
+        let auction = self.internal_start_auction(
+            owner_id,
+            nft,
+            token_id,
+            expiry.into(),
+            params,
+            AuctionedAsset::LazyMint {
+                deposit,
+                minting: false,
+            },
+        );
+        auction.stake = stake;
+
+        // Written apart from the auction, flush to measure it
+        let initial_storage_usage = env::storage_usage();
+        auction.mint_metadata.set(Some(token_metadata));
+        auction.mint_metadata.flush();
+        let storage_usage = env::storage_usage() - initial_storage_usage;
+        auction.storage_usage += storage_usage;
+        self.storage_usage += storage_usage;
+        true
+    }
+
+    /// Settles the auction of a lazily minted NFT once it was minted to the winner, or cancels
+    /// it if minting failed
+    #[private]
+    pub fn on_lazy_minted(&mut self, nft: AccountId, token_id: TokenId) -> PromiseOrValue<()> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let promise = if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            self.internal_settle(&nft_id)
+        } else {
+            if let Some(AuctionedAsset::LazyMint {
+                deposit, minting, ..
+            }) = self
+                .auctions
+                .get_mut(&nft_id)
+                .map(|auction| &mut auction.asset)
+            {
+                *minting = false;
+                // The failed mint refunded the deposit to this contract
+                accounting::add(&mut self.mint_deposits_held, *deposit);
+            }
+            // The deposit goes back to the seller
+            self.internal_cancel(&nft_id, Vec::new(), "Minting failed", false)
+        };
+        match promise {
+            Some(promise) => PromiseOrValue::Promise(promise),
+            None => PromiseOrValue::Value(()),
+        }
+    }
+}
+
+impl Contract {
+    /// Mints the NFT of an ended lazy-mint auction to its winner, then settles the auction, or
+    /// settles it right away if there is no winner
+    pub(crate) fn internal_mint_to_winner(&mut self, nft_id: &NFTId) -> Option<Promise> {
+        let auction = self.auctions.get_mut(nft_id).expect("auction exists");
+        let Settlement::Sold { winner, .. } = plan_settlement(auction.settlement_bids()) else {
+            return self.internal_settle(nft_id);
+        };
+        let AuctionedAsset::LazyMint { deposit, minting } = &mut auction.asset else {
+            env::panic_str("this auction does not mint its nft")
+        };
+        if *minting {
+            return None;
+        }
+        *minting = true;
+        accounting::sub(&mut self.mint_deposits_held, *deposit);
+        let metadata = auction
+            .mint_metadata
+            .get()
+            .clone()
+            .expect("lazy mints have metadata");
+        Some(
+            ext_nft_minter::ext(auction.nft.clone())
+                .with_attached_deposit(*deposit)
+                .nft_mint(auction.token_id.clone(), winner, metadata)
+                .then(
+                    Self::ext(env::current_account_id())
+                        .on_lazy_minted(auction.nft.clone(), auction.token_id.clone()),
+                ),
+        )
+    }
+}
+
+impl Contract {
+    /// Checks that `minter` authorized `seller` and that `seller` may list `token_id` of it
+    pub(crate) fn internal_check_minting(
+        &self,
+        minter: &AccountId,
+        seller: &AccountId,
+        token_id: &TokenId,
+    ) {
+        require!(
+            self.seller_authorizations
+                .contains(&(minter.clone(), seller.clone())),
+            "the minter did not authorize this seller"
+        );
+        self.internal_check_listable(seller, minter, token_id)
+            .and_then(|()| self.internal_check_listing_capacity(seller))
+            .unwrap_or_else(|err| env::panic_str(&err));
+    }
+}
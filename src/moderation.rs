@@ -0,0 +1,155 @@
+//! Taking down infringing auctions
+//!
+//! Moderators appointed by the owner can delist an auction at any time. What it sells goes back
+//! to the seller and every bid is refunded in full along with the bid fees paid, as when the
+//! admins cancel an auction. The NFT is flagged so it can't be listed again, whichever way it is
+//! listed, until a moderator clears it, and the seller can be blacklisted from listing anything.
+
+use near_sdk::{env, json_types::U64, near, require, serde_json::json, AccountId, PromiseOrValue};
+
+use crate::*;
+
+/// Why and by whom an NFT was taken down
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct Takedown {
+    pub moderator: AccountId,
+    pub seller: AccountId,
+    pub reason: String,
+    pub at: U64,
+}
+
+#[near]
+impl Contract {
+    /// Has to be proposed as an action instead once there are admins
+    pub fn set_moderators(&mut self, moderators: Vec<AccountId>) {
+        self.internal_assert_owner();
+        self.internal_assert_no_admins();
+        self.internal_set_moderators(moderators);
+    }
+
+    pub fn moderators(&self) -> Vec<AccountId> {
+        self.moderators.clone()
+    }
+
+    /// Delists an infringing auction, returning what it sells to the seller and refunding every
+    /// bid, and blacklists the seller if `blacklist_seller` is set
+    ///
+    /// Only moderators can take auctions down.
+    pub fn take_down(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        reason: String,
+        blacklist_seller: bool,
+    ) -> PromiseOrValue<()> {
+        // Validations
+        self.internal_assert_moderator();
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in auction")
+        };
+
+        // Operations
+        let moderator = env::predecessor_account_id();
+        let seller = auction.owner.clone();
+        let nfts = auction.nfts().cloned().collect();
+        AuctionEvent::AuctionTakenDown {
+            nft,
+            token_id,
+            seller: seller.clone(),
+            moderator: moderator.clone(),
+            reason: reason.clone(),
+        }
+        .emit();
+        self.takedowns.insert(
+            nft_id.clone(),
+            Takedown {
+                moderator,
+                seller: seller.clone(),
+                reason,
+                at: env::block_timestamp().into(),
+            },
+        );
+        if blacklist_seller {
+            self.internal_set_blacklisted(seller, true);
+        }
+        match self.internal_cancel(&nft_id, nfts, "Auction taken down", false) {
+            Some(promise) => PromiseOrValue::Promise(promise),
+            None => PromiseOrValue::Value(()),
+        }
+    }
+
+    /// Lets a taken down NFT be listed again
+    pub fn clear_takedown(&mut self, nft: AccountId, token_id: TokenId) {
+        self.internal_assert_moderator();
+        require!(
+            self.takedowns
+                .remove(&NFTId::new(&nft, &token_id))
+                .is_some(),
+            "this nft was not taken down"
+        );
+    }
+
+    pub fn takedown(&self, nft: AccountId, token_id: TokenId) -> Option<Takedown> {
+        self.takedowns.get(&NFTId::new(&nft, &token_id)).cloned()
+    }
+
+    /// Bars `account_id` from listing anything, or lifts the bar
+    pub fn set_blacklisted(&mut self, account_id: AccountId, blacklisted: bool) {
+        self.internal_assert_moderator();
+        self.internal_set_blacklisted(account_id, blacklisted);
+    }
+
+    pub fn is_blacklisted(&self, account_id: AccountId) -> bool {
+        self.blacklisted_sellers.contains(&account_id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_set_moderators(&mut self, moderators: Vec<AccountId>) {
+        AuctionEvent::ParameterSet {
+            parameter: "moderators".into(),
+            value: json!(moderators),
+        }
+        .emit();
+        self.moderators = moderators;
+    }
+
+    /// Checks that `seller` is not blacklisted and that `token_id` of `nft` was not taken down,
+    /// every path that lists something goes through this
+    pub(crate) fn internal_check_listable(
+        &self,
+        seller: &AccountId,
+        nft: &AccountId,
+        token_id: &TokenId,
+    ) -> Result<(), String> {
+        if self.blacklisted_sellers.contains(seller) {
+            return Err("seller is blacklisted".into());
+        }
+        if self.takedowns.contains_key(&NFTId::new(nft, token_id)) {
+            return Err("this nft was taken down by a moderator".into());
+        }
+        Ok(())
+    }
+
+    fn internal_assert_moderator(&self) {
+        require!(
+            self.moderators.contains(&env::predecessor_account_id()),
+            "only moderators can do this"
+        );
+    }
+
+    fn internal_set_blacklisted(&mut self, account_id: AccountId, blacklisted: bool) {
+        AuctionEvent::SellerBlacklisted {
+            account_id: account_id.clone(),
+            blacklisted,
+        }
+        .emit();
+        if blacklisted {
+            self.blacklisted_sellers.insert(account_id);
+        } else {
+            self.blacklisted_sellers.remove(&account_id);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Auctions that take their bids in a NEP-245 multi-token, such as an in-game currency
+//!
+//! An auction listed with a multi-token `payment_token` only takes bids through
+//! `mt_transfer_call`, every transfer placing the sender's bid or topping it up by the amount
+//! transferred. Bids are held in the token and refunded with `mt_transfer`, and so are the
+//! proceeds paid. These sales pay neither the protocol fee nor royalties, which are taken in NEAR,
+//! and don't count towards NEAR volume.
+//!
+//! Token amounts can't be held against the compliance threshold, which is in NEAR, so once
+//! compliance is on every bid paid in a token waits for the registry.
+
+use near_sdk::{
+    env, ext_contract, json_types::U128, near, require, serde_json, AccountId, NearToken, Promise,
+    PromiseError, PromiseOrValue,
+};
+
+use crate::{compliance::ext_compliance_registry, payment_tokens::TokenBidMsg, *};
+
+/// The transfer method of NEP-245 multi-token contracts
+#[ext_contract(ext_mt_core)]
+pub trait MultiTokenCore {
+    fn mt_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        amount: U128,
+        approval: Option<(AccountId, u64)>,
+        memo: Option<String>,
+    );
+}
+
+#[near]
+impl Contract {
+    /// Bids the transferred tokens on the auction `msg` names, a [`TokenBidMsg`], on behalf of
+    /// their previous owner, topping up their bid if they already have one
+    ///
+    /// Only the token the auction takes can be transferred, one token id at a time. Nothing is
+    /// ever returned unused, a bid that can't be placed panics so the whole transfer is refunded.
+    /// A bid held back for a compliance check returns the whole transfer if it is rejected.
+    pub fn mt_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_ids: Vec<AccountId>,
+        token_ids: Vec<TokenId>,
+        amounts: Vec<U128>,
+        msg: String,
+    ) -> PromiseOrValue<Vec<U128>> {
+        // Validations
+        require!(
+            token_ids.len() == 1 && amounts.len() == 1,
+            "bid with a single token id at a time"
+        );
+        let msg: TokenBidMsg = serde_json::from_str(&msg)
+            .unwrap_or_else(|_| env::panic_str("msg must be a `TokenBidMsg`"));
+        let payment = PaymentToken::Mt {
+            contract: env::predecessor_account_id(),
+            token_id: token_ids[0].clone(),
+        };
+        let bidder = previous_owner_ids.into_iter().next().unwrap_or(sender_id);
+
+        // Operations
+        if let Some(registry) = self.internal_token_compliance_registry() {
+            return PromiseOrValue::Promise(
+                ext_compliance_registry::ext(registry)
+                    .is_compliant(bidder.clone())
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .on_mt_bid_compliance_checked(bidder, payment, amounts[0], msg),
+                    ),
+            );
+        }
+        self.internal_token_bid(
+            bidder,
+            payment,
+            NearToken::from_yoctonear(amounts[0].0),
+            msg,
+        )
+        .unwrap_or_else(|err| env::panic_str(&err));
+        PromiseOrValue::Value(vec![U128(0)])
+    }
+
+    /// Places the checked bid if the registry found the bidder compliant, returning the whole
+    /// transfer as unused otherwise or if the bid can no longer be placed
+    #[private]
+    pub fn on_mt_bid_compliance_checked(
+        &mut self,
+        bidder: AccountId,
+        payment: PaymentToken,
+        amount: U128,
+        msg: TokenBidMsg,
+        #[callback_result] compliant: Result<bool, PromiseError>,
+    ) -> Vec<U128> {
+        vec![self.internal_checked_token_bid(bidder, payment, amount, msg, compliant)]
+    }
+}
+
+impl Contract {
+    /// Transfers `amount` of `token_id` of the multi-token `mt` to `receiver_id`, recording it as
+    /// owed if the transfer fails
+    pub(crate) fn internal_mt_transfer(
+        &mut self,
+        mt: AccountId,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: &str,
+    ) -> Promise {
+        let deposit = NearToken::from_yoctonear(1);
+        accounting::add(&mut self.nft_deposits_paid, deposit);
+        ext_mt_core::ext(mt.clone())
+            .with_attached_deposit(deposit)
+            .mt_transfer(
+                receiver_id.clone(),
+                token_id.clone(),
+                amount,
+                None,
+                Some(memo.into()),
+            )
+            .then(Self::ext(env::current_account_id()).on_payout(
+                receiver_id,
+                OwedPayout::Mt {
+                    mt,
+                    token_id,
+                    amount,
+                },
+            ))
+    }
+}
@@ -0,0 +1,215 @@
+//! M-of-N confirmation of privileged actions by a set of admins
+//!
+//! Once the owner sets admins, protocol fees can only be withdrawn, the arbiter, compliance
+//! registry, staking and moderators replaced, auctions cancelled in an emergency and the contract
+//! upgraded through an action that `threshold` admins confirmed.
+
+use near_nft_auction_core::multisig;
+use near_sdk::{
+    env,
+    json_types::{Base58CryptoHash, U64},
+    near, require, AccountId, PromiseOrValue,
+};
+
+use crate::*;
+
+/// A privileged call that runs once enough admins confirmed it
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub enum AdminAction {
+    /// Sends the protocol fees collected so far to `receiver_id`
+    WithdrawProtocolFees { receiver_id: AccountId },
+    /// Cancels an auction without slashing its owner, refunding every bid in full
+    CancelAuction { nft: AccountId, token_id: TokenId },
+    /// Approves code of hash `code_hash` to be deployed with `upgrade`
+    Upgrade { code_hash: Base58CryptoHash },
+    /// Replaces the admins and the number of them that have to confirm an action
+    SetAdmins {
+        admins: Vec<AccountId>,
+        threshold: u32,
+    },
+    /// Replaces the arbiter of disputes, as in `set_arbiter`
+    SetArbiter { arbiter: Option<AccountId> },
+    /// Replaces the compliance registry and threshold, as in `set_compliance`
+    SetCompliance {
+        registry: Option<AccountId>,
+        threshold: Option<NearToken>,
+    },
+    /// Replaces the stake token and the stakers' share of the protocol fees, as in `set_staking`
+    SetStaking {
+        stake_token: Option<AccountId>,
+        share_bps: u16,
+    },
+    /// Replaces the moderators, as in `set_moderators`
+    SetModerators { moderators: Vec<AccountId> },
+}
+
+/// An action waiting for confirmations
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct PendingAction {
+    pub action: AdminAction,
+    /// Admin that proposed the action, the only one that can revoke it
+    pub proposer: AccountId,
+    pub confirmations: Vec<AccountId>,
+    pub proposed_at: U64,
+}
+
+#[near]
+impl Contract {
+    /// Sets the accounts that have to confirm privileged actions, `threshold` of them per action
+    ///
+    /// Only the owner can set the first admins, changing them later is an action itself.
+    pub fn set_admins(&mut self, admins: Vec<AccountId>, threshold: u32) {
+        self.internal_assert_owner();
+        require!(
+            self.admins.is_empty(),
+            "admins are set, propose a `SetAdmins` action instead"
+        );
+        self.internal_set_admins(admins, threshold);
+    }
+
+    /// Proposes `action`, confirmed by the proposing admin right away, which runs it if that is
+    /// all the confirmations it needs
+    ///
+    /// The id of the action is logged in the `action_proposed` event.
+    pub fn propose_action(&mut self, action: AdminAction) -> PromiseOrValue<()> {
+        let action_id = self.next_action_id;
+        let proposer = env::predecessor_account_id();
+        self.next_action_id += 1;
+        self.pending_actions.insert(
+            action_id,
+            PendingAction {
+                action,
+                proposer: proposer.clone(),
+                confirmations: Vec::new(),
+                proposed_at: env::block_timestamp().into(),
+            },
+        );
+        AuctionEvent::ActionProposed {
+            action_id: action_id.into(),
+            proposer,
+        }
+        .emit();
+        self.confirm_action(action_id.into())
+    }
+
+    /// Confirms the action of `action_id`, which runs if that was the last confirmation needed
+    pub fn confirm_action(&mut self, action_id: U64) -> PromiseOrValue<()> {
+        // Validations
+        let Some(pending) = self.pending_actions.get_mut(&action_id.into()) else {
+            env::panic_str("no such action is pending")
+        };
+        let admin = env::predecessor_account_id();
+        let ready = multisig::confirm(
+            &mut pending.confirmations,
+            admin.clone(),
+            &self.admins,
+            self.admin_threshold,
+        )
+        .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+
+        // Operations
+        AuctionEvent::ActionConfirmed { action_id, admin }.emit();
+        if !ready {
+            return PromiseOrValue::Value(());
+        }
+        let pending = self
+            .pending_actions
+            .remove(&action_id.into())
+            .expect("action is pending");
+        AuctionEvent::ActionExecuted { action_id }.emit();
+        self.internal_execute(pending.action)
+    }
+
+    /// Drops an action its proposer no longer wants, such as one that can't reach its threshold
+    pub fn revoke_action(&mut self, action_id: U64) {
+        let Some(pending) = self.pending_actions.get(&action_id.into()) else {
+            env::panic_str("no such action is pending")
+        };
+        require!(
+            pending.proposer == env::predecessor_account_id(),
+            "only the proposer can revoke an action"
+        );
+        self.pending_actions.remove(&action_id.into());
+        AuctionEvent::ActionRevoked { action_id }.emit();
+    }
+
+    pub fn admins(&self) -> AdminsView {
+        AdminsView {
+            admins: self.admins.clone(),
+            threshold: self.admin_threshold,
+        }
+    }
+
+    pub fn get_pending_action(&self, action_id: U64) -> Option<PendingAction> {
+        self.pending_actions.get(&action_id.into()).cloned()
+    }
+}
+
+impl Contract {
+    /// Panics if privileged actions have to be confirmed by the admins
+    pub(crate) fn internal_assert_no_admins(&self) {
+        require!(
+            self.admins.is_empty(),
+            "admins are set, propose this as an action instead"
+        );
+    }
+
+    fn internal_set_admins(&mut self, admins: Vec<AccountId>, threshold: u32) {
+        multisig::validate_admins(&admins, threshold)
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        self.admins = admins;
+        self.admin_threshold = threshold;
+    }
+
+    fn internal_execute(&mut self, action: AdminAction) -> PromiseOrValue<()> {
+        match action {
+            AdminAction::WithdrawProtocolFees { receiver_id } => {
+                PromiseOrValue::Promise(self.internal_withdraw_protocol_fees(receiver_id))
+            }
+            AdminAction::CancelAuction { nft, token_id } => {
+                let nft_id = NFTId::new(&nft, &token_id);
+                let Some(auction) = self.auctions.get(&nft_id) else {
+                    env::panic_str("this nft is not in auction")
+                };
+                let nfts = auction.nfts().cloned().collect();
+                match self.internal_cancel(&nft_id, nfts, "Auction cancelled by the admins", false)
+                {
+                    Some(promise) => PromiseOrValue::Promise(promise),
+                    None => PromiseOrValue::Value(()),
+                }
+            }
+            AdminAction::Upgrade { code_hash } => {
+                self.approved_code_hash = Some(code_hash.into());
+                PromiseOrValue::Value(())
+            }
+            AdminAction::SetAdmins { admins, threshold } => {
+                self.internal_set_admins(admins, threshold);
+                PromiseOrValue::Value(())
+            }
+            AdminAction::SetArbiter { arbiter } => {
+                self.internal_set_arbiter(arbiter);
+                PromiseOrValue::Value(())
+            }
+            AdminAction::SetCompliance {
+                registry,
+                threshold,
+            } => {
+                self.internal_set_compliance(registry, threshold);
+                PromiseOrValue::Value(())
+            }
+            AdminAction::SetStaking {
+                stake_token,
+                share_bps,
+            } => {
+                self.internal_set_staking(stake_token, share_bps);
+                PromiseOrValue::Value(())
+            }
+            AdminAction::SetModerators { moderators } => {
+                self.internal_set_moderators(moderators);
+                PromiseOrValue::Value(())
+            }
+        }
+    }
+}
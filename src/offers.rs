@@ -0,0 +1,352 @@
+//! Standing offers on NFTs that are not in auction
+
+use near_contract_standards::non_fungible_token::{core::ext_nft_core, Token};
+use near_nft_auction_core::offer;
+use near_sdk::{env, json_types::U64, near, store::IterableMap, Promise, PromiseError};
+
+use crate::*;
+
+/// Offers made on a single NFT
+#[near(serializers = [borsh])]
+pub struct OfferBook {
+    offers: IterableMap<u64, Offer>,
+}
+
+#[near]
+impl Contract {
+    /// Offers the attached deposit for an NFT that is not in auction, returning the id of the
+    /// offer
+    ///
+    /// The deposit stays escrowed until the offer is withdrawn, it can't be accepted after
+    /// `expires_at`.
+    #[payable]
+    pub fn make_offer(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        expires_at: Option<U64>,
+    ) -> U64 {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        require!(
+            !self.auctions.contains_key(&nft_id),
+            "this nft is in auction, bid on it instead"
+        );
+        let amount = env::attached_deposit();
+        let now = env::block_timestamp();
+        let expires_at = expires_at.map(u64::from);
+        offer::validate_offer(now, amount.as_yoctonear(), expires_at)
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+
+        // Operations
+        let offer_id = self.next_offer_id;
+        self.next_offer_id += 1;
+        accounting::add(&mut self.offers_escrowed, amount);
+        self.internal_insert_offer(
+            nft_id,
+            offer_id,
+            Offer {
+                offerer: env::predecessor_account_id(),
+                amount,
+                expires_at,
+                made_at: now,
+                counter: None,
+            },
+        );
+        offer_id.into()
+    }
+
+    /// Withdraws the caller's offer and refunds its deposit
+    pub fn withdraw_offer(&mut self, nft: AccountId, token_id: TokenId, offer_id: U64) -> Promise {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let offerer = env::predecessor_account_id();
+        require!(
+            self.internal_offer(&nft_id, offer_id.into())
+                .is_some_and(|offer| offer.offerer == offerer),
+            "caller has no such offer on this nft"
+        );
+
+        // Operations
+        let offer = self.internal_remove_offer(&nft_id, offer_id.into());
+        accounting::sub(&mut self.offers_escrowed, offer.amount);
+        Self::internal_pay(offerer, offer.amount)
+    }
+
+    /// Sells the NFT to the maker of `offer_id` for the amount they escrowed
+    ///
+    /// The caller has to own the NFT and have approved this contract on it. Once the NFT is
+    /// transferred, the amount is credited to the caller's balance and every other offer on the
+    /// NFT is refunded.
+    pub fn accept_offer(&mut self, nft: AccountId, token_id: TokenId, offer_id: U64) -> Promise {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        require!(
+            !self.auctions.contains_key(&nft_id),
+            "this nft is in auction, offers can't be accepted"
+        );
+        let Some(offer) = self.internal_offer(&nft_id, offer_id.into()) else {
+            env::panic_str("this nft has no such offer")
+        };
+        require!(
+            offer::is_open(env::block_timestamp(), offer.expires_at),
+            "offer has expired"
+        );
+
+        // Operations
+        // Taken out while the swap is in flight, so it can't be withdrawn or accepted twice
+        let offer = self.internal_remove_offer(&nft_id, offer_id.into());
+        ext_nft_core::ext(nft.clone())
+            .nft_token(token_id.clone().into())
+            .then(Self::ext(env::current_account_id()).on_offer_nft_token(
+                nft,
+                token_id,
+                offer_id,
+                offer,
+                env::predecessor_account_id(),
+            ))
+    }
+
+    /// Transfers the NFT to the offerer if `seller` owns it, restores the offer otherwise
+    #[private]
+    pub fn on_offer_nft_token(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        offer_id: U64,
+        offer: Offer,
+        seller: AccountId,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> PromiseOrValue<bool> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        if !matches!(token, Ok(Some(token)) if token.owner_id == seller) {
+            self.internal_insert_offer(nft_id, offer_id.into(), offer);
+            return PromiseOrValue::Value(false);
+        }
+        let promise = self
+            .internal_nft_transfer(
+                nft.clone(),
+                offer.offerer.clone(),
+                token_id.clone(),
+                "Offer accepted",
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .on_offer_accepted(nft, token_id, offer_id, offer, seller),
+            );
+        PromiseOrValue::Promise(promise)
+    }
+
+    /// Pays `seller` and refunds the competing offers once the NFT reached the offerer, restores
+    /// the offer otherwise
+    #[private]
+    pub fn on_offer_accepted(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        offer_id: U64,
+        offer: Offer,
+        seller: AccountId,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        let nft_id = NFTId::new(&nft, &token_id);
+        if result.is_err() {
+            self.internal_insert_offer(nft_id, offer_id.into(), offer);
+            return false;
+        }
+        let fee = self.internal_record_trade(
+            &FeePolicyConfig::default(),
+            &nft,
+            &token_id,
+            &seller,
+            &offer.offerer,
+            offer.amount,
+        );
+        accounting::sub(&mut self.offers_escrowed, offer.amount);
+        let proceeds =
+            self.internal_charge_fees(&FeePolicyConfig::default(), &nft, offer.amount, fee);
+        self.internal_credit(seller, proceeds);
+        if let Some(mut book) = self.offers.remove(&nft_id) {
+            for (_, competing) in book.offers.drain() {
+                accounting::sub(&mut self.offers_escrowed, competing.amount);
+                Self::internal_pay(competing.offerer, competing.amount);
+            }
+        }
+        true
+    }
+
+    /// Answers `offer_id` with the `price` the caller would sell the NFT at, until `expires_at`
+    ///
+    /// The caller has to own the NFT and have approved this contract on it. The offerer can take
+    /// the counteroffer with `accept_counteroffer`, a newer counteroffer replaces it.
+    pub fn counter_offer(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        offer_id: U64,
+        price: NearToken,
+        expires_at: U64,
+    ) -> Promise {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(offer) = self.internal_offer(&nft_id, offer_id.into()) else {
+            env::panic_str("this nft has no such offer")
+        };
+        offer::validate_counter(
+            env::block_timestamp(),
+            offer.amount.as_yoctonear(),
+            price.as_yoctonear(),
+            expires_at.into(),
+        )
+        .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+
+        // Operations
+        let counter = Counteroffer {
+            seller: env::predecessor_account_id(),
+            price,
+            expires_at: expires_at.into(),
+        };
+        ext_nft_core::ext(nft.clone())
+            .nft_token(token_id.clone().into())
+            .then(
+                Self::ext(env::current_account_id())
+                    .on_counter_nft_token(nft, token_id, offer_id, counter),
+            )
+    }
+
+    /// Records the counteroffer if its seller owns the NFT and the offer is still there
+    #[private]
+    pub fn on_counter_nft_token(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        offer_id: U64,
+        counter: Counteroffer,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> bool {
+        if !matches!(token, Ok(Some(token)) if token.owner_id == counter.seller) {
+            return false;
+        }
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(offer) = self
+            .offers
+            .get_mut(&nft_id)
+            .and_then(|book| book.offers.get_mut(&offer_id.into()))
+        else {
+            return false;
+        };
+        offer.counter = Some(counter);
+        true
+    }
+
+    /// Tops up the caller's offer to the counter price by the attached deposit and buys the NFT
+    ///
+    /// Anything attached beyond the counter price is refunded. If the seller no longer owns the
+    /// NFT, the offer stays at the counter price and can be withdrawn as usual.
+    #[payable]
+    pub fn accept_counteroffer(
+        &mut self,
+        nft: AccountId,
+        token_id: TokenId,
+        offer_id: U64,
+    ) -> Promise {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let offerer = env::predecessor_account_id();
+        let Some(offer) = self
+            .internal_offer(&nft_id, offer_id.into())
+            .filter(|offer| offer.offerer == offerer)
+        else {
+            env::panic_str("caller has no such offer on this nft")
+        };
+        let Some(counter) = &offer.counter else {
+            env::panic_str("this offer has not been countered")
+        };
+        let deposit = env::attached_deposit();
+        let top_up = offer::counter_top_up(
+            env::block_timestamp(),
+            offer.amount.as_yoctonear(),
+            counter.price.as_yoctonear(),
+            counter.expires_at,
+            deposit.as_yoctonear(),
+        )
+        .map(NearToken::from_yoctonear)
+        .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+
+        // Operations
+        let excess = deposit.saturating_sub(top_up);
+        if !excess.is_zero() {
+            Self::internal_pay(offerer, excess);
+        }
+        // Taken out while the swap is in flight, so it can't be withdrawn or accepted twice
+        let mut offer = self.internal_remove_offer(&nft_id, offer_id.into());
+        let counter = offer.counter.take().expect("offer was countered");
+        accounting::add(&mut self.offers_escrowed, top_up);
+        offer.amount = counter.price;
+        ext_nft_core::ext(nft.clone())
+            .nft_token(token_id.clone().into())
+            .then(Self::ext(env::current_account_id()).on_offer_nft_token(
+                nft,
+                token_id,
+                offer_id,
+                offer,
+                counter.seller,
+            ))
+    }
+
+    pub fn get_offers(
+        &self,
+        nft: AccountId,
+        token_id: TokenId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<OfferView> {
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(book) = self.offers.get(&nft_id) else {
+            return Vec::new();
+        };
+        book.offers
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(offer_id, offer)| OfferView {
+                offer_id: (*offer_id).into(),
+                offerer: offer.offerer.clone(),
+                amount: offer.amount,
+                expires_at: offer.expires_at.map(U64),
+                made_at: offer.made_at.into(),
+                counter: offer.counter.as_ref().map(|counter| CounterofferView {
+                    seller: counter.seller.clone(),
+                    price: counter.price,
+                    expires_at: counter.expires_at.into(),
+                }),
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    fn internal_insert_offer(&mut self, nft_id: NFTId, offer_id: u64, offer: Offer) {
+        self.offers
+            .entry(nft_id.clone())
+            .or_insert_with(|| OfferBook {
+                offers: IterableMap::new(StorageKey::OffersOf { nft_id }),
+            })
+            .offers
+            .insert(offer_id, offer);
+    }
+
+    fn internal_offer(&self, nft_id: &NFTId, offer_id: u64) -> Option<&Offer> {
+        self.offers.get(nft_id)?.offers.get(&offer_id)
+    }
+
+    /// Removes an offer, along with the NFT's offer book once it is empty
+    fn internal_remove_offer(&mut self, nft_id: &NFTId, offer_id: u64) -> Offer {
+        let book = self.offers.get_mut(nft_id).expect("offer book exists");
+        let offer = book.offers.remove(&offer_id).expect("offer exists");
+        if book.offers.is_empty() {
+            self.offers.remove(nft_id);
+        }
+        offer
+    }
+}
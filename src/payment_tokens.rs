@@ -0,0 +1,337 @@
+//! Tokens auctions can take their bids in instead of NEAR
+//!
+//! The owner keeps an allowlist of payment tokens, each with the unit bids come in, its decimals
+//! and the fee its sales pay. Fungible tokens are meant to be ERC-20 tokens bridged from
+//! Ethereum, such as wETH, which the Rainbow Bridge deploys as NEP-141 tokens that require storage
+//! registration and keep their ERC-20 decimals. Allowing one registers the contract with it too,
+//! so it can receive bids, and views show amounts of allowed tokens in whole tokens.
+
+use near_contract_standards::storage_management::{ext_storage_management, StorageBalance};
+use near_nft_auction_core::{payment, units};
+use near_sdk::{
+    env,
+    json_types::{U128, U64},
+    near, require,
+    serde_json::json,
+    AccountId, NearToken, Promise, PromiseError,
+};
+
+use crate::{compliance::NOT_COMPLIANT, *};
+
+/// The `msg` to pass to `ft_transfer_call` or `mt_transfer_call` to bid on an auction taking the
+/// token
+#[near(serializers = [json])]
+pub struct TokenBidMsg {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    /// When a new bid lapses, unless it is the highest bid by then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<U64>,
+}
+
+/// How a payment token is accepted
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct PaymentTokenConfig {
+    /// Symbol amounts are shown with, such as `WETH`
+    pub symbol: String,
+    /// Decimals of the token, tokens bridged from Ethereum keep those of their ERC-20
+    pub decimals: u8,
+    /// Bids, top-ups and the minimum bid of listings have to be whole multiples of this
+    pub min_bid_unit: U128,
+    pub fee: TokenFee,
+}
+
+/// The fee sales in a payment token pay, instead of the protocol and quick-flip fees
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub enum TokenFee {
+    Waived,
+    /// `bps` of the price, sent to the contract owner in the token at settlement
+    Bps {
+        bps: u16,
+    },
+}
+
+impl TokenFee {
+    pub fn bps(&self) -> u16 {
+        match self {
+            Self::Waived => 0,
+            Self::Bps { bps } => *bps,
+        }
+    }
+}
+
+#[near]
+impl Contract {
+    /// Lets auctions take bids in `token` as `config` says, or stops letting new ones if `config`
+    /// is `None`
+    ///
+    /// Fungible tokens register the contract with them, the contract paying for its storage out
+    /// of the fees it collected. Auctions already taking the token are not affected.
+    pub fn set_payment_token(
+        &mut self,
+        token: PaymentToken,
+        config: Option<PaymentTokenConfig>,
+    ) -> Option<Promise> {
+        self.internal_assert_owner();
+        AuctionEvent::ParameterSet {
+            parameter: "payment_token".into(),
+            value: json!({ "token": token, "config": config }),
+        }
+        .emit();
+        let Some(config) = config else {
+            self.payment_tokens.remove(&token);
+            return None;
+        };
+        units::validate_decimals(config.decimals)
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        payment::validate_token(config.min_bid_unit.0, config.fee.bps())
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        self.payment_tokens.insert(token.clone(), config);
+        let PaymentToken::Ft { contract } = token else {
+            return None;
+        };
+        require!(
+            self.protocol_fees >= FT_STORAGE_DEPOSIT,
+            "the protocol fees don't cover the storage deposit"
+        );
+        accounting::sub(&mut self.protocol_fees, FT_STORAGE_DEPOSIT);
+        // Registering an account that already is refunds the whole deposit
+        Some(
+            ext_storage_management::ext(contract)
+                .with_attached_deposit(FT_STORAGE_DEPOSIT)
+                .storage_deposit(Some(env::current_account_id()), Some(true))
+                .then(Self::ext(env::current_account_id()).on_payment_token_registered()),
+        )
+    }
+
+    /// Books the storage deposit the contract registered itself with, returning whatever the
+    /// token refunded to the protocol fees
+    #[private]
+    pub fn on_payment_token_registered(
+        &mut self,
+        #[callback_result] registered: Result<StorageBalance, PromiseError>,
+    ) {
+        let paid = registered.map_or(NearToken::from_yoctonear(0), |balance| {
+            balance.total.min(FT_STORAGE_DEPOSIT)
+        });
+        accounting::add(&mut self.ft_storage_paid, paid);
+        accounting::add(
+            &mut self.protocol_fees,
+            FT_STORAGE_DEPOSIT.saturating_sub(paid),
+        );
+    }
+
+    /// Places the checked bid if the registry found the bidder compliant, returning the whole
+    /// transfer as unused otherwise or if the bid can no longer be placed
+    #[private]
+    pub fn on_ft_bid_compliance_checked(
+        &mut self,
+        bidder: AccountId,
+        payment: PaymentToken,
+        amount: U128,
+        msg: TokenBidMsg,
+        #[callback_result] compliant: Result<bool, PromiseError>,
+    ) -> U128 {
+        self.internal_checked_token_bid(bidder, payment, amount, msg, compliant)
+    }
+
+    /// Every token auctions can take bids in, along with how it is accepted
+    pub fn payment_tokens(&self) -> Vec<(PaymentToken, PaymentTokenConfig)> {
+        self.payment_tokens
+            .iter()
+            .map(|(token, config)| (token.clone(), config.clone()))
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Checks that `auction` takes bids paid in `payment`, a token or NEAR if `None`
+    pub(crate) fn internal_check_payment(
+        auction: &Auction,
+        payment: Option<&PaymentToken>,
+    ) -> Result<(), String> {
+        match (&auction.payment, payment) {
+            (Some(expected), Some(payment)) if expected == payment => Ok(()),
+            (Some(PaymentToken::Ft { .. }), _) => Err(
+                "this auction takes bids in a fungible token, bid with `ft_transfer_call`".into(),
+            ),
+            (Some(PaymentToken::Mt { .. }), _) => {
+                Err("this auction takes bids in a multi-token, bid with `mt_transfer_call`".into())
+            }
+            (None, Some(_)) => Err("this auction takes bids in NEAR".into()),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Places the bid `msg` describes for `bidder` with `amount` of `payment` they transferred,
+    /// topping up their bid if they already have one
+    pub(crate) fn internal_token_bid(
+        &mut self,
+        bidder: AccountId,
+        payment: PaymentToken,
+        amount: NearToken,
+        msg: TokenBidMsg,
+    ) -> Result<(), String> {
+        let TokenBidMsg {
+            nft,
+            token_id,
+            expires_at,
+        } = msg;
+        // Tokens taken off the allowlist still settle, without checking the unit
+        if let Some(config) = self.payment_tokens.get(&payment) {
+            payment::check_unit(amount.as_yoctonear(), config.min_bid_unit.0)
+                .map_err(|err| err.to_string())?;
+        }
+        let nft_id = NFTId::new(&nft, &token_id);
+        let has_bid = self
+            .auctions
+            .get(&nft_id)
+            .and_then(|auction| auction.bids.get(&bidder))
+            .is_some_and(|bid| !bid.paid);
+        if has_bid {
+            self.internal_increase_bid(bidder, nft, token_id, amount, Some(&payment))
+        } else {
+            let origin = match payment {
+                PaymentToken::Ft { .. } => BidOrigin::Ft,
+                PaymentToken::Mt { .. } => BidOrigin::Mt,
+            };
+            self.internal_make_bid(
+                bidder,
+                &nft_id,
+                amount,
+                expires_at.map(u64::from),
+                amount,
+                Some(&payment),
+                origin,
+            )
+            .map(|_| ())
+        }
+    }
+
+    /// Places a bid held back for a compliance check once the registry answered, returning the
+    /// amount left unused, all of it if the bid was rejected
+    pub(crate) fn internal_checked_token_bid(
+        &mut self,
+        bidder: AccountId,
+        payment: PaymentToken,
+        amount: U128,
+        msg: TokenBidMsg,
+        compliant: Result<bool, PromiseError>,
+    ) -> U128 {
+        let (nft, token_id) = (msg.nft.clone(), msg.token_id.clone());
+        let placed = match compliant {
+            Ok(true) => self.internal_token_bid(
+                bidder.clone(),
+                payment,
+                NearToken::from_yoctonear(amount.0),
+                msg,
+            ),
+            _ => Err(NOT_COMPLIANT.into()),
+        };
+        match placed {
+            Ok(()) => U128(0),
+            Err(reason) => {
+                AuctionEvent::BidRejected {
+                    nft,
+                    token_id,
+                    bidder,
+                    reason,
+                }
+                .emit();
+                amount
+            }
+        }
+    }
+
+    /// Transfers `amount` of `token` to `receiver_id`, recording it as owed if the transfer fails
+    pub(crate) fn internal_token_transfer(
+        &mut self,
+        token: &PaymentToken,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: &str,
+    ) -> Promise {
+        match token {
+            PaymentToken::Ft { contract } => {
+                self.internal_ft_transfer(contract.clone(), receiver_id, amount, memo)
+            }
+            PaymentToken::Mt { contract, token_id } => self.internal_mt_transfer(
+                contract.clone(),
+                token_id.clone(),
+                receiver_id,
+                amount,
+                memo,
+            ),
+        }
+    }
+
+    /// Pays `amount` of `token` out of a settlement to `receiver_id`, as in `internal_ft_payout`
+    pub(crate) fn internal_token_payout(
+        &mut self,
+        token: &PaymentToken,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: &str,
+    ) -> Promise {
+        match token {
+            PaymentToken::Ft { contract } => {
+                self.internal_ft_payout(contract.clone(), receiver_id, amount, memo)
+            }
+            PaymentToken::Mt { .. } => {
+                self.internal_token_transfer(token, receiver_id, amount, memo)
+            }
+        }
+    }
+
+    /// Checks that the token `params` take bids in, if any, is allowed and fits their minimum bid
+    pub(crate) fn internal_check_payment_token(
+        &self,
+        params: &AuctionParams,
+    ) -> Result<(), PaymentTokenRejection> {
+        let Some(token) = &params.payment_token else {
+            return Ok(());
+        };
+        let Some(config) = self.payment_tokens.get(token) else {
+            return Err(PaymentTokenRejection::Unlisted {
+                token: token.clone(),
+                accepted: self.payment_tokens.keys().cloned().collect(),
+            });
+        };
+        if payment::check_unit(params.minimum_bid.as_yoctonear(), config.min_bid_unit.0).is_err() {
+            return Err(PaymentTokenRejection::OffUnit {
+                token: token.clone(),
+                min_bid_unit: config.min_bid_unit,
+            });
+        }
+        Ok(())
+    }
+
+    /// The fee a sale for `price` in `token` pays
+    pub(crate) fn internal_token_fee(&self, token: &PaymentToken, price: NearToken) -> NearToken {
+        let bps = self
+            .payment_tokens
+            .get(token)
+            .map_or(0, |config| config.fee.bps());
+        NearToken::from_yoctonear(payment::token_fee(price.as_yoctonear(), bps))
+    }
+
+    /// The view of `auction`, with its amounts in whole tokens if it takes an allowed token
+    pub(crate) fn internal_auction_view(&self, auction: &Auction) -> AuctionView {
+        let mut view = auction.view();
+        view.display = auction
+            .payment
+            .as_ref()
+            .and_then(|token| self.payment_tokens.get(token))
+            .map(|config| DisplayAmounts {
+                symbol: config.symbol.clone(),
+                h_bid: units::format_units(auction.h_bid.as_yoctonear(), config.decimals),
+                minimum_bid: units::format_units(
+                    auction.minimum_bid.as_yoctonear(),
+                    config.decimals,
+                ),
+            });
+        view
+    }
+}
@@ -0,0 +1,201 @@
+//! NEAR, fungible and multi-tokens paid out by the contract, and a ledger of the payouts that
+//! failed so they aren't lost
+
+use near_contract_standards::storage_management::{ext_storage_management, StorageBalance};
+use near_nft_auction_core::retry;
+use near_sdk::{
+    env,
+    json_types::{U128, U64},
+    near, Promise, PromiseError,
+};
+
+use crate::*;
+
+/// Nanoseconds before failed payouts can be retried a second time, doubling with every retry
+/// after that
+const RETRY_DELAY: u64 = 60 * 1_000_000_000;
+
+#[near]
+impl Contract {
+    /// Records the payout in the owed ledger of `account_id` if it failed
+    #[private]
+    pub fn on_payout(
+        &mut self,
+        account_id: AccountId,
+        payout: OwedPayout,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_ok() {
+            // Everything retried went through, forget about the retries
+            if self
+                .owed
+                .get(&account_id)
+                .is_some_and(|owed| owed.payouts.is_empty())
+            {
+                self.owed.remove(&account_id);
+            }
+            return true;
+        }
+        AuctionEvent::PayoutFailed {
+            account_id: account_id.clone(),
+            payout: payout.clone(),
+        }
+        .emit();
+        if let OwedPayout::Near { amount } = payout {
+            accounting::add(&mut self.owed_total, amount);
+        }
+        self.owed
+            .entry(account_id)
+            .or_default()
+            .payouts
+            .push(payout);
+        false
+    }
+
+    /// Transfers a payout of `amount` of `ft` once it is known whether `receiver_id` is
+    /// registered with it, registering them first out of the protocol fees if not
+    #[private]
+    pub fn on_ft_storage_balance(
+        &mut self,
+        ft: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: String,
+        #[callback_result] balance: Result<Option<StorageBalance>, PromiseError>,
+    ) -> Promise {
+        // A failed query or fees too low to register leave the transfer to fail and be owed
+        if !matches!(balance, Ok(None)) || self.protocol_fees < FT_STORAGE_DEPOSIT {
+            return self.internal_ft_transfer(ft, receiver_id, amount, &memo);
+        }
+        accounting::sub(&mut self.protocol_fees, FT_STORAGE_DEPOSIT);
+        ext_storage_management::ext(ft.clone())
+            .with_attached_deposit(FT_STORAGE_DEPOSIT)
+            .storage_deposit(Some(receiver_id.clone()), Some(true))
+            .then(Self::ext(env::current_account_id()).on_ft_storage_deposit(
+                ft,
+                receiver_id,
+                amount,
+                memo,
+            ))
+    }
+
+    /// Books the storage deposit `receiver_id` was registered with and transfers them the payout,
+    /// returning whatever the token refunded to the protocol fees
+    #[private]
+    pub fn on_ft_storage_deposit(
+        &mut self,
+        ft: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: String,
+        #[callback_result] registered: Result<StorageBalance, PromiseError>,
+    ) -> Promise {
+        // A failed registration refunds the whole deposit
+        let paid = registered.map_or(NearToken::from_yoctonear(0), |balance| {
+            balance.total.min(FT_STORAGE_DEPOSIT)
+        });
+        accounting::add(&mut self.ft_storage_paid, paid);
+        accounting::add(
+            &mut self.protocol_fees,
+            FT_STORAGE_DEPOSIT.saturating_sub(paid),
+        );
+        self.internal_ft_transfer(ft, receiver_id, amount, &memo)
+    }
+
+    /// Transfers everything owed to `account_id` again, callable by anyone
+    ///
+    /// Payouts that fail once more stay owed. Every retry has to wait twice as long as the one
+    /// before, see `payout_retry_at`.
+    pub fn retry_payout(&mut self, account_id: AccountId) -> Promise {
+        // Validations
+        let Some(owed) = self
+            .owed
+            .get_mut(&account_id)
+            .filter(|owed| !owed.payouts.is_empty())
+        else {
+            env::panic_str("nothing is owed to this account")
+        };
+        let now = env::block_timestamp();
+        require!(
+            listing::is_expired(now, owed.retry_at),
+            "payouts were retried too recently"
+        );
+
+        // Operations
+        let payouts = std::mem::take(&mut owed.payouts);
+        owed.retry_at = retry::next_retry_at(now, owed.retries, RETRY_DELAY);
+        owed.retries += 1;
+        payouts
+            .into_iter()
+            .map(|payout| match payout {
+                OwedPayout::Near { amount } => {
+                    accounting::sub(&mut self.owed_total, amount);
+                    Self::internal_pay(account_id.clone(), amount)
+                }
+                OwedPayout::Ft { ft, amount } => {
+                    self.internal_ft_transfer(ft, account_id.clone(), amount, "Payout retried")
+                }
+                OwedPayout::Mt {
+                    mt,
+                    token_id,
+                    amount,
+                } => self.internal_mt_transfer(
+                    mt,
+                    token_id,
+                    account_id.clone(),
+                    amount,
+                    "Payout retried",
+                ),
+            })
+            .reduce(Promise::and)
+            .expect("something is owed")
+    }
+
+    /// Payouts to `account_id` that failed and are still owed
+    pub fn owed_to(&self, account_id: AccountId) -> Vec<OwedPayout> {
+        self.owed
+            .get(&account_id)
+            .map(|owed| owed.payouts.clone())
+            .unwrap_or_default()
+    }
+
+    /// When the payouts owed to `account_id` can be retried next, if any are owed
+    pub fn payout_retry_at(&self, account_id: AccountId) -> Option<U64> {
+        self.owed
+            .get(&account_id)
+            .filter(|owed| !owed.payouts.is_empty())
+            .map(|owed| owed.retry_at.into())
+    }
+}
+
+impl Contract {
+    /// Transfers `amount` of `ft` paid out at settlement to `receiver_id`, who may have never
+    /// held the token
+    ///
+    /// Unregistered receivers are registered with the token first, the storage deposit coming
+    /// out of the protocol fees. The transfer is recorded as owed if it fails all the same.
+    pub(crate) fn internal_ft_payout(
+        &mut self,
+        ft: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: &str,
+    ) -> Promise {
+        ext_storage_management::ext(ft.clone())
+            .storage_balance_of(receiver_id.clone())
+            .then(Self::ext(env::current_account_id()).on_ft_storage_balance(
+                ft,
+                receiver_id,
+                amount,
+                memo.into(),
+            ))
+    }
+
+    /// Transfers `amount` to `account_id`, recording it as owed if the transfer fails, such as
+    /// when the account was deleted
+    pub(crate) fn internal_pay(account_id: AccountId, amount: NearToken) -> Promise {
+        Promise::new(account_id.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id()).on_payout(account_id, OwedPayout::Near { amount }),
+        )
+    }
+}
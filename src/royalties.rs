@@ -0,0 +1,121 @@
+//! Royalties paid out of sale proceeds, split as the NFT contract's NEP-199 `nft_payout` says
+//!
+//! The split is queried when an NFT is listed into escrow and cached for the whole collection
+//! until it goes stale, so settling a sale never waits on a cross-contract query. Sales of a
+//! collection whose split is not cached yet pay no royalties.
+//!
+//! NEP-199 merges the royalty of the token's owner with what the sale leaves them, so the split
+//! is only queried while the contract owns the token. The seller may then be paid royalties as
+//! any other recipient, such as a creator listing their own NFT.
+
+use std::collections::HashMap;
+
+use near_nft_auction_core::royalty;
+use near_sdk::{
+    env, ext_contract,
+    json_types::{U128, U64},
+    near,
+    serde_json::json,
+    AccountId, PromiseError,
+};
+
+use crate::*;
+
+/// Accounts a payout is split among at most
+const MAX_PAYOUT_RECIPIENTS: u32 = 10;
+
+/// What NEP-199 `nft_payout` returns
+#[near(serializers = [json])]
+pub struct NftPayout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// The NEP-199 payout query
+#[ext_contract(ext_nft_payout)]
+pub trait NftPayouts {
+    fn nft_payout(
+        &self,
+        token_id: TokenId,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> NftPayout;
+}
+
+/// Royalty shares of a collection, in basis points, as of `fetched_at`
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct RoyaltySplit {
+    pub shares: Vec<(AccountId, u16)>,
+    pub fetched_at: U64,
+}
+
+#[near]
+impl Contract {
+    /// Pays royalties out of sale proceeds if `enabled`, querying each collection's split again
+    /// once it is older than `cache_ttl` seconds
+    pub fn set_royalties(&mut self, enabled: bool, cache_ttl: U64) {
+        self.internal_assert_owner();
+        AuctionEvent::ParameterSet {
+            parameter: "royalties".into(),
+            value: json!({ "enabled": enabled, "cache_ttl": cache_ttl }),
+        }
+        .emit();
+        self.royalties_enabled = enabled;
+        self.royalty_cache_ttl = time::saturating_secs_to_nanos(cache_ttl.into());
+    }
+
+    pub fn royalty_split(&self, nft: AccountId) -> Option<RoyaltySplit> {
+        self.royalty_splits.get(&nft).cloned()
+    }
+
+    /// Caches the split of a payout of an NFT of `nft`, held by the contract, as the split of its
+    /// whole collection
+    #[private]
+    pub fn on_nft_payout(
+        &mut self,
+        nft: AccountId,
+        #[callback_result] payout: Result<NftPayout, PromiseError>,
+    ) -> bool {
+        let Ok(NftPayout { payout }) = payout else {
+            return false;
+        };
+        let payout = payout
+            .into_iter()
+            .map(|(account, share)| (account, share.0))
+            .collect();
+        // Royalties above the cap are not honored at all
+        let shares =
+            royalty::shares_from_payout(payout, &[env::current_account_id()]).unwrap_or_default();
+        self.royalty_splits.insert(
+            nft,
+            RoyaltySplit {
+                shares,
+                fetched_at: env::block_timestamp().into(),
+            },
+        );
+        true
+    }
+}
+
+impl Contract {
+    /// Queries the royalty split of the collection of `token_id`, which the contract has to own,
+    /// if royalties are on and the cached split is missing or stale
+    pub(crate) fn internal_refresh_royalties(&self, nft: &AccountId, token_id: &TokenId) {
+        let now = env::block_timestamp();
+        if !self.royalties_enabled
+            || self.royalty_splits.get(nft).is_some_and(|split| {
+                royalty::is_fresh(split.fetched_at.into(), self.royalty_cache_ttl, now)
+            })
+        {
+            return;
+        }
+        // Scheduled without returning it, the listing doesn't depend on it
+        ext_nft_payout::ext(nft.clone())
+            .nft_payout(
+                token_id.clone(),
+                U128(royalty::BASIS),
+                Some(MAX_PAYOUT_RECIPIENTS),
+            )
+            .then(Self::ext(env::current_account_id()).on_nft_payout(nft.clone()));
+    }
+}
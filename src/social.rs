@@ -0,0 +1,59 @@
+//! Announcing listings and sales on [near.social](https://near.social)
+//!
+//! Once the owner sets the SocialDB contract, every new listing and every sale is posted there
+//! from the contract account, so it shows up in the BOS social feed. The contract account has to
+//! hold a storage deposit with SocialDB for the posts to go through. Posting is fire and forget,
+//! a failed post never holds up an auction.
+
+use near_sdk::{
+    env, ext_contract, near,
+    serde_json::{self, json},
+    AccountId,
+};
+
+use crate::*;
+
+/// The part of the SocialDB interface posts are written with
+#[ext_contract(ext_social_db)]
+pub trait SocialDb {
+    fn set(&mut self, data: serde_json::Value);
+}
+
+#[near]
+impl Contract {
+    /// Posts announcements to `social_db`, such as `social.near`, none if unset
+    pub fn set_social_db(&mut self, social_db: Option<AccountId>) {
+        self.internal_assert_owner();
+        AuctionEvent::ParameterSet {
+            parameter: "social_db".into(),
+            value: json!(social_db),
+        }
+        .emit();
+        self.social_db = social_db;
+    }
+
+    pub fn social_db(&self) -> Option<AccountId> {
+        self.social_db.clone()
+    }
+}
+
+impl Contract {
+    /// Posts `text` to the contract account's feed, if announcements are on
+    pub(crate) fn internal_announce(&self, text: String) {
+        let Some(social_db) = self.social_db.clone() else {
+            return;
+        };
+        let post = json!({ "type": "md", "text": text });
+        let index = json!({ "key": "main", "value": { "type": "md" } });
+        let mut data = serde_json::Map::new();
+        data.insert(
+            env::current_account_id().into(),
+            json!({
+                "post": { "main": post.to_string() },
+                "index": { "post": index.to_string() },
+            }),
+        );
+        // Scheduled without a callback, nothing depends on it
+        ext_social_db::ext(social_db).set(data.into());
+    }
+}
@@ -0,0 +1,210 @@
+//! Sharing protocol fees with the stakers of a token
+//!
+//! Holders of the stake token stake it with `ft_transfer_call` to this contract. A share of every
+//! protocol fee then goes to the stakers instead of the treasury, distributed pro rata once an
+//! epoch ends and claimed by each staker in NEAR.
+
+use near_nft_auction_core::staking::{self, Pool, Position};
+use near_sdk::{
+    assert_one_yocto, env, json_types::U128, near, require, serde_json::json, AccountId, NearToken,
+    Promise,
+};
+
+use crate::*;
+
+/// What an account staked and the rewards it earned, see [`Position`]
+#[near(serializers = [borsh])]
+#[derive(Clone, Default)]
+pub struct Staker {
+    staked: u128,
+    reward_per_token: u128,
+    unclaimed: NearToken,
+    unlocks_at: u64,
+}
+
+impl Staker {
+    fn position(&self) -> Position {
+        Position {
+            staked: self.staked,
+            reward_per_token: self.reward_per_token,
+            unclaimed: self.unclaimed.as_yoctonear(),
+            unlocks_at: self.unlocks_at,
+        }
+    }
+
+    fn from_position(position: Position) -> Self {
+        Self {
+            staked: position.staked,
+            reward_per_token: position.reward_per_token,
+            unclaimed: NearToken::from_yoctonear(position.unclaimed),
+            unlocks_at: position.unlocks_at,
+        }
+    }
+}
+
+#[near]
+impl Contract {
+    /// Shares `share_bps` of every protocol fee with the stakers of `stake_token`, none if unset
+    ///
+    /// The stake token can only be changed while nothing is staked. Has to be proposed as an
+    /// action instead once there are admins.
+    pub fn set_staking(&mut self, stake_token: Option<AccountId>, share_bps: u16) {
+        self.internal_assert_owner();
+        self.internal_assert_no_admins();
+        self.internal_set_staking(stake_token, share_bps);
+    }
+
+    pub fn staking(&self) -> StakingView {
+        StakingView {
+            stake_token: self.stake_token.clone(),
+            share_bps: self.staker_share_bps,
+            staked: self.total_staked.into(),
+            undistributed: self.staking_undistributed,
+            epoch_ends: self.epoch_started.saturating_add(staking::EPOCH).into(),
+        }
+    }
+
+    /// Stake and rewards of `account_id`, as of the last distribution
+    pub fn staker(&self, account_id: AccountId) -> Option<StakerView> {
+        let mut position = self.stakers.get(&account_id)?.position();
+        position.settle(&self.internal_staking_pool());
+        Some(StakerView {
+            staked: position.staked.into(),
+            unclaimed: NearToken::from_yoctonear(position.unclaimed),
+            unlocks_at: position.unlocks_at.into(),
+        })
+    }
+
+    /// Distributes the fees collected during the epoch that ended to the stakers, callable by
+    /// anyone
+    pub fn distribute_rewards(&mut self) {
+        let now = env::block_timestamp();
+        let elapsed = now - self.epoch_started;
+        require!(elapsed >= staking::EPOCH, "the epoch has not ended yet");
+
+        let mut pool = self.internal_staking_pool();
+        let distributed = pool.distribute();
+        self.reward_per_token = pool.reward_per_token;
+        self.staking_undistributed = NearToken::from_yoctonear(pool.undistributed);
+        self.epoch_started = now - elapsed % staking::EPOCH;
+        AuctionEvent::RewardsDistributed {
+            amount: NearToken::from_yoctonear(distributed),
+            staked: self.total_staked.into(),
+        }
+        .emit();
+    }
+
+    /// Sends the caller's unstaked tokens back, once their stake unlocked
+    #[payable]
+    pub fn unstake(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let Some(stake_token) = self.stake_token.clone() else {
+            env::panic_str("staking is not set up")
+        };
+        let account_id = env::predecessor_account_id();
+        let mut pool = self.internal_staking_pool();
+        let mut position = self
+            .stakers
+            .get(&account_id)
+            .map(Staker::position)
+            .unwrap_or_default();
+        position
+            .unstake(&mut pool, amount.0, env::block_timestamp())
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        self.total_staked = pool.staked;
+        self.internal_set_staker(&account_id, position);
+        AuctionEvent::Unstaked {
+            account_id: account_id.clone(),
+            amount,
+        }
+        .emit();
+        self.internal_ft_transfer(stake_token, account_id, amount, "Unstaked")
+    }
+
+    /// Sends the caller the rewards they earned so far
+    pub fn claim_rewards(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let Some(staker) = self.stakers.get(&account_id) else {
+            env::panic_str("account never staked")
+        };
+        let mut position = staker.position();
+        let amount = NearToken::from_yoctonear(position.claim(&self.internal_staking_pool()));
+        require!(!amount.is_zero(), "there are no rewards to claim");
+        self.internal_set_staker(&account_id, position);
+        accounting::sub(&mut self.staking_rewards, amount);
+        AuctionEvent::RewardsClaimed {
+            account_id: account_id.clone(),
+            amount,
+        }
+        .emit();
+        Self::internal_pay(account_id, amount)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_set_staking(&mut self, stake_token: Option<AccountId>, share_bps: u16) {
+        require!(
+            share_bps <= 10_000,
+            "staker share must be at most 10000 bps"
+        );
+        require!(
+            stake_token == self.stake_token || self.total_staked == 0,
+            "cannot change the stake token while tokens are staked"
+        );
+        AuctionEvent::ParameterSet {
+            parameter: "staking".into(),
+            value: json!({ "stake_token": stake_token, "share_bps": share_bps }),
+        }
+        .emit();
+        self.stake_token = stake_token;
+        self.staker_share_bps = share_bps;
+    }
+
+    fn internal_staking_pool(&self) -> Pool {
+        Pool {
+            staked: self.total_staked,
+            reward_per_token: self.reward_per_token,
+            undistributed: self.staking_undistributed.as_yoctonear(),
+        }
+    }
+
+    fn internal_set_staker(&mut self, account_id: &AccountId, position: Position) {
+        if position.staked == 0 && position.unclaimed == 0 {
+            self.stakers.remove(account_id);
+        } else {
+            self.stakers
+                .insert(account_id.clone(), Staker::from_position(position));
+        }
+    }
+
+    /// Stakes `amount` of the stake token received from `account_id`
+    pub(crate) fn internal_stake(&mut self, account_id: AccountId, amount: U128) {
+        let mut pool = self.internal_staking_pool();
+        let mut position = self
+            .stakers
+            .get(&account_id)
+            .map(Staker::position)
+            .unwrap_or_default();
+        position
+            .stake(&mut pool, amount.0, env::block_timestamp())
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()));
+        self.total_staked = pool.staked;
+        self.internal_set_staker(&account_id, position);
+        AuctionEvent::Staked { account_id, amount }.emit();
+    }
+
+    /// Adds `fee` to the protocol fees, setting the stakers' share of it aside for the next
+    /// distribution
+    pub(crate) fn internal_collect_protocol_fee(&mut self, fee: NearToken) {
+        accounting::add(&mut self.protocol_fees_total, fee);
+        let stakers_share = match self.stake_token {
+            Some(_) if self.total_staked > 0 => NearToken::from_yoctonear(
+                fee.as_yoctonear() * u128::from(self.staker_share_bps) / 10_000,
+            ),
+            _ => NearToken::from_yoctonear(0),
+        };
+        accounting::add(&mut self.staking_undistributed, stakers_share);
+        accounting::add(&mut self.staking_rewards, stakers_share);
+        accounting::add(&mut self.protocol_fees, fee.saturating_sub(stakers_share));
+    }
+}
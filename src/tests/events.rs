@@ -0,0 +1,141 @@
+//! Snapshots of the `EVENT_JSON` logs each step of an auction's life emits
+//!
+//! Indexers parse these logs, so a change to their format breaks them even when the contract
+//! behaves just the same. Every step compares its logs line for line against its snapshot in
+//! `src/tests/snapshots`, and fails if it has none. Run with `UPDATE_EVENT_SNAPSHOTS=1` to record
+//! the snapshots of new steps, or every snapshot again after a change that is meant to emit
+//! different events, and commit the new snapshots with it.
+
+use std::{fs, path::Path};
+
+use near_sdk::test_utils::get_logs;
+
+use super::*;
+
+const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/snapshots");
+
+/// Compares the events logged since the context was last set with the snapshot `name`
+fn assert_events(name: &str) {
+    let events: String = get_logs()
+        .into_iter()
+        .filter(|log| log.starts_with("EVENT_JSON:"))
+        .map(|log| log + "\n")
+        .collect();
+    assert!(!events.is_empty(), "{name} emitted no events");
+    let path = Path::new(SNAPSHOTS).join(format!("{name}.log"));
+    if std::env::var_os("UPDATE_EVENT_SNAPSHOTS").is_some() {
+        fs::create_dir_all(SNAPSHOTS).unwrap();
+        fs::write(path, events).unwrap();
+        return;
+    }
+    let Ok(snapshot) = fs::read_to_string(&path) else {
+        panic!("{name} has no snapshot, run with UPDATE_EVENT_SNAPSHOTS=1 to record it");
+    };
+    assert_eq!(
+        events, snapshot,
+        "events of {name} changed, run with UPDATE_EVENT_SNAPSHOTS=1 if that is intended"
+    );
+}
+
+/// A contract with an auction like `contract_with_auction`'s whose proceeds are held for an hour
+fn contract_with_held_proceeds() -> Contract {
+    let mut contract = Contract::default();
+    testing_env!(context(&contract_id()).build());
+    contract.start_auction(
+        accounts(0),
+        nft(),
+        token_id(),
+        HOUR,
+        AuctionParams::builder(HOUR_SECS, near(1))
+            .proceeds_delay(HOUR_SECS)
+            .build(),
+        None,
+    );
+    contract
+}
+
+#[test]
+fn listing() {
+    contract_with_auction();
+    assert_events("listing");
+}
+
+#[test]
+fn bid() {
+    let mut contract = contract_with_auction();
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    assert_events("bid");
+}
+
+#[test]
+fn bid_increase() {
+    let mut contract = contract_with_auction();
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    testing_env!(context(&accounts(1))
+        .block_timestamp(2 * MINUTE)
+        .attached_deposit(near(1))
+        .build());
+    contract.increase_bid(nft(), token_id(), Some(near(1)), None);
+    assert_events("bid_increase");
+}
+
+#[test]
+fn cancellation() {
+    let mut contract = contract_with_auction();
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    testing_env!(context(&accounts(0)).block_timestamp(2 * MINUTE).build());
+    contract.cancel_auction(nft(), token_id());
+    assert_events("cancellation");
+}
+
+#[test]
+fn settlement() {
+    let mut contract = contract_with_auction();
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    end_auction(&mut contract, HOUR);
+    assert_events("settlement");
+}
+
+#[test]
+fn settlement_without_bids() {
+    let mut contract = contract_with_auction();
+    end_auction(&mut contract, HOUR);
+    assert_events("settlement_without_bids");
+}
+
+#[test]
+fn settlement_with_quick_flip_fee() {
+    let mut contract = contract_with_auction();
+    testing_env!(context(&contract_id()).build());
+    contract.set_wash_trade_rules(Some(WashTradeConfig {
+        window: U64(u64::MAX),
+        free_trades: 0,
+        fee_bps: 250,
+    }));
+    assert_events("wash_trade_rules_set");
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    end_auction(&mut contract, HOUR);
+    assert_events("settlement_with_quick_flip_fee");
+}
+
+#[test]
+fn escrow_lost() {
+    let mut contract = contract_with_auction();
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    testing_env!(context(&accounts(5)).block_timestamp(HOUR).build());
+    contract.end_auction(nft(), token_id());
+    testing_env!(context(&contract_id()).block_timestamp(HOUR).build());
+    assert!(!contract.on_escrow_checked(nft(), token_id(), true, Ok(None)));
+    assert_events("escrow_lost");
+}
+
+#[test]
+fn held_proceeds() {
+    let mut contract = contract_with_held_proceeds();
+    make_bid(&mut contract, &accounts(1), near(2), MINUTE);
+    end_auction(&mut contract, HOUR);
+    assert_events("settlement_with_held_proceeds");
+    testing_env!(context(&accounts(5)).block_timestamp(2 * HOUR).build());
+    contract.release_proceeds(U64(0));
+    assert_events("proceeds_release");
+}
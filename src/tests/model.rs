@@ -0,0 +1,362 @@
+//! Differential tests against a reference model of english auctions
+//!
+//! The model keeps the rules as plainly as they can be written, with none of the contract's
+//! storage, accounting or promises. Random sequences of bids, withdrawals, cancellations and
+//! settlements are replayed on both, and every accepted or rejected operation, every refund, the
+//! seller's proceeds and the winner have to match.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use super::*;
+
+/// Sequences replayed, each from its own seed
+const RUNS: u64 = 40;
+
+/// Operations in a sequence, before the auction is ended if it is still going
+const OPS_PER_RUN: usize = 30;
+
+/// Number of bidders, `accounts(1)` and on
+const BIDDERS: usize = 3;
+
+/// xorshift64, deterministic so a failing seed can be replayed
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    /// A new bid of `amount`, or raising the bidder's bid to `amount` if they have one
+    Bid {
+        bidder: usize,
+        amount: u128,
+        expires_at: Option<u64>,
+    },
+    WithdrawLapsed {
+        bidder: usize,
+    },
+    Cancel,
+    End,
+}
+
+impl Op {
+    fn random(rng: &mut Rng, now: u64) -> Self {
+        let bidder = rng.below(BIDDERS as u64) as usize;
+        match rng.below(20) {
+            0..=11 => Op::Bid {
+                bidder,
+                amount: u128::from(rng.below(10) + 1),
+                expires_at: (rng.below(2) == 0).then(|| now + (rng.below(30) + 1) * MINUTE),
+            },
+            12..=14 => Op::WithdrawLapsed { bidder },
+            15 => Op::Cancel,
+            _ => Op::End,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ModelBid {
+    amount: u128,
+    expires_at: Option<u64>,
+    refunded: bool,
+    sequence: u64,
+}
+
+/// An english auction with a minimum bid of 1 NEAR ending at `HOUR`, amounts in NEAR
+#[derive(Debug)]
+struct Model {
+    h_bid: u128,
+    bids: BTreeMap<usize, ModelBid>,
+    next_sequence: u64,
+    ended: bool,
+    refunds: BTreeMap<usize, u128>,
+    proceeds: u128,
+    winner: Option<usize>,
+}
+
+impl Model {
+    fn new() -> Self {
+        Self {
+            h_bid: 1,
+            bids: BTreeMap::new(),
+            next_sequence: 0,
+            ended: false,
+            refunds: BTreeMap::new(),
+            proceeds: 0,
+            winner: None,
+        }
+    }
+
+    /// Applies `op` at `now`, returning whether it was accepted
+    fn apply(&mut self, op: Op, now: u64) -> bool {
+        if self.ended {
+            return false;
+        }
+        let over = now >= HOUR;
+        match op {
+            Op::Bid {
+                bidder,
+                amount,
+                expires_at,
+            } => {
+                if over || amount <= self.h_bid {
+                    return false;
+                }
+                match self.bids.get_mut(&bidder) {
+                    Some(bid) if bid.refunded || amount <= bid.amount => return false,
+                    Some(bid) => {
+                        bid.amount = amount;
+                        bid.sequence = self.next_sequence;
+                    }
+                    None => {
+                        if expires_at.is_some_and(|expires_at| now >= expires_at) {
+                            return false;
+                        }
+                        self.bids.insert(
+                            bidder,
+                            ModelBid {
+                                amount,
+                                expires_at,
+                                refunded: false,
+                                sequence: self.next_sequence,
+                            },
+                        );
+                    }
+                }
+                self.next_sequence += 1;
+                self.h_bid = amount;
+                true
+            }
+            Op::WithdrawLapsed { bidder } => {
+                let h_bid = self.h_bid;
+                let Some(bid) = self.bids.get_mut(&bidder) else {
+                    return false;
+                };
+                let lapsed = bid.amount < h_bid
+                    && bid.expires_at.is_some_and(|expires_at| now >= expires_at);
+                if bid.refunded || !lapsed {
+                    return false;
+                }
+                bid.refunded = true;
+                *self.refunds.entry(bidder).or_default() += bid.amount;
+                true
+            }
+            Op::Cancel => {
+                if over {
+                    return false;
+                }
+                self.settle(None);
+                true
+            }
+            Op::End => {
+                if !over {
+                    return false;
+                }
+                let winner = self
+                    .bids
+                    .iter()
+                    .filter(|(_, bid)| !bid.refunded)
+                    .max_by_key(|(_, bid)| (bid.amount, std::cmp::Reverse(bid.sequence)))
+                    .map(|(bidder, _)| *bidder);
+                self.settle(winner);
+                true
+            }
+        }
+    }
+
+    /// Sells to `winner`, if any, and refunds everyone else
+    fn settle(&mut self, winner: Option<usize>) {
+        for (bidder, bid) in &mut self.bids {
+            if bid.refunded {
+                continue;
+            }
+            bid.refunded = true;
+            if Some(*bidder) == winner {
+                self.proceeds += bid.amount;
+            } else {
+                *self.refunds.entry(*bidder).or_default() += bid.amount;
+            }
+        }
+        self.winner = winner;
+        self.ended = true;
+    }
+}
+
+/// The contract, with the auction of `contract_with_auction`, and the NEAR it transferred so far
+struct Driver {
+    contract: Contract,
+    /// Bidders the contract took a bid from, who have to raise it rather than bid again
+    placed: BTreeSet<usize>,
+    refunds: BTreeMap<usize, u128>,
+}
+
+impl Driver {
+    fn new() -> Self {
+        // Each run starts from empty storage, not the one the previous run left behind
+        near_sdk::mock::with_mocked_blockchain(|blockchain| blockchain.take_storage());
+        Self {
+            contract: contract_with_auction(),
+            placed: BTreeSet::new(),
+            refunds: BTreeMap::new(),
+        }
+    }
+
+    /// Calls the contract as `op` says at `now`, returning whether it did not panic
+    fn apply(&mut self, op: Op, now: u64) -> bool {
+        let contract = &mut self.contract;
+        let accepted = match op {
+            Op::Bid {
+                bidder,
+                amount,
+                expires_at,
+            } => {
+                let account = bidder_account(bidder);
+                let accepted = if self.placed.contains(&bidder) {
+                    let current = contract
+                        .auctions
+                        .get(&NFTId::new(&nft(), &token_id()))
+                        .and_then(|auction| auction.bids.get(&account))
+                        .map_or(NearToken::from_yoctonear(0), |bid| bid.amount);
+                    let top_up = near(amount).saturating_sub(current);
+                    testing_env!(context(&account)
+                        .block_timestamp(now)
+                        .attached_deposit(top_up)
+                        .build());
+                    call(|| contract.increase_bid(nft(), token_id(), Some(top_up), None))
+                } else {
+                    testing_env!(context(&account)
+                        .block_timestamp(now)
+                        .attached_deposit(near(amount))
+                        .build());
+                    call(|| {
+                        contract.make_bid(
+                            nft(),
+                            token_id(),
+                            near(amount),
+                            expires_at.map(U64),
+                            None,
+                            None,
+                        )
+                    })
+                };
+                if accepted {
+                    self.placed.insert(bidder);
+                }
+                accepted
+            }
+            Op::WithdrawLapsed { bidder } => {
+                testing_env!(context(&bidder_account(bidder))
+                    .block_timestamp(now)
+                    .build());
+                call(|| contract.withdraw_lapsed_bid(nft(), token_id()))
+            }
+            Op::Cancel => {
+                testing_env!(context(&accounts(0)).block_timestamp(now).build());
+                call(|| contract.cancel_auction(nft(), token_id()))
+            }
+            Op::End => {
+                testing_env!(context(&accounts(5)).block_timestamp(now).build());
+                if !call(|| contract.end_auction(nft(), token_id())) {
+                    return false;
+                }
+                testing_env!(context(&contract_id()).block_timestamp(now).build());
+                contract.on_escrow_checked(
+                    nft(),
+                    token_id(),
+                    true,
+                    Ok(token_owned_by(&contract_id())),
+                )
+            }
+        };
+        for bidder in 0..BIDDERS {
+            let refunded: u128 = transfers_to(&bidder_account(bidder))
+                .into_iter()
+                .map(|transfer| transfer.as_yoctonear())
+                .sum();
+            *self.refunds.entry(bidder).or_default() += refunded;
+        }
+        accepted
+    }
+}
+
+fn bidder_account(bidder: usize) -> AccountId {
+    accounts(bidder + 1)
+}
+
+/// Whether `f` returned without panicking, as a rejected call would
+fn call<T>(f: impl FnOnce() -> T) -> bool {
+    catch_unwind(AssertUnwindSafe(f)).is_ok()
+}
+
+/// The model's amounts in NEAR as yoctoNEAR, leaving out zeros
+fn in_yocto(amounts: &BTreeMap<usize, u128>) -> BTreeMap<usize, u128> {
+    amounts
+        .iter()
+        .filter(|(_, amount)| **amount > 0)
+        .map(|(bidder, amount)| (*bidder, near(*amount).as_yoctonear()))
+        .collect()
+}
+
+#[test]
+fn contract_matches_model() {
+    for seed in 1..=RUNS {
+        let mut rng = Rng(seed);
+        let mut model = Model::new();
+        let mut driver = Driver::new();
+        let mut now = 0;
+        let mut history = Vec::new();
+        for i in 0..=OPS_PER_RUN {
+            now += rng.below(8) * MINUTE;
+            // Every run ends with the auction settled, unless it was cancelled
+            let op = match i {
+                OPS_PER_RUN if model.ended => break,
+                OPS_PER_RUN => {
+                    now = now.max(HOUR);
+                    Op::End
+                }
+                _ => Op::random(&mut rng, now),
+            };
+            history.push((now / MINUTE, op));
+            let expected = model.apply(op, now);
+            let accepted = driver.apply(op, now);
+            assert_eq!(
+                accepted,
+                expected,
+                "seed {seed}: contract accepted {op:?} at minute {}: {accepted}, model: \
+                 {expected}\nhistory: {history:#?}",
+                now / MINUTE
+            );
+        }
+
+        let refunds: BTreeMap<usize, u128> = driver
+            .refunds
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .collect();
+        assert_eq!(
+            refunds,
+            in_yocto(&model.refunds),
+            "seed {seed}: refunds\nhistory: {history:#?}"
+        );
+        assert_eq!(
+            driver.contract.balance_of(accounts(0)),
+            near(model.proceeds),
+            "seed {seed}: proceeds\nhistory: {history:#?}"
+        );
+        assert_eq!(
+            driver.contract.nft_claim_of(nft(), token_id()),
+            model.winner.map(bidder_account),
+            "seed {seed}: winner\nhistory: {history:#?}"
+        );
+    }
+}
@@ -0,0 +1,1419 @@
+//! Types shared by the contract and off-chain code, such as indexers and bots
+//!
+//! These compile for non-wasm targets with the `library` feature.
+
+use std::{
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    str::FromStr,
+};
+
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_nft_auction_core::{
+    curve::Curve,
+    engine::{self, AuctionEngine},
+    fees::FeePolicy,
+    pay_on_win::PayOnWinRules,
+    penny::PennyRules,
+    time,
+    token_id::{validate_token_id, TokenIdError},
+    trending::Activity,
+    wash::WashTradeRules,
+};
+use near_sdk::{
+    borsh::{BorshDeserialize, BorshSerialize},
+    env,
+    json_types::{Base58CryptoHash, U128, U64},
+    near, AccountId, NearToken, StorageUsage,
+};
+
+/// Id of a token of an NFT contract, checked to be a valid token id whenever it is deserialized
+/// from JSON
+///
+/// It serializes just like a `String`, in JSON and in Borsh, so the NFT contracts it comes from
+/// and the storage keys it ends up in see no difference.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct TokenId(String);
+
+impl TokenId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for TokenId {
+    type Error = TokenIdError;
+
+    fn try_from(token_id: String) -> Result<Self, Self::Error> {
+        validate_token_id(&token_id)?;
+        Ok(Self(token_id))
+    }
+}
+
+impl FromStr for TokenId {
+    type Err = TokenIdError;
+
+    fn from_str(token_id: &str) -> Result<Self, Self::Err> {
+        Self::try_from(token_id.to_string())
+    }
+}
+
+impl From<TokenId> for String {
+    fn from(token_id: TokenId) -> Self {
+        token_id.0
+    }
+}
+
+impl AsRef<str> for TokenId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for TokenId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<String> for TokenId {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for TokenId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// How a bid reached the contract
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BidOrigin {
+    /// Called by the bidder, with `make_bid` or another bidding method
+    Direct,
+    /// Transferred with `ft_transfer_call`
+    Ft,
+    /// Transferred with `mt_transfer_call`
+    Mt,
+    /// Signed by the bidder as an intent and submitted by someone else
+    Relayed,
+}
+
+#[near(serializers = [borsh])]
+pub struct Bid {
+    pub amount: NearToken,
+    pub paid: bool,
+    /// When the bid lapses, unless it is the highest bid by then
+    pub expires_at: Option<u64>,
+    /// When the bid was first placed
+    pub placed_at: u64,
+    /// When the bid was last placed or increased
+    pub last_bid_at: u64,
+    /// Order in which bids on the auction reached their current amount, breaking ties at
+    /// settlement in favour of the earliest
+    pub sequence: u64,
+    /// NFT the bidder wants if they win a collection auction
+    pub pick: Option<TokenId>,
+    /// Part of `amount` escrowed on a pay-on-win auction, all of it is escrowed if `None`
+    pub deposit: Option<NearToken>,
+    /// Penny auction bid fees paid by the bidder, refunded if the auction is taken down
+    pub fees_paid: NearToken,
+    /// Token the bid was paid in, NEAR if `None`
+    pub payment: Option<PaymentToken>,
+    pub origin: BidOrigin,
+    /// Message the bidder left with the bid, its storage paid for by the bidder
+    pub memo: Option<String>,
+}
+
+/// A bid as stored before bids were versioned, version 0 of [`VersionedBid`]
+#[near(serializers = [borsh])]
+pub struct BidV1 {
+    pub amount: NearToken,
+    pub paid: bool,
+    pub expires_at: Option<u64>,
+    pub placed_at: u64,
+    pub last_bid_at: u64,
+    pub sequence: u64,
+    pub pick: Option<TokenId>,
+    pub deposit: Option<NearToken>,
+    pub fees_paid: NearToken,
+}
+
+/// A bid as stored before it had a memo, version 1 of [`VersionedBid`]
+#[near(serializers = [borsh])]
+pub struct BidV2 {
+    pub amount: NearToken,
+    pub paid: bool,
+    pub expires_at: Option<u64>,
+    pub placed_at: u64,
+    pub last_bid_at: u64,
+    pub sequence: u64,
+    pub pick: Option<TokenId>,
+    pub deposit: Option<NearToken>,
+    pub fees_paid: NearToken,
+    pub payment: Option<PaymentToken>,
+    pub origin: BidOrigin,
+}
+
+impl From<BidV1> for BidV2 {
+    fn from(bid: BidV1) -> Self {
+        // Only NEAR could be bid back then, and only by calling the contract
+        Self {
+            amount: bid.amount,
+            paid: bid.paid,
+            expires_at: bid.expires_at,
+            placed_at: bid.placed_at,
+            last_bid_at: bid.last_bid_at,
+            sequence: bid.sequence,
+            pick: bid.pick,
+            deposit: bid.deposit,
+            fees_paid: bid.fees_paid,
+            payment: None,
+            origin: BidOrigin::Direct,
+        }
+    }
+}
+
+impl From<BidV2> for Bid {
+    fn from(bid: BidV2) -> Self {
+        Self {
+            amount: bid.amount,
+            paid: bid.paid,
+            expires_at: bid.expires_at,
+            placed_at: bid.placed_at,
+            last_bid_at: bid.last_bid_at,
+            sequence: bid.sequence,
+            pick: bid.pick,
+            deposit: bid.deposit,
+            fees_paid: bid.fees_paid,
+            payment: bid.payment,
+            origin: bid.origin,
+            memo: None,
+        }
+    }
+}
+
+/// A bid as stored, tagged with the version of its layout
+///
+/// It is stored as a Borsh enum, one byte for the version followed by the bid in that version's
+/// layout: 0 for [`BidV1`], 1 for [`BidV2`] and 2 for [`Bid`]. Bids of older versions are
+/// upgraded as they are read, and always written back as the latest, so fields can be added to
+/// `Bid` by adding a version rather than breaking the bids already stored.
+pub struct VersionedBid(Bid);
+
+impl VersionedBid {
+    const V1: u8 = 0;
+    const V2: u8 = 1;
+    const LATEST: u8 = 2;
+}
+
+impl From<Bid> for VersionedBid {
+    fn from(bid: Bid) -> Self {
+        Self(bid)
+    }
+}
+
+impl std::ops::Deref for VersionedBid {
+    type Target = Bid;
+
+    fn deref(&self) -> &Bid {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for VersionedBid {
+    fn deref_mut(&mut self) -> &mut Bid {
+        &mut self.0
+    }
+}
+
+impl BorshSerialize for VersionedBid {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        Self::LATEST.serialize(writer)?;
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for VersionedBid {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            Self::V1 => Ok(Self(BidV2::from(BidV1::deserialize_reader(reader)?).into())),
+            Self::V2 => Ok(Self(BidV2::deserialize_reader(reader)?.into())),
+            Self::LATEST => Ok(Self(Bid::deserialize_reader(reader)?)),
+            version => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unknown bid version {version}"),
+            )),
+        }
+    }
+}
+
+impl Bid {
+    /// The NEAR the contract holds for this bid
+    pub fn escrowed(&self) -> NearToken {
+        self.deposit.unwrap_or(self.amount)
+    }
+
+    pub fn view(&self, bidder: AccountId) -> BidView {
+        BidView {
+            bidder,
+            amount: self.amount,
+            paid: self.paid,
+            expires_at: self.expires_at.map(U64),
+            placed_at: self.placed_at.into(),
+            last_bid_at: self.last_bid_at.into(),
+            sequence: self.sequence.into(),
+            pick: self.pick.clone(),
+            deposit: self.deposit,
+            payment: self.payment.clone(),
+            origin: self.origin,
+            memo: self.memo.clone(),
+        }
+    }
+}
+
+#[near(serializers = [json])]
+pub struct BidView {
+    pub bidder: AccountId,
+    pub amount: NearToken,
+    pub paid: bool,
+    pub expires_at: Option<U64>,
+    pub placed_at: U64,
+    pub last_bid_at: U64,
+    pub sequence: U64,
+    pub pick: Option<TokenId>,
+    pub deposit: Option<NearToken>,
+    /// Token the bid was paid in, NEAR if `None`
+    pub payment: Option<PaymentToken>,
+    pub origin: BidOrigin,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// A bid as it was placed, raised or reduced, kept in the auction's bid history after it is
+/// replaced, withdrawn or refunded
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct BidRecord {
+    pub bidder: AccountId,
+    /// What the bid came to, the bidder's earlier records being replaced by this one
+    pub amount: NearToken,
+    pub placed_at: U64,
+}
+
+/// How an auction of an NFT ended
+#[near(serializers = [borsh, json])]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AuctionOutcome {
+    /// Sold to `buyer` for `price`, in the auction's payment token if it had one
+    Sold { buyer: AccountId, price: NearToken },
+    /// Ended without a winner
+    Unsold,
+    /// Cancelled before it ended, by the owner, a moderator or because the NFT was lost
+    Cancelled,
+}
+
+/// An auction an NFT went through on this contract
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct ProvenanceRecord {
+    pub seller: AccountId,
+    pub listed_at: U64,
+    pub ended_at: U64,
+    /// Set if the auction took bids in this token instead of NEAR
+    pub payment: Option<PaymentToken>,
+    pub outcome: AuctionOutcome,
+}
+
+/// NEAR escrowed by `offerer` for an NFT that is not in auction
+#[near(serializers = [borsh, json])]
+pub struct Offer {
+    pub offerer: AccountId,
+    pub amount: NearToken,
+    /// When the offer can no longer be accepted, it can be withdrawn at any time
+    pub expires_at: Option<u64>,
+    pub made_at: u64,
+    /// The owner's answer to the offer, if any
+    pub counter: Option<Counteroffer>,
+}
+
+/// A price the owner of an NFT is willing to sell at instead of the amount offered
+#[near(serializers = [borsh, json])]
+pub struct Counteroffer {
+    pub seller: AccountId,
+    pub price: NearToken,
+    /// When the counteroffer can no longer be accepted
+    pub expires_at: u64,
+}
+
+#[near(serializers = [json])]
+pub struct CounterofferView {
+    pub seller: AccountId,
+    pub price: NearToken,
+    pub expires_at: U64,
+}
+
+#[near(serializers = [json])]
+pub struct OfferView {
+    pub offer_id: U64,
+    pub offerer: AccountId,
+    pub amount: NearToken,
+    pub expires_at: Option<U64>,
+    pub made_at: U64,
+    pub counter: Option<CounterofferView>,
+}
+
+/// Proceeds of a sale held back from the seller until `release_at`
+#[near(serializers = [borsh])]
+pub struct HeldProceeds {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    pub seller: AccountId,
+    pub payments: Vec<HeldPayment>,
+    /// Buyers the NFT they paid for hasn't reached yet, the proceeds are held until it has
+    pub undelivered: Vec<AccountId>,
+    pub release_at: u64,
+    /// Until when the arbiter can freeze the proceeds
+    pub dispute_until: u64,
+    /// Whether the arbiter froze the proceeds until they resolve a dispute
+    pub frozen: bool,
+    /// What the sale pays beyond the seller's share once released
+    pub fee_policy: FeePolicyConfig,
+}
+
+impl HeldProceeds {
+    pub fn amount(&self) -> NearToken {
+        self.payments
+            .iter()
+            .fold(NearToken::from_yoctonear(0), |total, payment| {
+                total.saturating_add(payment.amount)
+            })
+    }
+}
+
+/// What a buyer paid towards held proceeds
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct HeldPayment {
+    pub buyer: AccountId,
+    /// Refunded in full if the sale is undone
+    pub amount: NearToken,
+    /// Taken out of `amount` for the protocol on top of the fee policy once released to the
+    /// seller, such as a quick-flip fee
+    pub protocol_fee: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct HeldProceedsView {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    pub seller: AccountId,
+    pub payments: Vec<HeldPayment>,
+    pub undelivered: Vec<AccountId>,
+    pub release_at: U64,
+    pub dispute_until: U64,
+    pub frozen: bool,
+}
+
+/// When trades between the same two accounts are charged a quick-flip fee, see
+/// [`WashTradeRules`]
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct WashTradeConfig {
+    /// Seconds back trades are counted
+    pub window: U64,
+    /// Trades of an NFT between the same two accounts within `window` that are charged no fee
+    pub free_trades: u32,
+    /// Fee in basis points of the price, charged on every trade beyond `free_trades`
+    pub fee_bps: u16,
+}
+
+impl WashTradeConfig {
+    pub fn rules(&self) -> WashTradeRules {
+        WashTradeRules {
+            window: time::saturating_secs_to_nanos(self.window.into()),
+            free_trades: self.free_trades,
+            fee_bps: self.fee_bps,
+        }
+    }
+}
+
+/// An account's share of every sale, in basis points of the price
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct FeeShare {
+    pub account_id: AccountId,
+    pub bps: u16,
+}
+
+/// What sales under a policy pay beyond the seller's share, see [`FeePolicy`]
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub struct FeePolicyConfig {
+    /// Basis points of the price added to the protocol fees, on top of any quick-flip fee
+    #[serde(default)]
+    pub marketplace_bps: u16,
+    /// Leave out the royalties of the NFT's collection, which are paid by default
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_royalties: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referral: Option<FeeShare>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charity: Option<FeeShare>,
+}
+
+impl FeePolicyConfig {
+    pub fn policy(&self) -> FeePolicy<AccountId> {
+        let share = |share: &FeeShare| (share.account_id.clone(), share.bps);
+        FeePolicy {
+            marketplace_bps: self.marketplace_bps,
+            royalties: !self.no_royalties,
+            referral: self.referral.as_ref().map(share),
+            charity: self.charity.as_ref().map(share),
+        }
+    }
+}
+
+/// Terms the owner grants the NFTs of a single contract, each falling back to the global setting
+/// if absent
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub struct CollectionPolicy {
+    /// Replaces the `fee_bps` of the wash-trade rules, which still have to be set for quick-flip
+    /// fees to be charged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_bps: Option<u16>,
+    /// Replaces the minimum `timespan` of listings, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_timespan: Option<U64>,
+}
+
+/// A past trade of an NFT, kept while it counts towards quick-flip fees
+#[near(serializers = [borsh])]
+pub struct TradeRecord {
+    pub seller: AccountId,
+    pub buyer: AccountId,
+    pub at: u64,
+}
+
+/// Track record of an account, for UIs to show how far sellers and bidders can be trusted
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub struct Reputation {
+    /// Auctions the account sold something in
+    pub auctions_completed: u32,
+    /// Winning bids the account paid for
+    pub bids_honored: u32,
+    /// Auctions the account cancelled
+    pub cancellations: u32,
+    /// Disputes resolved against the account
+    pub disputes_lost: u32,
+}
+
+/// Trading totals of an account, over auctions, sales and accepted offers
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub struct AccountStats {
+    /// NFTs and lots the account sold
+    pub sold: u32,
+    /// NFTs and lots the account bought
+    pub won: u32,
+    /// NEAR paid for everything the account bought
+    pub spent: NearToken,
+    /// NEAR the account got for everything it sold, after fees
+    pub earned: NearToken,
+    /// Marketplace and quick-flip fees charged on the account's sales and bid fees of its penny
+    /// bids
+    pub fees_paid: NearToken,
+}
+
+/// A payout the contract still owes an account because transferring it failed
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub enum OwedPayout {
+    Near {
+        amount: NearToken,
+    },
+    /// `amount` of the fungible token `ft`
+    Ft {
+        ft: AccountId,
+        amount: U128,
+    },
+    /// `amount` of `token_id` of the multi-token `mt`
+    Mt {
+        mt: AccountId,
+        token_id: TokenId,
+        amount: U128,
+    },
+}
+
+/// Payouts that failed for an account, and when they can be retried
+#[near(serializers = [borsh])]
+#[derive(Default)]
+pub struct OwedPayouts {
+    pub payouts: Vec<OwedPayout>,
+    /// Times the payouts were retried so far
+    pub retries: u32,
+    /// When the payouts can be retried next
+    pub retry_at: u64,
+}
+
+/// An NFT won in an auction, held until the winner claims it
+#[near(serializers = [borsh, json])]
+pub struct NftClaim {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    pub winner: AccountId,
+    /// Held proceeds released once the winner has the NFT, if handing it over at settlement
+    /// failed
+    pub escrow_id: Option<u64>,
+}
+
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NFTId(u64);
+
+impl NFTId {
+    pub fn new(nft: &AccountId, token_id: &TokenId) -> Self {
+        let mut hasher = DefaultHasher::new();
+        nft.hash(&mut hasher);
+        token_id.hash(&mut hasher);
+        NFTId(hasher.finish())
+    }
+}
+
+/// How bids are placed and who wins
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub enum AuctionKind {
+    /// The highest bid wins
+    #[default]
+    English,
+    /// Every bid, placed with `penny_bid`, costs a non-refundable `bid_fee`, raises the price by
+    /// `increment` and resets the clock to `countdown` seconds if less was left. The last
+    /// bidder wins at the final price.
+    Penny {
+        bid_fee: NearToken,
+        increment: NearToken,
+        countdown: u64,
+    },
+    /// Sells the NFT together with the others added to its lot, see [`LotParams`]. The highest
+    /// bids win one NFT each and every winner pays the lowest winning bid.
+    Uniform,
+    /// Sells any one NFT out of the set made of this NFT and the others added to its lot, see
+    /// [`LotParams`]. The highest bid wins the NFT it picked with `pick_nft`, or this NFT if it
+    /// picked none.
+    Collection,
+    /// Sells this NFT and the others added to its lot, see [`LotParams`], one at a time with
+    /// `buy` instead of taking bids. The first costs `base_price` and every sale raises the price
+    /// by `slope`.
+    BondingCurve {
+        base_price: NearToken,
+        slope: NearToken,
+    },
+}
+
+/// An [`AuctionKind`] without its parameters, to turn the kind on or off
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AuctionKindName {
+    English,
+    Penny,
+    Uniform,
+    Collection,
+    BondingCurve,
+}
+
+impl AuctionKindName {
+    pub const ALL: [Self; 5] = [
+        Self::English,
+        Self::Penny,
+        Self::Uniform,
+        Self::Collection,
+        Self::BondingCurve,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::Penny => "Penny",
+            Self::Uniform => "Uniform",
+            Self::Collection => "Collection",
+            Self::BondingCurve => "BondingCurve",
+        }
+    }
+}
+
+impl AuctionKind {
+    pub fn name(&self) -> AuctionKindName {
+        match self {
+            Self::English => AuctionKindName::English,
+            Self::Penny { .. } => AuctionKindName::Penny,
+            Self::Uniform => AuctionKindName::Uniform,
+            Self::Collection => AuctionKindName::Collection,
+            Self::BondingCurve { .. } => AuctionKindName::BondingCurve,
+        }
+    }
+
+    /// The rules bids on this kind of auction follow
+    pub fn engine<A: Clone>(&self) -> &'static dyn AuctionEngine<A> {
+        match self {
+            // The NFT a collection auction's winner gets is picked apart from the bidding
+            Self::English | Self::Collection => &engine::English,
+            Self::Penny { .. } => &engine::Penny,
+            Self::Uniform => &engine::Uniform,
+            Self::BondingCurve { .. } => &engine::BondingCurve,
+        }
+    }
+
+    /// Whether other NFTs can be added to the auction's lot
+    pub fn has_lot(&self) -> bool {
+        matches!(
+            self,
+            Self::Uniform | Self::Collection | Self::BondingCurve { .. }
+        )
+    }
+
+    pub fn curve(&self) -> Option<Curve> {
+        match self {
+            Self::BondingCurve { base_price, slope } => Some(Curve {
+                base_price: base_price.as_yoctonear(),
+                slope: slope.as_yoctonear(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn penny_rules(&self) -> Option<PennyRules> {
+        match self {
+            Self::English | Self::Uniform | Self::Collection | Self::BondingCurve { .. } => None,
+            Self::Penny {
+                bid_fee,
+                increment,
+                countdown,
+            } => Some(PennyRules {
+                bid_fee: bid_fee.as_yoctonear(),
+                increment: increment.as_yoctonear(),
+                countdown: time::saturating_secs_to_nanos(*countdown),
+            }),
+        }
+    }
+}
+
+/// An auction on the trending board, with its activity as of its last bid
+#[near(serializers = [borsh])]
+pub struct TrendingEntry {
+    pub nft_id: NFTId,
+    pub score: u128,
+    pub at: u64,
+}
+
+impl TrendingEntry {
+    pub fn new(nft_id: NFTId, activity: Activity) -> Self {
+        Self {
+            nft_id,
+            score: activity.score,
+            at: activity.at,
+        }
+    }
+
+    pub fn activity(&self) -> Activity {
+        Activity {
+            score: self.score,
+            at: self.at,
+        }
+    }
+}
+
+#[near(serializers = [json])]
+pub struct TrendingView {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    /// Number of bids placed or raised so far
+    pub bids_placed: U64,
+    /// Decayed to the current block
+    pub activity: U128,
+}
+
+/// Full record of an auction, as dumped by `export_auctions` for migrating to a new contract
+#[near(serializers = [json])]
+pub struct AuctionExport {
+    pub nft_id: NFTId,
+    pub auction: AuctionView,
+    pub next_bid_sequence: U64,
+    pub storage_usage: U64,
+    pub activity: U128,
+    pub activity_at: U64,
+}
+
+/// What the deployment supports, for marketplaces and aggregators to integrate with it
+#[near(serializers = [json])]
+pub struct MarketplaceMetadata {
+    /// Version of the contract crate
+    pub version: String,
+    /// NEPs the contract implements or accepts tokens of
+    pub standards: Vec<String>,
+    /// Receiver methods tokens can be listed or bid through: `nft_on_approve` for NFTs, with
+    /// `AuctionParams` or `LotParams` as `msg`, `ft_on_transfer` for fungible token lots and bids,
+    /// and `mt_on_transfer` for multi-token bids
+    pub receivers: Vec<String>,
+    /// Names of the `AuctionKind` variants listings can currently use
+    pub auction_kinds: Vec<String>,
+    /// Tokens bids can be placed in: `near`, then the allowed fungible tokens by contract and
+    /// multi-tokens as `contract:token_id`
+    pub payment_tokens: Vec<String>,
+}
+
+/// Length of the periods `volume_history` buckets sales by
+#[near(serializers = [json])]
+pub enum VolumePeriod {
+    Daily,
+    Weekly,
+}
+
+#[near(serializers = [json])]
+pub struct VolumeBucketView {
+    /// Start of the period
+    pub start: U64,
+    pub volume: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct LeaderboardEntryView {
+    pub account_id: AccountId,
+    /// NEAR paid for all auctions the account won
+    pub volume: NearToken,
+}
+
+/// Lets bidders escrow only a deposit, see [`PayOnWinRules`]
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct PayOnWin {
+    /// Share of every bid, in basis points, escrowed when it is placed
+    pub deposit_bps: u16,
+    /// Seconds the winner has to pay the rest of their bid with `complete_purchase`, before
+    /// the next highest bid wins instead
+    pub payment_window: u64,
+}
+
+impl PayOnWin {
+    pub fn rules(&self) -> PayOnWinRules {
+        PayOnWinRules {
+            deposit_bps: self.deposit_bps,
+            payment_window: time::saturating_secs_to_nanos(self.payment_window),
+        }
+    }
+}
+
+/// A token an auction takes its bids in, instead of NEAR
+#[near(serializers = [borsh, json])]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PaymentToken {
+    /// A NEP-141 fungible token, such as wETH bridged from Ethereum
+    Ft { contract: AccountId },
+    /// `token_id` of the NEP-245 multi-token `contract`
+    Mt {
+        contract: AccountId,
+        token_id: TokenId,
+    },
+}
+
+/// Why the token a listing takes bids in was rejected
+///
+/// Its message is the rejection serialized to JSON, so clients can tell these apart from other
+/// listing violations.
+#[near(serializers = [json])]
+#[serde(tag = "reason", rename_all = "snake_case")]
+#[derive(Clone)]
+pub enum PaymentTokenRejection {
+    /// The token is not on the allowlist, which has the `accepted` tokens
+    Unlisted {
+        token: PaymentToken,
+        accepted: Vec<PaymentToken>,
+    },
+    /// The listing's minimum bid is not a multiple of the token's `min_bid_unit`
+    OffUnit {
+        token: PaymentToken,
+        min_bid_unit: U128,
+    },
+}
+
+impl fmt::Display for PaymentTokenRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Amounts of an auction taking an allowed payment token, in whole tokens
+#[near(serializers = [json])]
+pub struct DisplayAmounts {
+    pub symbol: String,
+    pub h_bid: String,
+    pub minimum_bid: String,
+}
+
+/// The winner of a pay-on-win auction, who has until `due` to pay the rest of their bid
+#[near(serializers = [borsh])]
+pub struct PendingPayment {
+    pub winner: AccountId,
+    pub due: u64,
+}
+
+/// Where an auction is in its life
+#[near(serializers = [json])]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuctionStatus {
+    /// Taking bids
+    Open,
+    /// Over and waiting for `end_auction`
+    Expired,
+    /// Over and waiting for the winner of a pay-on-win auction to pay the rest of their bid
+    AwaitingPayment,
+}
+
+/// What an auction takes its bids in
+#[near(serializers = [json])]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone)]
+pub enum PaidIn {
+    Near,
+    Token(PaymentToken),
+}
+
+/// Narrows down the auctions a list view returns, every condition that is set has to hold
+#[near(serializers = [json])]
+#[derive(Clone, Default)]
+pub struct AuctionFilter {
+    /// Lowest `h_bid`, in the unit the auction takes its bids in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_bid: Option<NearToken>,
+    /// Highest `h_bid`, in the unit the auction takes its bids in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bid: Option<NearToken>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<AuctionStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paid_in: Option<PaidIn>,
+}
+
+#[near(serializers = [json])]
+pub struct PendingPaymentView {
+    pub winner: AccountId,
+    pub due: U64,
+}
+
+/// What an auction sells
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub enum AuctionedAsset {
+    /// The NFT `token_id` of the contract `nft`, along with the NFTs of its lot
+    #[default]
+    Nft,
+    /// `amount` of the NEP-141 token `nft`, listed under the lot id `token_id`
+    Ft { amount: U128 },
+    /// The NFT `token_id` of the contract `nft`, minted with the auction's `mint_metadata`
+    /// straight to the winner once the auction ends, `deposit` paying for it
+    LazyMint {
+        deposit: NearToken,
+        /// Set while the NFT is being minted to the winner
+        minting: bool,
+    },
+}
+
+/// What happens to the NFTs of an auction that ends without a winner
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Default)]
+pub enum UnsoldAction {
+    /// The NFTs go back to the owner
+    #[default]
+    ReturnToOwner,
+    /// Once `after` listings of this NFT in a row end without a winner, the NFTs are deposited
+    /// into `fractionalizer` with `nft_transfer_call` and a [`FractionalizeMsg`], so the owner
+    /// gets shares instead. Earlier listings return the NFTs to the owner.
+    Fractionalize {
+        fractionalizer: AccountId,
+        after: u32,
+    },
+}
+
+/// The `msg` passed to a fractionalizer's `nft_on_transfer`
+#[near(serializers = [json])]
+pub struct FractionalizeMsg {
+    /// Account that receives the shares of the NFT
+    pub shares_receiver_id: AccountId,
+}
+
+/// A page of auctions filtered out of an index, see `auctions_by_nft_contract`
+#[near(serializers = [json])]
+pub struct AuctionPage {
+    pub auctions: Vec<AuctionView>,
+    /// `from_index` of the next page, `None` once there are no more auctions
+    pub next_index: Option<u32>,
+}
+
+/// An ongoing auction, as returned by `get_auction`
+#[near(serializers = [json])]
+pub struct AuctionView {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    /// Picks the auction in place of `nft` and `token_id` in the `_by_id` methods
+    pub nft_id: NFTId,
+    pub status: AuctionStatus,
+    pub owner: AccountId,
+    /// Highest bid so far, or the minimum bid if there are no bids
+    ///
+    /// In uniform auctions, the amount a new bid has to exceed to win one of the NFTs.
+    pub h_bid: NearToken,
+    pub minimum_bid: NearToken,
+    /// NFTs sold along with `token_id` in a uniform auction or bonding-curve sale, or instead of
+    /// it in a collection auction
+    pub lot: Vec<TokenId>,
+    /// NFTs a bonding-curve sale has sold so far, in order from `token_id` on
+    pub sold: u32,
+    pub expiry: U64,
+    /// `expiry` as an ISO 8601 date and time in UTC, to the second
+    pub expiry_iso: String,
+    pub bid_count: u32,
+    pub push_nft: bool,
+    /// Nanoseconds an account has to wait between its bids
+    pub bid_interval: U64,
+    pub kind: AuctionKind,
+    /// Bid fees collected so far, paid to the owner at settlement
+    pub fees_accrued: NearToken,
+    /// NEAR escrowed by the auction's outstanding bids
+    pub bids_escrowed: NearToken,
+    pub on_unsold: UnsoldAction,
+    pub asset: AuctionedAsset,
+    /// Posted by the owner, returned at settlement unless they cancel once there are bids
+    pub stake: NearToken,
+    /// Nanoseconds after settlement during which the proceeds can be disputed
+    pub dispute_window: U64,
+    /// Nanoseconds after settlement the proceeds are held for
+    pub proceeds_delay: U64,
+    /// Nanoseconds after expiry before the auction can be settled
+    pub settlement_delay: U64,
+    /// When the auction can be settled, `settlement_delay` after its expiry
+    pub settles_at: U64,
+    /// Approval the NFT was listed with, if it was listed through `nft_approve`
+    pub approval_id: Option<U64>,
+    /// Whether the NFT stays with the owner until settlement, which transfers it to the winner
+    /// with `approval_id`
+    pub escrowless: bool,
+    /// When the auction was started
+    pub listed_at: U64,
+    /// Nanoseconds since the auction was started
+    pub listing_age: U64,
+    pub pay_on_win: Option<PayOnWin>,
+    /// Set once a pay-on-win auction is over, until its winner paid in full
+    pub pending_payment: Option<PendingPaymentView>,
+    /// Set if bids are paid in this token, amounts being in its smallest unit instead of NEAR
+    pub payment: Option<PaymentToken>,
+    /// Metadata a lazily minted NFT will be minted with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint_metadata: Option<TokenMetadata>,
+    /// What the sale pays beyond the seller's share
+    pub fee_policy: FeePolicyConfig,
+    /// Largest bid the auction takes, if its seller capped bids, see `max_bid` for the global cap
+    pub max_bid: Option<NearToken>,
+    /// Amounts in whole tokens, if bids are paid in an allowed payment token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayAmounts>,
+}
+
+/// A listing waiting for its NFT to arrive in escrow
+#[near(serializers = [json])]
+pub struct PendingListing {
+    pub owner_id: AccountId,
+    pub expiry: U64,
+    pub params: AuctionParams,
+    pub stake: NearToken,
+    /// Approval the NFT was transferred with, if it was listed through `nft_approve`
+    pub approval_id: Option<U64>,
+}
+
+/// Adds an NFT to the lot of an ongoing uniform or collection auction by the same owner, passed
+/// as `msg` to
+/// `nft_approve`
+#[near(serializers = [json])]
+pub struct LotParams {
+    /// `token_id` the auction was started with
+    pub lot: TokenId,
+}
+
+impl LotParams {
+    pub fn to_msg(&self) -> String {
+        serde_json::to_string(self).expect("lot parameters are serializable")
+    }
+}
+
+/// Parameters of an auction, passed as `msg` to `nft_approve`, or to `ft_transfer_call` to
+/// auction the tokens transferred
+///
+/// ```
+/// use nftauction::AuctionParams;
+/// use near_sdk::NearToken;
+///
+/// let msg = AuctionParams::builder(100, NearToken::from_near(1))
+///     .push_nft(true)
+///     .build()
+///     .to_msg();
+/// assert_eq!(
+///     msg,
+///     r#"{"timespan":100,"minimum_bid":"1000000000000000000000000","push_nft":true,"kind":"English"}"#
+/// );
+/// ```
+#[near(serializers = [json])]
+pub struct AuctionParams {
+    /// Seconds the auction lasts from when it is listed
+    pub timespan: u64,
+    pub minimum_bid: NearToken,
+    /// Hand the NFT to the winner at settlement, by default the winner has to `claim_nft` it
+    #[serde(default)]
+    pub push_nft: bool,
+    /// Seconds an account has to wait between its bids
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid_interval: Option<u64>,
+    #[serde(default)]
+    pub kind: AuctionKind,
+    /// What to do with the NFTs if nobody wins, by default they go back to the owner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_unsold: Option<UnsoldAction>,
+    /// Seconds after settlement during which the arbiter can freeze the proceeds over a dispute,
+    /// the proceeds are held until then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispute_window: Option<u64>,
+    /// Seconds after settlement the proceeds are held for before the seller can withdraw them,
+    /// for high-value sales. Any dispute window still applies within it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proceeds_delay: Option<u64>,
+    /// Seconds after expiry before the auction can be settled, at most a week, leaving a window
+    /// to review its bids before any funds move
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_delay: Option<u64>,
+    /// Leave the NFT with the owner and only keep the approval, which is used to transfer it to
+    /// the winner at settlement. The auction is cancelled if the approval was revoked by then.
+    /// Only for auctions of a single NFT, listed with `nft_approve`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub escrowless: bool,
+    /// Have bidders escrow only a deposit and the winner pay the rest once the auction is over.
+    /// Only for english auctions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pay_on_win: Option<PayOnWin>,
+    /// Take bids in this token instead of NEAR, placed with `ft_transfer_call` or
+    /// `mt_transfer_call`. Only for english auctions whose winner claims the NFT, the proceeds
+    /// being paid right away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_token: Option<PaymentToken>,
+    /// Name of the fee policy the sale is settled under, see `fee_policies`. By default only
+    /// royalties are paid. Not for auctions taking bids in tokens, which pay the token's fee.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_policy: Option<String>,
+    /// Largest bid the auction takes, in the token bids are paid in, replacing the global
+    /// `max_bid` of auctions taking NEAR. Has to exceed `minimum_bid`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bid: Option<NearToken>,
+}
+
+impl AuctionParams {
+    pub fn builder(timespan: u64, minimum_bid: NearToken) -> AuctionParamsBuilder {
+        AuctionParamsBuilder(AuctionParams {
+            timespan,
+            minimum_bid,
+            push_nft: false,
+            bid_interval: None,
+            kind: AuctionKind::English,
+            on_unsold: None,
+            dispute_window: None,
+            proceeds_delay: None,
+            settlement_delay: None,
+            escrowless: false,
+            pay_on_win: None,
+            payment_token: None,
+            fee_policy: None,
+            max_bid: None,
+        })
+    }
+
+    /// The `msg` to pass to `nft_approve` to start an auction with these parameters
+    pub fn to_msg(&self) -> String {
+        serde_json::to_string(self).expect("auction parameters are serializable")
+    }
+}
+
+/// Builds [`AuctionParams`], leaving optional parameters at their defaults unless set
+pub struct AuctionParamsBuilder(AuctionParams);
+
+impl AuctionParamsBuilder {
+    pub fn push_nft(mut self, push_nft: bool) -> Self {
+        self.0.push_nft = push_nft;
+        self
+    }
+
+    pub fn bid_interval(mut self, bid_interval: u64) -> Self {
+        self.0.bid_interval = Some(bid_interval);
+        self
+    }
+
+    pub fn kind(mut self, kind: AuctionKind) -> Self {
+        self.0.kind = kind;
+        self
+    }
+
+    pub fn on_unsold(mut self, on_unsold: UnsoldAction) -> Self {
+        self.0.on_unsold = Some(on_unsold);
+        self
+    }
+
+    pub fn dispute_window(mut self, dispute_window: u64) -> Self {
+        self.0.dispute_window = Some(dispute_window);
+        self
+    }
+
+    pub fn proceeds_delay(mut self, proceeds_delay: u64) -> Self {
+        self.0.proceeds_delay = Some(proceeds_delay);
+        self
+    }
+
+    pub fn settlement_delay(mut self, settlement_delay: u64) -> Self {
+        self.0.settlement_delay = Some(settlement_delay);
+        self
+    }
+
+    pub fn escrowless(mut self, escrowless: bool) -> Self {
+        self.0.escrowless = escrowless;
+        self
+    }
+
+    pub fn pay_on_win(mut self, pay_on_win: PayOnWin) -> Self {
+        self.0.pay_on_win = Some(pay_on_win);
+        self
+    }
+
+    pub fn payment_token(mut self, payment_token: PaymentToken) -> Self {
+        self.0.payment_token = Some(payment_token);
+        self
+    }
+
+    pub fn fee_policy(mut self, fee_policy: String) -> Self {
+        self.0.fee_policy = Some(fee_policy);
+        self
+    }
+
+    pub fn max_bid(mut self, max_bid: NearToken) -> Self {
+        self.0.max_bid = Some(max_bid);
+        self
+    }
+
+    pub fn build(self) -> AuctionParams {
+        self.0
+    }
+}
+
+#[near(serializers = [json])]
+pub struct ListingValidation {
+    /// `None` if the message could not be parsed
+    pub params: Option<AuctionParams>,
+    /// Every listing rule the message violates, empty if the listing would be accepted
+    pub violations: Vec<String>,
+    /// Set if the token the listing takes bids in is rejected, which is among the violations too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_token_rejection: Option<PaymentTokenRejection>,
+}
+
+#[near(serializers = [json])]
+pub struct BidRequest {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    pub amount: NearToken,
+    #[serde(default)]
+    pub expires_at: Option<U64>,
+}
+
+#[near(serializers = [json])]
+pub struct BidResult {
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    /// Why the bid was not placed, `None` if it was
+    pub error: Option<String>,
+}
+
+/// What `fulfill_bid_intent` returns to the solver and sends the intent framework
+#[near(serializers = [json])]
+#[derive(Clone)]
+pub struct IntentReceipt {
+    /// Hash of the NEP-413 payload that was signed
+    pub intent_hash: Base58CryptoHash,
+    pub bidder: AccountId,
+    pub nft: AccountId,
+    pub token_id: TokenId,
+    pub amount: NearToken,
+    pub solver_fee: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct BidSimulation {
+    pub accepted: bool,
+    /// Why the bid would be rejected
+    pub reason: Option<String>,
+    /// Smallest bid accepted after this one, or instead of it if it would be rejected
+    pub min_next_bid: NearToken,
+}
+
+/// The deposit to attach to a bid or purchase
+#[near(serializers = [json])]
+pub struct RequiredDepositView {
+    /// Escrowed for the bid, or paid for the NFT bought
+    pub escrow: NearToken,
+    /// Non-refundable fee, such as the bid fee of a penny auction and any fee burned per bid
+    pub fee: NearToken,
+    /// Storage of the bid's memo, paid by the bidder
+    pub storage: NearToken,
+    pub total: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct ComplianceView {
+    pub registry: Option<AccountId>,
+    /// Bids above this are checked with `registry`
+    pub threshold: Option<NearToken>,
+}
+
+#[near(serializers = [json])]
+pub struct StakingView {
+    pub stake_token: Option<AccountId>,
+    /// Share of every protocol fee that goes to the stakers
+    pub share_bps: u16,
+    pub staked: U128,
+    /// Stakers' share of the fees collected since the last distribution
+    pub undistributed: NearToken,
+    /// `distribute_rewards` can be called from then on
+    pub epoch_ends: U64,
+}
+
+#[near(serializers = [json])]
+pub struct StakerView {
+    pub staked: U128,
+    /// Rewards `claim_rewards` sends
+    pub unclaimed: NearToken,
+    /// The stake can't be unstaked before then
+    pub unlocks_at: U64,
+}
+
+#[near(serializers = [json])]
+pub struct AdminsView {
+    pub admins: Vec<AccountId>,
+    /// Number of admins that have to confirm an action
+    pub threshold: u32,
+}
+
+#[near(serializers = [json])]
+pub struct CountsView {
+    /// Ongoing auctions, over or not
+    pub auctions: u32,
+    /// Bids across all auctions that are yet to be refunded or paid out
+    pub bids_outstanding: U64,
+    /// Pay-on-win auctions that are over and waiting for their winner to pay, before they can
+    /// settle
+    pub payments_pending: u32,
+    /// Accounts with a balance to `withdraw`
+    pub balance_holders: u32,
+}
+
+#[near(serializers = [json])]
+pub struct BidCapacityView {
+    /// Auctions the account has an outstanding bid in
+    pub active: u32,
+    /// Auctions the account can still bid in, unlimited if absent
+    pub remaining: Option<u32>,
+}
+
+#[near(serializers = [json])]
+pub struct ListingCapacityView {
+    /// Ongoing auctions of the account
+    pub active: u32,
+    /// Auctions the account can still start, unlimited if absent
+    pub remaining: Option<u32>,
+}
+
+#[near(serializers = [json])]
+pub struct StorageUsageView {
+    pub bytes: U64,
+    pub cost: NearToken,
+}
+
+impl From<StorageUsage> for StorageUsageView {
+    fn from(bytes: StorageUsage) -> Self {
+        Self {
+            bytes: bytes.into(),
+            cost: env::storage_byte_cost().saturating_mul(bytes.into()),
+        }
+    }
+}
+
+#[near(serializers = [json])]
+pub struct StorageUsageTotalsView {
+    /// Storage taken up by all ongoing auctions
+    pub auctions: StorageUsageView,
+    /// Storage taken up by the whole contract account, including its code
+    pub contract: StorageUsageView,
+}
+
+#[near(serializers = [json])]
+pub struct ReconciliationView {
+    /// NEAR escrowed by outstanding bids, refunded or paid to the sellers once auctions end
+    pub bids_escrowed: NearToken,
+    /// NEAR escrowed by standing offers
+    pub offers_escrowed: NearToken,
+    /// Listing stakes of ongoing auctions, returned or shared among the bidders once they end
+    pub stakes_escrowed: NearToken,
+    /// Bid fees collected by ongoing auctions, paid out once they end
+    pub bid_fees_escrowed: NearToken,
+    /// Sale proceeds held until they are released or refunded
+    pub proceeds_held: NearToken,
+    /// NEAR accounts can `withdraw`
+    pub balances: NearToken,
+    /// NEAR of failed payouts, paid again by `retry_payout`
+    pub owed: NearToken,
+    /// Quick-flip fees the contract account can withdraw
+    pub protocol_fees: NearToken,
+    /// Bid fees waiting to be burned
+    pub burn_pending: NearToken,
+    /// NEAR held for the stakers, claimed with `claim_rewards`
+    pub staking_rewards: NearToken,
+    /// Deposits of lazy-mint auctions, spent minting the NFT to the winner or returned to the
+    /// seller
+    pub mint_deposits_held: NearToken,
+    /// Everything above, which `liquid` has to cover
+    pub liabilities: NearToken,
+    /// NEAR locked to pay for the contract's storage
+    pub storage_staked: NearToken,
+    /// NEAR of the contract that is not locked for storage, which has to cover what it owes
+    pub liquid: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct AccountingTotalsView {
+    /// NEAR paid for everything sold so far, before fees
+    pub volume: NearToken,
+    /// Bid fees collected by all auctions, past and ongoing
+    pub bid_fees: NearToken,
+    /// Quick-flip fees collected so far, withdrawn or not
+    pub protocol_fees: NearToken,
+    /// Deposits the contract attached to NFT and FT calls out of its own balance
+    pub nft_deposits_paid: NearToken,
+    /// Storage deposits paid out of the protocol fees to register payees and the contract itself
+    /// with fungible tokens
+    pub ft_storage_paid: NearToken,
+    /// NEAR escrowed by the outstanding bids of all auctions
+    pub bids_escrowed: NearToken,
+    /// NEAR accounts can `withdraw`
+    pub balances: NearToken,
+    /// Bid fees charged to be burned, burned or not
+    pub burned: NearToken,
+}
@@ -0,0 +1,138 @@
+//! Deploying new code to the contract account and migrating the state to it
+//!
+//! `upgrade` stores the version of the state layout under [`STATE_VERSION_KEY`] before deploying,
+//! so `migrate` knows which layout it reads. The first deployed code stored none, a missing
+//! version is its layout, [`BaselineContract`]. Changing the layout of the state takes a new
+//! version whose previous layout is kept here and converted by `migrate`.
+
+use near_nft_auction_core::listing;
+use near_sdk::{
+    env,
+    json_types::{Base58CryptoHash, Base64VecU8},
+    near, require,
+    store::IterableMap,
+    AccountId, CryptoHash, Gas, GasWeight, NearToken, Promise,
+};
+
+use crate::*;
+
+/// Storage key of the layout version of the state, see [`STATE_VERSION`]
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Layout version of the state as this code stores it
+const STATE_VERSION: u8 = 1;
+
+/// Prefix the first deployed code stored its auctions under
+pub(crate) const LEGACY_AUCTIONS_PREFIX: &[u8] = b"a";
+
+/// The state of the first deployed code
+#[near(serializers = [borsh])]
+pub(crate) struct BaselineContract {
+    pub auctions: IterableMap<NFTId, LegacyAuction>,
+}
+
+/// An auction listed by the first deployed code
+///
+/// That code only kept the hash of the NFT, so `end_legacy_auction` is told its contract and
+/// token id. It took no bids either, `make_bid` accepting no deposit, so `bids` is empty.
+#[near(serializers = [borsh])]
+pub struct LegacyAuction {
+    pub owner: AccountId,
+    pub bids: IterableMap<AccountId, LegacyBid>,
+    pub h_bid: NearToken,
+    pub expiry: u64,
+}
+
+#[near(serializers = [borsh])]
+pub struct LegacyBid {
+    pub amount: NearToken,
+    pub paid: bool,
+}
+
+impl From<BaselineContract> for Contract {
+    fn from(baseline: BaselineContract) -> Self {
+        Self {
+            legacy_auctions: baseline.auctions,
+            ..Self::default()
+        }
+    }
+}
+
+#[near]
+impl Contract {
+    /// Deploys `code` to the contract account and calls its `migrate`
+    ///
+    /// Callable by the owner while there are no admins. Once there are, anyone can deploy code
+    /// whose hash the admins approved with an `Upgrade` action.
+    pub fn upgrade(&mut self, code: Base64VecU8) -> Promise {
+        // Validations
+        let code: Vec<u8> = code.into();
+        let code_hash: CryptoHash = env::sha256_array(&code);
+        if self.admins.is_empty() {
+            self.internal_assert_owner();
+        } else {
+            require!(
+                self.approved_code_hash == Some(code_hash),
+                "this code was not approved by the admins"
+            );
+        }
+
+        // Operations
+        self.approved_code_hash = None;
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+        AuctionEvent::Upgraded {
+            code_hash: code_hash.into(),
+        }
+        .emit();
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call_weight(
+                "migrate".into(),
+                Vec::new(),
+                NearToken::from_yoctonear(0),
+                Gas::from_gas(0),
+                GasWeight(1),
+            )
+    }
+
+    /// Reads the state left by the previously deployed code, called by `upgrade`
+    ///
+    /// State of an earlier layout version is converted to the current one.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = match env::storage_read(STATE_VERSION_KEY).as_deref() {
+            None => env::state_read::<BaselineContract>().map(Contract::from),
+            Some([STATE_VERSION]) => env::state_read(),
+            Some(version) => env::panic_str(&format!("unknown state version {version:?}")),
+        };
+        let state = state.unwrap_or_else(|| env::panic_str("there is no state to migrate"));
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+        state
+    }
+
+    /// Hash of the code the admins approved to be deployed with `upgrade`, if any
+    pub fn approved_code_hash(&self) -> Option<Base58CryptoHash> {
+        self.approved_code_hash.map(Base58CryptoHash::from)
+    }
+
+    /// Returns the NFT of an expired auction listed by the first deployed code to its owner
+    pub fn end_legacy_auction(&mut self, nft: AccountId, token_id: TokenId) -> Promise {
+        // Validations
+        let nft_id = NFTId::new(&nft, &token_id);
+        let Some(auction) = self.legacy_auctions.get(&nft_id) else {
+            env::panic_str("this nft is not in a legacy auction")
+        };
+        require!(
+            listing::is_expired(env::block_timestamp(), auction.expiry),
+            "cannot end, auction is still ongoing"
+        );
+
+        // Operations
+        let auction = self
+            .legacy_auctions
+            .remove(&nft_id)
+            .expect("auction exists");
+        self.internal_nft_transfer(nft, auction.owner, token_id, "Auction ended")
+    }
+}
@@ -0,0 +1,199 @@
+//! Runs a whole auction on a sandbox node, from listing an NFT to the winner owning it
+//!
+//! The NFT contract is the `mock-nft` workspace crate, which behaves like the example NFT contract
+//! the README deploys unless told otherwise.
+
+use near_workspaces::{network::Sandbox, types::NearToken, Account, Contract, Worker};
+use serde_json::json;
+
+/// Auction length in seconds, bids have to land before it runs out
+const TIMESPAN: u64 = 60;
+
+/// Gas a call can burn at most, well above what any call here burns
+const GAS_ALLOWANCE: NearToken = NearToken::from_millinear(100);
+
+#[tokio::test]
+async fn auction_runs_from_listing_to_claim() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./").await?)
+        .await?;
+    let seller = sandbox.dev_create_account().await?;
+    let alice = sandbox.dev_create_account().await?;
+    let bob = sandbox.dev_create_account().await?;
+    let nft = deploy_nft(&sandbox, &seller).await?;
+    let token_id = "first";
+
+    // Approve
+    list(&contract, &nft, &seller, token_id).await?;
+    assert_eq!(owner_of(&nft, token_id).await?, *contract.id());
+    let auction = contract
+        .view("get_auction")
+        .args_json(json!({ "nft": nft.id(), "token_id": token_id }))
+        .await?
+        .json::<serde_json::Value>()?;
+    assert_eq!(auction["owner"], json!(seller.id()));
+
+    // Bid, then outbid, outbid bids being refunded at settlement
+    let alice_before = alice.view_account().await?.balance;
+    make_bid(&contract, &alice, &nft, token_id, NearToken::from_near(1)).await?;
+    make_bid(&contract, &bob, &nft, token_id, NearToken::from_near(2)).await?;
+
+    // Expire
+    let outcome = seller
+        .call(contract.id(), "end_auction")
+        .args_json(json!({ "nft": nft.id(), "token_id": token_id }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure(), "ended a running auction");
+    sandbox.fast_forward(TIMESPAN * 2).await?;
+
+    // Settle
+    seller
+        .call(contract.id(), "end_auction")
+        .args_json(json!({ "nft": nft.id(), "token_id": token_id }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let alice_after = alice.view_account().await?.balance;
+    assert!(
+        alice_after.saturating_add(GAS_ALLOWANCE) > alice_before,
+        "outbid bid was not refunded: {alice_before} before, {alice_after} after"
+    );
+    bob.call(contract.id(), "claim_nft")
+        .args_json(json!({ "nft": nft.id(), "token_id": token_id }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(owner_of(&nft, token_id).await?, *bob.id());
+
+    let proceeds = contract
+        .view("balance_of")
+        .args_json(json!({ "account_id": seller.id() }))
+        .await?
+        .json::<NearToken>()?;
+    assert_eq!(proceeds, NearToken::from_near(2));
+    let seller_before = seller.view_account().await?.balance;
+    seller
+        .call(contract.id(), "withdraw")
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let seller_after = seller.view_account().await?.balance;
+    assert!(
+        seller_after.saturating_add(GAS_ALLOWANCE) > seller_before.saturating_add(proceeds),
+        "proceeds were not withdrawn: {seller_before} before, {seller_after} after"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn nft_that_never_arrives_is_not_listed() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./").await?)
+        .await?;
+    let seller = sandbox.dev_create_account().await?;
+    let nft = deploy_nft(&sandbox, &seller).await?;
+    let token_id = "first";
+    nft.call("set_behavior")
+        .args_json(json!({ "behavior": { "transfers": "Ignore" } }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    list(&contract, &nft, &seller, token_id).await?;
+    assert_eq!(owner_of(&nft, token_id).await?, *seller.id());
+    let auctions = contract.view("counts").await?.json::<serde_json::Value>()?;
+    assert_eq!(auctions["auctions"], json!(0));
+    Ok(())
+}
+
+/// Deploys the mock NFT contract with `token_id` "first" minted to `owner`
+async fn deploy_nft(
+    sandbox: &Worker<Sandbox>,
+    owner: &Account,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    let nft = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./mock-nft").await?)
+        .await?;
+    nft.call("new_default_meta")
+        .args_json(json!({ "owner_id": owner.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    owner
+        .call(nft.id(), "nft_mint")
+        .args_json(json!({
+            "token_id": "first",
+            "token_owner_id": owner.id(),
+            "token_metadata": {},
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(nft)
+}
+
+/// Lists `token_id` by approving the contract for it
+async fn list(
+    contract: &Contract,
+    nft: &Contract,
+    owner: &Account,
+    token_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let msg = json!({ "timespan": TIMESPAN, "minimum_bid": NearToken::from_near(1) });
+    owner
+        .call(nft.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": token_id,
+            "account_id": contract.id(),
+            "msg": msg.to_string(),
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn make_bid(
+    contract: &Contract,
+    bidder: &Account,
+    nft: &Contract,
+    token_id: &str,
+    amount: NearToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    bidder
+        .call(contract.id(), "make_bid")
+        .args_json(json!({ "nft": nft.id(), "token_id": token_id, "amount": amount }))
+        .deposit(amount)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+/// The account owning `token_id` according to the NFT contract
+async fn owner_of(
+    nft: &Contract,
+    token_id: &str,
+) -> Result<near_workspaces::AccountId, Box<dyn std::error::Error>> {
+    let token = nft
+        .view("nft_token")
+        .args_json(json!({ "token_id": token_id }))
+        .await?
+        .json::<serde_json::Value>()?;
+    Ok(token["owner_id"]
+        .as_str()
+        .ok_or("token does not exist")?
+        .parse()?)
+}
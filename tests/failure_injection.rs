@@ -0,0 +1,376 @@
+//! Runs the contract's cross-contract calls on a sandbox node against NFT and fungible token
+//! contracts that fail them or take a few blocks to answer
+//!
+//! The `mock-nft` and `mock-ft` workspace crates are told how to misbehave with `set_behavior`.
+//! Each test checks that the contract rolls back what a failed call was part of, or records the
+//! failure so it can be retried, and that delayed calls settle just as prompt ones do.
+
+use near_workspaces::{network::Sandbox, types::NearToken, Account, Contract, Worker};
+use serde_json::{json, Value};
+
+/// Auction length in seconds, bids have to land before it runs out
+const TIMESPAN: u64 = 60;
+
+/// Blocks the mock contracts delay calls by when told to
+const DELAY_BLOCKS: u32 = 3;
+
+#[tokio::test]
+async fn failed_claim_keeps_the_claim() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = deploy_contract(&sandbox).await?;
+    let seller = sandbox.dev_create_account().await?;
+    let bidder = sandbox.dev_create_account().await?;
+    let nft = deploy_nft(&sandbox, &seller).await?;
+    list(&contract, &nft, &seller, json!({})).await?;
+    make_bid(
+        &contract,
+        &bidder,
+        nft.id(),
+        "first",
+        NearToken::from_near(2),
+    )
+    .await?;
+    sandbox.fast_forward(TIMESPAN * 2).await?;
+    end_auction(&contract, &seller, nft.id(), "first").await?;
+
+    set_behavior(&nft, json!({ "transfers": "Fail" })).await?;
+    claim_nft(&contract, &bidder, &nft).await?;
+    assert_eq!(claim_of(&contract, &nft).await?, json!(bidder.id()));
+    assert_eq!(owner_of(&nft).await?, json!(contract.id()));
+
+    set_behavior(&nft, json!({})).await?;
+    claim_nft(&contract, &bidder, &nft).await?;
+    assert_eq!(claim_of(&contract, &nft).await?, Value::Null);
+    assert_eq!(owner_of(&nft).await?, json!(bidder.id()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_handover_refunds_the_winner() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = deploy_contract(&sandbox).await?;
+    let seller = sandbox.dev_create_account().await?;
+    let bidder = sandbox.dev_create_account().await?;
+    let nft = deploy_nft(&sandbox, &seller).await?;
+    list(&contract, &nft, &seller, json!({ "escrowless": true })).await?;
+    make_bid(
+        &contract,
+        &bidder,
+        nft.id(),
+        "first",
+        NearToken::from_near(2),
+    )
+    .await?;
+    sandbox.fast_forward(TIMESPAN * 2).await?;
+
+    set_behavior(&nft, json!({ "transfers": "Fail" })).await?;
+    end_auction(&contract, &seller, nft.id(), "first").await?;
+    assert_eq!(owner_of(&nft).await?, json!(seller.id()));
+    assert_eq!(
+        balance_of(&contract, &bidder).await?,
+        NearToken::from_near(2)
+    );
+    assert_eq!(
+        balance_of(&contract, &seller).await?,
+        NearToken::from_near(0)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_approval_lists_nothing() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = deploy_contract(&sandbox).await?;
+    let seller = sandbox.dev_create_account().await?;
+    let nft = deploy_nft(&sandbox, &seller).await?;
+    set_behavior(&nft, json!({ "approvals": "Fail" })).await?;
+
+    assert!(list(&contract, &nft, &seller, json!({})).await.is_err());
+    assert_eq!(owner_of(&nft).await?, json!(seller.id()));
+    let counts = contract.view("counts").await?.json::<Value>()?;
+    assert_eq!(counts["auctions"], json!(0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn delayed_nft_calls_still_settle() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = deploy_contract(&sandbox).await?;
+    let seller = sandbox.dev_create_account().await?;
+    let bidder = sandbox.dev_create_account().await?;
+    let nft = deploy_nft(&sandbox, &seller).await?;
+    set_behavior(&nft, json!({ "delay_blocks": DELAY_BLOCKS })).await?;
+
+    // The approval reaches the contract late, and so does the NFT it escrows
+    list(&contract, &nft, &seller, json!({})).await?;
+    assert_eq!(owner_of(&nft).await?, json!(contract.id()));
+    make_bid(
+        &contract,
+        &bidder,
+        nft.id(),
+        "first",
+        NearToken::from_near(2),
+    )
+    .await?;
+    sandbox.fast_forward(TIMESPAN * 2).await?;
+    end_auction(&contract, &seller, nft.id(), "first").await?;
+    claim_nft(&contract, &bidder, &nft).await?;
+    assert_eq!(owner_of(&nft).await?, json!(bidder.id()));
+    assert_eq!(
+        balance_of(&contract, &seller).await?,
+        NearToken::from_near(2)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_ft_payout_is_owed_until_retried() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = deploy_contract(&sandbox).await?;
+    let seller = sandbox.dev_create_account().await?;
+    let bidder = sandbox.dev_create_account().await?;
+    let ft = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./mock-ft").await?)
+        .await?;
+    let amount = "1000";
+    ft.call("new").transact().await?.into_result()?;
+    ft.call("ft_mint")
+        .args_json(json!({ "account_id": seller.id(), "amount": amount }))
+        .transact()
+        .await?
+        .into_result()?;
+    // Payouts are only retried as plain transfers, so the bidder has to be registered already
+    for account_id in [contract.id(), bidder.id()] {
+        seller
+            .call(ft.id(), "storage_deposit")
+            .args_json(json!({ "account_id": account_id }))
+            .deposit(NearToken::from_millinear(13))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    // Sell the tokens as a lot, which has to be handed over to the winner
+    let msg = json!({ "timespan": TIMESPAN, "minimum_bid": NearToken::from_near(1) });
+    seller
+        .call(ft.id(), "ft_transfer_call")
+        .args_json(
+            json!({ "receiver_id": contract.id(), "amount": amount, "msg": msg.to_string() }),
+        )
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    make_bid(
+        &contract,
+        &bidder,
+        ft.id(),
+        "ft-lot-0",
+        NearToken::from_near(2),
+    )
+    .await?;
+    sandbox.fast_forward(TIMESPAN * 2).await?;
+
+    set_behavior(&ft, json!({ "transfers": "Fail" })).await?;
+    end_auction(&contract, &seller, ft.id(), "ft-lot-0").await?;
+    assert_eq!(ft_balance_of(&ft, &bidder).await?, json!("0"));
+    assert_eq!(
+        ft_balance_of(&ft, contract.as_account()).await?,
+        json!(amount)
+    );
+    let owed = owed_to(&contract, &bidder).await?;
+    assert_eq!(owed.as_array().map(Vec::len), Some(1), "owed: {owed}");
+
+    // Retried once the token transfers again, even if it takes a few blocks to
+    set_behavior(&ft, json!({ "delay_blocks": DELAY_BLOCKS })).await?;
+    contract
+        .call("retry_payout")
+        .args_json(json!({ "account_id": bidder.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(ft_balance_of(&ft, &bidder).await?, json!(amount));
+    assert_eq!(owed_to(&contract, &bidder).await?, json!([]));
+    Ok(())
+}
+
+async fn deploy_contract(
+    sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    Ok(sandbox
+        .dev_deploy(&near_workspaces::compile_project("./").await?)
+        .await?)
+}
+
+/// Deploys the mock NFT contract with `token_id` "first" minted to `owner`
+async fn deploy_nft(
+    sandbox: &Worker<Sandbox>,
+    owner: &Account,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    let nft = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./mock-nft").await?)
+        .await?;
+    nft.call("new_default_meta")
+        .args_json(json!({ "owner_id": owner.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    owner
+        .call(nft.id(), "nft_mint")
+        .args_json(json!({
+            "token_id": "first",
+            "token_owner_id": owner.id(),
+            "token_metadata": {},
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(nft)
+}
+
+/// Tells a mock contract how to behave from now on
+async fn set_behavior(mock: &Contract, behavior: Value) -> Result<(), Box<dyn std::error::Error>> {
+    mock.call("set_behavior")
+        .args_json(json!({ "behavior": behavior }))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+/// Lists "first" by approving the contract for it, with `params` on top of the timespan and
+/// minimum bid
+async fn list(
+    contract: &Contract,
+    nft: &Contract,
+    owner: &Account,
+    params: Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut msg = json!({ "timespan": TIMESPAN, "minimum_bid": NearToken::from_near(1) });
+    if let (Some(msg), Some(params)) = (msg.as_object_mut(), params.as_object()) {
+        msg.extend(params.clone());
+    }
+    owner
+        .call(nft.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": "first",
+            "account_id": contract.id(),
+            "msg": msg.to_string(),
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn make_bid(
+    contract: &Contract,
+    bidder: &Account,
+    nft: &near_workspaces::AccountId,
+    token_id: &str,
+    amount: NearToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    bidder
+        .call(contract.id(), "make_bid")
+        .args_json(json!({ "nft": nft, "token_id": token_id, "amount": amount }))
+        .deposit(amount)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn end_auction(
+    contract: &Contract,
+    caller: &Account,
+    nft: &near_workspaces::AccountId,
+    token_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    caller
+        .call(contract.id(), "end_auction")
+        .args_json(json!({ "nft": nft, "token_id": token_id }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+/// Claims "first" for `winner`, whose claim is kept if the transfer fails, so the call itself
+/// succeeds either way
+async fn claim_nft(
+    contract: &Contract,
+    winner: &Account,
+    nft: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    winner
+        .call(contract.id(), "claim_nft")
+        .args_json(json!({ "nft": nft.id(), "token_id": "first" }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+/// Who can claim "first", null if no one can
+async fn claim_of(
+    contract: &Contract,
+    nft: &Contract,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Ok(contract
+        .view("nft_claim_of")
+        .args_json(json!({ "nft": nft.id(), "token_id": "first" }))
+        .await?
+        .json()?)
+}
+
+/// The account owning "first" according to the NFT contract
+async fn owner_of(nft: &Contract) -> Result<Value, Box<dyn std::error::Error>> {
+    let token = nft
+        .view("nft_token")
+        .args_json(json!({ "token_id": "first" }))
+        .await?
+        .json::<Value>()?;
+    Ok(token["owner_id"].clone())
+}
+
+async fn balance_of(
+    contract: &Contract,
+    account: &Account,
+) -> Result<NearToken, Box<dyn std::error::Error>> {
+    Ok(contract
+        .view("balance_of")
+        .args_json(json!({ "account_id": account.id() }))
+        .await?
+        .json()?)
+}
+
+async fn owed_to(
+    contract: &Contract,
+    account: &Account,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Ok(contract
+        .view("owed_to")
+        .args_json(json!({ "account_id": account.id() }))
+        .await?
+        .json()?)
+}
+
+async fn ft_balance_of(
+    ft: &Contract,
+    account: &Account,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Ok(ft
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": account.id() }))
+        .await?
+        .json()?)
+}
@@ -0,0 +1,157 @@
+//! Fails if `make_bid` or `end_auction` burn more gas than `tests/gas-baseline.json` records
+//!
+//! Only the gas burnt by the contract's own receipts is compared, as in the `gas` bench, since the
+//! rest depends on the NFT contract and the runtime. A scenario missing from the baseline is
+//! recorded the first time it runs. Run with `UPDATE_GAS_BASELINE=1` to record every scenario again
+//! after a change that is meant to cost more, and commit the new baseline with it.
+
+use std::collections::BTreeMap;
+
+use near_workspaces::{result::ExecutionFinalResult, types::NearToken, Account, Contract};
+use serde_json::json;
+
+/// How much more gas than its baseline a scenario can burn, in percent
+const THRESHOLD_PERCENT: u64 = 10;
+
+/// Number of bidders to settle an auction with
+const BIDDER_COUNTS: [usize; 3] = [0, 1, 5];
+
+/// Auction length in seconds, bids have to land before it runs out
+const TIMESPAN: u64 = 60;
+
+const BASELINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/gas-baseline.json");
+
+#[tokio::test]
+async fn gas_stays_within_baseline() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./").await?)
+        .await?;
+    let nft = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./mock-nft").await?)
+        .await?;
+    let seller = sandbox.dev_create_account().await?;
+    let mut bidders = Vec::new();
+    for _ in 0..BIDDER_COUNTS.iter().max().copied().unwrap_or_default() {
+        bidders.push(sandbox.dev_create_account().await?);
+    }
+    nft.call("new_default_meta")
+        .args_json(json!({ "owner_id": seller.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Auctions run side by side, so they can all expire at once
+    let mut measured = BTreeMap::new();
+    for bidder_count in BIDDER_COUNTS {
+        let token_id = format!("gas-{bidder_count}");
+        list(&contract, &nft, &seller, &token_id).await?;
+        for (i, bidder) in bidders.iter().take(bidder_count).enumerate() {
+            let amount = NearToken::from_millinear(i as u128 + 1);
+            let outcome = bidder
+                .call(contract.id(), "make_bid")
+                .args_json(json!({ "nft": nft.id(), "token_id": token_id, "amount": amount }))
+                .deposit(amount)
+                .max_gas()
+                .transact()
+                .await?;
+            measured.insert(
+                format!("make_bid/previous_bids_{i}"),
+                gas_burnt(&contract, &outcome)?,
+            );
+        }
+    }
+    sandbox.fast_forward(TIMESPAN * 2).await?;
+    for bidder_count in BIDDER_COUNTS {
+        let outcome = seller
+            .call(contract.id(), "end_auction")
+            .args_json(json!({ "nft": nft.id(), "token_id": format!("gas-{bidder_count}") }))
+            .max_gas()
+            .transact()
+            .await?;
+        measured.insert(
+            format!("end_auction/bidders_{bidder_count}"),
+            gas_burnt(&contract, &outcome)?,
+        );
+    }
+
+    let mut baseline: BTreeMap<String, u64> = match std::fs::read_to_string(BASELINE) {
+        Ok(baseline) => serde_json::from_str(&baseline)?,
+        Err(_) => BTreeMap::new(),
+    };
+    let update = std::env::var_os("UPDATE_GAS_BASELINE").is_some();
+    let mut regressions = Vec::new();
+    for (scenario, gas) in &measured {
+        match baseline.get(scenario) {
+            Some(&expected) if !update => {
+                if *gas > expected + expected / 100 * THRESHOLD_PERCENT {
+                    regressions.push(format!("{scenario}: {gas} gas, baseline {expected}"));
+                }
+            }
+            _ => {
+                println!("recording {scenario}: {gas} gas");
+                baseline.insert(scenario.clone(), *gas);
+            }
+        }
+    }
+    std::fs::write(BASELINE, serde_json::to_string_pretty(&baseline)? + "\n")?;
+    assert!(
+        regressions.is_empty(),
+        "gas grew by more than {THRESHOLD_PERCENT}%:\n{}",
+        regressions.join("\n")
+    );
+    Ok(())
+}
+
+/// Mints `token_id` to `owner` and lists it by approving the contract for it
+async fn list(
+    contract: &Contract,
+    nft: &Contract,
+    owner: &Account,
+    token_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    owner
+        .call(nft.id(), "nft_mint")
+        .args_json(json!({
+            "token_id": token_id,
+            "token_owner_id": owner.id(),
+            "token_metadata": {},
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?
+        .into_result()?;
+    let msg = json!({ "timespan": TIMESPAN, "minimum_bid": NearToken::from_yoctonear(0) });
+    owner
+        .call(nft.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": token_id,
+            "account_id": contract.id(),
+            "msg": msg.to_string(),
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+/// Gas burnt by the receipts `contract` executed in `outcome`, all of which have to succeed
+fn gas_burnt(contract: &Contract, outcome: &ExecutionFinalResult) -> Result<u64, String> {
+    let receipts: Vec<_> = outcome
+        .receipt_outcomes()
+        .iter()
+        .filter(|receipt| receipt.executor_id == *contract.id())
+        .collect();
+    if receipts.is_empty() {
+        return Err(format!("no receipt was executed: {outcome:?}"));
+    }
+    if !receipts.iter().all(|receipt| receipt.is_success()) {
+        return Err(format!("contract call failed: {outcome:?}"));
+    }
+    Ok(receipts
+        .iter()
+        .map(|receipt| receipt.gas_burnt.as_gas())
+        .sum())
+}
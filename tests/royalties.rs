@@ -0,0 +1,186 @@
+//! Settles sales on a sandbox node with royalties and quick-flip fees, checking that the fee, the
+//! royalties and the seller's proceeds add up to the winning bid to the yoctoNEAR
+
+use std::collections::HashMap;
+
+use near_workspaces::{types::NearToken, AccountId, Contract};
+use serde_json::json;
+
+/// Auction length in seconds, bids have to land before it runs out
+const TIMESPAN: u64 = 30;
+
+/// Most of a sale royalties can take, as the contract caps them
+const MAX_ROYALTY_BPS: u16 = 5_000;
+
+struct Config {
+    name: &'static str,
+    /// Royalty of each royalty account the collection pays, in basis points
+    royalties: Vec<u16>,
+    /// Quick-flip fee charged on every sale, in basis points
+    fee_bps: Option<u16>,
+    price: NearToken,
+}
+
+/// `amount` times `bps` basis points, rounded down as the contract rounds
+fn bps_of(amount: u128, bps: u16) -> u128 {
+    let bps = u128::from(bps);
+    amount / 10_000 * bps + amount % 10_000 * bps / 10_000
+}
+
+#[tokio::test]
+async fn settlement_adds_up_to_the_winning_bid() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = sandbox
+        .dev_deploy(&near_workspaces::compile_project("./").await?)
+        .await?;
+    let nft_wasm = near_workspaces::compile_project("./mock-nft").await?;
+    contract
+        .call("set_royalties")
+        .args_json(json!({ "enabled": true, "cache_ttl": u64::MAX.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let configs = [
+        Config {
+            name: "no royalties or fee",
+            royalties: vec![],
+            fee_bps: None,
+            price: NearToken::from_yoctonear(3_333_333_333_333_333_333_333_337),
+        },
+        Config {
+            name: "royalties",
+            royalties: vec![250, 100],
+            fee_bps: None,
+            price: NearToken::from_yoctonear(2_718_281_828_459_045_235_360_287),
+        },
+        Config {
+            name: "royalties and fee",
+            royalties: vec![250, 100],
+            fee_bps: Some(250),
+            price: NearToken::from_yoctonear(1_414_213_562_373_095_048_801_689),
+        },
+        Config {
+            name: "royalties above the cap",
+            royalties: vec![3_000, 2_500],
+            fee_bps: Some(100),
+            price: NearToken::from_yoctonear(1_732_050_807_568_877_293_527_447),
+        },
+    ];
+    for config in configs {
+        let name = config.name;
+        let nft = sandbox.dev_deploy(&nft_wasm).await?;
+        let seller = sandbox.dev_create_account().await?;
+        let bidder = sandbox.dev_create_account().await?;
+        let mut recipients = Vec::new();
+        for _ in &config.royalties {
+            recipients.push(sandbox.dev_create_account().await?);
+        }
+        let royalties: HashMap<&AccountId, u16> = recipients
+            .iter()
+            .map(|recipient| recipient.id())
+            .zip(config.royalties.iter().copied())
+            .collect();
+        nft.call("new_default_meta")
+            .args_json(json!({ "owner_id": seller.id() }))
+            .transact()
+            .await?
+            .into_result()?;
+        nft.call("set_royalties")
+            .args_json(json!({ "royalties": royalties }))
+            .transact()
+            .await?
+            .into_result()?;
+        let wash_trade_rules = config.fee_bps.map(|fee_bps| {
+            json!({ "window": u64::MAX.to_string(), "free_trades": 0, "fee_bps": fee_bps })
+        });
+        contract
+            .call("set_wash_trade_rules")
+            .args_json(json!({ "wash_trade_rules": wash_trade_rules }))
+            .transact()
+            .await?
+            .into_result()?;
+        let fees_before = protocol_fees(&contract).await?;
+
+        seller
+            .call(nft.id(), "nft_mint")
+            .args_json(json!({
+                "token_id": "first",
+                "token_owner_id": seller.id(),
+                "token_metadata": {},
+            }))
+            .deposit(NearToken::from_millinear(10))
+            .transact()
+            .await?
+            .into_result()?;
+        let msg = json!({ "timespan": TIMESPAN, "minimum_bid": NearToken::from_yoctonear(0) });
+        seller
+            .call(nft.id(), "nft_approve")
+            .args_json(json!({
+                "token_id": "first",
+                "account_id": contract.id(),
+                "msg": msg.to_string(),
+            }))
+            .deposit(NearToken::from_millinear(10))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        bidder
+            .call(contract.id(), "make_bid")
+            .args_json(json!({ "nft": nft.id(), "token_id": "first", "amount": config.price }))
+            .deposit(config.price)
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        sandbox.fast_forward(TIMESPAN * 2).await?;
+        seller
+            .call(contract.id(), "end_auction")
+            .args_json(json!({ "nft": nft.id(), "token_id": "first" }))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+
+        let price = config.price.as_yoctonear();
+        let fee = protocol_fees(&contract).await? - fees_before;
+        let expected_fee = config.fee_bps.map_or(0, |fee_bps| bps_of(price, fee_bps));
+        assert_eq!(fee, expected_fee, "{name}: fee");
+        let honored = config.royalties.iter().sum::<u16>() <= MAX_ROYALTY_BPS;
+        let mut paid = fee;
+        for (recipient, bps) in recipients.iter().zip(&config.royalties) {
+            let royalty = balance_of(&contract, recipient.id()).await?;
+            let expected = if honored {
+                bps_of(price - fee, *bps)
+            } else {
+                0
+            };
+            assert_eq!(royalty, expected, "{name}: royalty of {bps} basis points");
+            paid += royalty;
+        }
+        let proceeds = balance_of(&contract, seller.id()).await?;
+        assert_eq!(paid + proceeds, price, "{name}: payments don't add up");
+    }
+    Ok(())
+}
+
+async fn protocol_fees(contract: &Contract) -> Result<u128, Box<dyn std::error::Error>> {
+    Ok(contract
+        .view("protocol_fees")
+        .await?
+        .json::<NearToken>()?
+        .as_yoctonear())
+}
+
+async fn balance_of(
+    contract: &Contract,
+    account_id: &AccountId,
+) -> Result<u128, Box<dyn std::error::Error>> {
+    Ok(contract
+        .view("balance_of")
+        .args_json(json!({ "account_id": account_id }))
+        .await?
+        .json::<NearToken>()?
+        .as_yoctonear())
+}
@@ -0,0 +1,297 @@
+//! Auctions described as scenarios, a listing followed by bids at given times and a settlement
+//!
+//! The same scenario runs natively against the rules of `near-nft-auction-core` and on a sandbox
+//! node against the contract, and both runs have to end in the same [`Outcome`]. Times are in
+//! seconds since the listing. The sandbox can only move its clock forward, so a bid lands at its
+//! time or a few blocks after, and bids should keep a few seconds clear of the expiry.
+
+use std::collections::BTreeMap;
+
+use near_nft_auction_core::{
+    bid::{validate_bid, validate_increase, BidAttempt, BidContext},
+    settlement::{plan_settlement, Settlement, SettlementBid},
+    Balance, Timestamp,
+};
+use near_workspaces::{network::Sandbox, types::NearToken, Account, Contract, Worker};
+use serde_json::json;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// A bid of `bidder` bringing their bid to `amount`, made `at` seconds after the listing
+///
+/// Bidders are numbered from 0. A bidder's first bid is made with `make_bid`, later ones top it up
+/// with `increase_bid`.
+#[derive(Debug, Clone, Copy)]
+pub struct ScenarioBid {
+    pub bidder: usize,
+    pub at: u64,
+    pub amount: Balance,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    /// Auction length in seconds
+    pub timespan: u64,
+    pub minimum_bid: Balance,
+    pub bids: Vec<ScenarioBid>,
+}
+
+/// How a scenario ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Whether each bid was accepted, in the order they were made
+    pub accepted: Vec<bool>,
+    pub winner: Option<usize>,
+    /// What the seller got, 0 if unsold
+    pub price: Balance,
+}
+
+impl Scenario {
+    /// Lists an English auction lasting `timespan` seconds
+    pub fn list(timespan: u64, minimum_bid: Balance) -> Self {
+        Self {
+            timespan,
+            minimum_bid,
+            bids: Vec::new(),
+        }
+    }
+
+    pub fn bid(mut self, bidder: usize, at: u64, amount: Balance) -> Self {
+        self.bids.push(ScenarioBid { bidder, at, amount });
+        self
+    }
+
+    fn bidder_count(&self) -> usize {
+        self.bids
+            .iter()
+            .map(|bid| bid.bidder + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Runs the scenario against the core rules, listing at time 0 and settling after the expiry
+    pub fn run_native(&self) -> Outcome {
+        let expiry: Timestamp = self.timespan * NANOS_PER_SEC;
+        let mut h_bid = self.minimum_bid;
+        // Each bidder's bid and when it was last raised
+        let mut bids: BTreeMap<usize, (SettlementBid<usize>, Timestamp)> = BTreeMap::new();
+        let mut accepted = Vec::new();
+        for (sequence, step) in self.bids.iter().enumerate() {
+            let ctx = BidContext {
+                h_bid,
+                expiry,
+                bid_interval: 0,
+                now: step.at * NANOS_PER_SEC,
+            };
+            let result = match bids.get(&step.bidder) {
+                Some((bid, last_bid_at)) => validate_increase(
+                    &ctx,
+                    bid.amount,
+                    *last_bid_at,
+                    step.amount.saturating_sub(bid.amount),
+                ),
+                None => validate_bid(
+                    &ctx,
+                    &BidAttempt {
+                        amount: step.amount,
+                        deposit: step.amount,
+                        already_bid: false,
+                        expires_at: None,
+                        pay_on_win: None,
+                    },
+                )
+                .map(|()| step.amount),
+            };
+            accepted.push(result.is_ok());
+            if let Ok(amount) = result {
+                h_bid = amount;
+                let bid = SettlementBid {
+                    bidder: step.bidder,
+                    amount,
+                    paid: false,
+                    sequence: sequence as u64,
+                };
+                bids.insert(step.bidder, (bid, ctx.now));
+            }
+        }
+        match plan_settlement(bids.into_values().map(|(bid, _)| bid)) {
+            Settlement::Unsold => Outcome {
+                accepted,
+                winner: None,
+                price: 0,
+            },
+            Settlement::Sold { winner, price, .. } => Outcome {
+                accepted,
+                winner: Some(winner),
+                price,
+            },
+        }
+    }
+
+    /// Runs the scenario on `env`, listing a new NFT of a new seller
+    pub async fn run_sandbox(
+        &self,
+        env: &mut SandboxEnv,
+    ) -> Result<Outcome, Box<dyn std::error::Error>> {
+        while env.bidders.len() < self.bidder_count() {
+            env.bidders.push(env.sandbox.dev_create_account().await?);
+        }
+        let seller = env.sandbox.dev_create_account().await?;
+        let token_id = format!("scenario-{}", env.listed);
+        env.listed += 1;
+        let nft_args = json!({ "nft": env.nft.id(), "token_id": token_id });
+
+        seller
+            .call(env.nft.id(), "nft_mint")
+            .args_json(json!({
+                "token_id": token_id,
+                "token_owner_id": seller.id(),
+                "token_metadata": {},
+            }))
+            .deposit(NearToken::from_millinear(10))
+            .transact()
+            .await?
+            .into_result()?;
+        let msg = json!({
+            "timespan": self.timespan,
+            "minimum_bid": NearToken::from_yoctonear(self.minimum_bid),
+        });
+        seller
+            .call(env.nft.id(), "nft_approve")
+            .args_json(json!({
+                "token_id": token_id,
+                "account_id": env.contract.id(),
+                "msg": msg.to_string(),
+            }))
+            .deposit(NearToken::from_millinear(10))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        let auction = env
+            .contract
+            .view("get_auction")
+            .args_json(&nft_args)
+            .await?
+            .json::<serde_json::Value>()?;
+        let expiry: Timestamp = auction["expiry"]
+            .as_str()
+            .ok_or("auction has no expiry")?
+            .parse()?;
+        let listed_at = expiry - self.timespan * NANOS_PER_SEC;
+
+        let mut amounts: BTreeMap<usize, Balance> = BTreeMap::new();
+        let mut accepted = Vec::new();
+        for step in &self.bids {
+            env.wait_until(listed_at + step.at * NANOS_PER_SEC).await?;
+            let bidder = &env.bidders[step.bidder];
+            let outcome = match amounts.get(&step.bidder) {
+                Some(current) => {
+                    let top_up = NearToken::from_yoctonear(step.amount.saturating_sub(*current));
+                    bidder
+                        .call(env.contract.id(), "increase_bid")
+                        .args_json(json!({
+                            "nft": env.nft.id(),
+                            "token_id": token_id,
+                            "top_up": top_up,
+                        }))
+                        .deposit(top_up)
+                }
+                None => bidder
+                    .call(env.contract.id(), "make_bid")
+                    .args_json(json!({
+                        "nft": env.nft.id(),
+                        "token_id": token_id,
+                        "amount": NearToken::from_yoctonear(step.amount),
+                    }))
+                    .deposit(NearToken::from_yoctonear(step.amount)),
+            }
+            .max_gas()
+            .transact()
+            .await?;
+            accepted.push(outcome.is_success());
+            if outcome.is_success() {
+                amounts.insert(step.bidder, step.amount);
+            }
+        }
+
+        env.wait_until(expiry + NANOS_PER_SEC).await?;
+        seller
+            .call(env.contract.id(), "end_auction")
+            .args_json(&nft_args)
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        let winner = env
+            .contract
+            .view("nft_claim_of")
+            .args_json(&nft_args)
+            .await?
+            .json::<Option<near_workspaces::AccountId>>()?
+            .map(|winner| {
+                env.bidders
+                    .iter()
+                    .position(|bidder| *bidder.id() == winner)
+                    .ok_or("the winner is not a bidder")
+            })
+            .transpose()?;
+        let price = env
+            .contract
+            .view("balance_of")
+            .args_json(json!({ "account_id": seller.id() }))
+            .await?
+            .json::<NearToken>()?;
+        Ok(Outcome {
+            accepted,
+            winner,
+            price: price.as_yoctonear(),
+        })
+    }
+}
+
+/// The contract and the mock NFT contract deployed on a sandbox node, with the bidders of the
+/// scenarios run so far
+pub struct SandboxEnv {
+    sandbox: Worker<Sandbox>,
+    contract: Contract,
+    nft: Contract,
+    bidders: Vec<Account>,
+    listed: usize,
+}
+
+impl SandboxEnv {
+    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let sandbox = near_workspaces::sandbox().await?;
+        let contract = sandbox
+            .dev_deploy(&near_workspaces::compile_project("./").await?)
+            .await?;
+        let nft = sandbox
+            .dev_deploy(&near_workspaces::compile_project("./mock-nft").await?)
+            .await?;
+        nft.call("new_default_meta")
+            .args_json(json!({ "owner_id": nft.id() }))
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(Self {
+            sandbox,
+            contract,
+            nft,
+            bidders: Vec::new(),
+            listed: 0,
+        })
+    }
+
+    /// Produces blocks until the block timestamp is at least `timestamp`
+    async fn wait_until(&self, timestamp: Timestamp) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            let now = self.sandbox.view_block().await?.timestamp();
+            if now >= timestamp {
+                return Ok(());
+            }
+            let seconds = (timestamp - now).div_ceil(NANOS_PER_SEC);
+            self.sandbox.fast_forward(seconds.max(1)).await?;
+        }
+    }
+}
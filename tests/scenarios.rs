@@ -0,0 +1,67 @@
+//! Auction scenarios, each run against the core rules and on a sandbox node
+
+mod scenario;
+
+use near_nft_auction_core::Balance;
+use scenario::{Outcome, SandboxEnv, Scenario};
+
+fn near(amount: Balance) -> Balance {
+    amount * 10u128.pow(24)
+}
+
+/// Every scenario along with how it has to end
+fn scenarios() -> Vec<(&'static str, Scenario, Outcome)> {
+    vec![
+        (
+            "outbid bidder raises their bid",
+            Scenario::list(60, near(1))
+                .bid(0, 0, near(2))
+                .bid(1, 10, near(3))
+                .bid(0, 20, near(4)),
+            Outcome {
+                accepted: vec![true, true, true],
+                winner: Some(0),
+                price: near(4),
+            },
+        ),
+        (
+            "bids that are too low or too late",
+            Scenario::list(60, near(1))
+                .bid(0, 0, near(1))
+                .bid(1, 5, near(2))
+                .bid(2, 10, near(2))
+                .bid(1, 20, near(2))
+                .bid(2, 90, near(5)),
+            Outcome {
+                accepted: vec![false, true, false, false, false],
+                winner: Some(1),
+                price: near(2),
+            },
+        ),
+        (
+            "no bids",
+            Scenario::list(30, near(1)),
+            Outcome {
+                accepted: vec![],
+                winner: None,
+                price: 0,
+            },
+        ),
+    ]
+}
+
+#[test]
+fn scenarios_end_as_expected_natively() {
+    for (name, scenario, expected) in scenarios() {
+        assert_eq!(scenario.run_native(), expected, "{name}");
+    }
+}
+
+#[tokio::test]
+async fn scenarios_end_as_expected_on_sandbox() -> Result<(), Box<dyn std::error::Error>> {
+    let mut env = SandboxEnv::new().await?;
+    for (name, scenario, expected) in scenarios() {
+        assert_eq!(scenario.run_sandbox(&mut env).await?, expected, "{name}");
+    }
+    Ok(())
+}